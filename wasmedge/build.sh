@@ -13,7 +13,7 @@ echo ""
 
 # Build the podman image using Podman
 echo "🚀 Starting podman build..."
-podman build -t "$IMAGE_NAME" .
+podman build --build-arg GIT_COMMIT="$(git rev-parse --short HEAD 2>/dev/null || echo unknown)" -t "$IMAGE_NAME" .
 
 echo ""
 echo "✅ Build complete!"
@@ -1,9 +1,12 @@
 use serde_json::json;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::Infallible;
 use std::env;
-use std::sync::{Arc, Mutex};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::io::Write;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use hyper::{Body, Client, Method, Request, Response, Server, Uri};
 use hyper::service::{make_service_fn, service_fn};
@@ -11,9 +14,36 @@ use tokio::net::TcpListener;
 use serde::{Deserialize, Serialize};
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
 use hyper_rustls::HttpsConnectorBuilder;
+use flate2::{write::GzEncoder, Compression};
+use futures_util::{SinkExt, StreamExt};
+use hyper::upgrade::Upgraded;
+use tokio_tungstenite::tungstenite::{handshake::derive_accept_key, protocol::Role, Message};
+use tokio_tungstenite::WebSocketStream;
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
 
+tokio::task_local! {
+    // X-Request-Id of the request being handled; spawn_oracle_task carries it
+    // over into background tasks
+    static REQUEST_ID: String;
+}
+
+fn log_prefix() -> String {
+    REQUEST_ID.try_with(|id| format!("[{}] ", id)).unwrap_or_default()
+}
+
+// Shadow std's println!/eprintln! so every log line written on behalf of a
+// request starts with its request id
+macro_rules! println {
+    () => { std::println!() };
+    ($($arg:tt)*) => { std::println!("{}{}", log_prefix(), format_args!($($arg)*)) };
+}
+
+macro_rules! eprintln {
+    () => { std::eprintln!() };
+    ($($arg:tt)*) => { std::eprintln!("{}{}", log_prefix(), format_args!($($arg)*)) };
+}
+
 // Create HTTPS client for Oracle Cloud API calls
 fn create_https_client() -> Client<hyper_rustls::HttpsConnector<hyper::client::HttpConnector>> {
     let https = HttpsConnectorBuilder::new()
@@ -24,8 +54,17 @@ fn create_https_client() -> Client<hyper_rustls::HttpsConnector<hyper::client::H
     Client::builder().build::<_, hyper::Body>(https)
 }
 
+// Milliseconds since the Unix epoch, matching Wasmtime's get_timestamp() so
+// events from both runtimes order correctly on the shared topic
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
 // Game state structures
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 struct Player {
     id: String,
     x: f64,
@@ -34,6 +73,227 @@ struct Player {
     score: i32,
     color: String,
     alive: bool,
+    // Match this player belongs to (GAME_ID unless the client names one)
+    #[serde(default = "default_game_id")]
+    game_id: String,
+    // Last join/move time (ms); idle players are swept after PLAYER_IDLE_TIMEOUT_MS
+    #[serde(default)]
+    last_seen: u64,
+    #[serde(default)]
+    joined_at: u64,
+    // Cells this cycle has left behind (the last TRAIL_LENGTH of them when
+    // that is set, never more than MAX_TRAIL_CELLS); moving into any trail is
+    // a crash
+    #[serde(default)]
+    trail: Vec<Position>,
+    #[serde(default)]
+    stats: PlayerStats,
+    // Game tick of the last change to this player (see advance_tick)
+    #[serde(default)]
+    tick: u64,
+    // When the last move was accepted (ms), for MOVE_COOLDOWN_MS
+    #[serde(default)]
+    last_move_at: u64,
+}
+
+// Hard ceiling on a trail even with TRAIL_LENGTH unset, so a long-running cycle
+// can't grow its Vec and every saved state without bound
+const MAX_TRAIL_CELLS: usize = 10_000;
+
+impl Player {
+    // Time alive so far, frozen at the moment of elimination
+    fn survival_ms(&self) -> u64 {
+        if self.alive && self.joined_at > 0 {
+            now_ms().saturating_sub(self.joined_at)
+        } else {
+            self.stats.survival_ms
+        }
+    }
+
+    // Leaves `at` behind as trail. Past TRAIL_LENGTH (or MAX_TRAIL_CELLS) the
+    // oldest cells fall off the end and stop counting as collisions.
+    fn lay_trail(&mut self, at: Position, limit: usize) {
+        self.trail.push(at);
+        let limit = if limit == 0 { MAX_TRAIL_CELLS } else { limit.min(MAX_TRAIL_CELLS) };
+        if self.trail.len() > limit {
+            let excess = self.trail.len() - limit;
+            self.trail.drain(..excess);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct Position {
+    x: f64,
+    y: f64,
+}
+
+impl Position {
+    fn cell(&self) -> (i32, i32) {
+        grid_cell(self.x, self.y)
+    }
+}
+
+// The grid cell a coordinate falls in. Positions are f64 and can drift off
+// whole numbers, so they are rounded, never truncated (24.999999 is cell 25),
+// and every collision and trail comparison goes through here.
+fn grid_cell(x: f64, y: f64) -> (i32, i32) {
+    (x.round() as i32, y.round() as i32)
+}
+
+fn in_arena(at: Position) -> bool {
+    at.x >= 0.0 && at.y >= 0.0 && at.x <= GAME_CONFIG.arena_width && at.y <= GAME_CONFIG.arena_height
+}
+
+// Guard for a position handed to us rather than computed by apply_move. It
+// has to be in the arena and, with VALIDATE_MOVE_STEPS on, at most one
+// MOVE_STEP along a single axis from `from` (less is fine: walls stop cycles
+// short). Anything further is a teleport; movement on both axes a diagonal.
+fn validate_move(from: Position, to: Position) -> std::result::Result<(), String> {
+    if !in_arena(to) {
+        return Err(format!("({}, {}) is outside the arena", to.x, to.y));
+    }
+    if GAME_CONFIG.validate_move_steps {
+        // Positions drift off whole numbers, so compare with some slack
+        const SLACK: f64 = 1e-6;
+        let (dx, dy) = ((to.x - from.x).abs(), (to.y - from.y).abs());
+        if dx > SLACK && dy > SLACK {
+            return Err(format!("({}, {}) to ({}, {}) is a diagonal move", from.x, from.y, to.x, to.y));
+        }
+        if dx.max(dy) > GAME_CONFIG.move_step + SLACK {
+            return Err(format!("({}, {}) to ({}, {}) is more than one step", from.x, from.y, to.x, to.y));
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct PlayerStats {
+    moves: u32,
+    distance: f64,
+    kills: u32,
+    survival_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    fn parse(direction: &str) -> Option<Self> {
+        match direction {
+            "up" => Some(Direction::Up),
+            "down" => Some(Direction::Down),
+            "left" => Some(Direction::Left),
+            "right" => Some(Direction::Right),
+            _ => None,
+        }
+    }
+
+    fn opposite(self) -> Self {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Direction::Up => "up",
+            Direction::Down => "down",
+            Direction::Left => "left",
+            Direction::Right => "right",
+        }
+    }
+
+    // Unit (dx, dy) with y growing downwards
+    fn delta(self) -> (f64, f64) {
+        match self {
+            Direction::Up => (0.0, -1.0),
+            Direction::Down => (0.0, 1.0),
+            Direction::Left => (-1.0, 0.0),
+            Direction::Right => (1.0, 0.0),
+        }
+    }
+}
+
+// STATE_FILE keeps the players map on local disk for development without
+// Oracle: loaded at startup and rewritten after every change
+fn state_file_path() -> Option<String> {
+    env::var("STATE_FILE").ok().filter(|path| !path.is_empty())
+}
+
+fn load_state_file(path: &str) -> std::result::Result<HashMap<String, Player>, String> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => serde_json::from_str(&contents).map_err(|e| e.to_string()),
+        // First run: nothing saved yet
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+// Written to a sibling temp file and renamed over the target, so a crash
+// mid-write never leaves a truncated state file behind
+fn save_state_file(players: &HashMap<String, Player>) {
+    let path = match state_file_path() {
+        Some(path) => path,
+        None => return,
+    };
+    let tmp_path = format!("{}.tmp", path);
+    let result = serde_json::to_vec(players)
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| std::fs::write(&tmp_path, bytes).map_err(|e| e.to_string()))
+        .and_then(|_| std::fs::rename(&tmp_path, &path).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        eprintln!("⚠️ Failed to write state file {}: {}", path, e);
+    }
+}
+
+// Castle this runtime presents as in responses and events; CASTLE_NAME rebrands it
+fn castle_name() -> String {
+    env::var("CASTLE_NAME").unwrap_or_else(|_| "Quantum Nexus".to_string())
+}
+
+// Castle color from CASTLE_COLOR, used in responses and on published events
+fn runtime_color() -> String {
+    GAME_CONFIG.color.clone()
+}
+
+// Game/match id used when join/move requests don't name one
+fn default_game_id() -> String {
+    env::var("GAME_ID").unwrap_or_else(|_| "wasicycles-multiplayer".to_string())
+}
+
+// Players that haven't moved within this window are removed by the idle sweep
+fn player_idle_timeout_ms() -> u64 {
+    env::var("PLAYER_IDLE_TIMEOUT_MS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(60000)
+}
+
+// MOVE_COOLDOWN_MS is the least time between two accepted moves by the same
+// player, so a client with a faster frame rate can't out-move the others.
+// 0 (the default) turns it off.
+fn move_cooldown_ms() -> u64 {
+    env::var("MOVE_COOLDOWN_MS").ok().and_then(|v| v.parse().ok()).unwrap_or(0)
+}
+
+// ALLOW_REVERSE=true permits 180° turns for game variants that allow them
+fn allow_reverse() -> bool {
+    env::var("ALLOW_REVERSE").map(|v| v == "true").unwrap_or(false)
+}
+
+// RESPAWN_RESETS_SCORE=true zeroes the score on /respawn; by default a
+// respawned player keeps what they had scored before crashing
+fn respawn_resets_score() -> bool {
+    env::var("RESPAWN_RESETS_SCORE").map(|v| v == "true").unwrap_or(false)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,27 +305,226 @@ struct GameEvent {
     runtime: String,
     castle: String,
     timestamp: u64,
+    // Game tick this event produced; clients use gaps to spot missed updates
+    tick: Option<u64>,
+    // Color of the castle that published it, so timelines can be colored
+    // per castle; absent on older events
+    #[serde(skip_serializing_if = "Option::is_none")]
+    color: Option<String>,
+    // Player fields at top level, named as in wasmtime's events, so consumers
+    // can read them without knowing which runtime wrote the event. Under
+    // MINIMAL_EVENTS a move event leaves out the ones that didn't change.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    position: Option<Position>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    direction: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    score: Option<i32>,
+    // Per-player sequence number, taken when the event is built rather than
+    // when a spawned task gets round to publishing it. Consumers order by
+    // (timestamp, seq); events from older builds don't carry one.
+    seq: Option<u64>,
+    // Newest trail cells, capped at EVENT_TRAIL_CELLS. Wasmtime rebuilds its
+    // collision map from these when it reconstructs state from the topic.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trail: Option<Vec<Position>>,
+    // MINIMAL_EVENTS moves send just the cells laid since the player's last
+    // event here instead of the whole trail
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trail_added: Option<Vec<Position>>,
+    // Anything event-specific beyond the shared fields
     data: serde_json::Value,
 }
 
+impl GameEvent {
+    // Runtime, castle, timestamp and the default game_id are filled in;
+    // per-event fields go in with_data
+    fn new(event_type: &str, player_id: &str) -> Self {
+        Self {
+            event_type: event_type.to_string(),
+            player_id: player_id.to_string(),
+            game_id: default_game_id(),
+            runtime: "wasmedge".to_string(),
+            castle: castle_name(),
+            timestamp: now_ms(),
+            tick: None,
+            color: Some(runtime_color()),
+            position: None,
+            direction: None,
+            score: None,
+            seq: Some(next_event_seq(player_id)),
+            trail: None,
+            trail_added: None,
+            data: json!({}),
+        }
+    }
+
+    fn with_game_id(mut self, game_id: &str) -> Self {
+        self.game_id = game_id.to_string();
+        self
+    }
+
+    fn with_tick(mut self, tick: u64) -> Self {
+        self.tick = Some(tick);
+        self
+    }
+
+    fn with_data(mut self, data: serde_json::Value) -> Self {
+        self.data = data;
+        self
+    }
+
+    fn with_score(mut self, score: i32) -> Self {
+        self.score = Some(score);
+        self
+    }
+
+    // Fills the structured fields from the player; callers reporting a newer
+    // tick than the player's own (leave, timeout) set it afterwards
+    fn with_player(mut self, player: &Player) -> Self {
+        self.game_id = player.game_id.clone();
+        self.position = Some(Position { x: player.x, y: player.y });
+        self.direction = Some(player.direction.clone());
+        self.score = Some(player.score);
+        self.tick = Some(player.tick);
+        let start = player.trail.len().saturating_sub(EVENT_TRAIL_CELLS);
+        self.trail = Some(player.trail[start..].to_vec());
+        self.minimize()
+    }
+
+    // MINIMAL_EVENTS: a move event drops the fields it would repeat from the
+    // last event built for the same player in the same game, and its trail
+    // becomes trail_added. Every player event is recorded here, not just
+    // moves, so a join or respawn in between resets what counts as unchanged.
+    fn minimize(mut self) -> Self {
+        if !minimal_events() {
+            return self;
+        }
+        let mut last_events = lock_unpoisoned(&LAST_PLAYER_EVENTS);
+        let full = self.clone();
+        if self.event_type == "player_moved" {
+            if let Some(previous) = last_events.get(&self.player_id).filter(|e| e.game_id == self.game_id) {
+                if self.position == previous.position {
+                    self.position = None;
+                }
+                if self.direction == previous.direction {
+                    self.direction = None;
+                }
+                if self.score == previous.score {
+                    self.score = None;
+                }
+                if self.color == previous.color {
+                    self.color = None;
+                }
+                if let (Some(trail), Some(previous_trail)) = (&self.trail, &previous.trail) {
+                    if let Some(added) = trail_added_since(previous_trail, trail) {
+                        self.trail_added = if added.is_empty() { None } else { Some(added) };
+                        self.trail = None;
+                    }
+                }
+            }
+        }
+        last_events.insert(self.player_id.clone(), full);
+        self
+    }
+}
+
+// MINIMAL_EVENTS=true publishes move events as deltas against the player's
+// previous event. Wasmtime's replay carries the omitted fields forward.
+fn minimal_events() -> bool {
+    env::var("MINIMAL_EVENTS").map(|v| v == "true").unwrap_or(false)
+}
+
+// The cells `current` has beyond `previous`, when it is `previous` (perhaps
+// with its oldest cells trimmed) plus new ones at the end. None when the
+// trails don't line up, in which case the full trail has to be sent.
+fn trail_added_since(previous: &[Position], current: &[Position]) -> Option<Vec<Position>> {
+    (0..=current.len()).find_map(|added| {
+        let kept = &current[..current.len() - added];
+        if (!kept.is_empty() || previous.is_empty()) && previous.ends_with(kept) {
+            Some(current[current.len() - added..].to_vec())
+        } else {
+            None
+        }
+    })
+}
+
+// Every move event repeats the player's trail, so it is cut to the newest
+// cells to keep TxEventQ messages small
+const EVENT_TRAIL_CELLS: usize = 256;
+
+// Where TxEventQ put a published record, for matching a client-side move
+// against the topic
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+struct PublishReceipt {
+    partition: i64,
+    offset: i64,
+}
+
+// One consume call: the records, or why there were none. `empty` is a clean
+// "caught up" (TxEventQ returned []), `error` a failed call; drain loops stop
+// on either.
+#[derive(Debug, Clone, Serialize)]
+struct ConsumeResult {
+    records: Vec<serde_json::Value>,
+    empty: bool,
+    error: Option<String>,
+}
+
+impl ConsumeResult {
+    fn records(records: Vec<serde_json::Value>) -> Self {
+        let empty = records.is_empty();
+        ConsumeResult { records, empty, error: None }
+    }
+
+    fn empty() -> Self {
+        ConsumeResult { records: Vec::new(), empty: true, error: None }
+    }
+
+    fn error(error: String) -> Self {
+        ConsumeResult { records: Vec::new(), empty: false, error: Some(error) }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ConsumerInstance {
+    group: String,
+    instance_id: String,
+    created_at: u64,
+}
+
 // Application state
 type GameState = Arc<Mutex<HashMap<String, Player>>>;
 
+// Every shared map is locked through here. A request that panics while holding
+// a lock poisons it; with a bare unwrap every later request would then panic
+// on that lock too and the server would stop serving. The maps hold whole
+// players and counters, so carrying on with what the panicking request left is
+// better than refusing all traffic.
+fn lock_unpoisoned<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
 // Oracle configuration
 struct OracleConfig {
+    host: String,
     kafka_url: String,
     ords_url: String,
     username: String,
     password: String,
     topic: String,
-    host: String,
+    // Partitions new topics are created with (KAFKA_PARTITIONS, default 1)
+    partitions: usize,
     db_name: String,
+    // Schema base URL (https://{host}/ords/admin unless ORACLE_BASE_URL overrides it,
+    // e.g. to point at a local mock server)
+    base_url: String,
 }
 
 impl OracleConfig {
     fn from_env() -> Self {
         println!("🔍 Reading Oracle configuration from environment variables...");
-        let host = env::var("ORACLE_HOST").unwrap_or_else(|e| {
+        let host = secret_var("ORACLE_HOST").unwrap_or_else(|e| {
             println!("⚠️ ORACLE_HOST not found ({}), using default", e);
             "myhost.adb.region.oraclecloudapps.com".to_string()
         });
@@ -80,25 +539,167 @@ impl OracleConfig {
         let topic = env::var("KAFKA_TOPIC").unwrap_or_else(|_| "TEST_KAFKA_TOPIC_NEW".to_string());
         println!("✅ Kafka Topic from env: {}", topic);
         
+        let base_url = env::var("ORACLE_BASE_URL")
+            .map(|url| url.trim_end_matches('/').to_string())
+            .unwrap_or_else(|_| format!("https://{}/ords/admin", host));
+        
         Self {
+            host,
             kafka_url: env::var("ORACLE_KAFKA_URL").unwrap_or_else(|_| 
-                format!("https://{}/ords/admin/_/db-api/stable/database/txeventq/clusters/{}", host, db_name)
+                format!("{}/_/db-api/stable/database/txeventq/clusters/{}", base_url, db_name)
             ),
             ords_url: env::var("ORDS_URL").unwrap_or_else(|_| 
-                format!("https://{}/ords/admin/_sdw", host)
+                format!("{}/_sdw", base_url)
             ),
-            username: env::var("ORACLE_USERNAME").unwrap_or_else(|_| "ADMIN".to_string()),
-            password: env::var("ORACLE_PASSWORD").unwrap_or_else(|_| "mypassword".to_string()),
+            username: secret_var("ORACLE_USERNAME").unwrap_or_else(|_| "ADMIN".to_string()),
+            password: secret_var("ORACLE_PASSWORD").unwrap_or_else(|_| "mypassword".to_string()),
             topic,
-            host,
+            partitions: env::var("KAFKA_PARTITIONS").ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|n| *n > 0)
+                .unwrap_or(1),
             db_name,
+            base_url,
+        }
+    }
+
+    // Authorization header for every Oracle call. ORACLE_AUTH_MODE=bearer sends
+    // ORACLE_BEARER_TOKEN (for OAuth-fronted ORDS); anything else is Basic auth
+    // with the username/password.
+    fn auth_header(&self) -> String {
+        if env::var("ORACLE_AUTH_MODE").map(|mode| mode.eq_ignore_ascii_case("bearer")).unwrap_or(false) {
+            match env::var("ORACLE_BEARER_TOKEN") {
+                Ok(token) if !token.is_empty() => return format!("Bearer {}", token),
+                _ => eprintln!("⚠️ ORACLE_AUTH_MODE=bearer but ORACLE_BEARER_TOKEN is not set, falling back to Basic auth"),
+            }
+        }
+        let credentials = format!("{}:{}", self.username, self.password);
+        format!("Basic {}", BASE64.encode(credentials))
+    }
+
+    // Root of the TxEventQ REST API: {base_url}/_/db-api/stable/database/txeventq
+    fn txeventq_base_url(&self) -> String {
+        format!("{}/_/db-api/stable/database/txeventq", self.base_url)
+    }
+}
+
+// Game configuration (arena, movement, spawn), overridable via env vars
+struct GameConfig {
+    arena_width: f64,
+    arena_height: f64,
+    move_step: f64,
+    min_players: usize,
+    max_players: usize,
+    spawn_x: f64,
+    spawn_y: f64,
+    color: String,
+    // Trail cells kept per cycle, oldest dropped first; 0 keeps them all
+    trail_length: usize,
+    // Points per surviving move (SCORE_MULTIPLIER, default 1). For cross-runtime
+    // play set it to MOVE_STEP over the smallest step in the match, so points
+    // track distance: 20 here for 20px moves against wasmtime's 1 at 1-unit
+    // moves, and 20 units of travel score 20 on either runtime.
+    score_multiplier: i32,
+    // VALIDATE_MOVE_STEPS (default true): positions we didn't compute ourselves
+    // must sit one axis-aligned MOVE_STEP or less from the previous one.
+    // Arena bounds are checked either way.
+    validate_move_steps: bool,
+}
+
+impl GameConfig {
+    fn from_env() -> Self {
+        fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+            env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+        }
+
+        let arena_width = env_or("ARENA_WIDTH", 800.0);
+        let arena_height = env_or("ARENA_HEIGHT", 600.0);
+        let move_step = env_or("MOVE_STEP", 20.0);
+        // WasmEdge takes quadrant 0 unless RUNTIME_INDEX says otherwise
+        let (quadrant_x, quadrant_y) = quadrant_spawn(env_or("RUNTIME_INDEX", 0), arena_width, arena_height, move_step);
+
+        Self {
+            arena_width,
+            arena_height,
+            move_step,
+            min_players: env_or("MIN_PLAYERS", 1),
+            max_players: env_or("MAX_PLAYERS", 32),
+            spawn_x: env_or("SPAWN_X", quadrant_x),
+            spawn_y: env_or("SPAWN_Y", quadrant_y),
+            color: env_or("CASTLE_COLOR", "#9333ea".to_string()), // Purple for WasmEdge
+            trail_length: env_or("TRAIL_LENGTH", 0),
+            score_multiplier: env_or("SCORE_MULTIPLIER", 1),
+            validate_move_steps: env_or("VALIDATE_MOVE_STEPS", true),
         }
     }
 }
 
+// Runtimes sharing one arena and game_id spawn in different quadrants so their
+// players don't stack: RUNTIME_INDEX % 4 picks 0 top-left, 1 top-right,
+// 2 bottom-left, 3 bottom-right, and the spawn is the quadrant's centre,
+// snapped down onto the move_step grid. WasmEdge defaults to 0 and wasmtime to
+// 1; SPAWN_X/SPAWN_Y still override the result.
+fn quadrant_spawn(index: usize, width: f64, height: f64, step: f64) -> (f64, f64) {
+    let column = (index % 2) as f64;
+    let row = ((index % 4) / 2) as f64;
+    let snap = |v: f64| if step > 0.0 { (v / step).floor() * step } else { v.floor() };
+    (snap(width / 4.0 + column * width / 2.0), snap(height / 4.0 + row * height / 2.0))
+}
+
 // Global application state
 lazy_static::lazy_static! {
+    static ref GAME_CONFIG: GameConfig = GameConfig::from_env();
     static ref GAME_STATE: GameState = Arc::new(Mutex::new(HashMap::new()));
+    // Consumer instances this runtime has created in Oracle (for GET/DELETE /consumers)
+    static ref CONSUMER_INSTANCES: Mutex<Vec<ConsumerInstance>> = Mutex::new(Vec::new());
+    // Latest tick per game_id; bumped once for every join, move, leave and sweep
+    static ref GAME_TICKS: Mutex<HashMap<String, u64>> = Mutex::new(HashMap::new());
+    // Contents of the *_FILE secrets, read once at startup (see secret_var)
+    static ref SECRET_FILES: HashMap<&'static str, std::result::Result<String, String>> = load_secret_files();
+    // Last GameEvent seq handed out per player id
+    static ref EVENT_SEQS: Mutex<HashMap<String, u64>> = Mutex::new(HashMap::new());
+    // Full version of the last player event built per player id, which
+    // MINIMAL_EVENTS diffs move events against
+    static ref LAST_PLAYER_EVENTS: Mutex<HashMap<String, GameEvent>> = Mutex::new(HashMap::new());
+    // Woken by POST /shutdown; WASI has no signals to hook a graceful stop onto
+    static ref SHUTDOWN: tokio::sync::Notify = tokio::sync::Notify::new();
+    // Caps how many background Oracle calls (publishes, score updates) run at once
+    static ref ORACLE_PERMITS: tokio::sync::Semaphore = tokio::sync::Semaphore::new(oracle_concurrency());
+}
+
+// Event publishes spawned in the background and not yet finished; shutdown
+// waits for this to reach zero and GET /metrics reports it
+static IN_FLIGHT_EVENTS: AtomicUsize = AtomicUsize::new(0);
+// Set by POST /pause, cleared by POST /resume; moves are refused meanwhile
+static PAUSED: AtomicBool = AtomicBool::new(false);
+// Longest a graceful shutdown waits for in-flight publishes
+const SHUTDOWN_DRAIN_TIMEOUT_MS: u64 = 10000;
+
+// ORACLE_CONCURRENCY: background Oracle tasks allowed in flight at once
+// (default 16). Read once, when the semaphore is first used.
+fn oracle_concurrency() -> usize {
+    env::var("ORACLE_CONCURRENCY")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(16)
+}
+
+// Spawns a background Oracle call that waits for an ORACLE_PERMITS permit
+// first. The semaphore hands out permits in FIFO order, so tasks queued for
+// the same player still start in the order their moves were made.
+fn spawn_oracle_task<F>(task: F)
+where
+    F: std::future::Future<Output = ()> + Send + 'static,
+{
+    let request_id = REQUEST_ID.try_with(|id| id.clone()).ok();
+    tokio::spawn(async move {
+        let _permit = ORACLE_PERMITS.acquire().await;
+        match request_id {
+            Some(id) => REQUEST_ID.scope(id, task).await,
+            None => task.await,
+        }
+    });
 }
 
 // Oracle configuration helper function (reads from env each time)
@@ -106,29 +707,84 @@ fn get_oracle_config() -> OracleConfig {
     OracleConfig::from_env()
 }
 
+// Settings that can be mounted as Docker/Kubernetes secrets: when NAME_FILE is
+// set, the file's contents are used for NAME and win over the plain variable
+const SECRET_FILE_VARS: [&str; 3] = ["ORACLE_HOST", "ORACLE_USERNAME", "ORACLE_PASSWORD"];
+
+// Reads every NAME_FILE that is set. A file that can't be read is kept as an
+// Err so /ready can report it; lookups then fall back to the env var.
+fn load_secret_files() -> HashMap<&'static str, std::result::Result<String, String>> {
+    SECRET_FILE_VARS.iter()
+        .filter_map(|name| {
+            let path = env::var(format!("{}_FILE", name)).ok()?;
+            let contents = std::fs::read_to_string(&path)
+                .map(|secret| secret.trim_end_matches(['\r', '\n']).to_string())
+                .map_err(|e| format!("{}_FILE ({}) could not be read: {}", name, path, e));
+            match &contents {
+                Ok(_) => println!("🔐 {} read from {}", name, path),
+                Err(e) => eprintln!("⚠️ {}, falling back to {}", e, name),
+            }
+            Some((*name, contents))
+        })
+        .collect()
+}
+
+// The secret file's value if one was loaded, else the environment variable
+fn secret_var(name: &str) -> std::result::Result<String, env::VarError> {
+    match SECRET_FILES.get(name) {
+        Some(Ok(secret)) => Ok(secret.clone()),
+        _ => env::var(name),
+    }
+}
+
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<()> {
-    let addr = "0.0.0.0:8083";  // Different port for WasmEdge HTTPS
+    lazy_static::initialize(&SECRET_FILES);
+    let addr = match bind_address_from_env() {
+        Ok(addr) => addr,
+        Err(e) => {
+            eprintln!("❌ Invalid bind address configuration: {}", e);
+            return Err(e);
+        }
+    };
     
-    println!("🚀 WasmEdge WasiCycles Quantum Nexus listening on {}", addr);
+    println!("🚀 WasmEdge WasiCycles {} listening on {}", castle_name(), addr);
     println!("🎮 WasiCycles Game Server Endpoints:");
-    println!("  GET  /                                           - Server info");
-    println!("  GET  /health                                     - Health check");
-    println!("  POST /join                                       - Join game");
-    println!("  POST /move                                       - Move player");
-    println!("  POST /leave                                      - Leave game");
-    println!("  GET  /players                                    - Get all players");
-    println!("  GET  /leaderboard                                - Get leaderboard");
-    println!("  POST /test-kafka                                 - Test TxEventQ connectivity");
-    println!("  GET  /consume-kafka                              - Consume messages");
-    println!("  GET  /drain-messages                             - Drain all messages");
-    println!("  POST /ai-action                                  - AI decision endpoint");
+    for &(method, path, about) in ROUTES {
+        println!("  {:<48} - {}", format!("{:<4} {}", method, path), about);
+    }
     println!("");
     println!("🔐 HTTPS SUPPORT: Oracle Cloud integration enabled!");
     let oracle_config = get_oracle_config();
     println!("🏛️ Oracle Kafka: {}", oracle_config.kafka_url);
     println!("🗃️ Oracle ORDS: {}", oracle_config.ords_url);
-    println!("⚡ Quantum Nexus ready for interdimensional Snake battles!");
+    if let Some(path) = state_file_path() {
+        println!("💾 Local state file: {}", path);
+    }
+    println!("⚡ {} ready for interdimensional Snake battles!", castle_name());
+
+    if let Some(path) = state_file_path() {
+        match load_state_file(&path) {
+            Ok(loaded) => {
+                println!("💾 Loaded {} players from {}", loaded.len(), path);
+                lock_unpoisoned(&GAME_STATE).extend(loaded);
+            }
+            Err(e) => eprintln!("⚠️ Could not load state file {}: {}", path, e),
+        }
+    }
+
+    tokio::spawn(ensure_referenced_topics());
+
+    // Sweep players who closed the tab without sending /leave
+    tokio::spawn(async {
+        let timeout_ms = player_idle_timeout_ms();
+        println!("⏱️ Idle player sweep enabled (timeout {}ms)", timeout_ms);
+        let mut interval = tokio::time::interval(Duration::from_millis((timeout_ms / 4).max(1000)));
+        loop {
+            interval.tick().await;
+            expire_idle_players(timeout_ms).await;
+        }
+    });
 
     let make_svc = make_service_fn(|_conn| async {
         Ok::<_, Infallible>(service_fn(handle_request))
@@ -136,179 +792,470 @@ async fn main() -> Result<()> {
 
     let tcp_listener = TcpListener::bind(addr).await?;
     let server = Server::from_tcp(tcp_listener.into_std()?)?
-        .serve(make_svc);
+        .serve(make_svc)
+        .with_graceful_shutdown(async { SHUTDOWN.notified().await });
 
     server.await?;
+    drain_in_flight_events().await;
     Ok(())
 }
 
+// Bind address from BIND_ADDR (default 0.0.0.0) and PORT (default 8083, a
+// different port for WasmEdge HTTPS), validated before binding
+fn bind_address_from_env() -> Result<SocketAddr> {
+    let host = env::var("BIND_ADDR").unwrap_or_else(|_| "0.0.0.0".to_string());
+    let port = env::var("PORT").unwrap_or_else(|_| "8083".to_string());
+
+    let port: u16 = port.trim().parse()
+        .map_err(|e| format!("PORT '{}' is not a valid port number: {}", port, e))?;
+    let ip: IpAddr = host.trim().parse()
+        .map_err(|e| format!("BIND_ADDR '{}' is not a valid IP address: {}", host, e))?;
+
+    Ok(SocketAddr::new(ip, port))
+}
+
+// Runs the request under its X-Request-Id (see REQUEST_ID), then finishes the
+// response: request_id echoed into JSON bodies if the client sent one,
+// ?pretty=true applied, gzip last
 async fn handle_request(req: Request<Body>) -> std::result::Result<Response<Body>, Infallible> {
-    let uri = req.uri();
-    let path = uri.path();
+    let supplied_id = incoming_request_id(&req);
+    let request_id = supplied_id.clone().unwrap_or_else(generate_request_id);
+    let path = normalize_path(req.uri().path());
+    let accepts_gzip = req.headers()
+        .get("accept-encoding")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| accepts_encoding(v, "gzip"))
+        .unwrap_or(false);
+    // Only the potentially large /players, /drain-messages and export payloads
+    let compressible = req.method() == Method::GET && matches!(path, "/players" | "/drain-messages" | "/game-state/export");
+    let pretty = is_pretty_printable(path) && req.uri().query()
+        .map(|query| query.split('&').any(|pair| pair == "pretty=true"))
+        .unwrap_or(false);
+
+    let response = REQUEST_ID.scope(request_id.clone(), route_request(req)).await;
+    let response = if supplied_id.is_some() || pretty {
+        finish_json_response(response, supplied_id.as_deref(), pretty).await
+    } else {
+        response
+    };
+    let mut response = if compressible {
+        gzip_response(response, accepts_gzip).await
+    } else {
+        response
+    };
+
+    if let Ok(value) = hyper::header::HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert("x-request-id", value);
+        response.headers_mut().insert("access-control-expose-headers", hyper::header::HeaderValue::from_static("X-Request-Id"));
+    }
+    Ok(response)
+}
+
+// A client-supplied X-Request-Id, if it's short printable ASCII; anything
+// else is replaced so it can't garble the log lines it's stamped on
+fn incoming_request_id(req: &Request<Body>) -> Option<String> {
+    req.headers()
+        .get("x-request-id")
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|id| !id.is_empty() && id.len() <= 128 && id.bytes().all(|b| b.is_ascii_graphic()))
+        .map(str::to_string)
+}
+
+// 16 hex digits from a randomly keyed hasher (std's RandomState draws its keys
+// from the OS), so no rand dependency is needed
+fn generate_request_id() -> String {
+    use std::hash::{BuildHasher, Hasher};
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u64(now_ms());
+    format!("{:016x}", hasher.finish())
+}
+
+async fn route_request(req: Request<Body>) -> Response<Body> {
+    if let Some(rejection) = check_api_key(&req) {
+        return rejection;
+    }
+
+    if let Some(rejection) = check_json_content_type(&req) {
+        return rejection;
+    }
+
+    let req = match limit_body(req).await {
+        Ok(req) => req,
+        Err(rejection) => return rejection,
+    };
+
+    let path = normalize_path(req.uri().path());
     let method = req.method();
+    let wants_msgpack = accepts_msgpack(&req);
 
     let response = match (method, path) {
         (&Method::GET, "/") => server_info_response(),
-        (&Method::GET, "/health") => health_response(),
+        (&Method::GET, "/ws") => websocket_upgrade_response(req),
+        (&Method::GET, "/health") => {
+            let deep = req.uri().query()
+                .map(|query| query.split('&').any(|pair| pair == "deep=true"))
+                .unwrap_or(false);
+            health_response(deep).await
+        }
+        (&Method::GET, "/ready") => ready_response().await,
+        (&Method::GET, "/version") => version_response(),
         (&Method::POST, "/join") => join_response(req).await,
-        (&Method::POST, "/move") => move_response(req).await,
+        (&Method::POST, "/move") => move_response(req, wants_msgpack).await,
         (&Method::POST, "/leave") => leave_response(req).await,
-        (&Method::GET, "/players") => players_response(),
+        (&Method::POST, "/respawn") => respawn_response(req).await,
+        (&Method::POST, "/heartbeat") => heartbeat_response(req).await,
+        (&Method::GET, "/players") => players_response(wants_msgpack),
+        (&Method::POST, "/players/bulk") => bulk_players_response(req).await,
+        (&Method::GET, "/players/all") => all_players_response().await,
+        (&Method::GET, p) if p.starts_with("/players/") && !is_exact_route(p) => player_response(&p["/players/".len()..]),
+        (&Method::DELETE, p) if p.starts_with("/players/") && !is_exact_route(p) => kick_player_response(&req, &p["/players/".len()..]).await,
+        (&Method::GET, "/game-state/export") => export_state_response(),
+        (&Method::POST, "/game-state/import") => import_state_response(req).await,
         (&Method::GET, "/leaderboard") => leaderboard_response().await,
+        (&Method::POST, "/leaderboard/reset") => leaderboard_reset_response(&req).await,
+        (&Method::GET, "/config") => config_response(),
+        (&Method::GET, "/debug/env") => debug_env_response(&req),
+        (&Method::GET, "/metrics") => metrics_response(),
+        (&Method::POST, "/shutdown") => shutdown_response(&req),
+        (&Method::POST, "/pause") => pause_response(&req, true),
+        (&Method::POST, "/resume") => pause_response(&req, false),
         (&Method::POST, "/test-kafka") => test_kafka_response(req).await,
-        (&Method::GET, "/consume-kafka") => consume_kafka_response().await,
+        (&Method::GET, "/consume-kafka") => consume_kafka_response(&req).await,
+        (&Method::POST, "/topic/trim") => topic_trim_response(&req).await,
         (&Method::GET, "/drain-messages") => drain_messages_response().await,
+        (&Method::GET, "/ai-action") => ai_strategy_response(),
         (&Method::POST, "/ai-action") => ai_action_response(req).await,
+        (&Method::POST, "/simulate") => simulate_response(req).await,
+        (&Method::GET, "/consumers") => consumers_response(),
+        (&Method::DELETE, p) if p.starts_with("/consumers/") => delete_consumer_response(&req).await,
         (&Method::OPTIONS, _) => cors_response(),
-        _ => not_found_response(),
+        _ => match allowed_methods(path) {
+            Some(allow) => method_not_allowed_response(&allow),
+            None => not_found_response(),
+        },
     };
 
-    Ok(response)
+    response
 }
 
-fn server_info_response() -> Response<Body> {
-    let info = json!({
-        "message": "WasiCycles Quantum Nexus is running!",
-        "runtime": "wasmedge",
-        "castle": "Quantum Nexus",
-        "color": "#9333ea",
-        "endpoints": {
-            "health": "/health",
-            "join": "/join (POST)",
-            "move": "/move (POST)",
-            "leave": "/leave (POST)",
-            "players": "/players",
-            "leaderboard": "/leaderboard",
-            "test-kafka": "/test-kafka (POST)",
-            "consume-kafka": "/consume-kafka",
-            "drain-messages": "/drain-messages",
-            "ai-action": "/ai-action (POST)"
-        },
-        "integration": {
-            "kafka": "Oracle Database Kafka API",
-            "scores": "Oracle ORDS",
-            "https": "enabled"
-        },
-        "timestamp": SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs()
-    });
+// Route lookup key: one trailing slash is dropped so /players/ finds /players,
+// while "/" stays as is. Matching stays case-sensitive (/Players is a 404).
+fn normalize_path(path: &str) -> &str {
+    match path.strip_suffix('/') {
+        Some(trimmed) if !trimmed.is_empty() => trimmed,
+        _ => path,
+    }
+}
 
-    Response::builder()
+fn accepts_msgpack(req: &Request<Body>) -> bool {
+    req.headers()
+        .get("accept")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.contains("application/msgpack") || v.contains("application/x-msgpack"))
+        .unwrap_or(false)
+}
+
+// 200 response in MessagePack when the client asked for it, JSON otherwise
+// (including if msgpack encoding fails)
+fn negotiated_response(value: &serde_json::Value, wants_msgpack: bool) -> Response<Body> {
+    let builder = Response::builder()
         .status(200)
-        .header("content-type", "application/json")
         .header("Access-Control-Allow-Origin", "*")
-        .body(Body::from(info.to_string()))
-        .unwrap()
-}
+        .header("vary", "Accept");
 
-fn health_response() -> Response<Body> {
-    let players = GAME_STATE.lock().unwrap();
-    let health = json!({
-        "status": "healthy",
-        "runtime": "wasmedge",
-        "castle": "WASMEdge Cycle",
-        "service": "WasiCycles WASMEdge Cycle",
-        "version": "1.0.0",
-        "color": "#9333ea",
-        "active_players": players.len(),
-        "oracle_integration": "enabled",
-        "https_support": "enabled",
-        "timestamp": SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs()
-    });
+    if wants_msgpack {
+        match rmp_serde::to_vec_named(value) {
+            Ok(bytes) => {
+                return builder
+                    .header("content-type", "application/msgpack")
+                    .body(Body::from(bytes))
+                    .unwrap();
+            }
+            Err(e) => eprintln!("⚠️ MessagePack encoding failed, sending JSON: {}", e),
+        }
+    }
 
-    Response::builder()
-        .status(200)
+    builder
         .header("content-type", "application/json")
-        .header("Access-Control-Allow-Origin", "*")
-        .body(Body::from(health.to_string()))
+        .body(Body::from(value.to_string()))
         .unwrap()
 }
 
-async fn join_response(req: Request<Body>) -> Response<Body> {
-    let body_bytes = match hyper::body::to_bytes(req.into_body()).await {
+// State reads that accept ?pretty=true for troubleshooting with curl. The only
+// /debug route on this runtime is /debug/env.
+fn is_pretty_printable(path: &str) -> bool {
+    is_spectator_path(path)
+        || matches!(path, "/health" | "/consumers" | "/consume-kafka" | "/drain-messages" | "/debug/env")
+}
+
+// Only called when the client asked for a body change, since it buffers and
+// re-serializes the JSON: adds the client's own `request_id` to an object body
+// (unless a handler set one) and indents it when pretty is set. Runs before
+// gzip; msgpack, arrays and unparseable bodies are passed through.
+async fn finish_json_response(response: Response<Body>, request_id: Option<&str>, pretty: bool) -> Response<Body> {
+    let is_json = response.headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.contains("application/json"))
+        .unwrap_or(false);
+    if !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let body_bytes = match hyper::body::to_bytes(body).await {
         Ok(bytes) => bytes,
-        Err(_) => return error_response("Failed to read request body", 400),
+        Err(_) => return error_response("Failed to buffer response body", 500),
     };
 
-    let payload: serde_json::Value = match serde_json::from_slice(&body_bytes) {
-        Ok(payload) => payload,
-        Err(_) => return error_response("Invalid JSON", 400),
+    let mut value = match serde_json::from_slice::<serde_json::Value>(&body_bytes) {
+        Ok(value) => value,
+        Err(_) => return Response::from_parts(parts, Body::from(body_bytes)),
     };
+    if let (Some(id), Some(fields)) = (request_id, value.as_object_mut()) {
+        fields.entry("request_id").or_insert_with(|| json!(id));
+    } else if !pretty {
+        return Response::from_parts(parts, Body::from(body_bytes));
+    }
 
-    let player_id = match payload["player_id"].as_str() {
-        Some(id) => id.to_string(),
-        None => return error_response("Missing player_id", 400),
-    };
+    let serialized = if pretty { serde_json::to_vec_pretty(&value) } else { serde_json::to_vec(&value) };
+    match serialized {
+        Ok(bytes) => {
+            parts.headers.remove("content-length");
+            Response::from_parts(parts, Body::from(bytes))
+        }
+        Err(_) => Response::from_parts(parts, Body::from(body_bytes)),
+    }
+}
 
-    let new_player = Player {
-        id: player_id.clone(),
-        x: 600.0, // Different starting position for WasmEdge
-        y: 400.0,
-        direction: "left".to_string(),
-        score: 0,
-        color: "#9333ea".to_string(), // Purple for WasmEdge
-        alive: true,
-    };
+// Whether an Accept-Encoding header allows `encoding`. Entries are matched by
+// name (or "*") and refused when their q-value is 0, so "gzip;q=0" opts out; an
+// entry naming the encoding outranks "*"
+fn accepts_encoding(header: &str, encoding: &str) -> bool {
+    let mut wildcard = None;
+    for entry in header.split(',') {
+        let mut params = entry.split(';');
+        let name = params.next().unwrap_or_default().trim();
+        let q = params
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|value| value.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        if name.eq_ignore_ascii_case(encoding) {
+            return q > 0.0;
+        }
+        if name == "*" {
+            wildcard = Some(q > 0.0);
+        }
+    }
+    wildcard.unwrap_or(false)
+}
 
-    // Add player to game state
-    {
-        let mut players = GAME_STATE.lock().unwrap();
-        players.insert(player_id.clone(), new_player.clone());
+// Bodies smaller than this aren't worth the gzip overhead
+const GZIP_MIN_BYTES: usize = 1024;
+
+// Gzip-compress a response body when the client accepts it and the body is large
+// enough to benefit. Used for the potentially large /players and /drain-messages
+// payloads. (Wasmtime/waki responses are left uncompressed.)
+async fn gzip_response(response: Response<Body>, accepts_gzip: bool) -> Response<Body> {
+    if !accepts_gzip {
+        return response;
     }
 
-    // Create and publish join event to Oracle Kafka
-    let join_event = GameEvent {
-        event_type: "player_joined".to_string(),
-        player_id: player_id.clone(),
-        game_id: "wasicycles-multiplayer".to_string(),
-        runtime: "wasmedge".to_string(),
-        castle: "Quantum Nexus".to_string(),
-        timestamp: SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs(),
-        data: json!({
-            "x": new_player.x,
-            "y": new_player.y,
-            "direction": new_player.direction,
-            "color": new_player.color
-        }),
+    let (mut parts, body) = response.into_parts();
+    let body_bytes = match hyper::body::to_bytes(body).await {
+        Ok(bytes) => bytes,
+        Err(_) => return error_response("Failed to buffer response body", 500),
     };
 
-    tokio::spawn(async move {
-        if let Err(e) = publish_to_oracle_kafka(&join_event).await {
-            eprintln!("❌ Failed to publish join event: {}", e);
+    if body_bytes.len() < GZIP_MIN_BYTES {
+        return Response::from_parts(parts, Body::from(body_bytes));
+    }
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    let compressed = match encoder.write_all(&body_bytes).and_then(|_| encoder.finish()) {
+        Ok(compressed) => compressed,
+        Err(e) => {
+            eprintln!("⚠️ Gzip compression failed, sending uncompressed: {}", e);
+            return Response::from_parts(parts, Body::from(body_bytes));
         }
+    };
+
+    parts.headers.insert("content-encoding", hyper::header::HeaderValue::from_static("gzip"));
+    let vary = match parts.headers.get("vary").and_then(|v| v.to_str().ok()) {
+        Some(existing) => format!("{}, Accept-Encoding", existing),
+        None => "Accept-Encoding".to_string(),
+    };
+    if let Ok(value) = hyper::header::HeaderValue::from_str(&vary) {
+        parts.headers.insert("vary", value);
+    }
+    parts.headers.remove("content-length");
+    Response::from_parts(parts, Body::from(compressed))
+}
+
+// Built from CASTLE_NAME, CASTLE_COLOR and the arena config, with the endpoint
+// list taken from ROUTES so it can't drift from what route_request serves
+fn server_info_response() -> Response<Body> {
+    let endpoints: Vec<serde_json::Value> = ROUTES.iter()
+        .map(|&(method, path, about)| json!({"method": method, "path": path, "description": about}))
+        .collect();
+    let info = json!({
+        "message": format!("WasiCycles {} is running!", castle_name()),
+        "runtime": "wasmedge",
+        "castle": castle_name(),
+        "color": runtime_color(),
+        "theme": {
+            "castle": castle_name(),
+            "color": runtime_color(),
+            "arena": {
+                "width": GAME_CONFIG.arena_width,
+                "height": GAME_CONFIG.arena_height
+            }
+        },
+        "endpoints": endpoints,
+        "integration": {
+            "kafka": "Oracle Database Kafka API",
+            "scores": "Oracle ORDS",
+            "https": "enabled"
+        },
+        "timestamp": now_ms()
+    });
+
+    Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(Body::from(info.to_string()))
+        .unwrap()
+}
+
+// Crate version plus the commit and time build.rs stamped in
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+const GIT_COMMIT: &str = env!("GIT_COMMIT");
+const BUILD_TIMESTAMP: &str = env!("BUILD_TIMESTAMP");
+
+fn version_response() -> Response<Body> {
+    let version = json!({
+        "version": VERSION,
+        "git_commit": GIT_COMMIT,
+        "runtime": "wasmedge",
+        // Seconds since the epoch
+        "built_at": BUILD_TIMESTAMP.parse::<u64>().unwrap_or(0)
     });
 
+    Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(Body::from(version.to_string()))
+        .unwrap()
+}
+
+// Readiness, as opposed to /health's liveness: 503 until the configuration
+// holds together and, unless READY_CHECK_ORACLE=false, Oracle answers
+async fn ready_response() -> Response<Body> {
+    let mut problems = config_problems();
+    let check_oracle = env::var("READY_CHECK_ORACLE").map(|v| v != "false").unwrap_or(true);
+    if check_oracle && problems.is_empty() {
+        if let Err(e) = probe_oracle().await {
+            problems.push(format!("Oracle unreachable: {}", e));
+        }
+    }
+
+    let ready = problems.is_empty();
+    if !ready {
+        eprintln!("⚠️ Not ready: {}", problems.join("; "));
+    }
     let response = json!({
-        "status": "success",
+        "status": if ready { "ready" } else { "not_ready" },
         "runtime": "wasmedge",
-        "castle": "Quantum Nexus",
-        "message": format!("Player {} joined Quantum Nexus", player_id),
-        "player": new_player,
-        "quantum_power": "activated",
-        "oracle_integration": "active",
-        "timestamp": SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs()
+        "castle": castle_name(),
+        "oracle_checked": check_oracle,
+        "problems": problems,
+        "timestamp": now_ms()
+    });
+
+    let builder = Response::builder()
+        .status(if ready { 200 } else { 503 })
+        .header("content-type", "application/json")
+        .header("Access-Control-Allow-Origin", "*");
+    let builder = if ready {
+        builder
+    } else {
+        builder.header("Retry-After", ORACLE_RETRY_AFTER_SECS.to_string())
+    };
+    builder.body(Body::from(response.to_string())).unwrap()
+}
+
+// Settings that would leave the server unable to play or reach Oracle
+fn config_problems() -> Vec<String> {
+    let mut problems = Vec::new();
+    let oracle_config = get_oracle_config();
+    match oracle_config.base_url.parse::<Uri>() {
+        Ok(uri) if matches!(uri.scheme_str(), Some("http") | Some("https")) => {}
+        _ => problems.push(format!("ORACLE_BASE_URL is not an http(s) URL: {}", oracle_config.base_url)),
+    }
+    let bearer = env::var("ORACLE_AUTH_MODE").map(|mode| mode.eq_ignore_ascii_case("bearer")).unwrap_or(false);
+    if bearer && env::var("ORACLE_BEARER_TOKEN").map(|t| t.is_empty()).unwrap_or(true) {
+        problems.push("ORACLE_AUTH_MODE=bearer but ORACLE_BEARER_TOKEN is not set".to_string());
+    }
+    problems.extend(SECRET_FILES.values().filter_map(|contents| contents.as_ref().err().cloned()));
+    if GAME_CONFIG.arena_width <= 0.0 || GAME_CONFIG.arena_height <= 0.0 {
+        problems.push("ARENA_WIDTH and ARENA_HEIGHT must be positive".to_string());
+    }
+    if GAME_CONFIG.move_step <= 0.0 {
+        problems.push("MOVE_STEP must be positive".to_string());
+    }
+    if GAME_CONFIG.score_multiplier <= 0 {
+        problems.push("SCORE_MULTIPLIER must be at least 1".to_string());
+    }
+    if GAME_CONFIG.max_players == 0 || GAME_CONFIG.min_players > GAME_CONFIG.max_players {
+        problems.push("MIN_PLAYERS must not exceed MAX_PLAYERS, which must be at least 1".to_string());
+    }
+    problems
+}
+
+// ?deep=true also checks Oracle is reachable and answers 503 when it isn't,
+// so a load balancer can pull an instance that can't persist anything
+async fn health_response(deep: bool) -> Response<Body> {
+    let oracle = if deep {
+        match probe_oracle().await {
+            Ok(()) => Some("reachable"),
+            Err(e) => {
+                eprintln!("❌ Deep health check: Oracle unreachable: {}", e);
+                return upstream_unavailable_response("Oracle unreachable");
+            }
+        }
+    } else {
+        None
+    };
+
+    let players = lock_unpoisoned(&GAME_STATE);
+    let health = json!({
+        "status": "healthy",
+        "runtime": "wasmedge",
+        "castle": castle_name(),
+        "service": "WasiCycles WASMEdge Cycle",
+        "version": VERSION,
+        "color": runtime_color(),
+        "active_players": players.len(),
+        "oracle_integration": "enabled",
+        "oracle": oracle,
+        "paused": PAUSED.load(Ordering::SeqCst),
+        "https_support": "enabled",
+        "timestamp": now_ms()
     });
 
     Response::builder()
         .status(200)
         .header("content-type", "application/json")
         .header("Access-Control-Allow-Origin", "*")
-        .body(Body::from(response.to_string()))
+        .body(Body::from(health.to_string()))
         .unwrap()
 }
 
-async fn move_response(req: Request<Body>) -> Response<Body> {
+async fn join_response(req: Request<Body>) -> Response<Body> {
+    let delta_only = wants_delta(&req);
     let body_bytes = match hyper::body::to_bytes(req.into_body()).await {
         Ok(bytes) => bytes,
         Err(_) => return error_response("Failed to read request body", 400),
@@ -319,88 +1266,162 @@ async fn move_response(req: Request<Body>) -> Response<Body> {
         Err(_) => return error_response("Invalid JSON", 400),
     };
 
-    let player_id = match payload["player_id"].as_str() {
-        Some(id) => id.to_string(),
-        None => return error_response("Missing player_id", 400),
+    let player_id = match player_id_from_json(&payload["player_id"]) {
+        Ok(id) => id,
+        Err(message) => return error_response(message, 400),
     };
 
-    let direction = match payload["direction"].as_str() {
-        Some(dir) => dir.to_string(),
-        None => return error_response("Missing direction", 400),
+    // An explicit starting direction must be valid; without one we pick the
+    // most open heading once we can see the other cycles
+    let requested_direction = match payload["direction"].as_str() {
+        Some(dir) => match Direction::parse(dir) {
+            Some(parsed) => Some(parsed),
+            None => return error_response("Invalid direction", 400),
+        },
+        None => None,
     };
 
-    // Update player state
-    let updated_player = {
-        let mut players = GAME_STATE.lock().unwrap();
-        let player = match players.get_mut(&player_id) {
-            Some(p) => p,
-            None => return error_response("Player not found", 404),
-        };
-
-        // Update direction
-        player.direction = direction.clone();
+    let mut new_player = Player {
+        id: player_id.clone(),
+        x: GAME_CONFIG.spawn_x,
+        y: GAME_CONFIG.spawn_y,
+        direction: "left".to_string(),
+        score: 0,
+        color: GAME_CONFIG.color.clone(),
+        alive: true,
+        game_id: payload["game_id"].as_str().map(str::to_string).unwrap_or_else(default_game_id),
+        last_seen: now_ms(),
+        joined_at: now_ms(),
+        trail: Vec::new(),
+        stats: PlayerStats::default(),
+        tick: 0,
+        last_move_at: 0,
+    };
 
-        // Move player based on direction
-        match direction.as_str() {
-            "up" => player.y = (player.y - 20.0).max(0.0),
-            "down" => player.y = (player.y + 20.0).min(600.0),
-            "left" => player.x = (player.x - 20.0).max(0.0),
-            "right" => player.x = (player.x + 20.0).min(800.0),
-            _ => return error_response("Invalid direction", 400),
+    // Add player to game state, unless the arena is full. Dead cycles don't
+    // take a slot, and a player re-joining keeps theirs.
+    {
+        let mut players = lock_unpoisoned(&GAME_STATE);
+        let live_count = players.values()
+            .filter(|p| p.alive && p.id != player_id)
+            .count();
+        if live_count >= GAME_CONFIG.max_players {
+            return game_full_response(live_count, GAME_CONFIG.max_players);
         }
+        let direction = requested_direction
+            .unwrap_or_else(|| open_direction(&players, &new_player));
+        new_player.direction = direction.as_str().to_string();
+        new_player.tick = advance_tick(&players, &new_player.game_id);
+        players.insert(player_id.clone(), new_player.clone());
+        save_state_file(&players);
+    }
+
+    // Create and publish join event to Oracle Kafka
+    let join_event = GameEvent::new("player_joined", &player_id)
+        .with_player(&new_player)
+        .with_data(json!({
+            "x": new_player.x,
+            "y": new_player.y,
+            "direction": new_player.direction,
+            "color": new_player.color
+        }));
 
-        // Increment score for movement
-        player.score += 1;
+    spawn_publish(join_event);
 
-        player.clone()
+    // The starting direction is chosen server-side, so a delta join reports it too
+    let response = if delta_only {
+        let mut delta = player_delta(&new_player);
+        delta["direction"] = json!(new_player.direction);
+        delta
+    } else {
+        json!({
+            "status": "success",
+            "runtime": "wasmedge",
+            "castle": castle_name(),
+            "message": format!("Player {} joined {}", player_id, castle_name()),
+            "player": new_player,
+            "tick": new_player.tick,
+            "quantum_power": "activated",
+            "oracle_integration": "active",
+            "timestamp": now_ms()
+        })
     };
 
-    // Create and publish move event to Oracle Kafka
-    let move_event = GameEvent {
-        event_type: "player_moved".to_string(),
-        player_id: player_id.clone(),
-        game_id: "wasicycles-multiplayer".to_string(),
-        runtime: "wasmedge".to_string(),
-        castle: "Quantum Nexus".to_string(),
-        timestamp: SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs(),
-        data: json!({
-            "x": updated_player.x,
-            "y": updated_player.y,
-            "direction": updated_player.direction,
-            "score": updated_player.score
-        }),
+    Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(Body::from(response.to_string()))
+        .unwrap()
+}
+
+// Seeds many players in one call (demo setup). Each entry is checked on its
+// own and gets its own result; the join events go out as one TxEventQ batch.
+async fn bulk_players_response(req: Request<Body>) -> Response<Body> {
+    if let Some(rejection) = check_admin_token(&req) {
+        return rejection;
+    }
+
+    let body_bytes = match hyper::body::to_bytes(req.into_body()).await {
+        Ok(bytes) => bytes,
+        Err(_) => return error_response("Failed to read request body", 400),
     };
 
-    // Spawn async tasks for Oracle operations
-    let player_id_clone = player_id.clone();
-    let score = updated_player.score;
-    tokio::spawn(async move {
-        if let Err(e) = publish_to_oracle_kafka(&move_event).await {
-            eprintln!("❌ Failed to publish move event: {}", e);
-        }
-    });
+    let entries: Vec<serde_json::Value> = match serde_json::from_slice(&body_bytes) {
+        Ok(entries) => entries,
+        Err(_) => return error_response("Expected a JSON array of players", 400),
+    };
 
-    tokio::spawn(async move {
-        if let Err(e) = update_player_score_ords(&player_id_clone, score, "wasicycles-multiplayer").await {
-            eprintln!("❌ Failed to update score: {}", e);
+    let mut results = Vec::with_capacity(entries.len());
+    let mut join_events = Vec::new();
+    {
+        let mut players = lock_unpoisoned(&GAME_STATE);
+        for (index, entry) in entries.iter().enumerate() {
+            let mut player = match bulk_player_from_value(entry) {
+                Ok(player) => player,
+                Err(error) => {
+                    results.push(json!({"index": index, "player_id": entry["player_id"], "status": "failed", "error": error}));
+                    continue;
+                }
+            };
+
+            let live_count = players.values()
+                .filter(|p| p.alive && p.id != player.id)
+                .count();
+            if live_count >= GAME_CONFIG.max_players {
+                results.push(json!({"index": index, "player_id": player.id, "status": "failed", "error": "game_full"}));
+                continue;
+            }
+
+            player.tick = advance_tick(&players, &player.game_id);
+            join_events.push(GameEvent::new("player_joined", &player.id)
+                .with_player(&player)
+                .with_data(json!({
+                    "x": player.x,
+                    "y": player.y,
+                    "direction": player.direction,
+                    "color": player.color
+                })));
+            results.push(json!({"index": index, "player_id": player.id, "status": "joined", "tick": player.tick}));
+            players.insert(player.id.clone(), player);
         }
-    });
+        if !join_events.is_empty() {
+            save_state_file(&players);
+        }
+    }
+
+    let imported = join_events.len();
+    println!("📥 Bulk import: {} of {} players joined", imported, entries.len());
+    spawn_publish_batch(join_events);
 
     let response = json!({
         "status": "success",
         "runtime": "wasmedge",
-        "castle": "Quantum Nexus",
-        "message": format!("Player {} moved {} in Quantum Nexus", player_id, direction),
-        "player": updated_player,
-        "quantum_power": "flowing",
-        "oracle_integration": "active",
-        "timestamp": SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs()
+        "castle": castle_name(),
+        "imported": imported,
+        "failed": entries.len() - imported,
+        "results": results,
+        "timestamp": now_ms()
     });
 
     Response::builder()
@@ -411,88 +1432,132 @@ async fn move_response(req: Request<Body>) -> Response<Body> {
         .unwrap()
 }
 
-async fn leave_response(req: Request<Body>) -> Response<Body> {
-    let body_bytes = match hyper::body::to_bytes(req.into_body()).await {
-        Ok(bytes) => bytes,
-        Err(_) => return error_response("Failed to read request body", 400),
+// One /players/bulk entry: player_id is required; position, direction, score
+// and game_id are optional and default as they would on /join
+fn bulk_player_from_value(entry: &serde_json::Value) -> std::result::Result<Player, String> {
+    let id = player_id_from_json(&entry["player_id"])?;
+
+    let direction = match entry["direction"].as_str() {
+        Some(dir) => Direction::parse(dir).ok_or_else(|| "Invalid direction".to_string())?,
+        None => Direction::Left,
     };
 
-    let payload: serde_json::Value = match serde_json::from_slice(&body_bytes) {
-        Ok(payload) => payload,
-        Err(_) => return error_response("Invalid JSON", 400),
+    let x = entry["x"].as_f64().unwrap_or(GAME_CONFIG.spawn_x);
+    let y = entry["y"].as_f64().unwrap_or(GAME_CONFIG.spawn_y);
+    if !in_arena(Position { x, y }) {
+        return Err("Position outside the arena".to_string());
+    }
+
+    let score = match &entry["score"] {
+        serde_json::Value::Null => 0,
+        value => match value.as_i64() {
+            Some(score) if score >= 0 && score <= i32::MAX as i64 => score as i32,
+            _ => return Err("Invalid score".to_string()),
+        },
     };
 
-    let player_id = match payload["player_id"].as_str() {
-        Some(id) => id.to_string(),
-        None => return error_response("Missing player_id", 400),
+    Ok(Player {
+        id,
+        x,
+        y,
+        direction: direction.as_str().to_string(),
+        score,
+        color: GAME_CONFIG.color.clone(),
+        alive: true,
+        game_id: entry["game_id"].as_str().map(str::to_string).unwrap_or_else(default_game_id),
+        last_seen: now_ms(),
+        joined_at: now_ms(),
+        trail: Vec::new(),
+        stats: PlayerStats::default(),
+        tick: 0,
+        last_move_at: 0,
+    })
+}
+
+// Backup format shared with wasmtime's /game-state/export, so a snapshot taken
+// from either runtime can be imported into the other
+const STATE_EXPORT_FORMAT: &str = "wasicycles-game-state";
+const STATE_EXPORT_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StateExport {
+    format: String,
+    version: u32,
+    #[serde(default)]
+    runtime: String,
+    #[serde(default)]
+    exported_at: u64,
+    // GAME_ID of the exporting instance; each player still carries its own
+    #[serde(default = "default_game_id")]
+    game_id: String,
+    players: HashMap<String, Player>,
+    #[serde(default)]
+    ticks: HashMap<String, u64>,
+}
+
+fn export_state_response() -> Response<Body> {
+    let export = StateExport {
+        format: STATE_EXPORT_FORMAT.to_string(),
+        version: STATE_EXPORT_VERSION,
+        runtime: "wasmedge".to_string(),
+        exported_at: now_ms(),
+        game_id: default_game_id(),
+        players: lock_unpoisoned(&GAME_STATE).clone(),
+        ticks: lock_unpoisoned(&GAME_TICKS).clone(),
     };
 
-    // Remove player from game state
-    let removed_player = {
-        let mut players = GAME_STATE.lock().unwrap();
-        players.remove(&player_id)
+    let body = match serde_json::to_string(&export) {
+        Ok(body) => body,
+        Err(e) => return error_response(&format!("Failed to serialize game state: {}", e), 500),
     };
 
-    if let Some(player) = removed_player {
-        // Create and publish leave event to Oracle Kafka
-        let leave_event = GameEvent {
-            event_type: "player_left".to_string(),
-            player_id: player_id.clone(),
-            game_id: "wasicycles-multiplayer".to_string(),
-            runtime: "wasmedge".to_string(),
-            castle: "Quantum Nexus".to_string(),
-            timestamp: SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
-            data: json!({
-                "final_score": player.score
-            }),
-        };
+    Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .header("Content-Disposition", format!("attachment; filename=\"wasicycles-state-{}.json\"", export.exported_at))
+        .header("Access-Control-Allow-Origin", "*")
+        .body(Body::from(body))
+        .unwrap()
+}
 
-        tokio::spawn(async move {
-            if let Err(e) = publish_to_oracle_kafka(&leave_event).await {
-                eprintln!("❌ Failed to publish leave event: {}", e);
-            }
-        });
+// Replaces the whole game with an exported snapshot. Nothing changes unless
+// every player in it passes validation.
+async fn import_state_response(req: Request<Body>) -> Response<Body> {
+    if let Some(rejection) = check_admin_token(&req) {
+        return rejection;
+    }
 
-        let response = json!({
-            "status": "success",
-            "runtime": "wasmedge",
-            "castle": "Quantum Nexus",
-            "message": format!("Player {} left Quantum Nexus", player_id),
-            "final_score": player.score,
-            "quantum_status": "disconnected",
-            "timestamp": SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs()
-        });
+    let body_bytes = match hyper::body::to_bytes(req.into_body()).await {
+        Ok(bytes) => bytes,
+        Err(_) => return error_response("Failed to read request body", 400),
+    };
 
-        Response::builder()
-            .status(200)
-            .header("content-type", "application/json")
-            .header("Access-Control-Allow-Origin", "*")
-            .body(Body::from(response.to_string()))
-            .unwrap()
-    } else {
-        error_response("Player not found", 404)
+    let export: StateExport = match serde_json::from_slice(&body_bytes) {
+        Ok(export) => export,
+        Err(e) => return error_response(&format!("Invalid game state export: {}", e), 400),
+    };
+    if let Err(problem) = validate_state_export(&export) {
+        return error_response(&problem, 400);
     }
-}
 
-fn players_response() -> Response<Body> {
-    let players = GAME_STATE.lock().unwrap();
-    let players_vec: Vec<&Player> = players.values().collect();
-    
+    let imported = export.players.len();
+    {
+        let mut players = lock_unpoisoned(&GAME_STATE);
+        let mut ticks = lock_unpoisoned(&GAME_TICKS);
+        *players = export.players;
+        *ticks = export.ticks;
+        save_state_file(&players);
+    }
+    println!("📦 Imported {} players from a {} export of {}", imported, export.runtime, export.game_id);
+
     let response = json!({
+        "status": "success",
         "runtime": "wasmedge",
-        "castle": "Quantum Nexus",
-        "players": players_vec,
-        "count": players_vec.len(),
-        "timestamp": SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs()
+        "castle": castle_name(),
+        "imported": imported,
+        "source_runtime": export.runtime,
+        "exported_at": export.exported_at,
+        "timestamp": now_ms()
     });
 
     Response::builder()
@@ -503,91 +1568,1781 @@ fn players_response() -> Response<Body> {
         .unwrap()
 }
 
-async fn leaderboard_response() -> Response<Body> {
-    match get_leaderboard_ords().await {
-        Ok(leaderboard) => {
-            let response = json!({
-                "runtime": "wasmedge",
-                "castle": "Quantum Nexus",
-                "leaderboard": leaderboard,
-                "source": "Oracle ORDS",
-                "timestamp": SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs()
-            });
+fn validate_state_export(export: &StateExport) -> std::result::Result<(), String> {
+    if export.format != STATE_EXPORT_FORMAT {
+        return Err(format!("Unrecognized export format '{}'", export.format));
+    }
+    if export.version != STATE_EXPORT_VERSION {
+        return Err(format!("Unsupported export version {}", export.version));
+    }
 
-            Response::builder()
-                .status(200)
-                .header("content-type", "application/json")
+    for (key, player) in &export.players {
+        if player.id.trim().is_empty() || *key != player.id {
+            return Err(format!("Player entry '{}' does not match its id '{}'", key, player.id));
+        }
+        if Direction::parse(&player.direction).is_none() {
+            return Err(format!("Player {} has an invalid direction", player.id));
+        }
+        if player.score < 0 {
+            return Err(format!("Player {} has a negative score", player.id));
+        }
+        let head = Position { x: player.x, y: player.y };
+        if !in_arena(head) || player.trail.iter().any(|cell| !in_arena(*cell)) {
+            return Err(format!("Player {} lies outside the arena", player.id));
+        }
+        // The trail was laid one move at a time and ends a step behind the
+        // head, so every hop along it must pass the same check as a move
+        let path: Vec<Position> = player.trail.iter().copied().chain(std::iter::once(head)).collect();
+        for hop in path.windows(2) {
+            validate_move(hop[0], hop[1])
+                .map_err(|e| format!("Player {} has an impossible trail: {}", player.id, e))?;
+        }
+    }
+
+    let alive = export.players.values().filter(|p| p.alive).count();
+    if alive > GAME_CONFIG.max_players {
+        return Err(format!("Export has {} live players, more than MAX_PLAYERS ({})", alive, GAME_CONFIG.max_players));
+    }
+    Ok(())
+}
+
+// Next event sequence number for a player. Publishes run on spawned tasks and
+// can reach TxEventQ in any order; seq records the order the events were made.
+fn next_event_seq(player_id: &str) -> u64 {
+    let mut seqs = lock_unpoisoned(&EVENT_SEQS);
+    let seq = seqs.entry(player_id.to_string()).or_insert(0);
+    *seq += 1;
+    *seq
+}
+
+// Bumps and returns the game's tick. Called with GAME_STATE locked so ticks
+// follow the order changes were applied. Players restored from STATE_FILE
+// carry their last tick, which keeps the counter from restarting after a
+// restart.
+fn advance_tick(players: &HashMap<String, Player>, game_id: &str) -> u64 {
+    let seen = players.values()
+        .filter(|p| p.game_id == game_id)
+        .map(|p| p.tick)
+        .max()
+        .unwrap_or(0);
+    let mut ticks = lock_unpoisoned(&GAME_TICKS);
+    let tick = ticks.get(game_id).copied().unwrap_or(0).max(seen) + 1;
+    ticks.insert(game_id.to_string(), tick);
+    tick
+}
+
+// ?fields=delta asks /join and /move for just the player's mutable fields
+// instead of the full envelope
+fn wants_delta(req: &Request<Body>) -> bool {
+    req.uri().query()
+        .map(|query| query.split('&').any(|pair| pair == "fields=delta"))
+        .unwrap_or(false)
+}
+
+fn player_delta(player: &Player) -> serde_json::Value {
+    json!({
+        "x": player.x,
+        "y": player.y,
+        "score": player.score,
+        "alive": player.alive,
+        "tick": player.tick
+    })
+}
+
+fn game_full_response(current: usize, max: usize) -> Response<Body> {
+    error_response_with("game_full", 503, json!({
+        "current_players": current,
+        "max_players": max
+    }))
+}
+
+async fn move_response(req: Request<Body>, wants_msgpack: bool) -> Response<Body> {
+    let delta_only = wants_delta(&req);
+    let acknowledged = req.uri().query()
+        .map(|query| query.split('&').any(|pair| pair == "ack=true"))
+        .unwrap_or(false);
+    let body_bytes = match hyper::body::to_bytes(req.into_body()).await {
+        Ok(bytes) => bytes,
+        Err(_) => return error_response("Failed to read request body", 400),
+    };
+
+    let payload: serde_json::Value = match serde_json::from_slice(&body_bytes) {
+        Ok(payload) => payload,
+        Err(_) => return error_response("Invalid JSON", 400),
+    };
+
+    let player_id = match player_id_from_json(&payload["player_id"]) {
+        Ok(id) => id,
+        Err(message) => return error_response(message, 400),
+    };
+
+    let direction = match payload["direction"].as_str() {
+        Some(dir) => dir.to_string(),
+        None => return error_response("Missing direction", 400),
+    };
+
+    let (updated_player, move_event) = match apply_move(&player_id, &direction, payload["game_id"].as_str()) {
+        Ok(applied) => applied,
+        Err((message, status)) => return error_response(message, status),
+    };
+
+    // Published in the background like every other event, unless the client
+    // asked with ?ack=true to wait and learn where it landed
+    let event_offset = if acknowledged {
+        Some(publish_acknowledged(move_event).await)
+    } else {
+        spawn_publish(move_event);
+        None
+    };
+
+    let mut response = if delta_only {
+        player_delta(&updated_player)
+    } else {
+        json!({
+            "status": "success",
+            "runtime": "wasmedge",
+            "castle": castle_name(),
+            "message": format!("Player {} moved {} in {}", player_id, direction, castle_name()),
+            "player": updated_player,
+            "tick": updated_player.tick,
+            "quantum_power": "flowing",
+            "oracle_integration": "active",
+            "timestamp": now_ms()
+        })
+    };
+    if let (Some(offset), Some(fields)) = (event_offset, response.as_object_mut()) {
+        fields.insert("event_offset".to_string(), json!(offset));
+    }
+
+    negotiated_response(&response, wants_msgpack)
+}
+
+// Validates and applies one move and kicks off the ORDS score update. The
+// player_moved event is handed back for the caller to publish.
+// Shared by POST /move and the /ws socket so both paths behave identically.
+fn apply_move(player_id: &str, direction: &str, game_id: Option<&str>) -> std::result::Result<(Player, GameEvent), (&'static str, u16)> {
+    if PAUSED.load(Ordering::SeqCst) {
+        return Err(("Game is paused", 423));
+    }
+
+    let parsed_direction = match Direction::parse(direction) {
+        Some(dir) => dir,
+        None => return Err(("Invalid direction", 400)),
+    };
+
+    // Update player state
+    let (updated_player, killer) = {
+        let mut players = lock_unpoisoned(&GAME_STATE);
+        let mut player = match players.get(player_id) {
+            Some(p) => p.clone(),
+            None => return Err(("Player not found", 404)),
+        };
+
+        if !player.alive {
+            return Err(("Player has been eliminated", 400));
+        }
+
+        let cooldown = move_cooldown_ms();
+        if cooldown > 0 && now_ms().saturating_sub(player.last_move_at) < cooldown {
+            return Err(("Move cooldown active", 429));
+        }
+
+        // A cycle can't turn straight back into its own trail
+        if !allow_reverse() && Direction::parse(&player.direction) == Some(parsed_direction.opposite()) {
+            return Err(("Cannot reverse direction", 400));
+        }
+
+        // Update direction
+        player.direction = direction.to_string();
+
+        // Move player based on direction
+        let step = GAME_CONFIG.move_step;
+        let from = Position { x: player.x, y: player.y };
+        let mut to = from;
+        match parsed_direction {
+            Direction::Up => to.y = (from.y - step).max(0.0),
+            Direction::Down => to.y = (from.y + step).min(GAME_CONFIG.arena_height),
+            Direction::Left => to.x = (from.x - step).max(0.0),
+            Direction::Right => to.x = (from.x + step).min(GAME_CONFIG.arena_width),
+        }
+
+        // An explicit game_id moves the player into that game; otherwise they stay in theirs
+        if let Some(game_id) = game_id {
+            player.game_id = game_id.to_string();
+        }
+
+        // Pressed against the wall the cycle stays put, so it lays no trail there
+        let mut killer = None;
+        if to != from {
+            killer = collision_at(&players, &player, to);
+            player.lay_trail(from, GAME_CONFIG.trail_length);
+            player.x = to.x;
+            player.y = to.y;
+            player.stats.distance += step;
+        }
+
+        player.stats.moves += 1;
+        player.last_seen = now_ms();
+        player.last_move_at = player.last_seen;
+        player.stats.survival_ms = player.survival_ms();
+
+        match &killer {
+            Some(owner) => {
+                player.alive = false;
+                if owner != player_id {
+                    if let Some(other) = players.get_mut(owner) {
+                        other.stats.kills += 1;
+                    }
+                }
+            }
+            // Score for surviving the move, scaled by SCORE_MULTIPLIER
+            None => player.score += GAME_CONFIG.score_multiplier,
+        }
+
+        player.tick = advance_tick(&players, &player.game_id);
+        players.insert(player_id.to_string(), player.clone());
+        save_state_file(&players);
+        (player, killer)
+    };
+
+    // Move event for the caller to publish to Oracle Kafka
+    // The data copies of the player fields would undo MINIMAL_EVENTS' savings
+    let move_data = if minimal_events() {
+        json!({"alive": updated_player.alive})
+    } else {
+        json!({
+            "x": updated_player.x,
+            "y": updated_player.y,
+            "direction": updated_player.direction,
+            "score": updated_player.score,
+            "alive": updated_player.alive
+        })
+    };
+    let move_event = GameEvent::new("player_moved", player_id)
+        .with_player(&updated_player)
+        .with_data(move_data);
+
+    // Spawn async tasks for Oracle operations
+    let player_id_clone = player_id.to_string();
+    let score = updated_player.score;
+    let game_id = updated_player.game_id.clone();
+    let stats = updated_player.stats.clone();
+    spawn_oracle_task(async move {
+        if let Err(e) = update_player_score_ords(&player_id_clone, score, &game_id, &stats).await {
+            eprintln!("❌ Failed to update score: {}", e);
+        }
+    });
+
+    if let Some(killer) = killer {
+        println!("💥 Player {} crashed into {}'s trail", player_id, killer);
+        let eliminated_event = GameEvent::new("player_eliminated", player_id)
+            .with_player(&updated_player)
+            .with_data(json!({
+                "x": updated_player.x,
+                "y": updated_player.y,
+                "killer": killer,
+                "final_score": updated_player.score,
+                "stats": updated_player.stats
+            }));
+
+        spawn_publish(eliminated_event);
+        leaderboard_update(&updated_player, updated_player.tick, "eliminated");
+    } else if crossed_leaderboard_milestone(updated_player.score, GAME_CONFIG.score_multiplier) {
+        leaderboard_update(&updated_player, updated_player.tick, "milestone");
+    }
+
+    Ok((updated_player, move_event))
+}
+
+// Heading with the longest clear run from a new player's spawn cell before it
+// would meet a wall or another cycle in its game. Ties keep the earlier of
+// left/up/right/down.
+fn open_direction(players: &HashMap<String, Player>, player: &Player) -> Direction {
+    let step = GAME_CONFIG.move_step;
+    if step <= 0.0 {
+        return Direction::Left;
+    }
+    let others: Vec<&Player> = players.values()
+        .filter(|p| p.id != player.id && p.game_id == player.game_id)
+        .collect();
+    let occupied = |at: Position| others.iter().any(|p| {
+        p.trail.iter().any(|pos| pos.cell() == at.cell()) || (p.alive && grid_cell(p.x, p.y) == at.cell())
+    });
+
+    let clear_run = |direction: Direction| {
+        let (dx, dy) = direction.delta();
+        let mut at = Position { x: player.x, y: player.y };
+        let mut run = 0;
+        loop {
+            at = Position { x: at.x + dx * step, y: at.y + dy * step };
+            let in_arena = at.x >= 0.0 && at.y >= 0.0
+                && at.x <= GAME_CONFIG.arena_width && at.y <= GAME_CONFIG.arena_height;
+            if !in_arena || occupied(at) {
+                return run;
+            }
+            run += 1;
+        }
+    };
+
+    let mut best = Direction::Left;
+    let mut best_run = clear_run(best);
+    for direction in [Direction::Up, Direction::Right, Direction::Down] {
+        let run = clear_run(direction);
+        if run > best_run {
+            best = direction;
+            best_run = run;
+        }
+    }
+    best
+}
+
+// Id of whoever owns the cell `at` for a cycle moving there: any trail cell (its
+// own included) or the head of another live cycle in the same game
+fn collision_at(players: &HashMap<String, Player>, mover: &Player, at: Position) -> Option<String> {
+    players.values()
+        .filter(|p| p.game_id == mover.game_id)
+        .find(|p| {
+            p.trail.iter().any(|pos| pos.cell() == at.cell())
+                || (p.id != mover.id && p.alive && grid_cell(p.x, p.y) == at.cell())
+        })
+        .map(|p| p.id.clone())
+}
+
+// How often each WebSocket connection checks the shared state for changes
+const WS_DIFF_INTERVAL_MS: u64 = 100;
+
+// GET /ws: hyper answers the 101 handshake, then the upgraded connection is
+// handed to tungstenite. Clients send {"action":"move","direction":"up"} frames
+// and receive "state_diff" frames listing changed and removed players.
+fn websocket_upgrade_response(mut req: Request<Body>) -> Response<Body> {
+    let is_websocket = req.headers()
+        .get("upgrade")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.eq_ignore_ascii_case("websocket"))
+        .unwrap_or(false);
+
+    let accept_key = match req.headers().get("sec-websocket-key") {
+        Some(key) if is_websocket => derive_accept_key(key.as_bytes()),
+        _ => return error_response("Expected a WebSocket upgrade request", 400),
+    };
+
+    let player_id = req.uri().query().and_then(|query| {
+        query.split('&').find_map(|pair| pair.strip_prefix("player_id=").map(str::to_string))
+    });
+
+    tokio::spawn(async move {
+        match hyper::upgrade::on(&mut req).await {
+            Ok(upgraded) => {
+                let ws = WebSocketStream::from_raw_socket(upgraded, Role::Server, None).await;
+                websocket_session(ws, player_id).await;
+            }
+            Err(e) => eprintln!("❌ WebSocket upgrade failed: {}", e),
+        }
+    });
+
+    Response::builder()
+        .status(101)
+        .header("upgrade", "websocket")
+        .header("connection", "Upgrade")
+        .header("sec-websocket-accept", accept_key)
+        .body(Body::empty())
+        .unwrap()
+}
+
+async fn websocket_session(ws: WebSocketStream<Upgraded>, mut player_id: Option<String>) {
+    println!("🔌 WebSocket connected (player: {})", player_id.as_deref().unwrap_or("none"));
+
+    let (mut sender, mut receiver) = ws.split();
+    let mut known_players: HashMap<String, Player> = HashMap::new();
+    let mut ticker = tokio::time::interval(Duration::from_millis(WS_DIFF_INTERVAL_MS));
+
+    loop {
+        tokio::select! {
+            frame = receiver.next() => {
+                let text = match frame {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Ok(_)) => continue, // pings are answered by tungstenite; binary is ignored
+                    Some(Err(e)) => {
+                        eprintln!("❌ WebSocket error: {}", e);
+                        break;
+                    }
+                };
+
+                let reply = websocket_message_reply(&text, &mut player_id);
+                if sender.send(Message::Text(reply.to_string())).await.is_err() {
+                    break;
+                }
+            }
+            _ = ticker.tick() => {}
+        }
+
+        if let Some(diff) = state_diff(&mut known_players) {
+            if sender.send(Message::Text(diff.to_string())).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    println!("🔌 WebSocket closed (player: {})", player_id.as_deref().unwrap_or("none"));
+}
+
+fn websocket_message_reply(text: &str, player_id: &mut Option<String>) -> serde_json::Value {
+    let frame: serde_json::Value = match serde_json::from_str(text) {
+        Ok(frame) => frame,
+        Err(_) => return json!({"type": "error", "error": "Invalid JSON", "status": 400}),
+    };
+
+    // A frame may name the player, which also binds it for later frames
+    if !frame["player_id"].is_null() {
+        match player_id_from_json(&frame["player_id"]) {
+            Ok(id) => *player_id = Some(id),
+            Err(message) => return json!({"type": "error", "error": message, "status": 400}),
+        }
+    }
+
+    match frame["action"].as_str() {
+        Some("move") => {
+            let id = match player_id.as_deref() {
+                Some(id) => id,
+                None => return json!({"type": "error", "error": "Missing player_id", "status": 400}),
+            };
+            let direction = match frame["direction"].as_str() {
+                Some(dir) => dir,
+                None => return json!({"type": "error", "error": "Missing direction", "status": 400}),
+            };
+
+            match apply_move(id, direction, frame["game_id"].as_str()) {
+                Ok((player, move_event)) => {
+                    spawn_publish(move_event);
+                    json!({"type": "move_ack", "player": player, "timestamp": now_ms()})
+                }
+                Err((message, status)) => json!({"type": "error", "error": message, "status": status}),
+            }
+        }
+        _ => json!({"type": "error", "error": "Unknown action", "status": 400}),
+    }
+}
+
+// Players added or changed since the last diff sent on this connection, plus
+// ids that have since left; None when nothing changed
+fn state_diff(known_players: &mut HashMap<String, Player>) -> Option<serde_json::Value> {
+    let players = lock_unpoisoned(&GAME_STATE);
+
+    let changed: Vec<&Player> = players.values()
+        .filter(|p| known_players.get(&p.id) != Some(*p))
+        .collect();
+    let removed: Vec<&String> = known_players.keys()
+        .filter(|id| !players.contains_key(*id))
+        .collect();
+
+    if changed.is_empty() && removed.is_empty() {
+        return None;
+    }
+
+    let diff = json!({
+        "type": "state_diff",
+        "changed": changed,
+        "removed": removed,
+        "timestamp": now_ms()
+    });
+    *known_players = players.clone();
+    Some(diff)
+}
+
+async fn leave_response(req: Request<Body>) -> Response<Body> {
+    let body_bytes = match hyper::body::to_bytes(req.into_body()).await {
+        Ok(bytes) => bytes,
+        Err(_) => return error_response("Failed to read request body", 400),
+    };
+
+    let payload: serde_json::Value = match serde_json::from_slice(&body_bytes) {
+        Ok(payload) => payload,
+        Err(_) => return error_response("Invalid JSON", 400),
+    };
+
+    let player_id = match player_id_from_json(&payload["player_id"]) {
+        Ok(id) => id,
+        Err(message) => return error_response(message, 400),
+    };
+
+    // Remove player from game state
+    let removed_player = {
+        let mut players = lock_unpoisoned(&GAME_STATE);
+        let removed = players.remove(&player_id)
+            .map(|player| {
+                let tick = advance_tick(&players, &player.game_id);
+                (player, tick)
+            });
+        save_state_file(&players);
+        removed
+    };
+
+    if let Some((player, tick)) = removed_player {
+        // Create and publish leave event to Oracle Kafka
+        let leave_event = GameEvent::new("player_left", &player_id)
+            .with_player(&player)
+            .with_tick(tick)
+            .with_data(json!({
+                "final_score": player.score
+            }));
+
+        spawn_publish(leave_event);
+        leaderboard_update(&player, tick, "left");
+
+        let response = json!({
+            "status": "success",
+            "runtime": "wasmedge",
+            "castle": castle_name(),
+            "message": format!("Player {} left {}", player_id, castle_name()),
+            "final_score": player.score,
+            "tick": tick,
+            "quantum_status": "disconnected",
+            "timestamp": now_ms()
+        });
+
+        Response::builder()
+            .status(200)
+            .header("content-type", "application/json")
+            .header("Access-Control-Allow-Origin", "*")
+            .body(Body::from(response.to_string()))
+            .unwrap()
+    } else {
+        error_response("Player not found", 404)
+    }
+}
+
+// Refreshes last_seen so a player who is deliberately sitting still (watching,
+// or waiting out a pause) isn't swept by the idle timeout. Nothing moves, the
+// score stands and no tick is used; the game's current tick is reported.
+async fn heartbeat_response(req: Request<Body>) -> Response<Body> {
+    let body_bytes = match hyper::body::to_bytes(req.into_body()).await {
+        Ok(bytes) => bytes,
+        Err(_) => return error_response("Failed to read request body", 400),
+    };
+
+    let payload: serde_json::Value = match serde_json::from_slice(&body_bytes) {
+        Ok(payload) => payload,
+        Err(_) => return error_response("Invalid JSON", 400),
+    };
+
+    let player_id = match player_id_from_json(&payload["player_id"]) {
+        Ok(id) => id,
+        Err(message) => return error_response(message, 400),
+    };
+
+    let player = {
+        let mut players = lock_unpoisoned(&GAME_STATE);
+        let player = match players.get_mut(&player_id) {
+            Some(player) => {
+                player.last_seen = now_ms();
+                player.clone()
+            }
+            None => return error_response("Player not found", 404),
+        };
+        save_state_file(&players);
+        player
+    };
+    let tick = lock_unpoisoned(&GAME_TICKS)
+        .get(&player.game_id)
+        .copied()
+        .unwrap_or(player.tick);
+
+    let response = json!({
+        "status": "success",
+        "runtime": "wasmedge",
+        "castle": castle_name(),
+        "player": player,
+        "tick": tick,
+        "timestamp": now_ms()
+    });
+
+    Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(Body::from(response.to_string()))
+        .unwrap()
+}
+
+// Puts an eliminated player back in their game under the same id. The old
+// trail is cleared and they restart from the nearest free cell to the spawn
+// point; score carries over unless RESPAWN_RESETS_SCORE is set.
+async fn respawn_response(req: Request<Body>) -> Response<Body> {
+    let body_bytes = match hyper::body::to_bytes(req.into_body()).await {
+        Ok(bytes) => bytes,
+        Err(_) => return error_response("Failed to read request body", 400),
+    };
+
+    let payload: serde_json::Value = match serde_json::from_slice(&body_bytes) {
+        Ok(payload) => payload,
+        Err(_) => return error_response("Invalid JSON", 400),
+    };
+
+    let player_id = match player_id_from_json(&payload["player_id"]) {
+        Ok(id) => id,
+        Err(message) => return error_response(message, 400),
+    };
+
+    let respawned = {
+        let mut players = lock_unpoisoned(&GAME_STATE);
+        let mut player = match players.get(&player_id) {
+            Some(p) => p.clone(),
+            None => return error_response("Player not found", 404),
+        };
+        if player.alive {
+            return error_response("Player is still alive", 409);
+        }
+
+        let live_count = players.values().filter(|p| p.alive).count();
+        if live_count >= GAME_CONFIG.max_players {
+            return game_full_response(live_count, GAME_CONFIG.max_players);
+        }
+
+        // Drop the old trail first so it doesn't block the spawn search
+        player.trail.clear();
+        players.insert(player_id.clone(), player.clone());
+        let spawn = respawn_position(&players, &player);
+        player.x = spawn.x;
+        player.y = spawn.y;
+        player.alive = true;
+        if respawn_resets_score() {
+            player.score = 0;
+        }
+        player.last_seen = now_ms();
+        player.joined_at = player.last_seen;
+        player.last_move_at = 0;
+        player.direction = open_direction(&players, &player).as_str().to_string();
+        player.tick = advance_tick(&players, &player.game_id);
+        players.insert(player_id.clone(), player.clone());
+        save_state_file(&players);
+        player
+    };
+
+    println!("🔁 Player {} respawned at ({}, {})", player_id, respawned.x, respawned.y);
+    let respawn_event = GameEvent::new("player_respawned", &player_id)
+        .with_player(&respawned)
+        .with_data(json!({
+            "x": respawned.x,
+            "y": respawned.y,
+            "direction": respawned.direction,
+            "score": respawned.score
+        }));
+
+    spawn_publish(respawn_event);
+
+    let response = json!({
+        "status": "success",
+        "runtime": "wasmedge",
+        "castle": castle_name(),
+        "message": format!("Player {} respawned in {}", player_id, castle_name()),
+        "player": respawned,
+        "tick": respawned.tick,
+        "timestamp": now_ms()
+    });
+
+    Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(Body::from(response.to_string()))
+        .unwrap()
+}
+
+// The configured spawn point if nothing in the player's game covers it,
+// otherwise the closest free cell, searched in growing squares around it.
+// A completely covered arena falls back to the spawn point itself.
+fn respawn_position(players: &HashMap<String, Player>, player: &Player) -> Position {
+    let spawn = Position { x: GAME_CONFIG.spawn_x, y: GAME_CONFIG.spawn_y };
+    let step = GAME_CONFIG.move_step;
+    if step <= 0.0 {
+        return spawn;
+    }
+    let is_free = |at: Position| {
+        at.x >= 0.0 && at.y >= 0.0
+            && at.x <= GAME_CONFIG.arena_width && at.y <= GAME_CONFIG.arena_height
+            && collision_at(players, player, at).is_none()
+    };
+    if is_free(spawn) {
+        return spawn;
+    }
+
+    let max_ring = (GAME_CONFIG.arena_width.max(GAME_CONFIG.arena_height) / step).ceil() as i32;
+    for ring in 1..=max_ring {
+        for dx in -ring..=ring {
+            for dy in -ring..=ring {
+                if dx.abs() != ring && dy.abs() != ring {
+                    continue;
+                }
+                let at = Position { x: spawn.x + dx as f64 * step, y: spawn.y + dy as f64 * step };
+                if is_free(at) {
+                    return at;
+                }
+            }
+        }
+    }
+    spawn
+}
+
+async fn expire_idle_players(timeout_ms: u64) {
+    let now = now_ms();
+    let expired: Vec<(Player, u64)> = {
+        let mut players = lock_unpoisoned(&GAME_STATE);
+        let idle_ids: Vec<String> = players.values()
+            .filter(|p| now.saturating_sub(p.last_seen) > timeout_ms)
+            .map(|p| p.id.clone())
+            .collect();
+        let expired: Vec<Player> = idle_ids.iter().filter_map(|id| players.remove(id)).collect();
+        let expired: Vec<(Player, u64)> = expired.into_iter()
+            .map(|player| {
+                let tick = advance_tick(&players, &player.game_id);
+                (player, tick)
+            })
+            .collect();
+        if !expired.is_empty() {
+            save_state_file(&players);
+        }
+        expired
+    };
+
+    for (player, tick) in expired {
+        println!("⏱️ Player {} timed out after {}ms idle", player.id, now.saturating_sub(player.last_seen));
+
+        let timeout_event = GameEvent::new("player_timeout", &player.id)
+            .with_player(&player)
+            .with_tick(tick)
+            .with_data(json!({
+                "final_score": player.score,
+                "last_seen": player.last_seen,
+                "idle_timeout_ms": timeout_ms
+            }));
+
+        if let Err(e) = publish_to_oracle_kafka(&timeout_event).await {
+            eprintln!("❌ Failed to publish timeout event: {}", e);
+        }
+        leaderboard_update(&player, tick, "timeout");
+    }
+}
+
+fn players_response(wants_msgpack: bool) -> Response<Body> {
+    let players = lock_unpoisoned(&GAME_STATE);
+    let players_vec: Vec<&Player> = players.values().collect();
+    let alive: Vec<&&Player> = players_vec.iter().filter(|p| p.alive).collect();
+    let dead_count = players_vec.len() - alive.len();
+
+    // A round is over once a multi-player game is down to its last cycle
+    let game_over = players_vec.len() > 1 && alive.len() <= 1;
+    let winner = if game_over { alive.first().map(|p| p.id.clone()) } else { None };
+    
+    let response = json!({
+        "runtime": "wasmedge",
+        "castle": castle_name(),
+        "players": players_vec,
+        "count": players_vec.len(),
+        "game_id": default_game_id(),
+        "arena": {
+            "width": GAME_CONFIG.arena_width,
+            "height": GAME_CONFIG.arena_height
+        },
+        "alive_count": alive.len(),
+        "dead_count": dead_count,
+        "game_over": game_over,
+        "winner": winner,
+        "paused": PAUSED.load(Ordering::SeqCst),
+        "timestamp": now_ms()
+    });
+
+    negotiated_response(&response, wants_msgpack)
+}
+
+// PEER_URLS: comma-separated base URLs of the other castles (e.g.
+// http://localhost:8083), asked for their /players by /players/all
+fn peer_urls() -> Vec<String> {
+    env::var("PEER_URLS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|url| url.trim().trim_end_matches('/').to_string())
+        .filter(|url| !url.is_empty())
+        .collect()
+}
+
+// How long /players/all waits on each peer (PEER_TIMEOUT_MS, default 2000)
+fn peer_timeout_ms() -> u64 {
+    env::var("PEER_TIMEOUT_MS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|ms| *ms > 0)
+        .unwrap_or(2000)
+}
+
+// Every castle's players in one list, each tagged with the runtime and castle
+// it came from. Peers are asked in parallel; one that errors or doesn't answer
+// within PEER_TIMEOUT_MS is listed in peers_unreachable and the rest are
+// still returned. A player id seen more than once keeps its most recently
+// active copy.
+async fn all_players_response() -> Response<Body> {
+    let peers = peer_urls();
+    let client = create_https_client();
+    let fetches = peers.iter().map(|url| fetch_peer_players(&client, url));
+    let peer_results = futures_util::future::join_all(fetches).await;
+
+    let local: Vec<serde_json::Value> = lock_unpoisoned(&GAME_STATE)
+        .values()
+        .filter_map(|player| serde_json::to_value(player).ok())
+        .collect();
+    let mut sources = vec![("wasmedge".to_string(), castle_name(), local)];
+    let mut unreachable = Vec::new();
+    for (url, result) in peers.iter().zip(peer_results) {
+        match result {
+            Ok(source) => sources.push(source),
+            Err(e) => {
+                eprintln!("⚠️ Peer {} unreachable for /players/all: {}", url, e);
+                unreachable.push(json!({"url": url, "error": e}));
+            }
+        }
+    }
+
+    let mut merged: HashMap<String, serde_json::Value> = HashMap::new();
+    for (runtime, castle, players) in sources {
+        for mut player in players {
+            let id = match player_id_from_json(&player["id"]) {
+                Ok(id) => id,
+                Err(_) => continue,
+            };
+            player["source_runtime"] = json!(runtime);
+            player["source_castle"] = json!(castle);
+            let newer = merged.get(&id)
+                .map_or(true, |kept| player["last_seen"].as_u64() > kept["last_seen"].as_u64());
+            if newer {
+                merged.insert(id, player);
+            }
+        }
+    }
+    let mut players: Vec<serde_json::Value> = merged.into_values().collect();
+    players.sort_by(|a, b| a["id"].as_str().cmp(&b["id"].as_str()));
+
+    let response = json!({
+        "runtime": "wasmedge",
+        "castle": castle_name(),
+        "players": players,
+        "count": players.len(),
+        "peers": peers,
+        "peers_unreachable": unreachable,
+        "timestamp": now_ms()
+    });
+
+    Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(Body::from(response.to_string()))
+        .unwrap()
+}
+
+// One peer's (runtime, castle, players) from its GET /players
+async fn fetch_peer_players(
+    client: &Client<hyper_rustls::HttpsConnector<hyper::client::HttpConnector>>,
+    base_url: &str,
+) -> std::result::Result<(String, String, Vec<serde_json::Value>), String> {
+    let uri: Uri = format!("{}/players", base_url).parse().map_err(|e| format!("bad peer URL: {}", e))?;
+    let fetch = async {
+        let response = client.get(uri).await.map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("HTTP {}", response.status()));
+        }
+        let body = hyper::body::to_bytes(response.into_body()).await.map_err(|e| e.to_string())?;
+        serde_json::from_slice::<serde_json::Value>(&body).map_err(|e| format!("invalid JSON: {}", e))
+    };
+    let body = match tokio::time::timeout(Duration::from_millis(peer_timeout_ms()), fetch).await {
+        Ok(result) => result?,
+        Err(_) => return Err(format!("timed out after {}ms", peer_timeout_ms())),
+    };
+
+    let players = match body["players"].as_array() {
+        Some(players) => players.clone(),
+        None => return Err("response has no players array".to_string()),
+    };
+    let runtime = body["runtime"].as_str().unwrap_or("unknown").to_string();
+    let castle = body["castle"].as_str().unwrap_or(base_url).to_string();
+    Ok((runtime, castle, players))
+}
+
+fn player_response(player_id: &str) -> Response<Body> {
+    let player = match lock_unpoisoned(&GAME_STATE).get(player_id) {
+        Some(p) => p.clone(),
+        None => return error_response("Player not found", 404),
+    };
+
+    let response = json!({
+        "runtime": "wasmedge",
+        "castle": castle_name(),
+        "player": player,
+        "stats": {
+            "moves": player.stats.moves,
+            "distance": player.stats.distance,
+            "kills": player.stats.kills,
+            "survival_ms": player.survival_ms()
+        },
+        "timestamp": now_ms()
+    });
+
+    Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(Body::from(response.to_string()))
+        .unwrap()
+}
+
+// DELETE /players/{id}: an admin kick. Unlike /leave there is no final score
+// to keep, so the player's ORDS scores go too, and the event is player_kicked.
+async fn kick_player_response(req: &Request<Body>, player_id: &str) -> Response<Body> {
+    if let Some(rejection) = check_admin_token(req) {
+        return rejection;
+    }
+
+    let removed_player = {
+        let mut players = lock_unpoisoned(&GAME_STATE);
+        let removed = players.remove(player_id)
+            .map(|player| {
+                let tick = advance_tick(&players, &player.game_id);
+                (player, tick)
+            });
+        if removed.is_some() {
+            save_state_file(&players);
+        }
+        removed
+    };
+
+    let (player, tick) = match removed_player {
+        Some(removed) => removed,
+        None => return error_response("Player not found", 404),
+    };
+    println!("👢 Player {} kicked by an admin", player_id);
+
+    let kick_event = GameEvent::new("player_kicked", player_id)
+        .with_player(&player)
+        .with_tick(tick)
+        .with_data(json!({
+            "reason": "admin"
+        }));
+    spawn_publish(kick_event);
+
+    let ords_deleted = match delete_player_scores_ords(player_id).await {
+        Ok(deleted) => deleted,
+        Err(e) => {
+            eprintln!("❌ Failed to delete ORDS scores for kicked player {}: {}", player_id, e);
+            false
+        }
+    };
+
+    let response = json!({
+        "status": "success",
+        "runtime": "wasmedge",
+        "castle": castle_name(),
+        "message": format!("Player {} was removed from {}", player_id, castle_name()),
+        "kicked": player_id,
+        "tick": tick,
+        "ords_deleted": ords_deleted,
+        "timestamp": now_ms()
+    });
+
+    Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(Body::from(response.to_string()))
+        .unwrap()
+}
+
+async fn leaderboard_response() -> Response<Body> {
+    match get_leaderboard_ords().await {
+        Ok(leaderboard) => {
+            let response = json!({
+                "runtime": "wasmedge",
+                "castle": castle_name(),
+                "leaderboard": leaderboard,
+                "source": "Oracle ORDS",
+                "timestamp": now_ms()
+            });
+
+            Response::builder()
+                .status(200)
+                .header("content-type", "application/json")
+                .header("Access-Control-Allow-Origin", "*")
+                .body(Body::from(response.to_string()))
+                .unwrap()
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to get leaderboard: {}", e);
+            upstream_unavailable_response("Failed to get leaderboard")
+        }
+    }
+}
+
+// Prometheus text exposition, so operators can alert on a growing backlog
+fn metrics_response() -> Response<Body> {
+    let players = lock_unpoisoned(&GAME_STATE);
+    let alive = players.values().filter(|p| p.alive).count();
+    let body = format!(
+        "# HELP wasicycles_event_buffer_depth Game events waiting to be published to TxEventQ\n\
+         # TYPE wasicycles_event_buffer_depth gauge\n\
+         wasicycles_event_buffer_depth{{runtime=\"wasmedge\"}} {}\n\
+         # HELP wasicycles_players Players in the arena\n\
+         # TYPE wasicycles_players gauge\n\
+         wasicycles_players{{runtime=\"wasmedge\"}} {}\n\
+         # HELP wasicycles_players_alive Players whose cycle is still running\n\
+         # TYPE wasicycles_players_alive gauge\n\
+         wasicycles_players_alive{{runtime=\"wasmedge\"}} {}\n",
+        IN_FLIGHT_EVENTS.load(Ordering::SeqCst),
+        players.len(),
+        alive
+    );
+
+    Response::builder()
+        .status(200)
+        .header("content-type", "text/plain; version=0.0.4")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+// Admin: stop accepting connections, then exit once in-flight events are
+// published (or SHUTDOWN_DRAIN_TIMEOUT_MS passes)
+fn shutdown_response(req: &Request<Body>) -> Response<Body> {
+    if let Some(rejection) = check_admin_token(req) {
+        return rejection;
+    }
+
+    let pending = IN_FLIGHT_EVENTS.load(Ordering::SeqCst);
+    println!("🛑 Shutdown requested, {} events in flight", pending);
+    SHUTDOWN.notify_one();
+
+    let response = json!({
+        "status": "shutting_down",
+        "runtime": "wasmedge",
+        "castle": castle_name(),
+        "in_flight_events": pending,
+        "timestamp": now_ms()
+    });
+
+    Response::builder()
+        .status(202)
+        .header("content-type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(Body::from(response.to_string()))
+        .unwrap()
+}
+
+// POST /pause and /resume: freeze a match for a demo without losing any
+// state. Only moves are refused; joins, leaves and reads carry on.
+fn pause_response(req: &Request<Body>, paused: bool) -> Response<Body> {
+    if let Some(rejection) = check_admin_token(req) {
+        return rejection;
+    }
+
+    let was_paused = PAUSED.swap(paused, Ordering::SeqCst);
+    if paused && !was_paused {
+        println!("⏸️ Game paused");
+    } else if !paused && was_paused {
+        println!("▶️ Game resumed");
+    }
+
+    let response = json!({
+        "status": "success",
+        "runtime": "wasmedge",
+        "castle": castle_name(),
+        "paused": paused,
+        "changed": was_paused != paused,
+        "timestamp": now_ms()
+    });
+
+    Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(Body::from(response.to_string()))
+        .unwrap()
+}
+
+// Leaderboard reads go straight to ORDS, so clearing the scores collection is
+// all a reset needs; there is no cached copy to invalidate
+async fn leaderboard_reset_response(req: &Request<Body>) -> Response<Body> {
+    if let Some(rejection) = check_admin_token(req) {
+        return rejection;
+    }
+
+    match reset_leaderboard_ords().await {
+        Ok((status, rows_deleted)) if (200..300).contains(&status) => {
+            println!("🧹 Leaderboard reset ({} rows deleted)",
+                rows_deleted.map(|n| n.to_string()).unwrap_or_else(|| "unknown".to_string()));
+
+            let response = json!({
+                "status": "success",
+                "runtime": "wasmedge",
+                "castle": castle_name(),
+                "rows_deleted": rows_deleted,
+                "timestamp": now_ms()
+            });
+
+            Response::builder()
+                .status(200)
+                .header("content-type", "application/json")
                 .header("Access-Control-Allow-Origin", "*")
                 .body(Body::from(response.to_string()))
                 .unwrap()
         }
+        Ok((404, _)) => error_response("ORDS scores endpoint not found", 404),
+        Ok((405, _)) => error_response("ORDS doesn't allow DELETE on the scores endpoint", 501),
+        Ok((status, _)) => {
+            eprintln!("❌ Unexpected ORDS status resetting leaderboard: {}", status);
+            error_response("Failed to reset leaderboard", 502)
+        }
         Err(e) => {
-            eprintln!("❌ Failed to get leaderboard: {}", e);
-            error_response("Failed to get leaderboard", 500)
+            eprintln!("❌ Failed to reset leaderboard: {}", e);
+            upstream_unavailable_response("Failed to reset leaderboard")
+        }
+    }
+}
+
+fn config_response() -> Response<Body> {
+    let config = json!({
+        "runtime": "wasmedge",
+        "castle": castle_name(),
+        "arena": {
+            "width": GAME_CONFIG.arena_width,
+            "height": GAME_CONFIG.arena_height
+        },
+        "move_step": GAME_CONFIG.move_step,
+        "min_players": GAME_CONFIG.min_players,
+        "max_players": GAME_CONFIG.max_players,
+        "spawn": {
+            "x": GAME_CONFIG.spawn_x,
+            "y": GAME_CONFIG.spawn_y
+        },
+        "color": GAME_CONFIG.color,
+        "trail_length": GAME_CONFIG.trail_length,
+        "score_multiplier": GAME_CONFIG.score_multiplier,
+        "validate_move_steps": GAME_CONFIG.validate_move_steps,
+        "timestamp": now_ms()
+    });
+
+    Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(Body::from(config.to_string()))
+        .unwrap()
+}
+
+// GET /debug/env: every setting this process is running with, taken from
+// OracleConfig, GAME_CONFIG and the same helpers the handlers call. There is
+// no debug mode here, so ADMIN_TOKEN guards it like the other operator
+// endpoints. Secrets are never echoed, only whether they are set.
+fn debug_env_response(req: &Request<Body>) -> Response<Body> {
+    if let Some(rejection) = check_admin_token(req) {
+        return rejection;
+    }
+
+    fn secret_status(value: std::result::Result<String, env::VarError>) -> &'static str {
+        match value {
+            Ok(value) if !value.is_empty() => "set (redacted)",
+            _ => "unset",
+        }
+    }
+    let oracle_config = get_oracle_config();
+    let bearer = env::var("ORACLE_AUTH_MODE").map(|mode| mode.eq_ignore_ascii_case("bearer")).unwrap_or(false);
+    let mut secret_files: Vec<&str> = SECRET_FILES.iter()
+        .filter(|(_, contents)| contents.is_ok())
+        .map(|(name, _)| *name)
+        .collect();
+    secret_files.sort();
+
+    let config = json!({
+        "runtime": "wasmedge",
+        "castle": castle_name(),
+        "bind_addr": bind_address_from_env().map(|addr| addr.to_string()).unwrap_or_else(|e| e.to_string()),
+        "oracle": {
+            "host": oracle_config.host,
+            "db_name": oracle_config.db_name,
+            "base_url": oracle_config.base_url,
+            "ords_url": oracle_config.ords_url,
+            "kafka_url": oracle_config.kafka_url,
+            "topic": oracle_config.topic,
+            "partitions": oracle_config.partitions,
+            "username": oracle_config.username,
+            "password": if oracle_config.password.is_empty() { "unset" } else { "set (redacted)" },
+            "auth_mode": if bearer { "bearer" } else { "basic" },
+            "bearer_token": secret_status(env::var("ORACLE_BEARER_TOKEN")),
+            "from_secret_files": secret_files,
+            "concurrency": oracle_concurrency()
+        },
+        "game": {
+            "game_id": default_game_id(),
+            "arena": {
+                "width": GAME_CONFIG.arena_width,
+                "height": GAME_CONFIG.arena_height
+            },
+            "move_step": GAME_CONFIG.move_step,
+            "min_players": GAME_CONFIG.min_players,
+            "max_players": GAME_CONFIG.max_players,
+            "spawn": {
+                "x": GAME_CONFIG.spawn_x,
+                "y": GAME_CONFIG.spawn_y
+            },
+            "color": GAME_CONFIG.color,
+            "trail_length": GAME_CONFIG.trail_length,
+            "score_multiplier": GAME_CONFIG.score_multiplier,
+            "validate_move_steps": GAME_CONFIG.validate_move_steps,
+            "allow_reverse": allow_reverse(),
+            "respawn_resets_score": respawn_resets_score(),
+            "move_cooldown_ms": move_cooldown_ms(),
+            "player_idle_timeout_ms": player_idle_timeout_ms()
+        },
+        "limits": {
+            "max_body_bytes": max_body_bytes()
+        },
+        "peers": {
+            "urls": peer_urls(),
+            "timeout_ms": peer_timeout_ms()
+        },
+        "persistence": {
+            "state_file": state_file_path()
+        },
+        "leaderboard": {
+            "publish_events": publish_leaderboard_events(),
+            "event_points": leaderboard_event_points()
+        },
+        "minimal_events": minimal_events(),
+        "access": {
+            "admin_token": secret_status(env::var("ADMIN_TOKEN")),
+            "api_key": secret_status(env::var("API_KEY")),
+            "api_key_reads": env::var("API_KEY_READS").map(|v| v == "true").unwrap_or(false),
+            "spectate_token": secret_status(env::var("SPECTATE_TOKEN")),
+            "event_signing_key": secret_status(env::var("EVENT_SIGNING_KEY"))
+        },
+        "ready_check_oracle": env::var("READY_CHECK_ORACLE").map(|v| v != "false").unwrap_or(true),
+        "timestamp": now_ms()
+    });
+
+    Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(Body::from(config.to_string()))
+        .unwrap()
+}
+
+fn cors_response() -> Response<Body> {
+    Response::builder()
+        .status(204)
+        .header("Access-Control-Allow-Origin", "*")
+        .header("Access-Control-Allow-Methods", "GET, POST, DELETE, OPTIONS")
+        .header("Access-Control-Allow-Headers", "Content-Type, X-Admin-Token, X-API-Key, X-Request-Id")
+        .body(Body::empty())
+        .unwrap()
+}
+
+// Endpoints both runtimes serve, listed in 404 bodies. Error bodies use the
+// same keys in wasmtime so clients can handle either runtime the same way.
+const COMMON_ENDPOINTS: [&str; 15] = [
+    "/", "/health", "/ready", "/version", "/join", "/move", "/leave", "/respawn", "/heartbeat", "/players", "/players/{id}",
+    "/leaderboard", "/config", "/metrics", "/simulate",
+];
+
+fn not_found_response() -> Response<Body> {
+    let error = json!({
+        "error": "Endpoint not found",
+        "runtime": "wasmedge",
+        "castle": castle_name(),
+        "status": 404,
+        "available_endpoints": COMMON_ENDPOINTS,
+        "note": "Use POST for game commands; GET / lists every endpoint"
+    });
+
+    Response::builder()
+        .status(404)
+        .header("content-type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(Body::from(error.to_string()))
+        .unwrap()
+}
+
+// Every route route_request serves, as (method, path, description). `/` and
+// the startup banner list these and 405s take their Allow header from them, so
+// a new arm in route_request needs its row here. {name} stands for one path
+// segment.
+const ROUTES: &[(&str, &str, &str)] = &[
+    ("GET", "/", "Server info: castle theme and this route list"),
+    ("GET", "/ws", "WebSocket (?player_id=ID): move frames in, state diffs out"),
+    ("GET", "/health", "Health check (?deep=true checks Oracle)"),
+    ("GET", "/ready", "Config valid and Oracle reachable"),
+    ("GET", "/version", "Crate version, git commit, build time"),
+    ("POST", "/join", "Join game"),
+    ("POST", "/move", "Move player (?ack=true waits for its event_offset)"),
+    ("POST", "/leave", "Leave game"),
+    ("POST", "/respawn", "Bring an eliminated player back"),
+    ("POST", "/heartbeat", "Keep an idle player from timing out"),
+    ("GET", "/players", "Get all players"),
+    ("POST", "/players/bulk", "Seed many players at once (admin)"),
+    ("GET", "/players/all", "Players from this and every PEER_URLS castle, merged"),
+    ("GET", "/players/{id}", "Get one player with stats"),
+    ("DELETE", "/players/{id}", "Kick a player (admin)"),
+    ("GET", "/game-state/export", "Download a full game backup"),
+    ("POST", "/game-state/import", "Restore a game backup (admin)"),
+    ("GET", "/leaderboard", "Get leaderboard"),
+    ("POST", "/leaderboard/reset", "Clear ORDS scores (admin)"),
+    ("GET", "/config", "Arena and game config"),
+    ("GET", "/debug/env", "Effective configuration, secrets redacted (admin)"),
+    ("GET", "/metrics", "Prometheus metrics"),
+    ("POST", "/shutdown", "Graceful stop, draining events (admin)"),
+    ("POST", "/pause", "Freeze the game (admin)"),
+    ("POST", "/resume", "Unfreeze a paused game (admin)"),
+    ("POST", "/test-kafka", "Test TxEventQ connectivity"),
+    ("GET", "/consume-kafka", "Consume messages (?limit=N caps the batch)"),
+    ("POST", "/topic/trim", "Drop events older than ?before=MS (admin)"),
+    ("GET", "/drain-messages", "Drain all messages"),
+    ("GET", "/ai-action", "AI strategy profile"),
+    ("POST", "/ai-action", "AI decision endpoint"),
+    ("POST", "/simulate", "Headless AI-vs-AI match"),
+    ("GET", "/consumers", "List created consumer instances"),
+    ("DELETE", "/consumers/{group}/{instance}", "Delete a consumer instance (admin)"),
+];
+
+// A {name} pattern segment matches any one non-empty path segment
+fn route_matches(pattern: &str, path: &str) -> bool {
+    let mut pattern_segments = pattern.split('/');
+    let mut path_segments = path.split('/');
+    loop {
+        match (pattern_segments.next(), path_segments.next()) {
+            (None, None) => return true,
+            (Some(expected), Some(actual)) if expected.starts_with('{') && !actual.is_empty() => {}
+            (Some(expected), Some(actual)) if expected == actual => {}
+            _ => return false,
+        }
+    }
+}
+
+// A path spelled out in ROUTES, such as /players/bulk, is that route and
+// never a {param} pattern that happens to fit it
+fn is_exact_route(path: &str) -> bool {
+    ROUTES.iter().any(|&(_, pattern, _)| pattern == path)
+}
+
+// Methods ROUTES has for a path, for 405 Allow headers; None means the path
+// itself is unknown
+fn allowed_methods(path: &str) -> Option<String> {
+    let exact = is_exact_route(path);
+    let mut methods: Vec<&str> = Vec::new();
+    for &(method, pattern, _) in ROUTES {
+        let matches = if exact { pattern == path } else { route_matches(pattern, path) };
+        if matches && !methods.contains(&method) {
+            methods.push(method);
+        }
+    }
+    if methods.is_empty() {
+        None
+    } else {
+        Some(methods.join(", "))
+    }
+}
+
+fn method_not_allowed_response(allow: &str) -> Response<Body> {
+    let error = json!({
+        "error": "Method not allowed",
+        "runtime": "wasmedge",
+        "castle": castle_name(),
+        "status": 405,
+        "allow": allow
+    });
+
+    Response::builder()
+        .status(405)
+        .header("content-type", "application/json")
+        .header("Allow", allow)
+        .header("Access-Control-Allow-Origin", "*")
+        .body(Body::from(error.to_string()))
+        .unwrap()
+}
+
+// Seconds clients are told to wait before retrying after an Oracle outage
+const ORACLE_RETRY_AFTER_SECS: u64 = 5;
+
+// Oracle (ORDS or TxEventQ) couldn't be reached or failed the call. 503 with
+// Retry-After marks it as transient and upstream; 500 stays for our own bugs.
+fn upstream_unavailable_response(message: &str) -> Response<Body> {
+    let error = json!({
+        "error": message,
+        "runtime": "wasmedge",
+        "castle": castle_name(),
+        "status": 503,
+        "upstream": "oracle",
+        "retry_after": ORACLE_RETRY_AFTER_SECS
+    });
+
+    Response::builder()
+        .status(503)
+        .header("content-type", "application/json")
+        .header("Retry-After", ORACLE_RETRY_AFTER_SECS.to_string())
+        .header("Access-Control-Allow-Origin", "*")
+        .body(Body::from(error.to_string()))
+        .unwrap()
+}
+
+// Reads a player_id the way every handler should: a non-empty string is used
+// as sent and an integer becomes its decimal string, so numeric and quoted
+// ids name the same player. Booleans, floats, arrays and objects are refused.
+fn player_id_from_json(value: &serde_json::Value) -> std::result::Result<String, &'static str> {
+    match value {
+        serde_json::Value::String(id) if !id.trim().is_empty() => Ok(id.clone()),
+        serde_json::Value::String(_) | serde_json::Value::Null => Err("Missing player_id"),
+        serde_json::Value::Number(n) if n.is_i64() || n.is_u64() => Ok(n.to_string()),
+        _ => Err("player_id must be a string or an integer"),
+    }
+}
+
+fn error_response(message: &str, status: u16) -> Response<Body> {
+    error_response_with(message, status, json!({}))
+}
+
+// error_response plus rejection-specific fields (e.g. the player counts of a
+// full game) merged into the same body
+fn error_response_with(message: &str, status: u16, details: serde_json::Value) -> Response<Body> {
+    let mut error = json!({
+        "error": message,
+        "runtime": "wasmedge",
+        "castle": castle_name(),
+        "status": status
+    });
+    if let (Some(body), serde_json::Value::Object(extra)) = (error.as_object_mut(), details) {
+        body.extend(extra);
+    }
+
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(Body::from(error.to_string()))
+        .unwrap()
+}
+
+// Optional API_KEY: when set, every write (non-GET) request needs a matching
+// X-API-Key header; reads are only gated too with API_KEY_READS=true. The
+// GET /ws upgrade counts as a write, since its socket carries move frames.
+// /health and CORS preflights are always open. With gated reads, SPECTATE_TOKEN
+// can be sent as the key instead to read the spectator views (see
+// is_spectator_path), never to write.
+fn check_api_key(req: &Request<Body>) -> Option<Response<Body>> {
+    let expected = match env::var("API_KEY") {
+        Ok(key) if !key.is_empty() => key,
+        _ => return None,
+    };
+
+    let method = req.method();
+    let path = normalize_path(req.uri().path());
+    if method == Method::OPTIONS || path == "/health" {
+        return None;
+    }
+
+    let is_read = (method == Method::GET || method == Method::HEAD) && path != "/ws";
+    let gate_reads = env::var("API_KEY_READS").map(|v| v == "true").unwrap_or(false);
+    if is_read && !gate_reads {
+        return None;
+    }
+
+    let provided = req.headers()
+        .get("x-api-key")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+
+    let spectating = is_read
+        && is_spectator_path(path)
+        && env::var("SPECTATE_TOKEN").map(|token| !token.is_empty() && provided == token).unwrap_or(false);
+
+    if provided == expected || spectating {
+        None
+    } else {
+        Some(error_response("Invalid or missing X-API-Key", 401))
+    }
+}
+
+// What a read-only dashboard needs: player state, scores and arena config
+fn is_spectator_path(path: &str) -> bool {
+    matches!(path, "/players" | "/leaderboard" | "/config") || path.starts_with("/players/")
+}
+
+// Write endpoints only speak JSON. A missing Content-Type is tolerated (the
+// handler's parse decides), as is an empty body, but anything else is a 415.
+fn check_json_content_type(req: &Request<Body>) -> Option<Response<Body>> {
+    if !matches!(*req.method(), Method::POST | Method::PUT | Method::PATCH) {
+        return None;
+    }
+
+    let is_empty = req.headers()
+        .get("content-length")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim() == "0")
+        .unwrap_or(false);
+    let content_type = match req.headers().get("content-type").and_then(|v| v.to_str().ok()) {
+        Some(ct) if !is_empty => ct,
+        _ => return None,
+    };
+
+    if is_json_media_type(content_type) {
+        None
+    } else {
+        Some(error_response(
+            &format!("Unsupported Content-Type '{}': send the request body as application/json", content_type),
+            415,
+        ))
+    }
+}
+
+// MAX_BODY_BYTES: largest request body accepted (default 64 KiB)
+fn max_body_bytes() -> usize {
+    env::var("MAX_BODY_BYTES")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(64 * 1024)
+}
+
+// Buffers the body of a write request up to MAX_BODY_BYTES, answering 413 as
+// soon as the declared Content-Length or the bytes received pass it, so no
+// handler's to_bytes can be made to swallow an unbounded upload. GET and
+// friends pass through untouched (the /ws upgrade needs its original request).
+async fn limit_body(req: Request<Body>) -> std::result::Result<Request<Body>, Response<Body>> {
+    if !matches!(*req.method(), Method::POST | Method::PUT | Method::PATCH | Method::DELETE) {
+        return Ok(req);
+    }
+
+    let limit = max_body_bytes();
+    let too_large = || error_response(&format!("Request body exceeds MAX_BODY_BYTES ({} bytes)", limit), 413);
+    let declared = req.headers()
+        .get("content-length")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<usize>().ok());
+    if let Some(length) = declared.filter(|&length| length > limit) {
+        eprintln!("⚠️ Rejecting {} byte body for {}", length, req.uri().path());
+        return Err(too_large());
+    }
+
+    let (parts, mut body) = req.into_parts();
+    let mut buffered = Vec::new();
+    while let Some(chunk) = hyper::body::HttpBody::data(&mut body).await {
+        let chunk = chunk.map_err(|_| error_response("Failed to read request body", 400))?;
+        if buffered.len() + chunk.len() > limit {
+            return Err(too_large());
+        }
+        buffered.extend_from_slice(&chunk);
+    }
+    Ok(Request::from_parts(parts, Body::from(buffered)))
+}
+
+// application/json, or a +json suffix type, with any parameters (charset etc.) ignored
+fn is_json_media_type(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+    essence == "application/json" || essence.ends_with("+json")
+}
+
+// Admin-only endpoints require ADMIN_TOKEN to be configured and sent as X-Admin-Token.
+// Returns the rejection response when the request isn't authorized.
+fn check_admin_token(req: &Request<Body>) -> Option<Response<Body>> {
+    let expected = match env::var("ADMIN_TOKEN") {
+        Ok(token) if !token.is_empty() => token,
+        _ => return Some(error_response("Admin endpoints are disabled (ADMIN_TOKEN not set)", 403)),
+    };
+
+    let provided = req.headers()
+        .get("x-admin-token")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+
+    if provided == expected {
+        None
+    } else {
+        Some(error_response("Invalid or missing X-Admin-Token", 401))
+    }
+}
+
+fn consumers_response() -> Response<Body> {
+    let consumers = lock_unpoisoned(&CONSUMER_INSTANCES).clone();
+
+    let response = json!({
+        "runtime": "wasmedge",
+        "castle": castle_name(),
+        "consumers": consumers,
+        "count": consumers.len(),
+        "timestamp": now_ms()
+    });
+
+    Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(Body::from(response.to_string()))
+        .unwrap()
+}
+
+async fn delete_consumer_response(req: &Request<Body>) -> Response<Body> {
+    if let Some(rejection) = check_admin_token(req) {
+        return rejection;
+    }
+
+    // Expected path: /consumers/{group}/{instance}
+    let segments: Vec<&str> = req.uri().path()
+        .trim_start_matches("/consumers/")
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect();
+    let (group, instance_id) = match segments.as_slice() {
+        [group, instance_id] => (group.to_string(), instance_id.to_string()),
+        _ => return error_response("Expected /consumers/{group}/{instance}", 400),
+    };
+
+    match delete_oracle_consumer(&group, &instance_id).await {
+        Ok(found) => {
+            lock_unpoisoned(&CONSUMER_INSTANCES)
+                .retain(|c| !(c.group == group && c.instance_id == instance_id));
+
+            if !found {
+                return error_response("Consumer instance not found in Oracle", 404);
+            }
+
+            let response = json!({
+                "status": "success",
+                "runtime": "wasmedge",
+                "castle": castle_name(),
+                "deleted": {
+                    "group": group,
+                    "instance_id": instance_id
+                },
+                "timestamp": now_ms()
+            });
+
+            Response::builder()
+                .status(200)
+                .header("content-type", "application/json")
+                .header("Access-Control-Allow-Origin", "*")
+                .body(Body::from(response.to_string()))
+                .unwrap()
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to delete consumer {}/{}: {}", group, instance_id, e);
+            error_response("Failed to delete consumer instance", 502)
         }
     }
 }
 
-fn cors_response() -> Response<Body> {
-    Response::builder()
-        .status(204)
-        .header("Access-Control-Allow-Origin", "*")
-        .header("Access-Control-Allow-Methods", "GET, POST, OPTIONS")
-        .header("Access-Control-Allow-Headers", "Content-Type")
-        .body(Body::empty())
-        .unwrap()
+// Deletes every document in the ORDS scores collection (q={} matches all).
+// Returns the ORDS status and, when reported, the number of rows deleted.
+async fn reset_leaderboard_ords() -> Result<(u16, Option<u64>)> {
+    let oracle_config = get_oracle_config();
+    let auth_header = oracle_config.auth_header();
+
+    let url = format!("{}/scores/?q=%7B%7D", oracle_config.ords_url);
+    let uri: Uri = url.parse()?;
+    let client = create_https_client();
+
+    let req = Request::builder()
+        .method(Method::DELETE)
+        .uri(uri)
+        .header("Accept", "application/json")
+        .header("Authorization", &auth_header)
+        .body(Body::empty())?;
+
+    let response = client.request(req).await?;
+    let status = response.status().as_u16();
+
+    let body_bytes = hyper::body::to_bytes(response.into_body()).await?;
+    let rows_deleted = serde_json::from_slice::<serde_json::Value>(&body_bytes)
+        .ok()
+        .and_then(|v| v["rowsDeleted"].as_u64());
+
+    Ok((status, rows_deleted))
+}
+
+// Deletes one player's documents from the ORDS scores collection with a
+// {"player_id": ...} filter. Ok(false) when ORDS refused the delete.
+async fn delete_player_scores_ords(player_id: &str) -> Result<bool> {
+    let oracle_config = get_oracle_config();
+    let auth_header = oracle_config.auth_header();
+
+    let filter = json!({ "player_id": player_id }).to_string();
+    let url = format!("{}/scores/?q={}", oracle_config.ords_url, percent_encode(&filter));
+    let uri: Uri = url.parse()?;
+    let client = create_https_client();
+
+    let req = Request::builder()
+        .method(Method::DELETE)
+        .uri(uri)
+        .header("Accept", "application/json")
+        .header("Authorization", &auth_header)
+        .body(Body::empty())?;
+
+    let response = client.request(req).await?;
+    let status = response.status();
+    if !status.is_success() {
+        eprintln!("❌ ORDS refused to delete scores for {}: {}", player_id, status);
+    }
+    Ok(status.is_success())
+}
+
+// Percent-encodes everything but RFC 3986 unreserved characters, for values
+// placed in a query string
+fn percent_encode(value: &str) -> String {
+    value.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
 }
 
-fn not_found_response() -> Response<Body> {
-    let error = json!({
-        "error": "Endpoint not found",
-        "runtime": "wasmedge",
-        "castle": "Quantum Nexus",
-        "available_endpoints": ["/", "/health", "/join", "/move", "/leave", "/players", "/leaderboard"],
-        "note": "Use POST for game commands"
-    });
+// Delete a consumer instance in Oracle. Ok(false) means Oracle didn't know it.
+async fn delete_oracle_consumer(group: &str, instance_id: &str) -> Result<bool> {
+    let oracle_config = get_oracle_config();
+    let auth_header = oracle_config.auth_header();
 
-    Response::builder()
-        .status(404)
-        .header("content-type", "application/json")
-        .header("Access-Control-Allow-Origin", "*")
-        .body(Body::from(error.to_string()))
-        .unwrap()
-}
+    let url = format!("{}/consumers/{}/instances/{}",
+        oracle_config.txeventq_base_url(), group, instance_id);
+    let uri: Uri = url.parse()?;
+    let client = create_https_client();
 
-fn error_response(message: &str, status: u16) -> Response<Body> {
-    let error = json!({
-        "error": message,
-        "runtime": "wasmedge",
-        "castle": "Quantum Nexus",
-        "status": status
-    });
+    let req = Request::builder()
+        .method(Method::DELETE)
+        .uri(uri)
+        .header("Accept", "application/json")
+        .header("Authorization", &auth_header)
+        .body(Body::empty())?;
 
-    Response::builder()
-        .status(status)
-        .header("content-type", "application/json")
-        .header("Access-Control-Allow-Origin", "*")
-        .body(Body::from(error.to_string()))
-        .unwrap()
+    let response = client.request(req).await?;
+    let status = response.status();
+
+    if status.is_success() {
+        println!("✅ Deleted consumer instance {}/{}", group, instance_id);
+        Ok(true)
+    } else if status == 404 {
+        println!("ℹ️ Consumer instance {}/{} not found in Oracle", group, instance_id);
+        Ok(false)
+    } else {
+        let body_bytes = hyper::body::to_bytes(response.into_body()).await?;
+        Err(format!("Oracle consumer delete error: {} - {}", status, String::from_utf8_lossy(&body_bytes)).into())
+    }
 }
 
 // Oracle TxEventQ topic creation
 async fn create_txeventq_topic(topic_name: &str) -> Result<bool> {
     let oracle_config = get_oracle_config();
-    let auth = format!("{}:{}", oracle_config.username, oracle_config.password);
-    let auth_header = format!("Basic {}", BASE64.encode(auth));
+    let auth_header = oracle_config.auth_header();
 
     // Topic creation endpoint: /{schema}/_/db-api/stable/database/txeventq/clusters/{cluster}/topics
     let cluster_name = &oracle_config.db_name;
-    let base_url = format!("https://{}/ords/admin", oracle_config.host);
-    let url = format!("{}/_/db-api/stable/database/txeventq/clusters/{}/topics", base_url, cluster_name);
+    let url = format!("{}/clusters/{}/topics", oracle_config.txeventq_base_url(), cluster_name);
 
     let topic_payload = json!({
         "topic_name": topic_name,
-        "partitions_count": "1"
+        "partitions_count": oracle_config.partitions.to_string()
     });
 
     let uri: Uri = url.parse()?;
@@ -609,7 +3364,7 @@ async fn create_txeventq_topic(topic_name: &str) -> Result<bool> {
     if status.is_success() {
         println!("✅ TxEventQ topic '{}' created successfully from WasmEdge: {}", topic_name, response_text);
         Ok(true)
-    } else if status == 400 && response_text.to_lowercase().contains("already exists") {
+    } else if status == 409 || (status == 400 && response_text.to_lowercase().contains("already exists")) {
         println!("ℹ️ Topic '{}' already exists - WasmEdge", topic_name);
         Ok(true)
     } else {
@@ -618,33 +3373,182 @@ async fn create_txeventq_topic(topic_name: &str) -> Result<bool> {
     }
 }
 
-// Oracle Kafka integration
-async fn publish_to_oracle_kafka(event: &GameEvent) -> Result<()> {
+// A missing topic (404) counts as deleted
+async fn delete_txeventq_topic(topic_name: &str) -> Result<()> {
+    let oracle_config = get_oracle_config();
+    let url = format!("{}/clusters/{}/topics/{}",
+        oracle_config.txeventq_base_url(), oracle_config.db_name, topic_name);
+
+    let req = Request::builder()
+        .method("DELETE")
+        .uri(url.parse::<Uri>()?)
+        .header("Accept", "application/json")
+        .header("Authorization", oracle_config.auth_header())
+        .body(Body::empty())?;
+
+    let response = create_https_client().request(req).await?;
+    let status = response.status();
+    if status.is_success() || status == 404 {
+        println!("🗑️ TxEventQ topic '{}' deleted", topic_name);
+        Ok(())
+    } else {
+        let body_bytes = hyper::body::to_bytes(response.into_body()).await?;
+        Err(format!("{} - {}", status, String::from_utf8_lossy(&body_bytes)).into())
+    }
+}
+
+// Leaderboard stream, alongside the game events topic
+const LEADERBOARD_TOPIC: &str = "WASICYCLES_LEADERBOARD";
+
+// PUBLISH_LEADERBOARD_EVENTS=true streams score changes to LEADERBOARD_TOPIC
+// for event-driven leaderboards; ORDS stays the source for GET /leaderboard
+fn publish_leaderboard_events() -> bool {
+    env::var("PUBLISH_LEADERBOARD_EVENTS").map(|v| v == "true").unwrap_or(false)
+}
+
+// Whether the last gain (`gained` points) took the score over a multiple of
+// LEADERBOARD_EVENT_POINTS. A plain `score % points == 0` would miss every
+// milestone a SCORE_MULTIPLIER above 1 steps over.
+fn crossed_leaderboard_milestone(score: i32, gained: i32) -> bool {
+    let points = leaderboard_event_points();
+    score > 0 && score / points > (score - gained).max(0) / points
+}
+
+// A living player's score is streamed every this many points
+// (LEADERBOARD_EVENT_POINTS); deaths and departures are always sent
+fn leaderboard_event_points() -> i32 {
+    env::var("LEADERBOARD_EVENT_POINTS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|points| *points > 0)
+        .unwrap_or(10)
+}
+
+fn leaderboard_update(player: &Player, tick: u64, reason: &str) {
+    if !publish_leaderboard_events() {
+        return;
+    }
+    let event = GameEvent::new("leaderboard_update", &player.id)
+        .with_game_id(&player.game_id)
+        .with_score(player.score)
+        .with_tick(tick)
+        .with_data(json!({
+            "score": player.score,
+            "reason": reason,
+            "alive": player.alive
+        }));
+    spawn_publish_to(LEADERBOARD_TOPIC.to_string(), event);
+}
+
+// Created in the background at startup so the first publish or consume
+// against any of them doesn't fail on a fresh database
+async fn ensure_referenced_topics() {
+    let topics = [get_oracle_config().topic, LEADERBOARD_TOPIC.to_string()];
+    for topic in topics {
+        match create_txeventq_topic(&topic).await {
+            Ok(true) => {}
+            Ok(false) => eprintln!("⚠️ Topic {} could not be created at startup", topic),
+            Err(e) => eprintln!("⚠️ Topic {} could not be created at startup: {}", topic, e),
+        }
+    }
+}
+
+// Publishes in the background, counted in IN_FLIGHT_EVENTS until done
+fn spawn_publish(event: GameEvent) {
+    spawn_publish_to(get_oracle_config().topic, event);
+}
+
+fn spawn_publish_to(topic: String, event: GameEvent) {
+    IN_FLIGHT_EVENTS.fetch_add(1, Ordering::SeqCst);
+    spawn_oracle_task(async move {
+        if let Err(e) = publish_to_topic(&topic, &event).await {
+            eprintln!("❌ Failed to publish {} event: {}", event.event_type, e);
+        }
+        IN_FLIGHT_EVENTS.fetch_sub(1, Ordering::SeqCst);
+    });
+}
+
+// Like spawn_publish, but waits for the event to land and returns its topic
+// position (None if Oracle refused it or gave none). The publish is still a
+// counted background task under ORACLE_PERMITS, so it keeps to the
+// concurrency limit and shutdown waits for it even if the client hangs up.
+async fn publish_acknowledged(event: GameEvent) -> Option<PublishReceipt> {
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    IN_FLIGHT_EVENTS.fetch_add(1, Ordering::SeqCst);
+    spawn_oracle_task(async move {
+        let receipt = match publish_to_oracle_kafka(&event).await {
+            Ok(receipt) => receipt,
+            Err(e) => {
+                eprintln!("❌ Failed to publish {} event: {}", event.event_type, e);
+                None
+            }
+        };
+        IN_FLIGHT_EVENTS.fetch_sub(1, Ordering::SeqCst);
+        let _ = sender.send(receipt);
+    });
+    receiver.await.ok().flatten()
+}
+
+// Like spawn_publish, but sends all the events as records of a single produce
+// call; each one still counts towards IN_FLIGHT_EVENTS until it lands
+fn spawn_publish_batch(events: Vec<GameEvent>) {
+    if events.is_empty() {
+        return;
+    }
+    IN_FLIGHT_EVENTS.fetch_add(events.len(), Ordering::SeqCst);
+    spawn_oracle_task(async move {
+        if let Err(e) = publish_batch_to_oracle_kafka(&events).await {
+            eprintln!("❌ Failed to publish batch of {} events: {}", events.len(), e);
+        }
+        IN_FLIGHT_EVENTS.fetch_sub(events.len(), Ordering::SeqCst);
+    });
+}
+
+// After the server stops accepting requests, give background publishes a
+// bounded window to land before the process exits
+async fn drain_in_flight_events() {
+    let deadline = now_ms() + SHUTDOWN_DRAIN_TIMEOUT_MS;
+    loop {
+        let pending = IN_FLIGHT_EVENTS.load(Ordering::SeqCst);
+        if pending == 0 {
+            println!("✅ All events published, shutting down");
+            return;
+        }
+        if now_ms() >= deadline {
+            eprintln!("⚠️ Shutting down with {} events still unpublished", pending);
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+// Oracle Kafka integration. Returns the record's partition/offset when
+// TxEventQ reports one.
+async fn publish_to_oracle_kafka(event: &GameEvent) -> Result<Option<PublishReceipt>> {
+    publish_to_topic(&get_oracle_config().topic, event).await
+}
+
+async fn publish_to_topic(topic: &str, event: &GameEvent) -> Result<Option<PublishReceipt>> {
     let oracle_config = get_oracle_config();
     // Ensure topic exists (create if needed)
-    if let Err(e) = create_txeventq_topic(&oracle_config.topic).await {
+    if let Err(e) = create_txeventq_topic(topic).await {
         eprintln!("⚠️ Topic creation failed, attempting to publish anyway: {}", e);
     }
 
     // Oracle TxEventQ REST API format (key difference: value must be JSON string, not object)
     let kafka_payload = json!({
         "records": [{
-            "key": format!("wasmedge-{}", SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs()),
-            "value": serde_json::to_string(event)?  // JSON string, not object!
+            "key": format!("wasmedge-{}", now_ms()),
+            "value": event_payload(event)?  // JSON string, not object!
         }]
     });
 
-    let auth = format!("{}:{}", oracle_config.username, oracle_config.password);
-    let auth_header = format!("Basic {}", BASE64.encode(auth));
+    let auth_header = oracle_config.auth_header();
 
     // Use correct TxEventQ endpoint format (base URL, not cluster URL)
-    let base_url = format!("https://{}/ords/admin", oracle_config.host);
-    let url = format!("{}/_/db-api/stable/database/txeventq/topics/{}", 
-        base_url, 
-        oracle_config.topic
+    let url = format!("{}/topics/{}", 
+        oracle_config.txeventq_base_url(), 
+        topic
     );
 
     let uri: Uri = url.parse()?;
@@ -665,14 +3569,202 @@ async fn publish_to_oracle_kafka(event: &GameEvent) -> Result<()> {
         let response_text = String::from_utf8(body_bytes.to_vec())?;
         println!("✅ TxEventQ message published from WasmEdge: {} for player {} - Response: {}", 
                 event.event_type, event.player_id, response_text);
+        let receipt = parse_publish_receipt(&response_text);
+        match receipt {
+            Some(r) => println!("🔍 {} for player {} at partition {} offset {}",
+                    event.event_type, event.player_id, r.partition, r.offset),
+            None => println!("🔍 {} for player {}: no offset in TxEventQ response",
+                    event.event_type, event.player_id),
+        }
+        Ok(receipt)
     } else {
         let status = response.status();
         let body_bytes = hyper::body::to_bytes(response.into_body()).await?;
         let error_text = String::from_utf8(body_bytes.to_vec())?;
         eprintln!("❌ Oracle TxEventQ error from WasmEdge: {} - {}", status, error_text);
+        Ok(None)
     }
+}
 
-    Ok(())
+async fn publish_batch_to_oracle_kafka(events: &[GameEvent]) -> Result<()> {
+    let payloads = events.iter()
+        .map(event_payload)
+        .collect::<serde_json::Result<Vec<_>>>()?;
+    publish_payloads_to_oracle_kafka(&payloads).await
+}
+
+// Produces already-serialized event values in one call, byte for byte, so
+// events from the other runtime (or already signed ones) go back unchanged
+async fn publish_payloads_to_oracle_kafka(payloads: &[String]) -> Result<()> {
+    let oracle_config = get_oracle_config();
+    if let Err(e) = create_txeventq_topic(&oracle_config.topic).await {
+        eprintln!("⚠️ Topic creation failed, attempting to publish anyway: {}", e);
+    }
+
+    let records: Vec<serde_json::Value> = payloads.iter()
+        .enumerate()
+        .map(|(i, payload)| json!({
+            "key": format!("wasmedge-{}-{}", now_ms(), i),
+            "value": payload
+        }))
+        .collect();
+    let kafka_payload = json!({ "records": records });
+
+    let url = format!("{}/topics/{}", oracle_config.txeventq_base_url(), oracle_config.topic);
+    let req = Request::builder()
+        .method("POST")
+        .uri(url.parse::<Uri>()?)
+        .header("Content-Type", "application/json")
+        .header("Accept", "application/json")
+        .header("Authorization", oracle_config.auth_header())
+        .body(Body::from(kafka_payload.to_string()))?;
+
+    let response = create_https_client().request(req).await?;
+    let status = response.status();
+    let body_bytes = hyper::body::to_bytes(response.into_body()).await?;
+    if status.is_success() {
+        println!("✅ TxEventQ batch of {} events published from WasmEdge", payloads.len());
+        Ok(())
+    } else {
+        Err(format!("Oracle TxEventQ batch error: {} - {}", status, String::from_utf8_lossy(&body_bytes)).into())
+    }
+}
+
+// TxEventQ answers a produce call Kafka REST style:
+// {"offsets":[{"partition":0,"offset":42}]}. We publish one record per call,
+// so the first entry is ours. Offsets may come back as strings.
+fn parse_publish_receipt(response_text: &str) -> Option<PublishReceipt> {
+    let body: serde_json::Value = serde_json::from_str(response_text).ok()?;
+    let entry = body["offsets"].get(0)?;
+    let as_i64 = |v: &serde_json::Value| v.as_i64().or_else(|| v.as_str()?.parse().ok());
+    Some(PublishReceipt {
+        partition: as_i64(&entry["partition"])?,
+        offset: as_i64(&entry["offset"])?,
+    })
+}
+
+// EVENT_SIGNING_KEY enables HMAC-SHA256 signatures on published events; with
+// it set, consumed events without a valid signature are dropped, so another
+// publisher on the shared topic can't inject moves
+fn event_signing_key() -> Option<String> {
+    env::var("EVENT_SIGNING_KEY").ok().filter(|key| !key.is_empty())
+}
+
+// HMAC over the event's JSON minus any "signature" key. serde_json writes
+// object keys sorted, so wasmtime computes the same bytes for the same event.
+fn event_signature(key: &str, event: &serde_json::Value) -> String {
+    let mut unsigned = event.clone();
+    if let Some(fields) = unsigned.as_object_mut() {
+        fields.remove("signature");
+    }
+    hmac_sha256(key.as_bytes(), unsigned.to_string().as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// The record value published for an event, signed when a key is configured
+fn event_payload(event: &GameEvent) -> serde_json::Result<String> {
+    let mut value = serde_json::to_value(event)?;
+    if let Some(key) = event_signing_key() {
+        value["signature"] = json!(event_signature(&key, &value));
+    }
+    serde_json::to_string(&value)
+}
+
+// Always true without a signing key
+fn has_valid_signature(event: &serde_json::Value) -> bool {
+    let key = match event_signing_key() {
+        Some(key) => key,
+        None => return true,
+    };
+    let provided = match event["signature"].as_str() {
+        Some(signature) => signature,
+        None => return false,
+    };
+    let expected = event_signature(&key, event);
+    // Compare every byte so timing doesn't reveal how much matched
+    provided.len() == expected.len()
+        && provided.bytes().zip(expected.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+// SHA-256 and HMAC for event signing, kept here rather than adding a crypto
+// dependency for one MAC
+fn sha256(data: &[u8]) -> [u8; 32] {
+    const K: [u32; 64] = [
+        0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+        0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+        0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+        0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+        0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+        0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+        0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+        0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+    ];
+    let mut h: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+    ];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 64];
+        for (word, bytes) in w.iter_mut().zip(block.chunks(4)) {
+            *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        for (k, wi) in K.iter().zip(w.iter()) {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = hh.wrapping_add(s1).wrapping_add(ch).wrapping_add(*k).wrapping_add(*wi);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            hh = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0u8; 32];
+    for (bytes, word) in digest.chunks_mut(4).zip(h.iter()) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; 64];
+    if key.len() > 64 {
+        block[..32].copy_from_slice(&sha256(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner: Vec<u8> = block.iter().map(|b| b ^ 0x36).collect();
+    inner.extend_from_slice(message);
+    let mut outer: Vec<u8> = block.iter().map(|b| b ^ 0x5c).collect();
+    outer.extend_from_slice(&sha256(&inner));
+    sha256(&outer)
 }
 
 // Oracle ORDS integration
@@ -680,22 +3772,20 @@ async fn update_player_score_ords(
     player_id: &str,
     score: i32,
     game_id: &str,
+    stats: &PlayerStats,
 ) -> Result<()> {
     let oracle_config = get_oracle_config();
     let score_data = json!({
         "player_id": player_id,
         "score": score,
         "game_id": game_id,
+        "stats": stats,
         "runtime": "wasmedge",
-        "castle": "Quantum Nexus",
-        "timestamp": SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs()
+        "castle": castle_name(),
+        "timestamp": now_ms()
     });
 
-    let auth = format!("{}:{}", oracle_config.username, oracle_config.password);
-    let auth_header = format!("Basic {}", BASE64.encode(auth));
+    let auth_header = oracle_config.auth_header();
 
     let url = format!("{}/scores/", oracle_config.ords_url);
     let uri: Uri = url.parse()?;
@@ -719,15 +3809,13 @@ async fn update_player_score_ords(
     Ok(())
 }
 
-async fn get_leaderboard_ords() -> Result<serde_json::Value> {
+// Any HTTP answer below 500 from ORDS means the database is up, even a 404
+// for a collection that hasn't been created yet
+async fn probe_oracle() -> Result<()> {
     let oracle_config = get_oracle_config();
-    let auth = format!("{}:{}", oracle_config.username, oracle_config.password);
-    let auth_header = format!("Basic {}", BASE64.encode(auth));
-
-    let url = format!("{}/leaderboard/", oracle_config.ords_url);
-    let uri: Uri = url.parse()?;
-    let client = create_https_client();
+    let auth_header = oracle_config.auth_header();
 
+    let uri: Uri = format!("{}/leaderboard/", oracle_config.ords_url).parse()?;
     let req = Request::builder()
         .method("GET")
         .uri(uri)
@@ -735,15 +3823,86 @@ async fn get_leaderboard_ords() -> Result<serde_json::Value> {
         .header("Authorization", &auth_header)
         .body(Body::empty())?;
 
-    let response = client.request(req).await?;
+    let response = create_https_client().request(req).await?;
+    if response.status().is_server_error() {
+        return Err(format!("ORDS returned {}", response.status()).into());
+    }
+    Ok(())
+}
 
-    if response.status().is_success() {
-        let body_bytes = hyper::body::to_bytes(response.into_body()).await?;
-        let json_value: serde_json::Value = serde_json::from_slice(&body_bytes)?;
-        Ok(json_value)
-    } else {
-        Err(format!("ORDS leaderboard error: {}", response.status()).into())
+// Upper bound on ORDS pages followed via "next" links for one listing
+const ORDS_MAX_PAGES: usize = 20;
+
+// Collects leaderboard rows across ORDS pages. ORDS pages collections
+// ({"items", "hasMore", "links"}), so a single GET stops at its page size.
+// Only a failed first page is an error; if a later page fails, the rows read
+// so far are returned with "partial" set and the failure under "error".
+async fn get_leaderboard_ords() -> Result<serde_json::Value> {
+    let oracle_config = get_oracle_config();
+    let auth_header = oracle_config.auth_header();
+
+    let mut url = format!("{}/leaderboard/", oracle_config.ords_url);
+    let mut items = Vec::new();
+    let mut has_more = false;
+    let mut partial_error = None;
+
+    for page in 1..=ORDS_MAX_PAGES {
+        let json_value = match fetch_ords_page(&url, &auth_header).await {
+            Ok(json_value) => json_value,
+            Err(e) if page == 1 => return Err(e),
+            Err(e) => {
+                eprintln!("⚠️ Leaderboard page {} failed, returning the first {} rows: {}", page, items.len(), e);
+                partial_error = Some(e.to_string());
+                break;
+            }
+        };
+        // Some ORDS setups return the bare array instead of {"items": [...]}
+        if let Some(rows) = json_value.as_array().or_else(|| json_value["items"].as_array()) {
+            items.extend(rows.iter().cloned());
+        }
+
+        let next = json_value["links"].as_array()
+            .filter(|_| json_value["hasMore"].as_bool().unwrap_or(false))
+            .and_then(|links| links.iter().find(|link| link["rel"] == "next"))
+            .and_then(|link| link["href"].as_str());
+        match next {
+            Some(next) => url = next.to_string(),
+            None => break,
+        }
+        if page == ORDS_MAX_PAGES {
+            eprintln!("⚠️ Leaderboard cut off after {} ORDS pages", ORDS_MAX_PAGES);
+            has_more = true;
+        }
+    }
+
+    let mut listing = json!({
+        "items": items,
+        "count": items.len(),
+        "hasMore": has_more,
+        "partial": partial_error.is_some()
+    });
+    if let Some(error) = partial_error {
+        listing["error"] = json!(error);
+    }
+    Ok(listing)
+}
+
+// One GET of an ORDS collection page, parsed; non-2xx answers are errors
+async fn fetch_ords_page(url: &str, auth_header: &str) -> Result<serde_json::Value> {
+    let req = Request::builder()
+        .method("GET")
+        .uri(url.parse::<Uri>()?)
+        .header("Accept", "application/json")
+        .header("Authorization", auth_header)
+        .body(Body::empty())?;
+
+    let response = create_https_client().request(req).await?;
+    if !response.status().is_success() {
+        return Err(format!("ORDS leaderboard error: {}", response.status()).into());
     }
+
+    let body_bytes = hyper::body::to_bytes(response.into_body()).await?;
+    Ok(serde_json::from_slice(&body_bytes)?)
 }
 
 async fn test_kafka_response(req: Request<Body>) -> Response<Body> {
@@ -758,7 +3917,7 @@ async fn test_kafka_response(req: Request<Body>) -> Response<Body> {
                 .body(Body::from(json!({
                     "error": "Failed to read request body",
                     "runtime": "wasmedge",
-                    "castle": "Quantum Nexus"
+                    "castle": castle_name()
                 }).to_string()))
                 .unwrap();
         }
@@ -774,21 +3933,12 @@ async fn test_kafka_response(req: Request<Body>) -> Response<Body> {
         .unwrap_or("default-test");
 
     // Create test event
-    let test_event = GameEvent {
-        event_type: "test_connectivity".to_string(),
-        player_id: "wasmedge-test-player".to_string(),
-        game_id: "connectivity-test".to_string(),
-        runtime: "wasmedge".to_string(),
-        castle: "Quantum Nexus".to_string(),
-        timestamp: SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs(),
-        data: json!({
+    let test_event = GameEvent::new("test_connectivity", "wasmedge-test-player")
+        .with_game_id("connectivity-test")
+        .with_data(json!({
             "test_message": test_message,
             "connectivity_test": true
-        })
-    };
+        }));
 
     // Publish test message to TxEventQ
     let kafka_result = match publish_to_oracle_kafka(&test_event).await {
@@ -805,13 +3955,10 @@ async fn test_kafka_response(req: Request<Body>) -> Response<Body> {
     let response_data = json!({
         "status": "success",
         "runtime": "wasmedge",
-        "castle": "Quantum Nexus",
+        "castle": castle_name(),
         "test_message_sent": test_message,
         "kafka_result": kafka_result,
-        "timestamp": SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs()
+        "timestamp": now_ms()
     });
 
     Response::builder()
@@ -822,21 +3969,36 @@ async fn test_kafka_response(req: Request<Body>) -> Response<Body> {
         .unwrap()
 }
 
-async fn consume_kafka_response() -> Response<Body> {
+async fn consume_kafka_response(req: &Request<Body>) -> Response<Body> {
+    // ?limit=N is forwarded to the records call so a client can peek at a few
+    // messages; without it TxEventQ picks the batch size as before
+    let limit = match req.uri().query()
+        .and_then(|query| query.split('&').find_map(|pair| pair.strip_prefix("limit=")))
+    {
+        Some(value) => match value.parse::<usize>() {
+            Ok(limit) if limit > 0 => Some(limit),
+            _ => return error_response("limit must be a positive integer", 400),
+        },
+        None => None,
+    };
+
     // Consume messages from Oracle TxEventQ
-    let messages = consume_from_oracle_kafka().await;
+    let result = consume_from_oracle_kafka(limit).await;
+    if let Some(error) = &result.error {
+        return upstream_unavailable_response(error);
+    }
+    let messages = &result.records;
     
     let response_data = json!({
         "status": "success",
         "runtime": "wasmedge",
-        "castle": "Quantum Nexus",
+        "castle": castle_name(),
         "endpoint": "consume_kafka",
         "messages": messages,
         "count": messages.len(),
-        "timestamp": SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs()
+        "empty": result.empty,
+        "error": result.error,
+        "timestamp": now_ms()
     });
 
     Response::builder()
@@ -847,11 +4009,41 @@ async fn consume_kafka_response() -> Response<Body> {
         .unwrap()
 }
 
+// Per-partition tally kept while draining, so a partition left unread on a
+// multi-partition topic shows up in the /drain-messages response
+#[derive(Debug, Default, Serialize)]
+struct PartitionProgress {
+    records: usize,
+    last_offset: Option<i64>,
+}
+
+fn record_partition_progress(progress: &mut BTreeMap<i64, PartitionProgress>, records: &[serde_json::Value]) {
+    let as_i64 = |v: &serde_json::Value| v.as_i64().or_else(|| v.as_str()?.parse().ok());
+    for record in records {
+        let entry = progress.entry(as_i64(&record["partition"]).unwrap_or(0)).or_default();
+        entry.records += 1;
+        if let Some(offset) = as_i64(&record["offset"]) {
+            entry.last_offset = Some(entry.last_offset.map_or(offset, |last| last.max(offset)));
+        }
+    }
+}
+
+// Batches /drain-messages may read per configured partition
+const DRAIN_BATCHES_PER_PARTITION: usize = 10;
+
 async fn drain_messages_response() -> Response<Body> {
-    // Drain all messages from Oracle TxEventQ for this runtime
+    // Drain all messages from Oracle TxEventQ for this runtime. The consumer
+    // instance fetches from one of its partitions at a time, so an empty batch
+    // only means that partition is done: the topic counts as drained once
+    // every partition has come back empty in a row. The batch cap scales with
+    // the partition count so a multi-partition backlog isn't cut off early.
     let mut all_messages = Vec::new();
+    let mut partitions = BTreeMap::new();
     let mut batch_count = 0;
-    let max_batches = 10;  // Prevent infinite loop
+    let mut drained = false;
+    let partition_count = get_oracle_config().partitions;
+    let max_batches = DRAIN_BATCHES_PER_PARTITION * partition_count;  // Prevent infinite loop
+    let mut empty_in_a_row = 0;
 
     // Keep consuming until no more messages or max batches reached
     loop {
@@ -859,62 +4051,210 @@ async fn drain_messages_response() -> Response<Body> {
             break;
         }
         
-        let messages = consume_from_oracle_kafka().await;
+        let result = consume_from_oracle_kafka(None).await;
         
-        // Check if we got any actual messages (not just empty/error responses)
-        let has_actual_messages = messages.iter().any(|msg| {
-            !msg.get("error").is_some() && 
-            !msg.get("message").map(|m| m.as_str().unwrap_or("").contains("No messages available")).unwrap_or(false)
-        });
+        if let Some(error) = &result.error {
+            eprintln!("⚠️ Drain stopped after {} batches: {}", batch_count, error);
+            // Failing before the first batch means Oracle is down, not a partial drain
+            if batch_count == 0 && empty_in_a_row == 0 {
+                return upstream_unavailable_response(error);
+            }
+            break;
+        }
+        if result.empty {
+            empty_in_a_row += 1;
+            if empty_in_a_row >= partition_count {
+                drained = true;
+                break;
+            }
+            continue;
+        }
+        
+        empty_in_a_row = 0;
+        record_partition_progress(&mut partitions, &result.records);
+        all_messages.extend(result.records);
+        batch_count += 1;
         
-        if !has_actual_messages {
+        // Small delay between batches to avoid overwhelming the server
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    }
+
+    let response_data = json!({
+        "status": "success",
+        "runtime": "wasmedge",
+        "castle": castle_name(),
+        "endpoint": "drain_messages",
+        "messages_drained": all_messages,
+        "total_count": all_messages.len(),
+        "batches_processed": batch_count,
+        "partitions": partitions,
+        "fully_drained": drained,
+        "timestamp": now_ms()
+    });
+
+    Response::builder()
+        .status(200)
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(Body::from(response_data.to_string()))
+        .unwrap()
+}
+
+// POST /topic/trim?before=<ms> (admin). TxEventQ's REST API can neither delete
+// records by time nor change a topic's retention, so the topic is drained
+// through this runtime's consumer group, deleted, recreated, and the drained
+// events from `before` onwards are published again. Limitation: records this
+// group had already consumed before the trim can't be read back, so they go
+// with the old topic and aren't counted in `removed`. Nothing is deleted unless
+// the drain reaches the end of the topic.
+async fn topic_trim_response(req: &Request<Body>) -> Response<Body> {
+    if let Some(rejection) = check_admin_token(req) {
+        return rejection;
+    }
+
+    let before = match req.uri().query()
+        .and_then(|query| query.split('&').find_map(|pair| pair.strip_prefix("before=")))
+        .and_then(|v| v.parse::<u64>().ok())
+    {
+        Some(before) => before,
+        None => return error_response("before=<epoch ms> is required", 400),
+    };
+
+    // Drained means every partition came back empty, as in /drain-messages
+    let mut drained_records = Vec::new();
+    let mut drained = false;
+    let partition_count = get_oracle_config().partitions;
+    let mut empty_in_a_row = 0;
+    for _ in 0..DRAIN_BATCHES_PER_PARTITION * partition_count {
+        let result = consume_from_oracle_kafka(None).await;
+        if let Some(error) = &result.error {
+            if drained_records.is_empty() {
+                return upstream_unavailable_response(error);
+            }
             break;
         }
-        
-        all_messages.extend(messages);
-        batch_count += 1;
-        
-        // Small delay between batches to avoid overwhelming the server
+        if result.empty {
+            empty_in_a_row += 1;
+            if empty_in_a_row >= partition_count {
+                drained = true;
+                break;
+            }
+            continue;
+        }
+        empty_in_a_row = 0;
+        drained_records.extend(result.records);
         tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
     }
+    if !drained {
+        return error_response("Topic could not be fully drained; nothing was trimmed", 409);
+    }
 
-    let response_data = json!({
-        "status": "success",
+    // Kept events are matched on their raw timestamp and republished as they
+    // were consumed, so wasmtime's events (which have no data field) survive
+    // alongside ours. Records without a timestamp can't be dated and are
+    // trimmed with the old ones.
+    let kept: Vec<String> = drained_records.iter()
+        .map(|record| &record["data"])
+        .filter(|event| event["timestamp"].as_u64().is_some_and(|timestamp| timestamp >= before))
+        .map(|event| event.to_string())
+        .collect();
+    let removed = drained_records.len() - kept.len();
+
+    let topic = get_oracle_config().topic;
+    if let Err(e) = delete_txeventq_topic(&topic).await {
+        return upstream_unavailable_response(&format!("Topic delete failed: {}", e));
+    }
+    match create_txeventq_topic(&topic).await {
+        Ok(true) => {}
+        Ok(false) => return error_response("Topic was deleted but could not be recreated", 502),
+        Err(e) => return upstream_unavailable_response(&format!("Topic recreate failed: {}", e)),
+    }
+    // Republished inline rather than spawned so `kept` is only reported once
+    // it's back on the topic
+    let republish_error = if kept.is_empty() {
+        None
+    } else {
+        publish_payloads_to_oracle_kafka(&kept).await.err().map(|e| e.to_string())
+    };
+    println!("✂️ Trimmed topic {} before {}: {} removed, {} republished", topic, before, removed, kept.len());
+
+    let response = json!({
+        "status": if republish_error.is_none() { "success" } else { "partial" },
         "runtime": "wasmedge",
-        "castle": "Quantum Nexus",
-        "endpoint": "drain_messages",
-        "messages_drained": all_messages,
-        "total_count": all_messages.len(),
-        "batches_processed": batch_count,
-        "timestamp": SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs()
+        "castle": castle_name(),
+        "topic": topic,
+        "before": before,
+        "removed": removed,
+        "kept": kept.len(),
+        "republish_error": republish_error,
+        "note": "Records consumed by this group before the trim are dropped with the old topic and not counted",
+        "timestamp": now_ms()
     });
 
     Response::builder()
         .status(200)
         .header("Content-Type", "application/json")
         .header("Access-Control-Allow-Origin", "*")
-        .body(Body::from(response_data.to_string()))
+        .body(Body::from(response.to_string()))
         .unwrap()
 }
 
-async fn consume_from_oracle_kafka() -> Vec<serde_json::Value> {
+// POST /consumers/{group} (empty JSON body) and pull the new instance_id out
+// of the reply, or say why there isn't one
+async fn create_consumer_instance(
+    client: &Client<hyper_rustls::HttpsConnector<hyper::client::HttpConnector>>,
+    url: &str,
+    auth_header: &str,
+) -> std::result::Result<String, String> {
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri(url.parse::<Uri>().map_err(|e| format!("bad consumer instance URL: {}", e))?)
+        .header("Content-Type", "application/json")
+        .header("Authorization", auth_header)
+        .body(Body::from(json!({}).to_string()))
+        .map_err(|e| format!("could not build consumer instance request: {}", e))?;
+
+    let response = client.request(req).await
+        .map_err(|e| format!("consumer instance request failed: {}", e))?;
+    let status = response.status();
+    let body_bytes = hyper::body::to_bytes(response.into_body()).await
+        .map_err(|e| format!("could not read consumer instance response: {}", e))?;
+    let response_text = String::from_utf8_lossy(&body_bytes);
+    println!("🔧 Consumer instance response: {}", response_text);
+
+    if !status.is_success() {
+        return Err(format!("consumer instance creation returned {}: {}", status, response_text));
+    }
+    serde_json::from_str::<serde_json::Value>(&response_text)
+        .map_err(|e| format!("consumer instance response is not JSON: {}", e))?
+        .get("instance_id")
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+        .ok_or_else(|| "consumer instance response has no instance_id".to_string())
+}
+
+// `limit` becomes the records call's ?limit=; None leaves the batch size to TxEventQ
+async fn consume_from_oracle_kafka(limit: Option<usize>) -> ConsumeResult {
     let oracle_config = get_oracle_config();
     let client = create_https_client();
     
     // Oracle TxEventQ consumer setup using topic-specific consumer group
     let topic_safe = oracle_config.topic.to_lowercase().replace("_", "");
     let consumer_group_id = format!("wasmedge_{}_grp", topic_safe);
-    let txeventq_base_url = format!("https://{}/ords/admin/_/db-api/stable/database/txeventq", oracle_config.host);
+    let txeventq_base_url = oracle_config.txeventq_base_url();
     let cluster_name = &oracle_config.db_name;
     
     println!("🔍 Topic: {}, Consumer Group: {}", oracle_config.topic, consumer_group_id);
     
-    // Create Basic Auth header
-    let auth_string = format!("{}:{}", oracle_config.username, oracle_config.password);
-    let encoded_auth = BASE64.encode(auth_string.as_bytes());
+    let auth_header = oracle_config.auth_header();
+    
+    // A fresh deployment has no topic yet; create it rather than failing
+    // the first consume against it
+    match create_txeventq_topic(&oracle_config.topic).await {
+        Ok(true) => {}
+        Ok(false) => return ConsumeResult::error(format!("Topic '{}' could not be created", oracle_config.topic)),
+        Err(e) => return ConsumeResult::error(format!("Topic creation request failed: {}", e)),
+    }
     
     // Step 1: Create consumer group using correct URL pattern
     let consumer_group_url = format!("{}/clusters/{}/consumer-groups/{}", 
@@ -928,7 +4268,7 @@ async fn consume_from_oracle_kafka() -> Vec<serde_json::Value> {
             .method(Method::POST)
             .uri(uri)
             .header("Content-Type", "application/json")
-            .header("Authorization", format!("Basic {}", encoded_auth))
+            .header("Authorization", &auth_header)
             .body(Body::from(group_payload.to_string()));
             
         if let Ok(req) = req {
@@ -942,14 +4282,20 @@ async fn consume_from_oracle_kafka() -> Vec<serde_json::Value> {
                         } else if status == 409 || response_text.to_lowercase().contains("already exists") {
                             println!("ℹ️ Consumer group already exists: {}", consumer_group_id);
                         } else {
-                            println!("⚠️ Consumer group creation returned: {} - {}", status, response_text);
+                            // A missing group makes every read below come back empty,
+                            // so report the failure instead of "no messages"
                             println!("🔍 Debug: Consumer group URL: {}", consumer_group_url);
                             println!("🔍 Debug: Consumer group payload: {}", group_payload);
-                            println!("ℹ️ Continuing with consumer instance creation...");
+                            let error = format!("Consumer group creation failed with status {}: {}", status, response_text);
+                            eprintln!("❌ {}", error);
+                            return ConsumeResult::error(error);
                         }
                     }
                 }
-                Err(e) => println!("❌ Consumer group request failed: {}", e),
+                Err(e) => {
+                    eprintln!("❌ Consumer group request failed: {}", e);
+                    return ConsumeResult::error(format!("Consumer group request failed: {}", e));
+                }
             }
         }
     }
@@ -957,59 +4303,31 @@ async fn consume_from_oracle_kafka() -> Vec<serde_json::Value> {
     // Step 2: Create consumer instance using correct pattern (POST /consumers/{group})
     let consumer_instance_url = format!("{}/consumers/{}", 
         txeventq_base_url, consumer_group_id);
-    let consumer_payload = json!({});  // Empty payload for consumer creation
     
-    let consumer_instance_id = if let Ok(uri) = consumer_instance_url.parse::<Uri>() {
-        let req = Request::builder()
-            .method(Method::POST)
-            .uri(uri)
-            .header("Content-Type", "application/json")
-            .header("Authorization", format!("Basic {}", encoded_auth))
-            .body(Body::from(consumer_payload.to_string()));
-            
-        if let Ok(req) = req {
-            match client.request(req).await {
-                Ok(response) => {
-                    let status = response.status();
-                    if let Ok(body_bytes) = hyper::body::to_bytes(response.into_body()).await {
-                        let response_text = String::from_utf8_lossy(&body_bytes);
-                        println!("🔧 Consumer instance response: {}", response_text);
-                        
-                        if status.is_success() {
-                            if let Ok(consumer_data) = serde_json::from_str::<serde_json::Value>(&response_text) {
-                                let instance_id = consumer_data.get("instance_id")
-                                    .and_then(|v| v.as_str())
-                                    .unwrap_or("unknown_instance")
-                                    .to_string();
-                                println!("✅ Consumer instance created: {}", instance_id);
-                                instance_id
-                            } else {
-                                println!("❌ Failed to parse consumer response: {}", response_text);
-                                "unknown_instance".to_string()
-                            }
-                        } else {
-                            println!("❌ Consumer instance creation failed: {} - {}", status, response_text);
-                            "unknown_instance".to_string()
-                        }
-                    } else {
-                        "unknown_instance".to_string()
-                    }
-                }
-                Err(e) => {
-                    println!("❌ Consumer instance request failed: {}", e);
-                    "unknown_instance".to_string()
-                }
-            }
-        } else {
-            "unknown_instance".to_string()
+    // No instance id means the records GET can only fail; report the setup
+    // failure rather than spending a round trip on it
+    let consumer_instance_id = match create_consumer_instance(&client, &consumer_instance_url, &auth_header).await {
+        Ok(instance_id) => {
+            println!("✅ Consumer instance created: {}", instance_id);
+            lock_unpoisoned(&CONSUMER_INSTANCES).push(ConsumerInstance {
+                group: consumer_group_id.clone(),
+                instance_id: instance_id.clone(),
+                created_at: now_ms(),
+            });
+            instance_id
+        }
+        Err(reason) => {
+            eprintln!("❌ Consumer setup failed: {}", reason);
+            return ConsumeResult::error(format!("consumer_setup_failed: {}", reason));
         }
-    } else {
-        "unknown_instance".to_string()
     };
     
     // Step 3: Consume records using correct pattern: /consumers/{group}/instances/{instance_id}/records
-    let consume_url = format!("{}/consumers/{}/instances/{}/records", 
+    let mut consume_url = format!("{}/consumers/{}/instances/{}/records", 
         txeventq_base_url, consumer_group_id, consumer_instance_id);
+    if let Some(limit) = limit {
+        consume_url.push_str(&format!("?limit={}", limit));
+    }
     
     println!("🔍 Consuming from URL: {}", consume_url);
     println!("🔍 Debug: Consumer group: {}", consumer_group_id);
@@ -1021,7 +4339,7 @@ async fn consume_from_oracle_kafka() -> Vec<serde_json::Value> {
             .method(Method::GET)
             .uri(uri)
             .header("Accept", "application/json")
-            .header("Authorization", format!("Basic {}", encoded_auth))
+            .header("Authorization", &auth_header)
             .body(Body::empty());
             
         if let Ok(req) = req {
@@ -1042,89 +4360,59 @@ async fn consume_from_oracle_kafka() -> Vec<serde_json::Value> {
                                 match serde_json::from_str::<Vec<serde_json::Value>>(&response_text) {
                                     Ok(records) => {
                                         let mut processed_messages = Vec::new();
+                                        let mut unsigned = 0;
                                         
                                         for record in records {
+                                            let data = parse_message_value(&record);
+                                            if !has_valid_signature(&data) {
+                                                unsigned += 1;
+                                                continue;
+                                            }
                                             let processed_msg = json!({
                                                 "topic": record.get("topic").unwrap_or(&json!(oracle_config.topic)),
                                                 "partition": record.get("partition").unwrap_or(&json!(0)),
                                                 "offset": record.get("offset").unwrap_or(&json!("unknown")),
-                                                "timestamp": record.get("timestamp").unwrap_or(&json!(SystemTime::now()
-                                                    .duration_since(UNIX_EPOCH)
-                                                    .unwrap()
-                                                    .as_secs())),
+                                                "timestamp": record.get("timestamp").unwrap_or(&json!(now_ms())),
                                                 "key": record.get("key"),
-                                                "data": parse_message_value(&record),
+                                                "data": data,
                                                 "consumed_by": "wasmedge",
-                                                "consumed_at": SystemTime::now()
-                                                    .duration_since(UNIX_EPOCH)
-                                                    .unwrap()
-                                                    .as_secs(),
+                                                "consumed_at": now_ms(),
                                                 "instance_id": consumer_instance_id.clone()
                                             });
                                             processed_messages.push(processed_msg);
                                         }
                                         
-                                        return processed_messages;
+                                        let mut result = ConsumeResult::records(processed_messages);
+                                        if unsigned > 0 {
+                                            eprintln!("⚠️ Dropped {} events with a missing or bad signature", unsigned);
+                                            // The topic isn't caught up just because this batch was all forged
+                                            result.empty = false;
+                                        }
+                                        return result;
                                     }
                                     Err(e) => {
                                         println!("❌ Failed to parse records: {}", e);
-                                        return vec![json!({
-                                            "error": format!("Parse error: {}", e),
-                                            "raw_response": response_text,
-                                            "consumed_by": "wasmedge",
-                                            "consumed_at": SystemTime::now()
-                                                .duration_since(UNIX_EPOCH)
-                                                .unwrap()
-                                                .as_secs()
-                                        })];
+                                        return ConsumeResult::error(format!("Parse error: {}", e));
                                     }
                                 }
                             } else {
-                                return vec![json!({
-                                    "message": "No messages available (empty response)",
-                                    "consumed_by": "wasmedge",
-                                    "consumed_at": SystemTime::now()
-                                        .duration_since(UNIX_EPOCH)
-                                        .unwrap()
-                                        .as_secs()
-                                })];
+                                return ConsumeResult::empty();
                             }
                         } else {
-                            return vec![json!({
-                                "error": format!("HTTP {}: {}", status, response_text),
-                                "consumed_by": "wasmedge",
-                                "consumed_at": SystemTime::now()
-                                    .duration_since(UNIX_EPOCH)
-                                    .unwrap()
-                                    .as_secs()
-                            })];
+                            return ConsumeResult::error(format!("HTTP {}: {}", status, response_text));
                         }
                     }
                 }
                 Err(e) => {
                     println!("❌ Consume request failed: {}", e);
-                    return vec![json!({
-                        "error": format!("Request failed: {}", e),
-                        "consumed_by": "wasmedge",
-                        "consumed_at": SystemTime::now()
-                            .duration_since(UNIX_EPOCH)
-                            .unwrap()
-                            .as_secs()
-                    })];
+                    return ConsumeResult::error(format!("Request failed: {}", e));
                 }
             }
         }
     }
     
     // Return error if we reach here
-    vec![json!({
-        "error": "Consumer setup failed",
-        "consumed_by": "wasmedge", 
-        "consumed_at": SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs()
-    })]
+    ConsumeResult::error("Consumer setup failed".to_string())
 }
 
 fn parse_message_value(msg: &serde_json::Value) -> serde_json::Value {
@@ -1157,17 +4445,34 @@ async fn ai_action_response(req: Request<Body>) -> Response<Body> {
         }
     };
 
-    // WasmEdge AI: Strategic territory controller  
-    let action = get_wasmedge_ai_action(&game_data);
+    // WasmEdge AI: Strategic territory controller. "my_id" lets the frontend
+    // run several bots, each finding itself among the players.
+    let my_id = game_data["my_id"].as_str().unwrap_or(AI_DEFAULT_PLAYER_ID);
+    // Optional "seed": the same seed with the same game state always gets
+    // the same action back. The seed used is echoed either way.
+    let mut rng = game_data["seed"].as_u64().map(AiRng::seeded).unwrap_or_else(AiRng::from_clock);
+    // Optional "aggression" from 0.0 (territory) to 1.0 (hunter)
+    let aggression = match &game_data["aggression"] {
+        serde_json::Value::Null => AI_DEFAULT_AGGRESSION,
+        value => match value.as_f64() {
+            Some(aggression) if (0.0..=1.0).contains(&aggression) => aggression,
+            _ => return error_response("aggression must be a number from 0.0 to 1.0", 400),
+        },
+    };
+    let action = if PAUSED.load(Ordering::SeqCst) {
+        AiAction::forward("game_paused")
+    } else {
+        blended_ai_action(&game_data, my_id, aggression, &mut rng)
+    };
     
     let response = json!({
         "runtime": "wasmedge",
-        "castle": "Quantum Nexus",
+        "castle": castle_name(),
+        "ai_player_id": my_id,
         "action": action,
-        "timestamp": SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis()
+        "aggression": aggression,
+        "seed": rng.seed,
+        "timestamp": now_ms()
     });
 
     Response::builder()
@@ -1175,12 +4480,136 @@ async fn ai_action_response(req: Request<Body>) -> Response<Body> {
         .header("Content-Type", "application/json")
         .header("Access-Control-Allow-Origin", "*")
         .header("Access-Control-Allow-Methods", "GET, POST, OPTIONS")
-        .header("Access-Control-Allow-Headers", "Content-Type")
+        .header("Access-Control-Allow-Headers", "Content-Type, X-API-Key")
         .body(Body::from(response.to_string()))
         .unwrap()
 }
 
-fn get_wasmedge_ai_action(game_data: &serde_json::Value) -> serde_json::Value {
+fn ai_strategy_response() -> Response<Body> {
+    let strategy = json!({
+        "runtime": "wasmedge",
+        "castle": castle_name(),
+        "ai_player_id": AI_DEFAULT_PLAYER_ID,
+        "strategy": "territory_controller",
+        "description": "Strategic territory controller: holds the center of the arena, builds defensive walls and turns toward open space to expand its territory.",
+        "behaviors": [
+            "avoid collisions over the next few steps, counting the trail it lays itself",
+            "when forced to turn, prefer the turn closest to the center",
+            "steer back toward the center when drifting too far out",
+            "turn into larger open lanes when the current lane gets short",
+            "otherwise keep moving forward to lay down walls"
+        ],
+        "parameters": {
+            "default_arena_size": AI_DEFAULT_ARENA_SIZE,
+            "center_radius_ratio": AI_CENTER_RADIUS_RATIO,
+            "wall_lookahead": AI_WALL_LOOKAHEAD,
+            "expansion_margin": AI_EXPANSION_MARGIN,
+            "danger_lookahead": AI_DANGER_LOOKAHEAD,
+            "default_aggression": AI_DEFAULT_AGGRESSION
+        },
+        "difficulty": AI_DEFAULT_DIFFICULTY,
+        "timestamp": now_ms()
+    });
+
+    Response::builder()
+        .status(200)
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(Body::from(strategy.to_string()))
+        .unwrap()
+}
+
+// Decision returned by the AI. Serializes to {"type", "direction", "reason"},
+// the shape /ai-action clients and /simulate already read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum AiActionType {
+    Move,
+    Turn,
+}
+
+// Relative to the cycle's current heading, unlike the absolute Direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum AiDirection {
+    Forward,
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct AiAction {
+    #[serde(rename = "type")]
+    action_type: AiActionType,
+    direction: AiDirection,
+    reason: String,
+}
+
+impl AiAction {
+    fn new(action_type: AiActionType, direction: AiDirection, reason: impl Into<String>) -> Self {
+        AiAction { action_type, direction, reason: reason.into() }
+    }
+
+    fn forward(reason: impl Into<String>) -> Self {
+        AiAction::new(AiActionType::Move, AiDirection::Forward, reason)
+    }
+
+    fn turn(direction: AiDirection, reason: impl Into<String>) -> Self {
+        AiAction::new(AiActionType::Turn, direction, reason)
+    }
+
+    // Heading (0=north, 1=east, 2=south, 3=west) after applying this action
+    fn heading_from(&self, direction: i32) -> i32 {
+        match self.direction {
+            AiDirection::Forward => direction,
+            AiDirection::Left => (direction + 3) % 4,
+            AiDirection::Right => (direction + 1) % 4,
+        }
+    }
+}
+
+// WasmEdge AI tuning parameters (also reported by GET /ai-action)
+const AI_DEFAULT_ARENA_SIZE: u64 = 20;
+// Player id the AI looks for when a request doesn't send "my_id"
+const AI_DEFAULT_PLAYER_ID: &str = "ai-wasmedge";
+// Fraction of the arena size beyond which the AI heads back to the center
+const AI_CENTER_RADIUS_RATIO: f64 = 0.25;
+// Open cells ahead below which the AI looks for a better lane
+const AI_WALL_LOOKAHEAD: i32 = 8;
+// How many more open cells a side lane needs before the AI turns into it
+const AI_EXPANSION_MARGIN: i32 = 2;
+// Steps a direction must stay survivable, own projected trail included,
+// before the AI counts it as safe
+const AI_DANGER_LOOKAHEAD: u32 = 6;
+const AI_DEFAULT_DIFFICULTY: &str = "normal";
+// Blend used when /ai-action doesn't send "aggression": pure territory, as
+// this AI has always played
+const AI_DEFAULT_AGGRESSION: f64 = 0.0;
+
+// Mixes the two AI personalities. At 0.0 this is the territory controller
+// below and at 1.0 the hunter (wasmtime's default AI); only that one runs, so
+// a seeded request answers exactly as before. In between both decide, and
+// when they disagree the hunter's choice is taken with probability
+// `aggression`. Each already avoids danger on its own, so either is safe.
+fn blended_ai_action(game_data: &serde_json::Value, my_player_id: &str, aggression: f64, rng: &mut AiRng) -> AiAction {
+    if aggression <= 0.0 {
+        return get_wasmedge_ai_action(game_data, my_player_id, rng);
+    }
+    if aggression >= 1.0 {
+        return get_hunter_ai_action(game_data, my_player_id, rng);
+    }
+
+    let hunter = get_hunter_ai_action(game_data, my_player_id, rng);
+    let territory = get_wasmedge_ai_action(game_data, my_player_id, rng);
+    let take_hunter = if hunter.direction == territory.direction {
+        aggression >= 0.5
+    } else {
+        rng.chance(aggression)
+    };
+    if take_hunter { hunter } else { territory }
+}
+
+fn get_wasmedge_ai_action(game_data: &serde_json::Value, my_player_id: &str, rng: &mut AiRng) -> AiAction {
     // WasmEdge Quantum Nexus AI: Strategic territory controller
     // Strategy: Control center, build defensive walls, expand territory
     
@@ -1191,20 +4620,14 @@ fn get_wasmedge_ai_action(game_data: &serde_json::Value) -> serde_json::Value {
     let empty_position = serde_json::Map::new();
     
     let arena = game_data.get("arena").and_then(|a| a.as_object()).unwrap_or(&empty_arena);
-    let arena_size = arena.get("size").and_then(|s| s.as_u64()).unwrap_or(20) as f64;
+    let arena_size = arena.get("size").and_then(|s| s.as_u64()).unwrap_or(AI_DEFAULT_ARENA_SIZE) as f64;
     let players = game_data.get("players").and_then(|p| p.as_object()).unwrap_or(&empty_players);
     let trails = game_data.get("trails").and_then(|t| t.as_object()).unwrap_or(&empty_trails);
     
-    let my_player_id = "ai-wasmedge";
-    
     // Find my player data
     let my_player = players.get(my_player_id).and_then(|p| p.as_object());
     if my_player.is_none() {
-        return json!({
-            "type": "turn",
-            "direction": "forward",
-            "reason": "initializing"
-        });
+        return AiAction::new(AiActionType::Turn, AiDirection::Forward, "initializing");
     }
     
     let my_player = my_player.unwrap();
@@ -1213,59 +4636,53 @@ fn get_wasmedge_ai_action(game_data: &serde_json::Value) -> serde_json::Value {
     let my_z = my_pos.get("z").and_then(|z| z.as_f64()).unwrap_or(0.0);
     let my_direction = my_player.get("direction").and_then(|d| d.as_u64()).unwrap_or(0) as i32;
     
-    // Check immediate danger ahead
-    let (next_x, next_z) = calculate_next_position(my_x, my_z, my_direction);
-    if is_position_dangerous(next_x, next_z, trails, arena_size) {
-        // Immediate danger - choose safest turn
-        let left_dir = (my_direction - 1 + 4) % 4;
-        let right_dir = (my_direction + 1) % 4;
-        
-        let (left_x, left_z) = calculate_next_position(my_x, my_z, left_dir);
-        let (right_x, right_z) = calculate_next_position(my_x, my_z, right_dir);
-        
-        let left_safe = !is_position_dangerous(left_x, left_z, trails, arena_size);
-        let right_safe = !is_position_dangerous(right_x, right_z, trails, arena_size);
-        
+    // Cells already taken, plus room in each direction once the cycle's own
+    // new trail is counted; a direction is safe with the full lookahead free
+    let blocked = trail_cells(trails);
+    let room = |direction: i32| projected_room(my_x, my_z, direction, &blocked, arena_size, AI_DANGER_LOOKAHEAD);
+    let left_dir = (my_direction - 1 + 4) % 4;
+    let right_dir = (my_direction + 1) % 4;
+
+    // Check danger ahead
+    let forward_room = room(my_direction);
+    if forward_room < AI_DANGER_LOOKAHEAD {
+        let left_room = room(left_dir);
+        let right_room = room(right_dir);
+        let left_safe = left_room == AI_DANGER_LOOKAHEAD;
+        let right_safe = right_room == AI_DANGER_LOOKAHEAD;
+
         if left_safe && right_safe {
             // Both safe - choose based on territory strategy
             let center_x = arena_size / 2.0;
             let center_z = arena_size / 2.0;
-            
+
+            let (left_x, left_z) = calculate_next_position(my_x, my_z, left_dir);
+            let (right_x, right_z) = calculate_next_position(my_x, my_z, right_dir);
             let left_to_center = ((left_x - center_x).powi(2) + (left_z - center_z).powi(2)).sqrt();
             let right_to_center = ((right_x - center_x).powi(2) + (right_z - center_z).powi(2)).sqrt();
             
             // Prefer staying near center for territory control
-            if left_to_center < right_to_center {
-                return json!({
-                    "type": "turn",
-                    "direction": "left",
-                    "reason": "avoid_and_control_territory_left"
-                });
+            let go_left = if left_to_center == right_to_center { rng.coin() } else { left_to_center < right_to_center };
+            if go_left {
+                return AiAction::turn(AiDirection::Left, "avoid_and_control_territory_left");
             } else {
-                return json!({
-                    "type": "turn",
-                    "direction": "right",
-                    "reason": "avoid_and_control_territory_right"
-                });
+                return AiAction::turn(AiDirection::Right, "avoid_and_control_territory_right");
             }
         } else if left_safe {
-            return json!({
-                "type": "turn",
-                "direction": "left", 
-                "reason": "avoid_collision_left_only"
-            });
+            return AiAction::turn(AiDirection::Left, "avoid_collision_left_only");
         } else if right_safe {
-            return json!({
-                "type": "turn",
-                "direction": "right",
-                "reason": "avoid_collision_right_only"
-            });
+            return AiAction::turn(AiDirection::Right, "avoid_collision_right_only");
+        } else if forward_room > 0 && forward_room >= left_room.max(right_room) {
+            // Every way is closing in; ahead buys the most time
+            return AiAction::forward("longest_escape_forward");
+        } else if left_room > 0 || right_room > 0 {
+            let go_left = if left_room == right_room { rng.coin() } else { left_room > right_room };
+            let direction = if go_left { AiDirection::Left } else { AiDirection::Right };
+            let reason = if direction == AiDirection::Left { "longest_escape_left" } else { "longest_escape_right" };
+            return AiAction::turn(direction, reason);
         } else {
-            return json!({
-                "type": "turn",
-                "direction": "left",
-                "reason": "desperate_turn"
-            });
+            let direction = if rng.coin() { AiDirection::Left } else { AiDirection::Right };
+            return AiAction::turn(direction, "desperate_turn");
         }
     }
     
@@ -1275,7 +4692,7 @@ fn get_wasmedge_ai_action(game_data: &serde_json::Value) -> serde_json::Value {
     let distance_to_center = ((my_x - center_x).powi(2) + (my_z - center_z).powi(2)).sqrt();
     
     // If far from center, try to move toward it
-    if distance_to_center > arena_size / 4.0 {
+    if distance_to_center > arena_size * AI_CENTER_RADIUS_RATIO {
         let dx = center_x - my_x;
         let dz = center_z - my_z;
         
@@ -1285,20 +4702,12 @@ fn get_wasmedge_ai_action(game_data: &serde_json::Value) -> serde_json::Value {
             if dz < 0.0 { 0 } else { 2 }  // North or South
         };
         
-        if target_direction != my_direction {
+        if target_direction != my_direction && room(target_direction) == AI_DANGER_LOOKAHEAD {
             let turn_diff = (target_direction - my_direction + 4) % 4;
             if turn_diff == 1 {
-                return json!({
-                    "type": "turn",
-                    "direction": "right",
-                    "reason": "moving_to_center_right"
-                });
+                return AiAction::turn(AiDirection::Right, "moving_to_center_right");
             } else if turn_diff == 3 {
-                return json!({
-                    "type": "turn", 
-                    "direction": "left",
-                    "reason": "moving_to_center_left"
-                });
+                return AiAction::turn(AiDirection::Left, "moving_to_center_left");
             }
         }
     }
@@ -1307,34 +4716,381 @@ fn get_wasmedge_ai_action(game_data: &serde_json::Value) -> serde_json::Value {
     // Check if we're creating a good defensive pattern
     let steps_ahead = calculate_safe_distance(my_x, my_z, my_direction, trails, arena_size);
     
-    if steps_ahead < 8 {
+    if steps_ahead < AI_WALL_LOOKAHEAD {
         // Look for a turn that creates more territory
-        let left_dir = (my_direction - 1 + 4) % 4;
-        let right_dir = (my_direction + 1) % 4;
-        
         let left_distance = calculate_safe_distance(my_x, my_z, left_dir, trails, arena_size);
         let right_distance = calculate_safe_distance(my_x, my_z, right_dir, trails, arena_size);
         
-        if left_distance > steps_ahead + 2 {
-            return json!({
-                "type": "turn",
-                "direction": "left",
-                "reason": "strategic_territory_expansion_left"
-            });
-        } else if right_distance > steps_ahead + 2 {
-            return json!({
-                "type": "turn",
-                "direction": "right", 
-                "reason": "strategic_territory_expansion_right"
-            });
+        if left_distance > steps_ahead + AI_EXPANSION_MARGIN && room(left_dir) == AI_DANGER_LOOKAHEAD {
+            return AiAction::turn(AiDirection::Left, "strategic_territory_expansion_left");
+        } else if right_distance > steps_ahead + AI_EXPANSION_MARGIN && room(right_dir) == AI_DANGER_LOOKAHEAD {
+            return AiAction::turn(AiDirection::Right, "strategic_territory_expansion_right");
         }
     }
     
     // Default: continue forward building walls
+    AiAction::forward("building_defensive_wall")
+}
+
+// The hunter personality (wasmtime's AI), for aggression above 0.0: dodge
+// danger, otherwise turn toward the nearest living opponent
+fn get_hunter_ai_action(game_data: &serde_json::Value, my_player_id: &str, rng: &mut AiRng) -> AiAction {
+    let empty_arena = serde_json::Map::new();
+    let empty_players = serde_json::Map::new();
+    let empty_trails = serde_json::Map::new();
+    let empty_position = serde_json::Map::new();
+
+    let arena = game_data.get("arena").and_then(|a| a.as_object()).unwrap_or(&empty_arena);
+    let arena_size = arena.get("size").and_then(|s| s.as_u64()).unwrap_or(AI_DEFAULT_ARENA_SIZE) as f64;
+    let players = game_data.get("players").and_then(|p| p.as_object()).unwrap_or(&empty_players);
+    let trails = game_data.get("trails").and_then(|t| t.as_object()).unwrap_or(&empty_trails);
+
+    let my_player = match players.get(my_player_id).and_then(|p| p.as_object()) {
+        Some(player) => player,
+        None => return AiAction::new(AiActionType::Turn, AiDirection::Forward, "initializing"),
+    };
+    let my_pos = my_player.get("position").and_then(|p| p.as_object()).unwrap_or(&empty_position);
+    let my_x = my_pos.get("x").and_then(|x| x.as_f64()).unwrap_or(0.0);
+    let my_z = my_pos.get("z").and_then(|z| z.as_f64()).unwrap_or(0.0);
+    let my_direction = my_player.get("direction").and_then(|d| d.as_u64()).unwrap_or(0) as i32;
+
+    // Nearest living opponent, if any
+    let nearest_opponent = players.iter()
+        .filter(|(player_id, _)| player_id.as_str() != my_player_id)
+        .filter_map(|(_, player)| player.as_object())
+        .filter(|player| player.get("alive").and_then(|a| a.as_bool()).unwrap_or(true))
+        .map(|player| {
+            let pos = player.get("position").and_then(|p| p.as_object()).unwrap_or(&empty_position);
+            let x = pos.get("x").and_then(|x| x.as_f64()).unwrap_or(0.0);
+            let z = pos.get("z").and_then(|z| z.as_f64()).unwrap_or(0.0);
+            (x, z, ((x - my_x).powi(2) + (z - my_z).powi(2)).sqrt())
+        })
+        .fold(None, |nearest: Option<(f64, f64, f64)>, candidate| match nearest {
+            Some(best) if best.2 <= candidate.2 => Some(best),
+            _ => Some(candidate),
+        });
+
+    let blocked = trail_cells(trails);
+    let room = |direction: i32| projected_room(my_x, my_z, direction, &blocked, arena_size, AI_DANGER_LOOKAHEAD);
+    let left_dir = (my_direction - 1 + 4) % 4;
+    let right_dir = (my_direction + 1) % 4;
+
+    let forward_room = room(my_direction);
+    if forward_room < AI_DANGER_LOOKAHEAD {
+        let left_room = room(left_dir);
+        let right_room = room(right_dir);
+        let left_safe = left_room == AI_DANGER_LOOKAHEAD;
+        let right_safe = right_room == AI_DANGER_LOOKAHEAD;
+
+        if left_safe && right_safe {
+            // Both safe - take the side closer to the prey
+            return match nearest_opponent {
+                Some((opp_x, opp_z, _)) => {
+                    let (left_x, left_z) = calculate_next_position(my_x, my_z, left_dir);
+                    let (right_x, right_z) = calculate_next_position(my_x, my_z, right_dir);
+                    let left_dist = ((left_x - opp_x).powi(2) + (left_z - opp_z).powi(2)).sqrt();
+                    let right_dist = ((right_x - opp_x).powi(2) + (right_z - opp_z).powi(2)).sqrt();
+                    let go_left = if left_dist == right_dist { rng.coin() } else { left_dist < right_dist };
+                    if go_left {
+                        AiAction::turn(AiDirection::Left, "avoid_and_hunt_left")
+                    } else {
+                        AiAction::turn(AiDirection::Right, "avoid_and_hunt_right")
+                    }
+                }
+                None if rng.coin() => AiAction::turn(AiDirection::Left, "avoid_collision_left"),
+                None => AiAction::turn(AiDirection::Right, "avoid_collision_right"),
+            };
+        } else if left_safe {
+            return AiAction::turn(AiDirection::Left, "avoid_collision_only_left_safe");
+        } else if right_safe {
+            return AiAction::turn(AiDirection::Right, "avoid_collision_only_right_safe");
+        } else if forward_room > 0 && forward_room >= left_room.max(right_room) {
+            return AiAction::forward("longest_escape_forward");
+        } else if left_room > 0 || right_room > 0 {
+            let go_right = if right_room == left_room { rng.coin() } else { right_room > left_room };
+            let direction = if go_right { AiDirection::Right } else { AiDirection::Left };
+            let reason = if direction == AiDirection::Left { "longest_escape_left" } else { "longest_escape_right" };
+            return AiAction::turn(direction, reason);
+        } else {
+            let direction = if rng.coin() { AiDirection::Right } else { AiDirection::Left };
+            return AiAction::turn(direction, "desperate_turn");
+        }
+    }
+
+    // No immediate danger - close in along the dominant axis when that turn is safe
+    if let Some((opp_x, opp_z, _)) = nearest_opponent {
+        let dx = opp_x - my_x;
+        let dz = opp_z - my_z;
+        let target_direction = if dx.abs() > dz.abs() {
+            if dx > 0.0 { 1 } else { 3 }
+        } else if dz < 0.0 {
+            0
+        } else {
+            2
+        };
+
+        if target_direction != my_direction && room(target_direction) == AI_DANGER_LOOKAHEAD {
+            let turn_diff = (target_direction - my_direction + 4) % 4;
+            if turn_diff == 1 {
+                return AiAction::turn(AiDirection::Right, "hunting_opponent_right");
+            } else if turn_diff == 3 {
+                return AiAction::turn(AiDirection::Left, "hunting_opponent_left");
+            }
+        }
+    }
+
+    AiAction::forward("continuing_hunt")
+}
+
+// POST /simulate limits
+const SIMULATE_DEFAULT_TICKS: u64 = 500;
+const SIMULATE_MAX_TICKS: u64 = 5000;
+const SIMULATE_MAX_PLAYERS: usize = 8;
+const SIMULATE_MAX_ARENA_SIZE: u64 = 200;
+
+// An AI decision function, as /simulate runs one per cycle
+type AiDecision = fn(&serde_json::Value, &str, &mut AiRng) -> AiAction;
+
+// The strategy a /simulate "runtimes" entry names, with the name its cycles
+// are labelled by. A runtime's name stands for its default personality.
+fn simulate_strategy(name: &str) -> Option<(&'static str, AiDecision)> {
+    match name {
+        "territory" | "wasmedge" => Some(("territory", get_wasmedge_ai_action)),
+        "hunter" | "wasmtime" => Some(("hunter", get_hunter_ai_action)),
+        _ => None,
+    }
+}
+
+// Headless match between AI cycles, one per "runtimes" entry, each running
+// the strategy it names: {"runtimes": ["hunter", "territory"],
+// "arena_size": 20, "ticks": 500, "seed": 42}
+async fn simulate_response(req: Request<Body>) -> Response<Body> {
+    let body_bytes = match hyper::body::to_bytes(req.into_body()).await {
+        Ok(bytes) => bytes,
+        Err(_) => return error_response("Failed to read request body", 400),
+    };
+
+    let payload: serde_json::Value = match serde_json::from_slice(&body_bytes) {
+        Ok(payload) => payload,
+        Err(_) => return error_response("Invalid JSON", 400),
+    };
+
+    let strategies: Vec<&str> = payload["runtimes"].as_array()
+        .map(|r| r.iter().filter_map(|s| s.as_str()).collect())
+        .unwrap_or_default();
+    if strategies.len() < 2 || strategies.len() > SIMULATE_MAX_PLAYERS {
+        return error_response("runtimes must list between 2 and 8 AI strategies", 400);
+    }
+    let mut resolved = Vec::new();
+    for name in &strategies {
+        match simulate_strategy(name) {
+            Some(strategy) => resolved.push(strategy),
+            None => return error_response(
+                &format!("Unknown strategy '{}' (supported: hunter, territory)", name),
+                400,
+            ),
+        }
+    }
+
+    let arena_size = payload["arena_size"].as_u64()
+        .unwrap_or(AI_DEFAULT_ARENA_SIZE)
+        .clamp(5, SIMULATE_MAX_ARENA_SIZE) as i32;
+    let ticks = payload["ticks"].as_u64()
+        .unwrap_or(SIMULATE_DEFAULT_TICKS)
+        .min(SIMULATE_MAX_TICKS);
+    let seed = payload["seed"].as_u64();
+
+    let mut result = run_simulation(&resolved, arena_size, ticks, seed);
+    result["runtime"] = json!("wasmedge");
+    result["castle"] = json!(castle_name());
+    result["timestamp"] = json!(now_ms());
+
+    Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(Body::from(result.to_string()))
+        .unwrap()
+}
+
+struct SimCycle {
+    id: String,
+    strategy: &'static str,
+    decide: AiDecision,
+    x: i32,
+    z: i32,
+    direction: i32,
+    alive: bool,
+    kills: u32,
+    trail: Vec<(i32, i32)>,
+}
+
+// Deterministic xorshift64, so a given seed always replays the same match
+fn next_random(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+// Every random choice the AI makes (for now, breaking exact ties between two
+// turns) draws from this. A seeded AiRng makes /ai-action and /simulate
+// reproducible: same seed and same state, same actions.
+struct AiRng {
+    seed: u64,
+    state: u64,
+}
+
+impl AiRng {
+    fn seeded(seed: u64) -> Self {
+        // xorshift is stuck at zero forever
+        AiRng { seed, state: seed.max(1) }
+    }
+
+    fn from_clock() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        AiRng::seeded(nanos)
+    }
+
+    fn coin(&mut self) -> bool {
+        next_random(&mut self.state) & 1 == 1
+    }
+
+    // True with probability `p` (0.0 to 1.0)
+    fn chance(&mut self, p: f64) -> bool {
+        (next_random(&mut self.state) % 1_000_000) as f64 / 1_000_000.0 < p
+    }
+}
+
+fn run_simulation(strategies: &[(&'static str, AiDecision)], arena_size: i32, max_ticks: u64, seed: Option<u64>) -> serde_json::Value {
+    let player_count = strategies.len();
+    let mut rng = seed.map(|s| s.max(1));
+    let mut ai_rng = seed.map(AiRng::seeded).unwrap_or_else(AiRng::from_clock);
+    let mut occupied: HashMap<(i32, i32), String> = HashMap::new();
+    let mut cycles: Vec<SimCycle> = Vec::new();
+
+    // Without a seed cycles start in fixed columns, alternating top and bottom
+    for (i, &(strategy, decide)) in strategies.iter().enumerate() {
+        let (x, z, direction) = match rng.as_mut() {
+            Some(state) => loop {
+                let x = (next_random(state) % arena_size as u64) as i32;
+                let z = (next_random(state) % arena_size as u64) as i32;
+                if !occupied.contains_key(&(x, z)) {
+                    break (x, z, (next_random(state) % 4) as i32);
+                }
+            },
+            None => {
+                let x = arena_size * (i as i32 + 1) / (player_count as i32 + 1);
+                if i % 2 == 0 { (x, arena_size / 4, 2) } else { (x, arena_size - 1 - arena_size / 4, 0) }
+            }
+        };
+        let id = format!("{}-{}", strategy, i + 1);
+        occupied.insert((x, z), id.clone());
+        cycles.push(SimCycle { id, strategy, decide, x, z, direction, alive: true, kills: 0, trail: vec![(x, z)] });
+    }
+
+    let mut log = Vec::new();
+    let mut ticks_played = 0;
+
+    while ticks_played < max_ticks && cycles.iter().filter(|c| c.alive).count() > 1 {
+        ticks_played += 1;
+
+        // Every cycle decides against the same snapshot, then all moves resolve together
+        let game_data = json!({
+            "arena": { "size": arena_size },
+            "players": cycles.iter().map(|c| (c.id.clone(), json!({
+                "position": { "x": c.x, "z": c.z },
+                "direction": c.direction,
+                "alive": c.alive
+            }))).collect::<serde_json::Map<_, _>>(),
+            "trails": cycles.iter().map(|c| (c.id.clone(), json!(
+                c.trail.iter().map(|(x, z)| format!("{},{}", x, z)).collect::<Vec<_>>()
+            ))).collect::<serde_json::Map<_, _>>()
+        });
+
+        let mut planned = Vec::new();
+        for (i, cycle) in cycles.iter().enumerate().filter(|(_, c)| c.alive) {
+            let action = (cycle.decide)(&game_data, &cycle.id, &mut ai_rng);
+            let direction = action.heading_from(cycle.direction);
+            let (x, z) = calculate_next_position(cycle.x as f64, cycle.z as f64, direction);
+            let (x, z) = grid_cell(x, z);
+            planned.push((i, direction, x, z, action.reason));
+        }
+
+        for (i, direction, x, z, reason) in &planned {
+            let (i, x, z) = (*i, *x, *z);
+            let head_on = planned.iter().any(|(j, _, ox, oz, _)| *j != i && *ox == x && *oz == z);
+            let crash = if x < 0 || z < 0 || x >= arena_size || z >= arena_size {
+                Some("wall".to_string())
+            } else if let Some(owner) = occupied.get(&(x, z)) {
+                Some(owner.clone())
+            } else if head_on {
+                Some("head_on".to_string())
+            } else {
+                None
+            };
+
+            // Running into someone else's trail credits them with the kill
+            if let Some(owner) = crash.as_ref().and_then(|c| cycles.iter().position(|o| &o.id == c)) {
+                if owner != i {
+                    cycles[owner].kills += 1;
+                }
+            }
+
+            let cycle = &mut cycles[i];
+            cycle.direction = *direction;
+            if crash.is_some() {
+                cycle.alive = false;
+            } else {
+                cycle.x = x;
+                cycle.z = z;
+                cycle.trail.push((x, z));
+            }
+
+            log.push(json!({
+                "tick": ticks_played,
+                "player": cycle.id,
+                "direction": direction,
+                "x": x,
+                "z": z,
+                "reason": reason,
+                "crashed_into": crash
+            }));
+        }
+
+        for (i, _, x, z, _) in &planned {
+            if cycles[*i].alive {
+                occupied.insert((*x, *z), cycles[*i].id.clone());
+            }
+        }
+    }
+
+    let survivors: Vec<&SimCycle> = cycles.iter().filter(|c| c.alive).collect();
+    let (outcome, winner) = match survivors.as_slice() {
+        [winner] => ("winner", Some(winner.id.clone())),
+        [] => ("draw", None),
+        _ => ("tick_limit", None),
+    };
+
     json!({
-        "type": "move",
-        "direction": "forward",
-        "reason": "building_defensive_wall"
+        "result": outcome,
+        "winner": winner,
+        "ticks": ticks_played,
+        "arena_size": arena_size,
+        "seed": seed,
+        "players": cycles.iter().map(|c| json!({
+            "id": c.id,
+            "strategy": c.strategy,
+            "alive": c.alive,
+            "x": c.x,
+            "z": c.z,
+            "trail_length": c.trail.len(),
+            "kills": c.kills
+        })).collect::<Vec<_>>(),
+        "moves": log
     })
 }
 
@@ -1350,12 +5106,14 @@ fn calculate_next_position(x: f64, z: f64, direction: i32) -> (f64, f64) {
 
 fn is_position_dangerous(x: f64, z: f64, trails: &serde_json::Map<String, serde_json::Value>, arena_size: f64) -> bool {
     // Check arena bounds
-    if x < 0.0 || x >= arena_size || z < 0.0 || z >= arena_size {
+    let (cell_x, cell_z) = grid_cell(x, z);
+    let size = arena_size as i32;
+    if cell_x < 0 || cell_x >= size || cell_z < 0 || cell_z >= size {
         return true;
     }
     
     // Check trail collisions
-    let pos_key = format!("{},{}", x as i32, z as i32);
+    let pos_key = format!("{},{}", cell_x, cell_z);
     for (_, trail_positions) in trails.iter() {
         if let Some(positions) = trail_positions.as_array() {
             for pos in positions {
@@ -1371,6 +5129,52 @@ fn is_position_dangerous(x: f64, z: f64, trails: &serde_json::Map<String, serde_
     false
 }
 
+// Every "x,z" cell listed in the request's trails
+fn trail_cells(trails: &serde_json::Map<String, serde_json::Value>) -> HashSet<(i32, i32)> {
+    trails.values()
+        .filter_map(|positions| positions.as_array())
+        .flatten()
+        .filter_map(|pos| {
+            let (x, z) = pos.as_str()?.split_once(',')?;
+            Some((x.trim().parse().ok()?, z.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+// How many of the next `depth` steps the cycle can survive heading off in
+// `direction` and turning freely after that. The cells it leaves behind,
+// starting with the one it is on, count as trail, so a turn that curls back
+// into its own fresh wall scores low even when the first cell is open.
+fn projected_room(x: f64, z: f64, direction: i32, blocked: &HashSet<(i32, i32)>, arena_size: f64, depth: u32) -> u32 {
+    fn search(x: i32, z: i32, direction: i32, blocked: &HashSet<(i32, i32)>, arena_size: i32,
+              depth: u32, laid: &mut Vec<(i32, i32)>) -> u32 {
+        if depth == 0 {
+            return 0;
+        }
+        let (next_x, next_z) = calculate_next_position(x as f64, z as f64, direction);
+        let next = grid_cell(next_x, next_z);
+        let in_arena = next.0 >= 0 && next.1 >= 0 && next.0 < arena_size && next.1 < arena_size;
+        if !in_arena || blocked.contains(&next) || laid.contains(&next) {
+            return 0;
+        }
+
+        laid.push(next);
+        let mut best = 0;
+        for turn in [0, 3, 1] {
+            best = best.max(search(next.0, next.1, (direction + turn) % 4, blocked, arena_size, depth - 1, laid));
+            if best == depth - 1 {
+                break;
+            }
+        }
+        laid.pop();
+        best + 1
+    }
+
+    let start = grid_cell(x, z);
+    let mut laid = vec![start];
+    search(start.0, start.1, direction, blocked, arena_size as i32, depth, &mut laid)
+}
+
 fn calculate_safe_distance(x: f64, z: f64, direction: i32, trails: &serde_json::Map<String, serde_json::Value>, arena_size: f64) -> i32 {
     let mut distance = 0;
     let mut current_x = x;
@@ -1388,3 +5192,6 @@ fn calculate_safe_distance(x: f64, z: f64, direction: i32, trails: &serde_json::
     
     distance
 }
+
+#[cfg(test)]
+mod tests;
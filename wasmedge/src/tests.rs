@@ -0,0 +1,1457 @@
+use super::*;
+
+mod mock_oracle;
+
+use mock_oracle::MockServer;
+use serde_json::Value;
+
+lazy_static::lazy_static! {
+    // Tests that touch the game state, the environment or the mock Oracle
+    // take turns; the rest run in parallel
+    static ref SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+    static ref ORACLE: &'static MockServer = {
+        let mock = MockServer::start();
+        env::set_var("ORACLE_BASE_URL", &mock.url);
+        mock
+    };
+}
+
+// Takes the test lock and hands back the mock Oracle, with it and the game
+// state emptied
+async fn oracle() -> (tokio::sync::MutexGuard<'static, ()>, &'static MockServer) {
+    let serial = SERIAL.lock().await;
+    lock_unpoisoned(&GAME_STATE).clear();
+    lock_unpoisoned(&GAME_TICKS).clear();
+    lock_unpoisoned(&LAST_PLAYER_EVENTS).clear();
+    lock_unpoisoned(&CONSUMER_INSTANCES).clear();
+    PAUSED.store(false, Ordering::SeqCst);
+    // Publishes spawned by earlier tests died with those tests' runtimes
+    // without counting themselves off
+    IN_FLIGHT_EVENTS.store(0, Ordering::SeqCst);
+    ORACLE.reset();
+    (serial, *ORACLE)
+}
+
+// Sets an environment variable until dropped, then puts back what was there
+struct EnvVar {
+    name: &'static str,
+    previous: Option<String>,
+}
+
+impl EnvVar {
+    fn set(name: &'static str, value: &str) -> Self {
+        let previous = env::var(name).ok();
+        env::set_var(name, value);
+        Self { name, previous }
+    }
+}
+
+impl Drop for EnvVar {
+    fn drop(&mut self) {
+        match &self.previous {
+            Some(value) => env::set_var(self.name, value),
+            None => env::remove_var(self.name),
+        }
+    }
+}
+
+fn json_request(method: Method, uri: &str, body: &Value) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+// Runs a request through the full handler stack and decodes the JSON reply
+async fn send(req: Request<Body>) -> (u16, Value) {
+    let response = handle_request(req).await.unwrap();
+    let status = response.status().as_u16();
+    let bytes = body_bytes(response).await;
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+async fn call(method: Method, uri: &str, body: Value) -> (u16, Value) {
+    send(json_request(method, uri, &body)).await
+}
+
+async fn join(player_id: &str) -> Value {
+    let (status, body) = call(Method::POST, "/join", json!({"player_id": player_id})).await;
+    assert_eq!(status, 200, "join {} failed: {}", player_id, body);
+    body["player"].clone()
+}
+
+// Events the runtime produced to the game topic, decoded from their records
+fn published_events(oracle: &MockServer) -> Vec<Value> {
+    oracle.records(&get_oracle_config().topic).iter()
+        .filter_map(|record| serde_json::from_str(record["value"].as_str()?).ok())
+        .collect()
+}
+
+async fn wait_for_event(oracle: &MockServer, event_type: &str, player_id: &str) -> Value {
+    for _ in 0..100 {
+        if let Some(event) = published_events(oracle).into_iter()
+            .find(|e| e["type"] == event_type && e["player_id"] == player_id)
+        {
+            return event;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("no {} event for {} was published", event_type, player_id);
+}
+
+async fn body_bytes(response: Response<Body>) -> Vec<u8> {
+    hyper::body::to_bytes(response.into_body()).await.unwrap().to_vec()
+}
+
+#[test]
+fn back_to_back_events_have_monotonic_ms_timestamps() {
+    let first = GameEvent::new("player_moved", "ts-player");
+    let second = GameEvent::new("player_moved", "ts-player");
+    assert!(second.timestamp >= first.timestamp);
+    assert!(second.seq > first.seq);
+    // Seconds since the epoch are ~1.7e9; milliseconds are ~1.7e12
+    assert!(first.timestamp > 1_000_000_000_000);
+    assert!(now_ms() >= second.timestamp);
+}
+
+#[tokio::test]
+async fn large_response_round_trips_through_gzip() {
+    use std::io::Read;
+    let players: Vec<serde_json::Value> = (0..200)
+        .map(|i| json!({"id": format!("player-{}", i), "x": i, "y": i * 2, "direction": "up"}))
+        .collect();
+    let original = serde_json::to_vec(&json!({"players": players})).unwrap();
+    assert!(original.len() > GZIP_MIN_BYTES);
+    let response = Response::builder()
+        .header("content-type", "application/json")
+        .body(Body::from(original.clone()))
+        .unwrap();
+
+    let compressed = gzip_response(response, true).await;
+    assert_eq!(compressed.headers()["content-encoding"], "gzip");
+    assert_eq!(compressed.headers()["vary"], "Accept-Encoding");
+    let bytes = body_bytes(compressed).await;
+    assert!(bytes.len() < original.len());
+    let mut decoded = Vec::new();
+    flate2::read::GzDecoder::new(&bytes[..]).read_to_end(&mut decoded).unwrap();
+    assert_eq!(decoded, original);
+}
+
+#[tokio::test]
+async fn small_or_unaccepted_responses_stay_uncompressed() {
+    let small = gzip_response(Response::new(Body::from("{}")), true).await;
+    assert!(small.headers().get("content-encoding").is_none());
+    assert_eq!(body_bytes(small).await, b"{}");
+
+    let large = vec![b'a'; GZIP_MIN_BYTES * 4];
+    let refused = gzip_response(Response::new(Body::from(large.clone())), false).await;
+    assert!(refused.headers().get("content-encoding").is_none());
+    assert_eq!(body_bytes(refused).await, large);
+}
+
+#[test]
+fn accept_encoding_q_zero_refuses_gzip() {
+    assert!(accepts_encoding("gzip", "gzip"));
+    assert!(accepts_encoding("deflate, GZIP;q=0.5", "gzip"));
+    assert!(!accepts_encoding("gzip;q=0", "gzip"));
+    assert!(!accepts_encoding("br, gzip; q=0.0", "gzip"));
+    assert!(!accepts_encoding("deflate, br", "gzip"));
+    // "*" covers gzip unless gzip is listed on its own
+    assert!(accepts_encoding("*", "gzip"));
+    assert!(!accepts_encoding("*, gzip;q=0", "gzip"));
+    assert!(!accepts_encoding("gzip;q=0, *", "gzip"));
+    assert!(!accepts_encoding("*;q=0", "gzip"));
+}
+
+#[tokio::test]
+async fn join_and_move_publish_events_to_txeventq() {
+    let (_serial, oracle) = oracle().await;
+    let player = join("mock-alice").await;
+    let (status, moved) = call(Method::POST, "/move",
+        json!({"player_id": "mock-alice", "direction": player["direction"]})).await;
+    assert_eq!(status, 200, "{}", moved);
+
+    let joined = wait_for_event(oracle, "player_joined", "mock-alice").await;
+    assert_eq!(joined["runtime"], "wasmedge");
+    let moved_event = wait_for_event(oracle, "player_moved", "mock-alice").await;
+    assert_eq!(moved_event["position"]["x"], moved["player"]["x"]);
+    assert_eq!(moved_event["position"]["y"], moved["player"]["y"]);
+
+    let topic = get_oracle_config().topic;
+    let produce = oracle.wait_for(|r| r.method == Method::POST && r.path().ends_with(&format!("/topics/{}", topic))).await;
+    assert!(produce.header("authorization").unwrap_or_default().starts_with("Basic "));
+    // The topic is created (or found to exist) before the first produce call
+    let requests = oracle.requests();
+    let created = requests.iter().position(|r| r.path().ends_with("/topics") && r.json()["topic_name"] == topic.as_str());
+    let produced = requests.iter().position(|r| r.path().ends_with(&format!("/topics/{}", topic)));
+    assert!(created.unwrap() < produced.unwrap());
+}
+
+#[tokio::test]
+async fn move_saves_the_score_to_ords() {
+    let (_serial, oracle) = oracle().await;
+    let player = join("mock-bob").await;
+    call(Method::POST, "/move", json!({"player_id": "mock-bob", "direction": player["direction"]})).await;
+
+    let update = oracle.wait_for(|r| r.method == Method::POST && r.path().ends_with("/_sdw/scores/")).await;
+    assert_eq!(update.json()["player_id"], "mock-bob");
+    assert_eq!(update.json()["score"], GAME_CONFIG.score_multiplier);
+    assert_eq!(oracle.rows("scores").len(), 1);
+}
+
+#[tokio::test]
+async fn consume_reads_back_published_events_once() {
+    let (_serial, oracle) = oracle().await;
+    let event = GameEvent::new("player_joined", "mock-carol").with_data(json!({"x": 1}));
+    let receipt = publish_to_oracle_kafka(&event).await.unwrap();
+    assert_eq!(receipt, Some(PublishReceipt { partition: 0, offset: 0 }));
+
+    // A record from the other runtime on the shared topic
+    oracle.produce(&get_oracle_config().topic, &json!({"type": "player_moved", "player_id": "wasmtime-carol", "runtime": "wasmtime"}));
+
+    let first = consume_from_oracle_kafka(None).await;
+    assert_eq!(first.error, None);
+    assert_eq!(first.records.len(), 2);
+    assert_eq!(first.records[0]["data"]["type"], "player_joined");
+    assert_eq!(first.records[0]["data"]["player_id"], "mock-carol");
+    assert_eq!(first.records[1]["data"]["runtime"], "wasmtime");
+    assert_eq!(first.records[1]["offset"], 1);
+    assert!(!first.empty);
+
+    // The group's offset moved past the record
+    let second = consume_from_oracle_kafka(None).await;
+    assert!(second.records.is_empty());
+    assert!(second.empty);
+    assert_eq!(lock_unpoisoned(&CONSUMER_INSTANCES).len(), 2);
+}
+
+#[tokio::test]
+async fn consume_creates_a_fresh_state_topic_before_reading_it() {
+    let (_serial, oracle) = oracle().await;
+    let _topic = EnvVar::set("KAFKA_TOPIC", "WASICYCLES_GAME_EVENTS_STATE");
+
+    let result = consume_from_oracle_kafka(None).await;
+    assert_eq!(result.error, None);
+    assert!(result.empty);
+
+    let requests = oracle.requests();
+    let created = requests.iter().position(|r| r.method == Method::POST && r.path().ends_with("/topics")
+        && r.json()["topic_name"] == "WASICYCLES_GAME_EVENTS_STATE").expect("topic was never created");
+    let grouped = requests.iter().position(|r| r.path().contains("/consumer-groups/")).unwrap();
+    let read = requests.iter().position(|r| r.method == Method::GET && r.path().ends_with("/records")).unwrap();
+    assert!(created < grouped && grouped < read, "topic created at {}, group at {}, read at {}", created, grouped, read);
+}
+
+#[tokio::test]
+async fn bulk_import_adds_every_player_to_players() {
+    let (_serial, oracle) = oracle().await;
+    let entries: Vec<Value> = (0..10)
+        .map(|i| json!({"player_id": format!("bulk-{}", i), "x": 2 + i * 4, "y": 5, "direction": "up"}))
+        .collect();
+
+    let _admin = EnvVar::set("ADMIN_TOKEN", "seed");
+    let mut req = json_request(Method::POST, "/players/bulk", &Value::Array(entries));
+    req.headers_mut().insert("x-admin-token", "seed".parse().unwrap());
+    let (status, body) = send(req).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["imported"], 10);
+    assert_eq!(body["failed"], 0);
+
+    let (_, listing) = call(Method::GET, "/players", Value::Null).await;
+    assert_eq!(listing["count"], 10);
+    let mut ids: Vec<&str> = listing["players"].as_array().unwrap().iter().map(|p| p["id"].as_str().unwrap()).collect();
+    ids.sort_unstable();
+    assert_eq!(ids, (0..10).map(|i| format!("bulk-{}", i)).collect::<Vec<_>>());
+    // The joins go out as one batch
+    wait_for_event(oracle, "player_joined", "bulk-9").await;
+    assert_eq!(published_events(oracle).len(), 10);
+}
+
+#[tokio::test]
+async fn ready_fails_while_oracle_is_down_but_health_does_not() {
+    let (_serial, _oracle) = oracle().await;
+    let (status, body) = call(Method::GET, "/ready", Value::Null).await;
+    assert_eq!(status, 200, "{}", body);
+
+    let _down = EnvVar::set("ORACLE_BASE_URL", &MockServer::refused_url());
+    let (status, body) = call(Method::GET, "/ready", Value::Null).await;
+    assert_eq!(status, 503);
+    assert_eq!(body["status"], "not_ready");
+    assert!(body["problems"][0].as_str().unwrap().contains("Oracle unreachable"), "{}", body);
+    assert_eq!(call(Method::GET, "/health", Value::Null).await.0, 200);
+}
+
+#[tokio::test]
+async fn known_paths_with_the_wrong_method_get_405_with_allow() {
+    let (_serial, _oracle) = oracle().await;
+    for path in ["/join", "/players/bulk"] {
+        let response = handle_request(json_request(Method::GET, path, &Value::Null)).await.unwrap();
+        assert_eq!(response.status(), 405, "{}", path);
+        assert_eq!(response.headers()["allow"], "POST", "{}", path);
+    }
+    assert_eq!(call(Method::GET, "/nonexistent", Value::Null).await.0, 404);
+}
+
+#[tokio::test]
+async fn failed_instance_creation_skips_the_records_call() {
+    let (_serial, oracle) = oracle().await;
+    oracle.respond(Method::POST, "/consumers/", 500, json!({"message": "ORA-24000"}));
+
+    let result = consume_from_oracle_kafka(None).await;
+    assert!(!result.empty);
+    let error = result.error.unwrap();
+    assert!(error.starts_with("consumer_setup_failed"), "{}", error);
+    assert!(!oracle.requests().iter().any(|r| r.method == Method::GET && r.path().ends_with("/records")));
+}
+
+#[tokio::test]
+async fn failed_records_call_is_reported_not_empty() {
+    let (_serial, oracle) = oracle().await;
+    oracle.respond(Method::GET, "/records", 500, json!({"message": "ORA-00600"}));
+
+    let result = consume_from_oracle_kafka(None).await;
+    assert!(result.records.is_empty());
+    assert!(!result.empty);
+    assert!(result.error.unwrap().contains("ORA-00600"));
+}
+
+#[tokio::test]
+async fn leaderboard_is_unavailable_while_ords_is_down() {
+    let (_serial, _oracle) = oracle().await;
+    let _down = EnvVar::set("ORACLE_BASE_URL", &MockServer::refused_url());
+
+    let (status, body) = call(Method::GET, "/leaderboard", Value::Null).await;
+    assert_eq!(status, 503);
+    assert!(body["error"].as_str().is_some());
+}
+
+#[tokio::test]
+async fn oracle_refusing_connections_is_a_503_with_retry_after() {
+    let (_serial, _oracle) = oracle().await;
+    let _down = EnvVar::set("ORACLE_BASE_URL", &MockServer::refused_url());
+
+    for path in ["/leaderboard", "/consume-kafka", "/health?deep=true"] {
+        let response = handle_request(json_request(Method::GET, path, &Value::Null)).await.unwrap();
+        assert_eq!(response.status(), 503, "{}", path);
+        let retry_after = response.headers().get("retry-after").and_then(|v| v.to_str().ok());
+        assert_eq!(retry_after, Some(ORACLE_RETRY_AFTER_SECS.to_string().as_str()), "{}", path);
+    }
+    // Only ?deep=true reaches out to Oracle
+    let (status, _) = call(Method::GET, "/health", Value::Null).await;
+    assert_eq!(status, 200);
+}
+
+#[tokio::test]
+async fn oracle_calls_send_the_header_for_the_auth_mode() {
+    let (_serial, oracle) = oracle().await;
+    let _user = EnvVar::set("ORACLE_USERNAME", "cycles");
+    let _password = EnvVar::set("ORACLE_PASSWORD", "hunter2");
+    let _token = EnvVar::set("ORACLE_BEARER_TOKEN", "tok-123");
+
+    let leaderboard_auth = |oracle: &MockServer| {
+        let request = oracle.requests().into_iter().rev().find(|r| r.path().contains("/_sdw/leaderboard")).unwrap();
+        request.header("authorization").unwrap_or_default().to_string()
+    };
+
+    call(Method::GET, "/leaderboard", Value::Null).await;
+    assert_eq!(leaderboard_auth(oracle), format!("Basic {}", BASE64.encode("cycles:hunter2")));
+
+    let _bearer = EnvVar::set("ORACLE_AUTH_MODE", "bearer");
+    call(Method::GET, "/leaderboard", Value::Null).await;
+    assert_eq!(leaderboard_auth(oracle), "Bearer tok-123");
+    publish_to_oracle_kafka(&GameEvent::new("player_joined", "bearer-auth")).await.unwrap();
+    let produce = oracle.requests().into_iter().rev().find(|r| r.method == Method::POST && r.path().contains("/topics/")).unwrap();
+    assert_eq!(produce.header("authorization"), Some("Bearer tok-123"));
+}
+
+#[tokio::test]
+async fn leaderboard_lists_ords_rows() {
+    let (_serial, oracle) = oracle().await;
+    oracle.insert_rows("leaderboard", vec![
+        json!({"player_id": "mock-dave", "score": 40}),
+        json!({"player_id": "mock-erin", "score": 25}),
+    ]);
+
+    let (status, body) = call(Method::GET, "/leaderboard", Value::Null).await;
+    assert_eq!(status, 200);
+    assert_eq!(body["leaderboard"]["count"], 2);
+    assert_eq!(body["leaderboard"]["items"][0]["player_id"], "mock-dave");
+    let read = oracle.wait_for(|r| r.method == Method::GET && r.path().ends_with("/_sdw/leaderboard/")).await;
+    assert!(read.header("authorization").is_some());
+}
+
+#[tokio::test]
+async fn leaderboard_merges_every_ords_page() {
+    let (_serial, oracle) = oracle().await;
+    let next = format!("{}/leaderboard/?offset=2", get_oracle_config().ords_url);
+    // Page two first: the first canned response whose fragment matches wins
+    oracle.respond(Method::GET, "/leaderboard/?offset=2", 200, json!({
+        "items": [{"player_id": "page2-ivan", "score": 10}],
+        "hasMore": false,
+        "links": []
+    }));
+    oracle.respond(Method::GET, "/leaderboard/", 200, json!({
+        "items": [{"player_id": "page1-judy", "score": 30}, {"player_id": "page1-ken", "score": 20}],
+        "hasMore": true,
+        "limit": 2,
+        "offset": 0,
+        "links": [{"rel": "self", "href": "ignored"}, {"rel": "next", "href": next}]
+    }));
+
+    let (status, body) = call(Method::GET, "/leaderboard", Value::Null).await;
+    assert_eq!(status, 200, "{}", body);
+    let ids: Vec<&str> = body["leaderboard"]["items"].as_array().unwrap().iter().map(|row| row["player_id"].as_str().unwrap()).collect();
+    assert_eq!(ids, ["page1-judy", "page1-ken", "page2-ivan"]);
+    assert_eq!(body["leaderboard"]["count"], 3);
+    assert_eq!(body["leaderboard"]["hasMore"], false);
+}
+
+#[tokio::test]
+async fn state_file_saves_and_loads_players() {
+    let (_serial, _oracle) = oracle().await;
+    let path = env::temp_dir().join(format!("wasicycles-state-{}.json", generate_request_id()));
+    let _state_file = EnvVar::set("STATE_FILE", path.to_str().unwrap());
+
+    let player = join("mock-frank").await;
+    let saved = load_state_file(path.to_str().unwrap()).unwrap();
+    assert_eq!(saved["mock-frank"].x, player["x"].as_f64().unwrap());
+    assert_eq!(saved["mock-frank"].direction, player["direction"].as_str().unwrap());
+    std::fs::remove_file(&path).ok();
+}
+
+async fn join_heading(player_id: &str, direction: &str) {
+    let (status, body) = call(Method::POST, "/join", json!({"player_id": player_id, "direction": direction})).await;
+    assert_eq!(status, 200, "{}", body);
+}
+
+async fn move_player(player_id: &str, direction: &str) -> (u16, Value) {
+    call(Method::POST, "/move", json!({"player_id": player_id, "direction": direction})).await
+}
+
+#[tokio::test]
+async fn each_move_advances_the_tick_by_one() {
+    let (_serial, _oracle) = oracle().await;
+    let (_, joined) = call(Method::POST, "/join", json!({"player_id": "ticker", "direction": "right"})).await;
+    let mut last = joined["tick"].as_u64().expect("join reports a tick");
+    for direction in ["right", "down", "down", "left"] {
+        let (status, body) = move_player("ticker", direction).await;
+        assert_eq!(status, 200, "{}", body);
+        let tick = body["tick"].as_u64().expect("move reports a tick");
+        assert_eq!(tick, last + 1);
+        last = tick;
+    }
+}
+
+#[tokio::test]
+async fn responses_and_events_use_the_configured_castle_name() {
+    let (_serial, oracle) = oracle().await;
+    let _castle = EnvVar::set("CASTLE_NAME", "Rebranded Keep");
+    let mut bodies = vec![
+        call(Method::POST, "/join", json!({"player_id": "keeper", "direction": "right"})).await.1,
+        move_player("keeper", "right").await.1,
+    ];
+    for path in ["/", "/health", "/players", "/players/keeper", "/leaderboard", "/config", "/ai-action"] {
+        bodies.push(call(Method::GET, path, Value::Null).await.1);
+    }
+    bodies.push(call(Method::POST, "/leave", json!({"player_id": "keeper"})).await.1);
+
+    for body in &bodies {
+        if let Some(castle) = body.get("castle") {
+            assert_eq!(castle, "Rebranded Keep", "{}", body);
+        }
+        assert!(!body.to_string().contains("Quantum Nexus"), "{}", body);
+    }
+    assert!(bodies.iter().filter(|b| b.get("castle").is_some()).count() >= 4);
+
+    let event = wait_for_event(oracle, "player_left", "keeper").await;
+    assert_eq!(event["castle"], "Rebranded Keep");
+}
+
+#[tokio::test]
+async fn moves_straight_back_are_rejected() {
+    let (_serial, _oracle) = oracle().await;
+    join_heading("reverse-up", "up").await;
+    let (status, body) = move_player("reverse-up", "down").await;
+    assert_eq!(status, 400);
+    assert_eq!(body["error"], "Cannot reverse direction");
+
+    join_heading("reverse-left", "left").await;
+    let (status, body) = move_player("reverse-left", "right").await;
+    assert_eq!(status, 400);
+    assert_eq!(body["error"], "Cannot reverse direction");
+
+    // Turning is still allowed, and the rejected moves left the players in place
+    assert_eq!(move_player("reverse-up", "left").await.0, 200);
+    assert_eq!(lock_unpoisoned(&GAME_STATE)["reverse-left"].stats.moves, 0);
+}
+
+#[tokio::test]
+async fn allow_reverse_permits_moves_straight_back() {
+    let (_serial, _oracle) = oracle().await;
+    let _allow = EnvVar::set("ALLOW_REVERSE", "true");
+    join_heading("reverse-allowed", "up").await;
+    let (status, body) = move_player("reverse-allowed", "down").await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["player"]["direction"], "down");
+}
+
+#[tokio::test]
+async fn game_id_is_carried_through_events_and_score_updates() {
+    let (_serial, oracle) = oracle().await;
+    let (status, body) = call(Method::POST, "/join", json!({"player_id": "match-a", "game_id": "A", "direction": "up"})).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["player"]["game_id"], "A");
+    move_player("match-a", "left").await;
+
+    assert_eq!(wait_for_event(oracle, "player_joined", "match-a").await["game_id"], "A");
+    assert_eq!(wait_for_event(oracle, "player_moved", "match-a").await["game_id"], "A");
+    let update = oracle.wait_for(|r| r.path().ends_with("/_sdw/scores/") && r.json()["player_id"] == "match-a").await;
+    assert_eq!(update.json()["game_id"], "A");
+
+    // Without one, players land in GAME_ID's game
+    let _game = EnvVar::set("GAME_ID", "B");
+    assert_eq!(join("match-b").await["game_id"], "B");
+}
+
+fn keyed_join(player_id: &str, api_key: Option<&str>) -> Request<Body> {
+    let mut req = json_request(Method::POST, "/join", &json!({"player_id": player_id}));
+    if let Some(key) = api_key {
+        req.headers_mut().insert("x-api-key", key.parse().unwrap());
+    }
+    req
+}
+
+#[tokio::test]
+async fn api_key_gates_writes_when_configured() {
+    let (_serial, _oracle) = oracle().await;
+    let _key = EnvVar::set("API_KEY", "secret");
+
+    let (status, body) = send(keyed_join("keyed-none", None)).await;
+    assert_eq!(status, 401);
+    assert_eq!(body["error"], "Invalid or missing X-API-Key");
+    assert_eq!(send(keyed_join("keyed-wrong", Some("guess"))).await.0, 401);
+    assert_eq!(send(keyed_join("keyed-right", Some("secret"))).await.0, 200);
+    assert!(!lock_unpoisoned(&GAME_STATE).contains_key("keyed-wrong"));
+
+    // Reads stay open unless API_KEY_READS asks for them to be gated too
+    assert_eq!(call(Method::GET, "/players", Value::Null).await.0, 200);
+    let _reads = EnvVar::set("API_KEY_READS", "true");
+    assert_eq!(call(Method::GET, "/players", Value::Null).await.0, 401);
+    assert_eq!(call(Method::GET, "/health", Value::Null).await.0, 200);
+}
+
+#[tokio::test]
+async fn spectate_token_reads_but_cannot_write() {
+    let (_serial, _oracle) = oracle().await;
+    let _key = EnvVar::set("API_KEY", "secret");
+    let _reads = EnvVar::set("API_KEY_READS", "true");
+    let _spectate = EnvVar::set("SPECTATE_TOKEN", "watcher");
+    let spectating = |method, uri: &str, body: Value| {
+        let mut req = json_request(method, uri, &body);
+        req.headers_mut().insert("x-api-key", "watcher".parse().unwrap());
+        req
+    };
+
+    assert_eq!(send(spectating(Method::GET, "/players", Value::Null)).await.0, 200);
+    let (status, body) = send(spectating(Method::POST, "/move", json!({"player_id": "p", "direction": "up"}))).await;
+    assert_eq!(status, 401);
+    assert_eq!(body["error"], "Invalid or missing X-API-Key");
+}
+
+#[tokio::test]
+async fn join_past_max_players_is_rejected() {
+    let (_serial, _oracle) = oracle().await;
+    for i in 0..GAME_CONFIG.max_players {
+        join(&format!("full-{}", i)).await;
+    }
+
+    let (status, body) = call(Method::POST, "/join", json!({"player_id": "one-too-many"})).await;
+    assert_eq!(status, 503);
+    assert_eq!(body["error"], "game_full");
+    assert_eq!(body["current_players"], GAME_CONFIG.max_players);
+    assert_eq!(body["max_players"], GAME_CONFIG.max_players);
+    let mut keys: Vec<&str> = body.as_object().unwrap().keys().map(String::as_str).collect();
+    keys.sort_unstable();
+    assert_eq!(keys, ["castle", "current_players", "error", "max_players", "runtime", "status"]);
+    assert!(!lock_unpoisoned(&GAME_STATE).contains_key("one-too-many"));
+
+    // A player already in the game can still re-join
+    join("full-0").await;
+}
+
+#[tokio::test]
+async fn stats_add_up_over_a_short_game() {
+    let (_serial, oracle) = oracle().await;
+    join_heading("stats-a", "up").await;
+    join_heading("stats-b", "up").await;
+    let step = GAME_CONFIG.move_step;
+    {
+        let mut players = lock_unpoisoned(&GAME_STATE);
+        let a = players.get_mut("stats-a").unwrap();
+        a.x = 400.0;
+        a.y = 300.0;
+        let b = players.get_mut("stats-b").unwrap();
+        b.x = 100.0;
+        b.y = 100.0;
+        // stats-a's third move runs into this
+        b.trail = vec![Position { x: 400.0, y: 300.0 - 3.0 * step }];
+    }
+
+    assert_eq!(move_player("stats-a", "up").await.0, 200);
+    assert_eq!(move_player("stats-a", "up").await.0, 200);
+    let (status, crashed) = move_player("stats-a", "up").await;
+    assert_eq!(status, 200, "{}", crashed);
+
+    let (_, a) = call(Method::GET, "/players/stats-a", Value::Null).await;
+    assert_eq!(a["player"]["alive"], false);
+    assert_eq!(a["stats"]["moves"], 3);
+    assert_eq!(a["stats"]["distance"], 3.0 * step);
+    let (_, b) = call(Method::GET, "/players/stats-b", Value::Null).await;
+    assert_eq!(b["stats"]["kills"], 1);
+    assert_eq!(b["stats"]["moves"], 0);
+
+    // The ORDS score row carries the stats alongside the score
+    let update = oracle.wait_for(|r| r.path().ends_with("/_sdw/scores/") && r.json()["stats"]["moves"] == 3).await;
+    assert_eq!(update.json()["player_id"], "stats-a");
+}
+
+#[tokio::test]
+async fn a_crashed_cycle_respawns_with_a_clean_trail_and_moves_again() {
+    let (_serial, oracle) = oracle().await;
+    join("phoenix").await;
+    {
+        let mut players = lock_unpoisoned(&GAME_STATE);
+        let phoenix = players.get_mut("phoenix").unwrap();
+        phoenix.alive = false;
+        phoenix.score = 5;
+        phoenix.trail = vec![Position { x: 0.0, y: 0.0 }, Position { x: 0.0, y: GAME_CONFIG.move_step }];
+    }
+    assert_eq!(move_player("phoenix", "up").await.0, 400);
+
+    let (status, body) = call(Method::POST, "/respawn", json!({"player_id": "phoenix"})).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["player"]["alive"], true);
+    assert_eq!(body["player"]["trail"], json!([]));
+    assert_eq!(body["player"]["score"], 5);
+    wait_for_event(oracle, "player_respawned", "phoenix").await;
+
+    let (status, moved) = move_player("phoenix", "up").await;
+    assert_eq!(status, 200, "{}", moved);
+    assert_eq!(moved["player"]["alive"], true);
+}
+
+fn player_at(id: &str, x: f64, y: f64) -> Player {
+    serde_json::from_value(json!({
+        "id": id, "x": x, "y": y, "direction": "up", "score": 0, "color": "", "alive": true,
+        "game_id": default_game_id()
+    })).unwrap()
+}
+
+#[test]
+fn trails_are_capped_even_without_trail_length() {
+    let mut player = player_at("long-trail", 0.0, 0.0);
+    for i in 0..MAX_TRAIL_CELLS + 5 {
+        player.lay_trail(Position { x: i as f64, y: 0.0 }, 0);
+    }
+    assert_eq!(player.trail.len(), MAX_TRAIL_CELLS);
+    assert_eq!(player.trail[0].x, 5.0);
+
+    player.lay_trail(Position { x: -1.0, y: 0.0 }, 3);
+    assert_eq!(player.trail.len(), 3);
+}
+
+#[tokio::test]
+async fn simulate_runs_a_strategy_per_cycle() {
+    let request = json!({"runtimes": ["hunter", "territory"], "arena_size": 12, "ticks": 200, "seed": 7});
+    let (status, first) = call(Method::POST, "/simulate", request.clone()).await;
+    assert_eq!(status, 200, "{}", first);
+    assert_eq!(first["players"][0]["id"], "hunter-1");
+    assert_eq!(first["players"][0]["strategy"], "hunter");
+    assert_eq!(first["players"][1]["id"], "territory-2");
+    assert_eq!(first["players"][1]["strategy"], "territory");
+
+    // Seeded matches replay move for move
+    let (_, second) = call(Method::POST, "/simulate", request).await;
+    assert_eq!(first["moves"], second["moves"]);
+    assert_eq!(first["winner"], second["winner"]);
+
+    let (status, body) = call(Method::POST, "/simulate", json!({"runtimes": ["hunter", "chess"]})).await;
+    assert_eq!(status, 400);
+    assert!(body["error"].as_str().unwrap().contains("chess"));
+}
+
+fn join_with_content_type(player_id: &str, content_type: Option<&str>) -> Request<Body> {
+    let mut req = json_request(Method::POST, "/join", &json!({"player_id": player_id}));
+    req.headers_mut().remove("content-type");
+    if let Some(content_type) = content_type {
+        req.headers_mut().insert("content-type", content_type.parse().unwrap());
+    }
+    req
+}
+
+#[tokio::test]
+async fn text_plain_join_is_unsupported_media_type() {
+    let (_serial, _oracle) = oracle().await;
+    let (status, body) = send(join_with_content_type("typed-text", Some("text/plain"))).await;
+    assert_eq!(status, 415);
+    assert!(body["error"].as_str().unwrap().contains("text/plain"));
+    assert!(!lock_unpoisoned(&GAME_STATE).contains_key("typed-text"));
+
+    // No header is fine when the body parses, and parameters don't matter
+    assert_eq!(send(join_with_content_type("typed-none", None)).await.0, 200);
+    assert_eq!(send(join_with_content_type("typed-utf8", Some("application/json; charset=utf-8"))).await.0, 200);
+}
+
+#[tokio::test]
+async fn players_response_round_trips_through_msgpack() {
+    let (_serial, _oracle) = oracle().await;
+    join("packed-1").await;
+    join("packed-2").await;
+
+    let req = Request::builder()
+        .uri("/players")
+        .header("accept", "application/msgpack")
+        .body(Body::empty())
+        .unwrap();
+    let response = handle_request(req).await.unwrap();
+    assert_eq!(response.headers()["content-type"], "application/msgpack");
+    let bytes = body_bytes(response).await;
+    let decoded: Value = rmp_serde::from_slice(&bytes).unwrap();
+
+    let (_, json) = call(Method::GET, "/players", Value::Null).await;
+    assert_eq!(decoded["count"], 2);
+    assert_eq!(decoded["players"], json["players"]);
+}
+
+#[tokio::test]
+async fn move_answers_without_waiting_for_the_publish() {
+    let (_serial, oracle) = oracle().await;
+    join_heading("unhurried", "up").await;
+    // Every produce call fails; the move must not care
+    let topic = get_oracle_config().topic;
+    oracle.respond(Method::POST, &format!("/topics/{}", topic), 500, json!({"message": "ORA-24002"}));
+
+    let (status, body) = move_player("unhurried", "left").await;
+    assert_eq!(status, 200, "{}", body);
+    assert!(body.get("event_offset").is_none());
+    oracle.wait_for(|r| r.method == Method::POST && r.path().ends_with(&format!("/topics/{}", topic)) && r.json()["records"][0]["value"].as_str().unwrap_or_default().contains("player_moved")).await;
+}
+
+async fn acknowledged_move(player_id: &str, direction: &str) -> (u16, Value) {
+    call(Method::POST, "/move?ack=true", json!({"player_id": player_id, "direction": direction})).await
+}
+
+#[tokio::test]
+async fn an_acknowledged_move_reports_where_its_event_landed() {
+    let (_serial, oracle) = oracle().await;
+    oracle.set_partitions(2);
+    join_heading("traced", "up").await;
+    let (status, body) = acknowledged_move("traced", "left").await;
+    assert_eq!(status, 200, "{}", body);
+
+    let offset = &body["event_offset"];
+    let record = oracle.records(&get_oracle_config().topic).into_iter()
+        .find(|r| r["partition"] == offset["partition"] && r["offset"] == offset["offset"])
+        .unwrap_or_else(|| panic!("no record at {}", offset));
+    let event: Value = serde_json::from_str(record["value"].as_str().unwrap()).unwrap();
+    assert_eq!(event["type"], "player_moved");
+    assert_eq!(event["player_id"], "traced");
+
+    // A produce Oracle refuses still moves the player, just without an offset
+    oracle.respond(Method::POST, &format!("/topics/{}", get_oracle_config().topic), 500, json!({"message": "ORA-24002"}));
+    let (status, body) = acknowledged_move("traced", "up").await;
+    assert_eq!(status, 200, "{}", body);
+    assert!(body["event_offset"].is_null());
+}
+
+#[tokio::test]
+async fn adjacent_spawns_do_not_face_each_other() {
+    let (_serial, _oracle) = oracle().await;
+    let step = GAME_CONFIG.move_step;
+    let left = player_at("adjacent-left", 400.0, 300.0);
+    let right = player_at("adjacent-right", 400.0 + step, 300.0);
+    let players: HashMap<String, Player> = [left.clone(), right.clone()].into_iter()
+        .map(|p| (p.id.clone(), p))
+        .collect();
+
+    assert_ne!(open_direction(&players, &left), Direction::Right);
+    assert_ne!(open_direction(&players, &right), Direction::Left);
+
+    let (status, body) = call(Method::POST, "/join", json!({"player_id": "adjacent-bad", "direction": "sideways"})).await;
+    assert_eq!(status, 400, "{}", body);
+}
+
+#[tokio::test]
+async fn leaderboard_reads_a_bare_array_from_ords() {
+    let (_serial, oracle) = oracle().await;
+    oracle.respond(Method::GET, "/_sdw/leaderboard/", 200, json!([
+        {"player_id": "bare-1", "score": 9},
+        {"player_id": "bare-2", "score": 4}
+    ]));
+
+    let (status, body) = call(Method::GET, "/leaderboard", Value::Null).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["leaderboard"]["count"], 2);
+    assert_eq!(body["leaderboard"]["items"][1]["player_id"], "bare-2");
+    assert_eq!(body["leaderboard"]["partial"], false);
+}
+
+#[tokio::test]
+async fn leaderboard_keeps_earlier_pages_when_a_later_one_fails() {
+    let (_serial, oracle) = oracle().await;
+    oracle.respond(Method::GET, "/_sdw/leaderboard/?page=2", 500, json!({"message": "ORA-03113"}));
+    oracle.respond(Method::GET, "/_sdw/leaderboard/", 200, json!({
+        "items": [{"player_id": "paged-1", "score": 30}],
+        "hasMore": true,
+        "links": [{"rel": "next", "href": format!("{}/_sdw/leaderboard/?page=2", oracle.url)}]
+    }));
+
+    let (status, body) = call(Method::GET, "/leaderboard", Value::Null).await;
+    assert_eq!(status, 200, "{}", body);
+    let listing = &body["leaderboard"];
+    assert_eq!(listing["partial"], true);
+    assert_eq!(listing["count"], 1);
+    assert_eq!(listing["items"][0]["player_id"], "paged-1");
+    assert!(listing["error"].as_str().unwrap().contains("500"));
+}
+
+#[tokio::test]
+async fn trailing_slash_reaches_the_same_handler() {
+    let (_serial, _oracle) = oracle().await;
+    join("slashed").await;
+
+    let (status, health) = call(Method::GET, "/health/", Value::Null).await;
+    assert_eq!(status, 200);
+    assert_eq!(health["status"], call(Method::GET, "/health", Value::Null).await.1["status"]);
+
+    let (status, with_slash) = call(Method::GET, "/players/", Value::Null).await;
+    assert_eq!(status, 200);
+    let (_, without) = call(Method::GET, "/players", Value::Null).await;
+    assert_eq!(with_slash["players"], without["players"]);
+
+    // Matching stays case-sensitive
+    assert_eq!(call(Method::GET, "/Players", Value::Null).await.0, 404);
+}
+
+fn sorted_keys(body: &Value) -> Vec<String> {
+    let mut keys: Vec<String> = body.as_object().unwrap().keys().cloned().collect();
+    keys.sort_unstable();
+    keys
+}
+
+// wasmtime's tests pin the same key list for its error bodies
+#[tokio::test]
+async fn error_bodies_share_one_shape() {
+    let (_serial, _oracle) = oracle().await;
+    join_heading("shaped", "up").await;
+    let rejections = [
+        move_player("nobody-here", "up").await,
+        move_player("shaped", "sideways").await,
+        move_player("shaped", "down").await,
+        call(Method::POST, "/move", json!({"direction": "up"})).await,
+    ];
+    for (status, body) in rejections {
+        assert!(status >= 400, "{}", body);
+        assert_eq!(body["status"], status);
+        assert_eq!(body["runtime"], "wasmedge");
+        assert_eq!(sorted_keys(&body), ["castle", "error", "runtime", "status"]);
+    }
+}
+
+#[tokio::test]
+async fn empty_batch_sets_empty() {
+    let (_serial, _oracle) = oracle().await;
+    let result = consume_from_oracle_kafka(None).await;
+    assert!(result.empty);
+    assert!(result.records.is_empty());
+    assert_eq!(result.error, None);
+}
+
+#[tokio::test]
+async fn drain_empties_both_partitions() {
+    let (_serial, oracle) = oracle().await;
+    let _partitions = EnvVar::set("KAFKA_PARTITIONS", "2");
+    oracle.set_partitions(2);
+    let topic = get_oracle_config().topic;
+    for i in 0..5 {
+        oracle.produce(&topic, &json!({"type": "player_moved", "player_id": format!("p{}", i)}));
+    }
+
+    let (status, body) = call(Method::GET, "/drain-messages", Value::Null).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["total_count"], 5);
+    assert_eq!(body["partitions"]["0"]["records"], 3);
+    assert_eq!(body["partitions"]["1"]["records"], 2);
+    assert_eq!(body["fully_drained"], true);
+}
+
+#[tokio::test]
+async fn failed_group_creation_is_reported_not_empty() {
+    let (_serial, oracle) = oracle().await;
+    oracle.respond(Method::POST, "consumer-groups", 500, json!({"message": "internal error"}));
+    let result = consume_from_oracle_kafka(None).await;
+    assert!(!result.empty);
+    assert!(result.records.is_empty());
+    let error = result.error.expect("group creation failure should be surfaced");
+    assert!(error.contains("500"), "{}", error);
+}
+
+// A cycle at (10,10) heading north with its own trail curled around it: the
+// cell ahead is taken, west is a four-cell dead end walled in by that trail,
+// east only runs three cells straight but opens up after that
+fn spiral_game() -> Value {
+    let mut trail = vec!["10,10".to_string(), "10,9".to_string(), "5,10".to_string(), "14,10".to_string()];
+    for x in 6..=9 {
+        trail.push(format!("{},9", x));
+        trail.push(format!("{},11", x));
+    }
+    json!({
+        "arena": {"size": 20},
+        "players": {"spiral": {"position": {"x": 10, "z": 10}, "direction": 0, "alive": true}},
+        "trails": {"spiral": trail}
+    })
+}
+
+#[test]
+fn ai_turns_away_from_a_dead_end_its_own_trail_made() {
+    let game = spiral_game();
+    let trails = game["trails"].as_object().unwrap();
+    // Judged by straight-line distance alone, west looks like the way out
+    let west = calculate_safe_distance(10.0, 10.0, 3, trails, 20.0);
+    let east = calculate_safe_distance(10.0, 10.0, 1, trails, 20.0);
+    assert!(west > east);
+
+    let blocked = trail_cells(trails);
+    assert!(projected_room(10.0, 10.0, 3, &blocked, 20.0, AI_DANGER_LOOKAHEAD) < AI_DANGER_LOOKAHEAD);
+    for seed in 1..20 {
+        let action = get_wasmedge_ai_action(&game, "spiral", &mut AiRng::seeded(seed));
+        assert_eq!(action.direction, AiDirection::Right, "{:?}", action);
+    }
+}
+
+fn lone_cycle(x: i32, z: i32, direction: i32) -> Value {
+    json!({
+        "arena": {"size": 20},
+        "players": {"solo": {"position": {"x": x, "z": z}, "direction": direction, "alive": true}},
+        "trails": {"solo": [format!("{},{}", x, z)]}
+    })
+}
+
+#[test]
+fn territory_ai_picks_typed_actions_for_known_positions() {
+    let decide = |game: Value| get_wasmedge_ai_action(&game, "solo", &mut AiRng::seeded(1));
+
+    // Settled in the middle with open road ahead: keep laying wall
+    assert_eq!(decide(lone_cycle(10, 10, 0)), AiAction::forward("building_defensive_wall"));
+    // Out by the west wall heading north: swing east toward the centre
+    assert_eq!(decide(lone_cycle(2, 10, 0)), AiAction::turn(AiDirection::Right, "moving_to_center_right"));
+    // Boxed into the top-left corner: only the right turn has room
+    assert_eq!(decide(lone_cycle(0, 0, 0)), AiAction::turn(AiDirection::Right, "avoid_collision_right_only"));
+    // Not in the game yet
+    let waiting = get_wasmedge_ai_action(&lone_cycle(10, 10, 0), "absent", &mut AiRng::seeded(1));
+    assert_eq!(waiting.action_type, AiActionType::Turn);
+    assert_eq!(waiting.direction, AiDirection::Forward);
+
+    assert_eq!(
+        serde_json::to_value(AiAction::turn(AiDirection::Left, "x")).unwrap(),
+        json!({"type": "turn", "direction": "left", "reason": "x"})
+    );
+}
+
+#[tokio::test]
+async fn ai_finds_itself_under_a_custom_id() {
+    let game = |my_id: Option<&str>| {
+        let mut game = json!({
+            "arena": {"size": 20},
+            "players": {"bot-7": {"position": {"x": 10, "z": 10}, "direction": 0, "alive": true}},
+            "trails": {"bot-7": ["10,10"]},
+            "seed": 3
+        });
+        if let Some(id) = my_id {
+            game["my_id"] = json!(id);
+        }
+        game
+    };
+
+    let (status, body) = call(Method::POST, "/ai-action", game(Some("bot-7"))).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["ai_player_id"], "bot-7");
+    assert_ne!(body["action"]["reason"], "initializing");
+
+    // Without my_id the bot looks for the default id, which isn't in this game
+    let (_, body) = call(Method::POST, "/ai-action", game(None)).await;
+    assert_eq!(body["ai_player_id"], AI_DEFAULT_PLAYER_ID);
+    assert_eq!(body["action"]["reason"], "initializing");
+}
+
+#[tokio::test]
+async fn shutdown_waits_for_buffered_events() {
+    let (_serial, oracle) = oracle().await;
+    for i in 0..3 {
+        spawn_publish(GameEvent::new("player_moved", &format!("draining-{}", i)));
+    }
+
+    drain_in_flight_events().await;
+    assert_eq!(IN_FLIGHT_EVENTS.load(Ordering::SeqCst), 0);
+    let metrics = handle_request(Request::get("/metrics").body(Body::empty()).unwrap()).await.unwrap();
+    let metrics = String::from_utf8(body_bytes(metrics).await).unwrap();
+    assert!(metrics.contains("wasicycles_event_buffer_depth{runtime=\"wasmedge\"} 0\n"), "{}", metrics);
+    let drained = published_events(oracle).iter()
+        .filter(|e| e["player_id"].as_str().unwrap_or_default().starts_with("draining-"))
+        .count();
+    assert_eq!(drained, 3);
+}
+
+#[tokio::test]
+async fn moves_inside_the_cooldown_are_throttled() {
+    let (_serial, _oracle) = oracle().await;
+    let _cooldown = EnvVar::set("MOVE_COOLDOWN_MS", "60000");
+    join("hasty").await;
+
+    let (status, body) = move_player("hasty", "right").await;
+    assert_eq!(status, 200, "{}", body);
+    let (status, body) = move_player("hasty", "right").await;
+    assert_eq!(status, 429, "{}", body);
+}
+
+#[tokio::test]
+async fn move_events_round_trip_with_top_level_player_fields() {
+    let (_serial, _oracle) = oracle().await;
+    let mut player = player_at("structured", 4.0, 7.0);
+    player.score = 12;
+    let event = GameEvent::new("player_moved", "structured").with_player(&player);
+
+    let encoded = serde_json::to_value(&event).unwrap();
+    assert_eq!(encoded["type"], "player_moved");
+    assert_eq!(encoded["position"], json!({"x": 4.0, "y": 7.0}));
+    assert_eq!(encoded["direction"], "up");
+    assert_eq!(encoded["score"], 12);
+
+    let decoded: GameEvent = serde_json::from_value(encoded.clone()).unwrap();
+    assert_eq!(decoded.position, Some(Position { x: 4.0, y: 7.0 }));
+    assert_eq!(decoded.score, Some(12));
+    assert_eq!(serde_json::to_value(&decoded).unwrap(), encoded);
+}
+
+#[tokio::test]
+async fn moves_are_locked_while_paused_and_accepted_after_resume() {
+    let (_serial, _oracle) = oracle().await;
+    let _admin = EnvVar::set("ADMIN_TOKEN", "ops");
+    let admin = |path: &str| {
+        let mut req = json_request(Method::POST, path, &Value::Null);
+        req.headers_mut().insert("x-admin-token", "ops".parse().unwrap());
+        req
+    };
+    join("frozen").await;
+
+    assert_eq!(send(admin("/pause")).await.0, 200);
+    let (status, body) = move_player("frozen", "up").await;
+    assert_eq!(status, 423, "{}", body);
+
+    assert_eq!(send(admin("/resume")).await.0, 200);
+    let (status, body) = move_player("frozen", "up").await;
+    assert_eq!(status, 200, "{}", body);
+}
+
+#[tokio::test]
+async fn concurrent_joins_from_each_castle_spawn_in_different_quadrants() {
+    let (_serial, _oracle) = oracle().await;
+    let (width, height) = (GAME_CONFIG.arena_width, GAME_CONFIG.arena_height);
+    let quadrant = |x: f64, y: f64| ((x >= width / 2.0) as u8, (y >= height / 2.0) as u8);
+
+    let (first, second) = tokio::join!(join("castle-a"), join("castle-b"));
+    for player in [&first, &second] {
+        assert_eq!(quadrant(player["x"].as_f64().unwrap(), player["y"].as_f64().unwrap()), (0, 0), "{}", player);
+    }
+    // Wasmtime (RUNTIME_INDEX 1) joining the same arena at the same time lands top-right
+    let (x, y) = quadrant_spawn(1, width, height, GAME_CONFIG.move_step);
+    assert_eq!(quadrant(x, y), (1, 0));
+}
+
+#[test]
+fn hmac_sha256_matches_rfc_4231() {
+    let hex = |bytes: [u8; 32]| bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+    let long_key = [0xaa; 131];
+    let cases: [(&[u8], &[u8], &str); 5] = [
+        (&[0x0b; 20], b"Hi There", "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"),
+        (b"Jefe", b"what do ya want for nothing?", "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"),
+        (&[0xaa; 20], &[0xdd; 50], "773ea91e36800e46854db8ebd09181a72959098b3ef8c122d9635514ced565fe"),
+        (&long_key, b"Test Using Larger Than Block-Size Key - Hash Key First", "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"),
+        (&long_key, b"This is a test using a larger than block-size key and a larger than block-size data. The key needs to be hashed before being used by the HMAC algorithm.", "9b09ffa71b942fcb27635fbcd5b0e944bfdc63644f0713938a7f51535c3a35e2"),
+    ];
+    for (key, message, expected) in cases {
+        assert_eq!(hex(hmac_sha256(key, message)), expected);
+    }
+}
+
+#[tokio::test]
+async fn oracle_tasks_past_the_concurrency_limit_wait_their_turn() {
+    let (_serial, _oracle) = oracle().await;
+    let limit = ORACLE_PERMITS.available_permits();
+    let running = Arc::new(AtomicUsize::new(0));
+    let finished = Arc::new(AtomicUsize::new(0));
+    let gate = Arc::new(tokio::sync::Semaphore::new(0));
+    for _ in 0..limit + 5 {
+        let (running, finished, gate) = (running.clone(), finished.clone(), gate.clone());
+        spawn_oracle_task(async move {
+            running.fetch_add(1, Ordering::SeqCst);
+            let _open = gate.acquire().await;
+            finished.fetch_add(1, Ordering::SeqCst);
+        });
+    }
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(running.load(Ordering::SeqCst), limit, "only {} tasks may hold a permit", limit);
+    gate.add_permits(limit + 5);
+    for _ in 0..100 {
+        if finished.load(Ordering::SeqCst) == limit + 5 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(finished.load(Ordering::SeqCst), limit + 5);
+    assert_eq!(ORACLE_PERMITS.available_permits(), limit);
+}
+
+#[tokio::test]
+async fn oversized_bodies_get_413() {
+    let (_serial, _oracle) = oracle().await;
+    let _limit = EnvVar::set("MAX_BODY_BYTES", "1024");
+    let padding = "x".repeat(2048);
+
+    // Streamed without a Content-Length: caught while buffering
+    let (status, body) = call(Method::POST, "/join", json!({"player_id": "bulky", "padding": padding})).await;
+    assert_eq!(status, 413, "{}", body);
+    assert_eq!(body["error"], "Request body exceeds MAX_BODY_BYTES (1024 bytes)");
+    // Declared up front: refused before any of it is read
+    let mut declared = json_request(Method::POST, "/join", &json!({"player_id": "bulky"}));
+    declared.headers_mut().insert("content-length", "4096".parse().unwrap());
+    assert_eq!(send(declared).await.0, 413);
+    assert!(!lock_unpoisoned(&GAME_STATE).contains_key("bulky"));
+}
+
+#[tokio::test]
+async fn debug_env_redacts_the_password_and_tokens() {
+    let (_serial, _oracle) = oracle().await;
+    let secrets = [
+        ("ORACLE_PASSWORD", "pw-hunter2"),
+        ("ORACLE_BEARER_TOKEN", "bearer-abc"),
+        ("API_KEY", "api-def"),
+        ("SPECTATE_TOKEN", "spectate-ghi"),
+        ("EVENT_SIGNING_KEY", "signing-jkl"),
+        ("ADMIN_TOKEN", "admin-mno"),
+    ];
+    let _vars: Vec<EnvVar> = secrets.iter().map(|(name, value)| EnvVar::set(name, value)).collect();
+    assert_eq!(call(Method::GET, "/debug/env", Value::Null).await.0, 401);
+
+    let mut req = json_request(Method::GET, "/debug/env", &Value::Null);
+    req.headers_mut().insert("x-admin-token", "admin-mno".parse().unwrap());
+    let (status, body) = send(req).await;
+    assert_eq!(status, 200, "{}", body);
+    let text = body.to_string();
+    for (name, value) in secrets {
+        assert!(!text.contains(value), "{} leaked: {}", name, text);
+    }
+    assert_eq!(body["oracle"]["password"], "set (redacted)");
+    assert_eq!(body["access"]["api_key"], "set (redacted)");
+    assert_eq!(body["oracle"]["topic"], get_oracle_config().topic);
+}
+
+#[tokio::test]
+async fn a_kicked_player_leaves_players_and_a_kick_event_is_published() {
+    let (_serial, oracle) = oracle().await;
+    let _admin = EnvVar::set("ADMIN_TOKEN", "ops");
+    join("troll").await;
+    join("bystander").await;
+
+    let kick = |player_id: &str, token: &str| {
+        let mut req = json_request(Method::DELETE, &format!("/players/{}", player_id), &Value::Null);
+        req.headers_mut().insert("x-admin-token", token.parse().unwrap());
+        req
+    };
+    assert_eq!(send(kick("troll", "guess")).await.0, 401);
+    let (status, body) = send(kick("troll", "ops")).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["kicked"], "troll");
+
+    let (_, listing) = call(Method::GET, "/players", Value::Null).await;
+    let ids: Vec<&str> = listing["players"].as_array().unwrap().iter().map(|p| p["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, ["bystander"]);
+    let event = wait_for_event(oracle, "player_kicked", "troll").await;
+    assert_eq!(event["data"]["reason"], "admin");
+    assert_eq!(send(kick("troll", "ops")).await.0, 404);
+}
+
+#[tokio::test]
+async fn a_heartbeat_keeps_an_idle_player_from_expiring() {
+    let (_serial, _oracle) = oracle().await;
+    join("resting").await;
+    join("gone-quiet").await;
+    for player in lock_unpoisoned(&GAME_STATE).values_mut() {
+        player.last_seen = now_ms() - 10_000;
+    }
+
+    let (status, body) = call(Method::POST, "/heartbeat", json!({"player_id": "resting"})).await;
+    assert_eq!(status, 200, "{}", body);
+    expire_idle_players(5_000).await;
+
+    let players = lock_unpoisoned(&GAME_STATE);
+    assert!(players.contains_key("resting"));
+    assert!(!players.contains_key("gone-quiet"));
+}
+
+#[tokio::test]
+async fn numeric_and_quoted_player_ids_name_the_same_player() {
+    let (_serial, _oracle) = oracle().await;
+    let (status, joined) = call(Method::POST, "/join", json!({"player_id": 42})).await;
+    assert_eq!(status, 200, "{}", joined);
+    assert_eq!(joined["player"]["id"], "42");
+
+    let (status, moved) = call(Method::POST, "/move", json!({"player_id": "42", "direction": "down"})).await;
+    assert_eq!(status, 200, "{}", moved);
+    {
+        let players = lock_unpoisoned(&GAME_STATE);
+        assert_eq!(players.len(), 1);
+        assert_eq!(players["42"].stats.moves, 1);
+    }
+
+    let (status, rejected) = call(Method::POST, "/move", json!({"player_id": true, "direction": "down"})).await;
+    assert_eq!(status, 400);
+    assert_eq!(rejected["error"], "player_id must be a string or an integer");
+}
+
+#[tokio::test]
+async fn an_export_imports_back_into_a_fresh_instance() {
+    let (_serial, _oracle) = oracle().await;
+    join("saved-a").await;
+    join("saved-b").await;
+    for direction in ["right", "down"] {
+        assert_eq!(move_player("saved-a", direction).await.0, 200);
+    }
+    let (status, export) = call(Method::GET, "/game-state/export", Value::Null).await;
+    assert_eq!(status, 200, "{}", export);
+    let players = serde_json::to_value(&*lock_unpoisoned(&GAME_STATE)).unwrap();
+    let ticks = serde_json::to_value(&*lock_unpoisoned(&GAME_TICKS)).unwrap();
+
+    // A fresh instance starts with nothing
+    lock_unpoisoned(&GAME_STATE).clear();
+    lock_unpoisoned(&GAME_TICKS).clear();
+
+    let _admin = EnvVar::set("ADMIN_TOKEN", "restore");
+    let mut req = json_request(Method::POST, "/game-state/import", &export);
+    req.headers_mut().insert("x-admin-token", "restore".parse().unwrap());
+    let (status, body) = send(req).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["imported"], 2);
+
+    assert_eq!(serde_json::to_value(&*lock_unpoisoned(&GAME_STATE)).unwrap(), players);
+    assert_eq!(serde_json::to_value(&*lock_unpoisoned(&GAME_TICKS)).unwrap(), ticks);
+}
+
+#[tokio::test]
+async fn root_lists_every_route() {
+    let (status, info) = call(Method::GET, "/", Value::Null).await;
+    assert_eq!(status, 200);
+    let listed: Vec<(&str, &str)> = info["endpoints"].as_array().unwrap().iter()
+        .map(|e| (e["method"].as_str().unwrap(), e["path"].as_str().unwrap()))
+        .collect();
+    for &(method, path, _) in ROUTES {
+        assert!(listed.contains(&(method, path)), "{} {} missing from /", method, path);
+    }
+    assert_eq!(listed.len(), ROUTES.len());
+}
+
+#[tokio::test]
+async fn a_poisoned_game_state_still_serves_requests() {
+    let (_serial, _oracle) = oracle().await;
+    join("survivor").await;
+    let panicked = std::thread::spawn(|| {
+        let _players = GAME_STATE.lock().unwrap();
+        panic!("handler died holding the game state");
+    }).join();
+    assert!(panicked.is_err());
+    assert!(GAME_STATE.is_poisoned());
+
+    assert_eq!(call(Method::GET, "/health", Value::Null).await.0, 200);
+    let (status, players) = call(Method::GET, "/players", Value::Null).await;
+    assert_eq!(status, 200, "{}", players);
+    assert_eq!(players["count"], 1);
+    join("latecomer").await;
+    assert_eq!(move_player("survivor", "up").await.0, 200);
+
+    GAME_STATE.clear_poison();
+}
+
+#[tokio::test]
+async fn an_import_with_a_player_outside_the_arena_is_rejected() {
+    let (_serial, _oracle) = oracle().await;
+    join("resident").await;
+    let (_, mut export) = call(Method::GET, "/game-state/export", Value::Null).await;
+    let mut stray = export["players"]["resident"].clone();
+    stray["id"] = json!("stray");
+    stray["x"] = json!(GAME_CONFIG.arena_width + 100.0);
+    stray["trail"] = json!([]);
+    export["players"] = json!({"stray": stray});
+
+    let _admin = EnvVar::set("ADMIN_TOKEN", "restore");
+    let mut req = json_request(Method::POST, "/game-state/import", &export);
+    req.headers_mut().insert("x-admin-token", "restore".parse().unwrap());
+    let (status, body) = send(req).await;
+    assert_eq!(status, 400);
+    assert_eq!(body["error"], "Player stray lies outside the arena");
+    // Nothing was replaced
+    let players = lock_unpoisoned(&GAME_STATE);
+    assert!(players.contains_key("resident") && !players.contains_key("stray"));
+}
+
+#[tokio::test]
+async fn all_players_merges_a_peer_and_reports_the_unreachable_one() {
+    let (_serial, oracle) = oracle().await;
+    join("edge-only").await;
+    let shared = join("shared").await;
+    // A wasmtime castle served from the mock, where "shared" moved since
+    let newer = shared["last_seen"].as_u64().unwrap() + 1_000;
+    oracle.respond(Method::GET, "/peer-castle/players", 200, json!({
+        "runtime": "wasmtime",
+        "castle": "Wasmtime Fortress",
+        "players": [
+            {"id": "time-only", "x": 5.0, "y": 5.0, "last_seen": 1},
+            {"id": "shared", "x": 9.0, "y": 9.0, "last_seen": newer}
+        ]
+    }));
+    let peer = format!("{}/peer-castle", oracle.url);
+    let down = MockServer::refused_url();
+    let _peers = EnvVar::set("PEER_URLS", &format!("{}, {}", peer, down));
+
+    let (status, body) = call(Method::GET, "/players/all", Value::Null).await;
+    assert_eq!(status, 200, "{}", body);
+    let sources: Vec<(&str, &str)> = body["players"].as_array().unwrap().iter()
+        .map(|p| (p["id"].as_str().unwrap(), p["source_runtime"].as_str().unwrap()))
+        .collect();
+    assert_eq!(sources, vec![("edge-only", "wasmedge"), ("shared", "wasmtime"), ("time-only", "wasmtime")]);
+    assert_eq!(body["players"][1]["source_castle"], "Wasmtime Fortress");
+    assert_eq!(body["peers_unreachable"].as_array().unwrap().len(), 1);
+    assert_eq!(body["peers_unreachable"][0]["url"], down.as_str());
+}
+
+#[tokio::test]
+async fn consume_limit_is_forwarded_to_the_records_call() {
+    let (_serial, oracle) = oracle().await;
+    let records_queries = || -> Vec<String> {
+        oracle.requests().into_iter()
+            .filter(|r| r.method == Method::GET && r.path().ends_with("/records"))
+            .map(|r| r.uri.split_once('?').map(|(_, query)| query.to_string()).unwrap_or_default())
+            .collect()
+    };
+
+    let (status, body) = call(Method::GET, "/consume-kafka?limit=3", Value::Null).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(records_queries(), vec!["limit=3"]);
+
+    // Without one, TxEventQ keeps choosing the batch size
+    assert_eq!(call(Method::GET, "/consume-kafka", Value::Null).await.0, 200);
+    assert_eq!(records_queries(), vec!["limit=3", ""]);
+
+    assert_eq!(call(Method::GET, "/consume-kafka?limit=0", Value::Null).await.0, 400);
+    assert_eq!(records_queries().len(), 2);
+}
+
+#[tokio::test]
+async fn a_supplied_request_id_is_echoed_and_a_missing_one_generated() {
+    let (_serial, _oracle) = oracle().await;
+    let mut req = json_request(Method::GET, "/players", &Value::Null);
+    req.headers_mut().insert("x-request-id", "trace-abc-123".parse().unwrap());
+    let response = handle_request(req).await.unwrap();
+    assert_eq!(response.headers()["x-request-id"], "trace-abc-123");
+    let body: Value = serde_json::from_slice(&body_bytes(response).await).unwrap();
+    assert_eq!(body["request_id"], "trace-abc-123");
+
+    // Without one the id is made up and only sent back in the header; the
+    // body goes out as the handler wrote it
+    let response = handle_request(json_request(Method::GET, "/players", &Value::Null)).await.unwrap();
+    let generated = response.headers()["x-request-id"].to_str().unwrap().to_string();
+    assert_eq!(generated.len(), 16);
+    assert!(generated.bytes().all(|b| b.is_ascii_hexdigit()));
+    let body: Value = serde_json::from_slice(&body_bytes(response).await).unwrap();
+    assert!(body.get("request_id").is_none());
+}
+
+#[tokio::test]
+async fn websocket_upgrades_need_the_api_key_even_with_open_reads() {
+    let (_serial, _oracle) = oracle().await;
+    let _key = EnvVar::set("API_KEY", "secret");
+    let upgrade = |key: Option<&str>| {
+        let mut req = Request::builder()
+            .method(Method::GET)
+            .uri("/ws?player_id=socket")
+            .header("upgrade", "websocket")
+            .header("connection", "Upgrade")
+            .header("sec-websocket-key", "dGhlIHNhbXBsZSBub25jZQ==")
+            .body(Body::empty())
+            .unwrap();
+        if let Some(key) = key {
+            req.headers_mut().insert("x-api-key", key.parse().unwrap());
+        }
+        req
+    };
+
+    let (status, body) = send(upgrade(None)).await;
+    assert_eq!(status, 401);
+    assert_eq!(body["error"], "Invalid or missing X-API-Key");
+    assert_eq!(handle_request(upgrade(Some("secret"))).await.unwrap().status().as_u16(), 101);
+}
+
+#[tokio::test]
+async fn trimming_a_mixed_runtime_topic_keeps_every_newer_event_as_published() {
+    let (_serial, oracle) = oracle().await;
+    let topic = get_oracle_config().topic;
+    let before = 2_000_000_000_000u64;
+    // wasmtime's events carry no data field
+    let old_wasmtime = json!({"type": "player_moved", "player_id": "t-old", "runtime": "wasmtime", "timestamp": before - 1});
+    let new_wasmtime = json!({"type": "player_moved", "player_id": "t-new", "runtime": "wasmtime", "timestamp": before, "tick": 4});
+    let mut old_wasmedge = serde_json::to_value(GameEvent::new("player_moved", "e-old")).unwrap();
+    old_wasmedge["timestamp"] = json!(before - 1);
+    let mut new_wasmedge = serde_json::to_value(GameEvent::new("player_moved", "e-new")).unwrap();
+    new_wasmedge["timestamp"] = json!(before + 1);
+    for event in [&old_wasmtime, &new_wasmtime, &old_wasmedge, &new_wasmedge] {
+        oracle.produce(&topic, event);
+    }
+
+    let _admin = EnvVar::set("ADMIN_TOKEN", "trim");
+    let mut req = json_request(Method::POST, &format!("/topic/trim?before={}", before), &Value::Null);
+    req.headers_mut().insert("x-admin-token", "trim".parse().unwrap());
+    let (status, body) = send(req).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!((body["removed"].as_u64(), body["kept"].as_u64()), (Some(2), Some(2)));
+
+    let republished: Vec<Value> = oracle.records(&topic).iter()
+        .map(|record| serde_json::from_str(record["value"].as_str().unwrap()).unwrap())
+        .collect();
+    assert_eq!(republished, vec![new_wasmtime, new_wasmedge]);
+}
+
+#[tokio::test]
+async fn an_acknowledged_publish_waits_for_a_permit_and_counts_as_in_flight() {
+    let (_serial, _oracle) = oracle().await;
+    join_heading("patient", "up").await;
+    for _ in 0..100 {
+        if IN_FLIGHT_EVENTS.load(Ordering::SeqCst) == 0 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let permits = ORACLE_PERMITS.acquire_many(ORACLE_PERMITS.available_permits() as u32).await.unwrap();
+    let moving = tokio::spawn(acknowledged_move("patient", "left"));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!moving.is_finished(), "published without a permit");
+    assert_eq!(IN_FLIGHT_EVENTS.load(Ordering::SeqCst), 1);
+
+    drop(permits);
+    let (status, body) = moving.await.unwrap();
+    assert_eq!(status, 200, "{}", body);
+    assert!(body["event_offset"]["offset"].is_number(), "{}", body);
+    assert_eq!(IN_FLIGHT_EVENTS.load(Ordering::SeqCst), 0);
+}
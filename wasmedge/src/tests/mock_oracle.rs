@@ -0,0 +1,352 @@
+// Local stand-in for the ORDS and TxEventQ REST APIs, served by hyper on a
+// loopback port. Enough of both is emulated for the runtime's calls to work
+// end to end: topics and consumer groups are created on demand, produced
+// records queue up per topic and each consumer group reads them in order, and
+// ORDS collections are plain row lists. Canned responses take precedence over
+// the emulation for error paths, and every request is recorded so tests can
+// assert on URLs, headers and bodies.
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, HeaderMap, Method, Request, Response, Server};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::TcpListener;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+// Records a consumer gets per GET .../records without ?limit=
+const DEFAULT_RECORDS_LIMIT: usize = 100;
+
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: Method,
+    // Path and query, e.g. /_/db-api/.../records?limit=5
+    pub uri: String,
+    pub headers: HeaderMap,
+    pub body: String,
+}
+
+impl RecordedRequest {
+    pub fn path(&self) -> &str {
+        self.uri.split('?').next().unwrap_or_default()
+    }
+
+    pub fn json(&self) -> Value {
+        serde_json::from_str(&self.body).unwrap_or(Value::Null)
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|v| v.to_str().ok())
+    }
+}
+
+struct Canned {
+    method: Method,
+    path_fragment: String,
+    status: u16,
+    body: String,
+}
+
+#[derive(Default)]
+struct MockState {
+    requests: Vec<RecordedRequest>,
+    canned: Vec<Canned>,
+    // Produced records per topic, each already carrying partition and offset
+    topics: HashMap<String, Vec<Value>>,
+    // Consumer group -> where it has read up to
+    groups: HashMap<String, GroupCursor>,
+    // Partitions records are spread over, round robin
+    partitions: usize,
+    instances: u64,
+    // ORDS collections by name (scores, leaderboard, players, ...)
+    collections: HashMap<String, Vec<Value>>,
+}
+
+// A consumer group fetches from one partition at a time, like a Kafka
+// consumer working through its assignment: a batch comes from `current`, and
+// once that partition reads empty the next fetch moves on to the following one
+struct GroupCursor {
+    topic: String,
+    // Records read so far, per partition
+    read: HashMap<usize, usize>,
+    current: usize,
+}
+
+pub struct MockServer {
+    pub url: String,
+    state: Mutex<MockState>,
+}
+
+impl MockServer {
+    // Serves on its own thread and runtime, so it outlives the runtime of
+    // whichever #[tokio::test] started it
+    pub fn start() -> &'static MockServer {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind mock server");
+        listener.set_nonblocking(true).expect("non-blocking mock listener");
+        let server: &'static MockServer = Box::leak(Box::new(MockServer {
+            url: format!("http://{}", listener.local_addr().expect("mock server address")),
+            state: Mutex::new(MockState { partitions: 1, ..MockState::default() }),
+        }));
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("mock server runtime");
+            runtime.block_on(async move {
+                let make_svc = make_service_fn(move |_conn| async move {
+                    Ok::<_, Infallible>(service_fn(move |req| server.handle(req)))
+                });
+                Server::from_tcp(listener)
+                    .expect("mock server listener")
+                    .serve(make_svc)
+                    .await
+                    .expect("mock server");
+            });
+        });
+        server
+    }
+
+    // A URL nothing is listening on, for Oracle-down scenarios
+    pub fn refused_url() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind probe port");
+        format!("http://{}", listener.local_addr().expect("probe address"))
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn reset(&self) {
+        *self.state() = MockState { partitions: 1, ..MockState::default() };
+    }
+
+    // Answers every matching request with `status`/`body` from now on. The
+    // first canned response whose method matches and whose fragment appears in
+    // the request's path and query wins.
+    pub fn respond(&self, method: Method, path_fragment: &str, status: u16, body: Value) {
+        self.state().canned.push(Canned {
+            method,
+            path_fragment: path_fragment.to_string(),
+            status,
+            body: body.to_string(),
+        });
+    }
+
+    // Spreads records produced from now on over `count` partitions
+    pub fn set_partitions(&self, count: usize) {
+        self.state().partitions = count.max(1);
+    }
+
+    // Queues a record on `topic` as if another publisher had produced it.
+    // `value` is sent the way TxEventQ stores it, as a JSON string.
+    pub fn produce(&self, topic: &str, value: &Value) {
+        let mut state = self.state();
+        append_record(&mut state, topic, json!({"key": "mock", "value": value.to_string()}));
+    }
+
+    pub fn records(&self, topic: &str) -> Vec<Value> {
+        self.state().topics.get(topic).cloned().unwrap_or_default()
+    }
+
+    pub fn insert_rows(&self, collection: &str, rows: Vec<Value>) {
+        self.state().collections.entry(collection.to_string()).or_default().extend(rows);
+    }
+
+    pub fn rows(&self, collection: &str) -> Vec<Value> {
+        self.state().collections.get(collection).cloned().unwrap_or_default()
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state().requests.clone()
+    }
+
+    // Waits up to two seconds for a matching request, for calls the runtime
+    // makes from spawned tasks
+    pub async fn wait_for(&self, matches: impl Fn(&RecordedRequest) -> bool) -> RecordedRequest {
+        for _ in 0..100 {
+            if let Some(request) = self.requests().into_iter().find(|r| matches(r)) {
+                return request;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("mock Oracle never received the expected request; got {:?}",
+            self.requests().iter().map(|r| format!("{} {}", r.method, r.uri)).collect::<Vec<_>>());
+    }
+
+    async fn handle(&self, req: Request<Body>) -> Result<Response<Body>, Infallible> {
+        let (parts, body) = req.into_parts();
+        let body = hyper::body::to_bytes(body).await
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+            .unwrap_or_default();
+        let request = RecordedRequest {
+            method: parts.method,
+            uri: parts.uri.path_and_query().map(|pq| pq.to_string()).unwrap_or_default(),
+            headers: parts.headers,
+            body,
+        };
+        let (status, body) = self.reply(request);
+        Ok(Response::builder()
+            .status(status)
+            .header("content-type", "application/json")
+            .body(Body::from(body))
+            .unwrap())
+    }
+
+    fn reply(&self, request: RecordedRequest) -> (u16, String) {
+        let mut state = self.state();
+        state.requests.push(request.clone());
+        let canned = state.canned.iter()
+            .position(|c| c.method == request.method && request.uri.contains(&c.path_fragment));
+        if let Some(index) = canned {
+            return (state.canned[index].status, state.canned[index].body.clone());
+        }
+        let (status, body) = emulate(&mut state, &request);
+        (status, body.to_string())
+    }
+}
+
+fn append_record(state: &mut MockState, topic: &str, record: Value) -> (usize, usize) {
+    let partitions = state.partitions;
+    let records = state.topics.entry(topic.to_string()).or_default();
+    let partition = records.len() % partitions;
+    let offset = records.iter().filter(|r| r["partition"] == json!(partition)).count();
+    records.push(json!({
+        "topic": topic,
+        "partition": partition,
+        "offset": offset,
+        "key": record["key"],
+        "value": record["value"],
+    }));
+    (partition, offset)
+}
+
+fn emulate(state: &mut MockState, request: &RecordedRequest) -> (u16, Value) {
+    let segments: Vec<&str> = request.path().split('/').filter(|s| !s.is_empty()).collect();
+    let query = request.uri.split_once('?').map(|(_, q)| q).unwrap_or("");
+    let body = request.json();
+    let method = &request.method;
+
+    // TxEventQ: .../clusters/{db}/topics[/{topic}], .../clusters/{db}/consumer-groups/{group},
+    // .../consumers/{group}[/instances/{id}[/records]] and .../topics/{topic}
+    if let Some(i) = segments.iter().position(|s| *s == "clusters") {
+        return match (method, &segments[i + 2..]) {
+            (&Method::POST, ["topics"]) => {
+                let topic = body["topic_name"].as_str().unwrap_or_default().to_string();
+                if state.topics.contains_key(&topic) {
+                    (409, json!({"message": format!("Topic {} already exists", topic)}))
+                } else {
+                    state.topics.insert(topic.clone(), Vec::new());
+                    (201, json!({"topic_name": topic}))
+                }
+            }
+            (&Method::DELETE, ["topics", topic]) => match state.topics.remove(*topic) {
+                Some(_) => (200, json!({})),
+                None => (404, json!({"message": "Topic not found"})),
+            },
+            (&Method::POST, ["consumer-groups", group]) => {
+                if state.groups.contains_key(*group) {
+                    (409, json!({"message": format!("Consumer group {} already exists", group)}))
+                } else {
+                    let topic = body["topic_name"].as_str().unwrap_or_default().to_string();
+                    state.groups.insert(group.to_string(), GroupCursor { topic, read: HashMap::new(), current: 0 });
+                    (201, json!({}))
+                }
+            }
+            _ => (404, json!({"message": "not found"})),
+        };
+    }
+    if let Some(i) = segments.iter().position(|s| *s == "consumers") {
+        return match (method, &segments[i + 1..]) {
+            (&Method::POST, [_group]) => {
+                state.instances += 1;
+                (200, json!({"instance_id": format!("mock-instance-{}", state.instances)}))
+            }
+            (&Method::DELETE, [_group, "instances", _id]) => (204, Value::Null),
+            (&Method::GET, [group, "instances", _id, "records"]) => {
+                let limit = query.split('&')
+                    .find_map(|pair| pair.strip_prefix("limit="))
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(DEFAULT_RECORDS_LIMIT);
+                let partitions = state.partitions;
+                let Some(cursor) = state.groups.get_mut(*group) else {
+                    return (404, json!({"message": "Consumer group not found"}));
+                };
+                let partition = cursor.current;
+                let read = cursor.read.get(&partition).copied().unwrap_or(0);
+                let batch: Vec<Value> = state.topics.get(&cursor.topic).into_iter().flatten()
+                    .filter(|record| record["partition"] == json!(partition))
+                    .skip(read)
+                    .take(limit)
+                    .cloned()
+                    .collect();
+                if batch.is_empty() {
+                    cursor.current = (partition + 1) % partitions;
+                } else {
+                    cursor.read.insert(partition, read + batch.len());
+                }
+                (200, Value::Array(batch))
+            }
+            _ => (404, json!({"message": "not found"})),
+        };
+    }
+    if let Some(i) = segments.iter().position(|s| *s == "topics") {
+        if let (&Method::POST, [topic]) = (method, &segments[i + 1..]) {
+            let records = body["records"].as_array().cloned().unwrap_or_default();
+            let offsets: Vec<Value> = records.into_iter()
+                .map(|record| {
+                    let (partition, offset) = append_record(state, topic, record);
+                    json!({"partition": partition, "offset": offset})
+                })
+                .collect();
+            return (200, json!({"offsets": offsets}));
+        }
+    }
+
+    // ORDS: .../_sdw/{collection}/ with GET listing, POST inserting and
+    // DELETE ?q= removing rows (a {"player_id": ...} filter or {} for all)
+    if let Some(i) = segments.iter().position(|s| *s == "_sdw") {
+        if let Some(collection) = segments.get(i + 1) {
+            let rows = state.collections.entry(collection.to_string()).or_default();
+            return match *method {
+                Method::GET => (200, json!({"items": rows.clone(), "hasMore": false, "links": []})),
+                Method::POST => {
+                    rows.push(body.clone());
+                    (201, body)
+                }
+                Method::DELETE => {
+                    let filter = query.strip_prefix("q=").map(percent_decode).unwrap_or_default();
+                    let filter: Value = serde_json::from_str(&filter).unwrap_or(json!({}));
+                    let before = rows.len();
+                    match filter["player_id"].as_str() {
+                        Some(player_id) => rows.retain(|row| row["player_id"] != player_id),
+                        None => rows.clear(),
+                    }
+                    (200, json!({"rowsDeleted": before - rows.len()}))
+                }
+                _ => (405, json!({"message": "method not allowed"})),
+            };
+        }
+    }
+
+    (404, json!({"message": format!("mock Oracle has no route for {} {}", method, request.uri)}))
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], value.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok())) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
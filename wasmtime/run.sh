@@ -35,7 +35,11 @@ echo "  GET  /health          - Server health check"
 echo "  POST /join            - Join the game"
 echo "  POST /move            - Move a player"
 echo "  POST /leave           - Leave the game"
+echo "  POST /respawn         - Respawn an eliminated player"
+echo "  POST /heartbeat       - Keep an idle player registered"
 echo "  GET  /players         - Get all players"
+echo "  GET  /game-state/export - Download a backup of the whole game"
+echo "  POST /game-state/import - Restore a backup (admin)"
 echo "  GET  /leaderboard     - Get the leaderboard"
 echo "  GET  /consume-kafka   - Consume Kafka messages"
 echo "  POST /test-kafka      - Test Kafka publishing"
@@ -1,8 +1,16 @@
 use waki::{handler, ErrorCode, Request, Response, Client, Method};
-use std::collections::HashMap;
+use waki::header::{HeaderName, CONTENT_TYPE};
+use std::collections::{BTreeMap, HashMap};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
+// Shadows std's eprintln! so log lines carry the X-Request-Id of the request
+// being handled (see REQUEST_ID)
+macro_rules! eprintln {
+    () => { std::eprintln!() };
+    ($($arg:tt)*) => { std::eprintln!("{}{}", log_prefix(), format_args!($($arg)*)) };
+}
+
 // Game state structures
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct Player {
@@ -13,6 +21,168 @@ struct Player {
     score: i32,
     color: String,
     alive: bool,
+    // Match this player belongs to; older saved state falls back to GAME_ID
+    #[serde(default = "get_default_game_id")]
+    game_id: String,
+    // Last join/move time (ms); idle players are swept after PLAYER_IDLE_TIMEOUT_MS
+    #[serde(default)]
+    last_seen: u64,
+    #[serde(default)]
+    joined_at: u64,
+    // Cells this cycle has left behind (the last TRAIL_LENGTH of them, or at
+    // most MAX_TRAIL_CELLS); moving into any trail is a crash
+    #[serde(default)]
+    trail: Vec<Position>,
+    #[serde(default)]
+    stats: PlayerStats,
+    // Game tick of the last change to this player (see advance_tick)
+    #[serde(default)]
+    tick: u64,
+    // When the last move was accepted (ms), for MOVE_COOLDOWN_MS
+    #[serde(default)]
+    last_move_at: u64,
+}
+
+// Upper bound on trail cells when TRAIL_LENGTH is 0 (and on TRAIL_LENGTH
+// itself), keeping player state and its persisted copies finite
+const MAX_TRAIL_CELLS: usize = 10_000;
+
+impl Player {
+    // Time alive so far, frozen at the moment of elimination
+    fn survival_ms(&self) -> u64 {
+        if self.alive && self.joined_at > 0 {
+            get_timestamp().saturating_sub(self.joined_at)
+        } else {
+            self.stats.survival_ms
+        }
+    }
+
+    // Appends a trail cell, trimming from the tail end once the trail passes
+    // TRAIL_LENGTH or MAX_TRAIL_CELLS so freed cells can be driven through again
+    fn lay_trail(&mut self, at: Position, limit: usize) {
+        self.trail.push(at);
+        let limit = if limit == 0 { MAX_TRAIL_CELLS } else { limit.min(MAX_TRAIL_CELLS) };
+        if self.trail.len() > limit {
+            let excess = self.trail.len() - limit;
+            self.trail.drain(..excess);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct PlayerStats {
+    moves: u32,
+    distance: f64,
+    kills: u32,
+    survival_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    fn parse(direction: &str) -> Option<Self> {
+        match direction {
+            "up" => Some(Direction::Up),
+            "down" => Some(Direction::Down),
+            "left" => Some(Direction::Left),
+            "right" => Some(Direction::Right),
+            _ => None,
+        }
+    }
+
+    fn opposite(self) -> Self {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Direction::Up => "up",
+            Direction::Down => "down",
+            Direction::Left => "left",
+            Direction::Right => "right",
+        }
+    }
+
+    // Unit (dx, dy) with y growing downwards
+    fn delta(self) -> (f64, f64) {
+        match self {
+            Direction::Up => (0.0, -1.0),
+            Direction::Down => (0.0, 1.0),
+            Direction::Left => (-1.0, 0.0),
+            Direction::Right => (1.0, 0.0),
+        }
+    }
+}
+
+// Players that haven't moved within this window are removed by the idle sweep
+fn get_player_idle_timeout_ms() -> u64 {
+    std::env::var("PLAYER_IDLE_TIMEOUT_MS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(60000)
+}
+
+// FLUSH_BEFORE_RESPONSE=true publishes queued events before each response
+// instead of at the start of the next request. Use it when the host tears the
+// instance down after every response, since that is the last point the queue
+// still exists; it costs a Kafka round trip on every move.
+fn flush_before_response() -> bool {
+    std::env::var("FLUSH_BEFORE_RESPONSE").map(|v| v == "true").unwrap_or(false)
+}
+
+// PUBLISH_LEADERBOARD_EVENTS=true also writes score changes to LEADERBOARD_TOPIC,
+// so a consumer can keep a leaderboard without polling ORDS
+fn publish_leaderboard_events() -> bool {
+    std::env::var("PUBLISH_LEADERBOARD_EVENTS").map(|v| v == "true").unwrap_or(false)
+}
+
+// True when a gain of `gained` carried the score past a multiple of
+// LEADERBOARD_EVENT_POINTS, so milestones still fire when SCORE_MULTIPLIER
+// makes the score jump over the exact multiple
+fn crossed_leaderboard_milestone(score: i32, gained: i32) -> bool {
+    let points = get_leaderboard_event_points();
+    score > 0 && score / points > (score - gained).max(0) / points
+}
+
+// Living players are published every LEADERBOARD_EVENT_POINTS points;
+// eliminations and departures are published regardless
+fn get_leaderboard_event_points() -> i32 {
+    std::env::var("LEADERBOARD_EVENT_POINTS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|points: &i32| *points > 0)
+        .unwrap_or(10)
+}
+
+// Minimum ms between accepted moves per player (MOVE_COOLDOWN_MS), so move
+// rate doesn't depend on the client's frame rate; 0 disables it
+fn get_move_cooldown_ms() -> u64 {
+    std::env::var("MOVE_COOLDOWN_MS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0)
+}
+
+// ALLOW_REVERSE=true permits 180° turns for game variants that allow them
+fn allow_reverse() -> bool {
+    std::env::var("ALLOW_REVERSE").map(|v| v == "true").unwrap_or(false)
+}
+
+// RESPAWN_RESETS_SCORE=true starts a respawned cycle from zero instead of
+// keeping the score it had when it crashed
+fn respawn_resets_score() -> bool {
+    std::env::var("RESPAWN_RESETS_SCORE").map(|v| v == "true").unwrap_or(false)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,20 +194,255 @@ struct GameEvent {
     runtime: String,
     castle: String,
     timestamp: u64,
+    // Omitted from MINIMAL_EVENTS moves when unchanged; replay keeps the
+    // player's previous value for anything missing
+    #[serde(skip_serializing_if = "Option::is_none")]
     position: Option<Position>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     direction: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     score: Option<i32>,
+    // Game tick this event produced; clients use gaps to spot missed updates
+    tick: Option<u64>,
+    // Publishing castle's color, so consumers can color-code without a lookup;
+    // None on events written before this field existed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    color: Option<String>,
+    // Per-player counter assigned when the event is built. Replay orders by
+    // (timestamp, seq), so two events in the same millisecond keep the order
+    // they happened in. None on events from before seq was added.
+    seq: Option<u64>,
+    // The player's most recent trail cells (at most EVENT_TRAIL_CELLS), so a
+    // reconstructed cycle still blocks the cells it left behind
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trail: Option<Vec<Position>>,
+    // Instead of trail on MINIMAL_EVENTS moves: cells laid since the player's
+    // previous event, appended on replay
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trail_added: Option<Vec<Position>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+// Cap on the trail cells one event carries. Move events repeat the whole
+// trail, so this bounds the message size; older cells don't survive a replay.
+const EVENT_TRAIL_CELLS: usize = 256;
+
+// MINIMAL_EVENTS=true sends move events as deltas (see GameEvent::minimize)
+fn minimal_events() -> bool {
+    std::env::var("MINIMAL_EVENTS").map(|v| v == "true").unwrap_or(false)
+}
+
+// New cells at the end of `current` relative to `previous`, allowing for
+// cells trimmed off the front of `previous`. None if `current` isn't an
+// extension of it, meaning the whole trail must go out.
+fn trail_added_since(previous: &[Position], current: &[Position]) -> Option<Vec<Position>> {
+    (0..=current.len()).find_map(|added| {
+        let kept = &current[..current.len() - added];
+        if (!kept.is_empty() || previous.is_empty()) && previous.ends_with(kept) {
+            Some(current[current.len() - added..].to_vec())
+        } else {
+            None
+        }
+    })
+}
+
+impl GameEvent {
+    // Runtime, castle, timestamp and the default game_id are filled in; the
+    // optional fields are set with the with_* methods below
+    fn new(event_type: &str, player_id: &str) -> Self {
+        Self {
+            event_type: event_type.to_string(),
+            player_id: player_id.to_string(),
+            game_id: get_default_game_id(),
+            runtime: "wasmtime".to_string(),
+            castle: get_castle_name(),
+            timestamp: get_timestamp(),
+            position: None,
+            direction: None,
+            score: None,
+            tick: None,
+            color: Some(runtime_color()),
+            seq: Some(next_event_seq(player_id)),
+            trail: None,
+            trail_added: None,
+        }
+    }
+
+    // Replay order; unsequenced events sort first within their millisecond
+    fn order_key(&self) -> (u64, u64) {
+        (self.timestamp, self.seq.unwrap_or(0))
+    }
+
+    fn with_game_id(mut self, game_id: &str) -> Self {
+        self.game_id = game_id.to_string();
+        self
+    }
+
+    fn with_position(mut self, x: f64, y: f64) -> Self {
+        self.position = Some(Position { x, y });
+        self
+    }
+
+    fn with_direction(mut self, direction: &str) -> Self {
+        self.direction = Some(direction.to_string());
+        self
+    }
+
+    fn with_score(mut self, score: i32) -> Self {
+        self.score = Some(score);
+        self
+    }
+
+    fn with_tick(mut self, tick: u64) -> Self {
+        self.tick = Some(tick);
+        self
+    }
+
+    fn with_trail(mut self, trail: &[Position]) -> Self {
+        let start = trail.len().saturating_sub(EVENT_TRAIL_CELLS);
+        self.trail = Some(trail[start..].to_vec());
+        self
+    }
+
+    // Snapshot of a player's full state, as carried by join/move/timeout events
+    fn with_player(self, player: &Player) -> Self {
+        self.with_game_id(&player.game_id)
+            .with_position(player.x, player.y)
+            .with_direction(&player.direction)
+            .with_score(player.score)
+            .with_tick(player.tick)
+            .with_trail(&player.trail)
+            .minimize()
+    }
+    
+    // With MINIMAL_EVENTS, strips from a move event anything equal to the last
+    // event built for this player in this game, and swaps its trail for
+    // trail_added. Joins, respawns and the rest are recorded too, so a move
+    // after one is diffed against it. After a static reset nothing is
+    // recorded and the next move goes out in full.
+    fn minimize(mut self) -> Self {
+        if !minimal_events() {
+            return self;
+        }
+        let last_events = get_last_player_events();
+        let full = self.clone();
+        if self.event_type == "player_moved" {
+            if let Some(previous) = last_events.get(&self.player_id).filter(|e| e.game_id == self.game_id) {
+                if self.position == previous.position {
+                    self.position = None;
+                }
+                if self.direction == previous.direction {
+                    self.direction = None;
+                }
+                if self.score == previous.score {
+                    self.score = None;
+                }
+                if self.color == previous.color {
+                    self.color = None;
+                }
+                if let (Some(trail), Some(previous_trail)) = (&self.trail, &previous.trail) {
+                    if let Some(added) = trail_added_since(previous_trail, trail) {
+                        self.trail_added = if added.is_empty() { None } else { Some(added) };
+                        self.trail = None;
+                    }
+                }
+            }
+        }
+        last_events.insert(self.player_id.clone(), full);
+        self
+    }
+}
+
+// Where TxEventQ put a published record, for matching a client-side move
+// against the topic
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+struct PublishReceipt {
+    partition: i64,
+    offset: i64,
+}
+
+// One consume call: the records, or why there were none. `empty` is a clean
+// "caught up" (TxEventQ returned []), `error` a failed call; drain loops stop
+// on either.
+#[derive(Debug, Clone, Serialize)]
+struct ConsumeResult {
+    records: Vec<Value>,
+    empty: bool,
+    error: Option<String>,
+}
+
+impl ConsumeResult {
+    fn records(records: Vec<Value>) -> Self {
+        let empty = records.is_empty();
+        ConsumeResult { records, empty, error: None }
+    }
+
+    fn empty() -> Self {
+        ConsumeResult { records: Vec::new(), empty: true, error: None }
+    }
+
+    fn error(error: String) -> Self {
+        ConsumeResult { records: Vec::new(), empty: false, error: Some(error) }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ConsumerInstance {
+    group: String,
+    instance_id: String,
+    // Where the instance was created; deleting it goes through the same path,
+    // since the reconstruction consumers live under /clusters/.../consumer-groups
+    url: String,
+    created_at: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 struct Position {
     x: f64,
     y: f64,
 }
 
+impl Position {
+    fn cell(&self) -> (i32, i32) {
+        grid_cell(self.x, self.y)
+    }
+}
+
+// Cell for a grid coordinate. Rounding (not `as i32` truncation) keeps a value
+// that drifted to 24.999999 in cell 25; collisions and the AI's trail cells
+// both use this so they always agree.
+fn grid_cell(x: f64, y: f64) -> (i32, i32) {
+    (x.round() as i32, y.round() as i32)
+}
+
+fn in_arena(at: Position, config: &GameConfig) -> bool {
+    at.x >= 0.0 && at.y >= 0.0 && at.x <= config.arena_width && at.y <= config.arena_height
+}
+
+// Checks a position that came from outside instead of from the move handler:
+// inside the arena, and with VALIDATE_MOVE_STEPS no more than one MOVE_STEP
+// along one axis from `from`. A shorter hop is allowed since walls cut moves
+// short; a longer one is a teleport and one on both axes a diagonal.
+fn validate_move(from: Position, to: Position, config: &GameConfig) -> Result<(), String> {
+    if !in_arena(to, config) {
+        return Err(format!("({}, {}) is outside the arena", to.x, to.y));
+    }
+    if config.validate_move_steps {
+        // Float positions drift slightly, so exact comparisons would misfire
+        const SLACK: f64 = 1e-6;
+        let (dx, dy) = ((to.x - from.x).abs(), (to.y - from.y).abs());
+        if dx > SLACK && dy > SLACK {
+            return Err(format!("({}, {}) to ({}, {}) is a diagonal move", from.x, from.y, to.x, to.y));
+        }
+        if dx.max(dy) > config.move_step + SLACK {
+            return Err(format!("({}, {}) to ({}, {}) is more than one step", from.x, from.y, to.x, to.y));
+        }
+    }
+    Ok(())
+}
+
 // Oracle configuration from environment variables
 fn get_oracle_host() -> String {
-    std::env::var("ORACLE_HOST").unwrap_or_else(|_| 
+    secret_var("ORACLE_HOST").unwrap_or_else(|_| 
         "myhost.adb.region.oraclecloudapps.com".to_string()
     )
 }
@@ -48,20 +453,78 @@ fn get_oracle_db_name() -> String {
     )
 }
 
+// Schema base URL; ORACLE_BASE_URL overrides the host-derived default so the
+// Oracle calls can be pointed at a local mock server
 fn get_oracle_base_url() -> String {
-    format!("https://{}/ords/admin", get_oracle_host())
+    match std::env::var("ORACLE_BASE_URL") {
+        Ok(url) => url.trim_end_matches('/').to_string(),
+        Err(_) => format!("https://{}/ords/admin", get_oracle_host()),
+    }
 }
 
 fn get_ords_url() -> String {
-    format!("https://{}/ords/admin/_sdw", get_oracle_host())
+    format!("{}/_sdw", get_oracle_base_url())
 }
 
 fn get_oracle_user() -> String {
-    std::env::var("ORACLE_USERNAME").unwrap_or_else(|_| "ADMIN".to_string())
+    secret_var("ORACLE_USERNAME").unwrap_or_else(|_| "ADMIN".to_string())
 }
 
 fn get_oracle_password() -> String {
-    std::env::var("ORACLE_PASSWORD").unwrap_or_else(|_| "mypassword".to_string())
+    secret_var("ORACLE_PASSWORD").unwrap_or_else(|_| "mypassword".to_string())
+}
+
+// These may instead come from a mounted secret: NAME_FILE points at a file
+// holding the value and takes precedence over NAME. The secret's directory has
+// to be preopened (wasmtime serve --dir) for the component to see it.
+const SECRET_FILE_VARS: [&str; 3] = ["ORACLE_HOST", "ORACLE_USERNAME", "ORACLE_PASSWORD"];
+
+// Each NAME_FILE is read the first time any secret is needed and then kept for
+// the life of the instance. Unreadable files are remembered as errors for /ready.
+fn get_secret_files() -> &'static HashMap<&'static str, Result<String, String>> {
+    unsafe {
+        let secret_files = &mut *std::ptr::addr_of_mut!(SECRET_FILES);
+        if secret_files.is_none() {
+            let mut secrets = HashMap::new();
+            for name in SECRET_FILE_VARS {
+                let path = match std::env::var(format!("{}_FILE", name)) {
+                    Ok(path) => path,
+                    Err(_) => continue,
+                };
+                let contents = std::fs::read_to_string(&path)
+                    .map(|secret| secret.trim_end_matches(['\r', '\n']).to_string())
+                    .map_err(|e| format!("{}_FILE ({}) could not be read: {}", name, path, e));
+                if let Err(e) = &contents {
+                    eprintln!("[WARN] {}; using {} from the environment", e, name);
+                }
+                secrets.insert(name, contents);
+            }
+            *secret_files = Some(secrets);
+        }
+        secret_files.as_ref().unwrap()
+    }
+}
+
+fn secret_var(name: &str) -> Result<String, std::env::VarError> {
+    match get_secret_files().get(name) {
+        Some(Ok(secret)) => Ok(secret.clone()),
+        _ => std::env::var(name),
+    }
+}
+
+// Authorization header value for Oracle calls: Bearer ORACLE_BEARER_TOKEN when
+// ORACLE_AUTH_MODE=bearer, otherwise Basic with ORACLE_USERNAME/ORACLE_PASSWORD
+fn oracle_auth_header() -> String {
+    let bearer = std::env::var("ORACLE_AUTH_MODE")
+        .map(|mode| mode.eq_ignore_ascii_case("bearer"))
+        .unwrap_or(false);
+    if bearer {
+        match std::env::var("ORACLE_BEARER_TOKEN") {
+            Ok(token) if !token.is_empty() => return format!("Bearer {}", token),
+            _ => eprintln!("[WARN] ORACLE_AUTH_MODE=bearer but ORACLE_BEARER_TOKEN is not set, using Basic auth"),
+        }
+    }
+    format!("Basic {}", base64_encode(&format!("{}:{}", get_oracle_user(), get_oracle_password())))
 }
 
 fn get_txeventq_base_url() -> String {
@@ -72,131 +535,692 @@ fn get_kafka_topic() -> String {
     std::env::var("KAFKA_TOPIC").unwrap_or_else(|_| "TEST_KAFKA_TOPIC_NEW".to_string())
 }
 
+// Castle name reported in every response and event (CASTLE_NAME overrides it)
+fn get_castle_name() -> String {
+    std::env::var("CASTLE_NAME").unwrap_or_else(|_| "Temporal Sanctuary".to_string())
+}
+
+// Game/match id used when join/move requests don't name one
+fn get_default_game_id() -> String {
+    std::env::var("GAME_ID").unwrap_or_else(|_| "wasicycles-multiplayer".to_string())
+}
+
+// Partitions new topics are created with
+fn get_kafka_partitions() -> usize {
+    std::env::var("KAFKA_PARTITIONS")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(1)
+}
+
+fn get_reconstruct_event_limit() -> usize {
+    std::env::var("RECONSTRUCT_EVENT_LIMIT")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|limit| *limit > 0)
+        .unwrap_or(100)
+}
+
+// Hard cap on events held in memory for one reconstruction, whatever the page
+// settings; a huge topic would otherwise exhaust a small instance
+fn get_reconstruct_max_events() -> usize {
+    std::env::var("RECONSTRUCT_MAX_EVENTS")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|max| *max > 0)
+        .unwrap_or(5000)
+}
+
+// RECONSTRUCT_MAX_AGE_MS drops events older than this from replay, so a join
+// whose leave has aged out of the consume window doesn't bring the player
+// back. Unset or 0 replays everything consumed.
+fn get_reconstruct_max_age_ms() -> Option<u64> {
+    std::env::var("RECONSTRUCT_MAX_AGE_MS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|age| *age > 0)
+}
+
+// Game configuration (arena, movement, spawn), overridable via env vars
+struct GameConfig {
+    arena_width: f64,
+    arena_height: f64,
+    move_step: f64,
+    min_players: usize,
+    max_players: usize,
+    spawn_x: f64,
+    spawn_y: f64,
+    color: String,
+    // Trail cells kept per cycle, oldest dropped first; 0 keeps them all
+    trail_length: usize,
+    // Points per surviving move (SCORE_MULTIPLIER, default 1). Scaling it with
+    // MOVE_STEP keeps scores comparable across runtimes: with wasmedge on
+    // 20px steps and SCORE_MULTIPLIER=20, this runtime's 1-unit steps stay at
+    // 1, so a point is one unit of travel everywhere.
+    score_multiplier: i32,
+    // VALIDATE_MOVE_STEPS, on unless set to false: a position supplied from
+    // outside may only be one straight MOVE_STEP (or less) from the previous
+    // one. Staying inside the arena is required regardless.
+    validate_move_steps: bool,
+}
+
+// Spawn point for a runtime sharing the arena with others. RUNTIME_INDEX % 4
+// selects a quadrant (0 top-left, 1 top-right, 2 bottom-left, 3 bottom-right)
+// and players start at its centre, rounded down to a whole move_step so they
+// stay on the movement grid. Explicit SPAWN_X/SPAWN_Y win over this.
+fn quadrant_spawn(index: usize, width: f64, height: f64, step: f64) -> (f64, f64) {
+    let column = (index % 2) as f64;
+    let row = ((index % 4) / 2) as f64;
+    let snap = |v: f64| if step > 0.0 { (v / step).floor() * step } else { v.floor() };
+    (snap(width / 4.0 + column * width / 2.0), snap(height / 4.0 + row * height / 2.0))
+}
+
+fn get_game_config() -> GameConfig {
+    fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+        std::env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+    }
+    
+    let arena_width = env_or("ARENA_WIDTH", 50.0);
+    let arena_height = env_or("ARENA_HEIGHT", 50.0);
+    let move_step = env_or("MOVE_STEP", 1.0);
+    // Quadrant 1 (top-right) by default, leaving 0 to wasmedge
+    let (quadrant_x, quadrant_y) = quadrant_spawn(env_or("RUNTIME_INDEX", 1), arena_width, arena_height, move_step);
+    
+    GameConfig {
+        arena_width,
+        arena_height,
+        move_step,
+        min_players: env_or("MIN_PLAYERS", 1),
+        max_players: env_or("MAX_PLAYERS", 32),
+        spawn_x: env_or("SPAWN_X", quadrant_x),
+        spawn_y: env_or("SPAWN_Y", quadrant_y),
+        color: env_or("CASTLE_COLOR", "#06b6d4".to_string()), // Cyan for Wasmtime
+        trail_length: env_or("TRAIL_LENGTH", 0),
+        score_multiplier: env_or("SCORE_MULTIPLIER", 1),
+        validate_move_steps: env_or("VALIDATE_MOVE_STEPS", true),
+    }
+}
+
+// The castle's color (CASTLE_COLOR), shown in responses and stamped on events
+fn runtime_color() -> String {
+    get_game_config().color
+}
+
+// Default color of the runtime that published an event, for events from
+// before they carried a color. Our own runtime uses its configured color.
+fn color_for_runtime(runtime: &str) -> String {
+    match runtime {
+        "wasmedge" => "#9333ea".to_string(), // Purple, wasmedge's CASTLE_COLOR default
+        _ => runtime_color(),
+    }
+}
+
 const LEADERBOARD_TOPIC: &str = "WASICYCLES_LEADERBOARD";
 
+// Published straight away rather than queued: the event queue only feeds the
+// game events topic, and these are rare enough not to need batching
+fn publish_leaderboard_update(player: &Player, tick: u64) {
+    if !publish_leaderboard_events() {
+        return;
+    }
+    let event = GameEvent::new("leaderboard_update", &player.id)
+        .with_game_id(&player.game_id)
+        .with_score(player.score)
+        .with_tick(tick);
+    if let Err(e) = publish_to_topic(LEADERBOARD_TOPIC, &event) {
+        eprintln!("[WARN] Failed to publish leaderboard update for {}: {}", player.id, e);
+    }
+}
+
+// Every topic this runtime reads or writes: game events, the optional state
+// topic reconstruction tries first, and the leaderboard stream
+fn referenced_topics() -> Vec<String> {
+    vec![
+        get_kafka_topic(),
+        format!("{}_STATE", get_kafka_topic()),
+        LEADERBOARD_TOPIC.to_string(),
+    ]
+}
+
+// Upper bound on record pages fetched per topic during state reconstruction
+const RECONSTRUCT_MAX_PAGES: usize = 20;
+
 // Cache for created topics
 static mut CREATED_TOPICS: Option<std::collections::HashSet<String>> = None;
+// Consumer instances created by this runtime (for GET/DELETE /consumers)
+static mut CREATED_CONSUMERS: Option<Vec<ConsumerInstance>> = None;
+// Outbound events waiting to be published (see flush_event_queue)
+static mut PENDING_EVENTS: Option<Vec<GameEvent>> = None;
+// Oldest queued events are dropped beyond this many
+const MAX_PENDING_EVENTS: usize = 500;
 
 // Static game state (simplified for WASM)
 static mut GAME_STATE: Option<HashMap<String, Player>> = None;
+// Latest tick per game_id. Reset with the other statics; advance_tick and
+// reconstruction re-seed it from saved players and replayed events.
+static mut GAME_TICKS: Option<HashMap<String, u64>> = None;
+// Serialized state as last accepted by a persistence store (or restored
+// from one); save_game_state skips saves that wouldn't change it
 static mut LAST_SAVED_STATE: Option<String> = None;
 // Flag to control log verbosity
 static mut DEBUG_LOGGING: bool = false;
+// POST /pause / /resume. Per instance, like every static here: a recycled
+// instance comes back unpaused.
+static mut GAME_PAUSED: bool = false;
+// Paths whose request/response bodies are logged in full (POST /debug/trace)
+static mut TRACED_ENDPOINTS: Option<std::collections::HashSet<String>> = None;
 // Global request counter to track requests across handler invocations
 static mut GLOBAL_REQUEST_COUNTER: u64 = 0;
 // Last state reconstruction time to prevent excessive calls
 static mut LAST_RECONSTRUCTION_TIME: u64 = 0;
+// Which tier get_game_state last loaded from, and when (GET /debug/state-source)
+static mut LAST_STATE_SOURCE: &str = "not_loaded";
+static mut LAST_STATE_SOURCE_AT: u64 = 0;
+// X-Request-Id of the request in progress, stamped on log lines
+static mut REQUEST_ID: Option<String> = None;
+// Event types replay didn't recognize: type -> (times seen, last warned at)
+static mut UNKNOWN_EVENT_TYPES: Option<HashMap<String, (u64, u64)>> = None;
+// *_FILE secrets by variable name (see get_secret_files)
+static mut SECRET_FILES: Option<HashMap<&'static str, Result<String, String>>> = None;
+// Last GameEvent seq handed out per player. Lost on a static reset, which is
+// harmless: seq only breaks ties inside one millisecond, and a recycled
+// instance never shares a millisecond with the one it replaced.
+static mut EVENT_SEQS: Option<HashMap<String, u64>> = None;
+// Unminimized last event per player, for MINIMAL_EVENTS diffs
+static mut LAST_PLAYER_EVENTS: Option<HashMap<String, GameEvent>> = None;
 
 // Helper function to safely access DEBUG_LOGGING
 fn is_debug_enabled() -> bool {
     unsafe { DEBUG_LOGGING }
 }
 
-// Serialize and deserialize game state for persistence across requests
-fn save_game_state(players: &mut HashMap<String, Player>) -> Result<(), ErrorCode> {
-    unsafe {
-        // Minimal logging - only if debug enabled or non-empty state
-        let player_count = players.len();
-        if player_count > 0 {
-            eprintln!("[INFO] Saving game state with {} players", player_count);
-        }
-        
-        // Serialize game state to JSON
-        let serialized = match serde_json::to_string(players) {
-            Ok(s) => s,
-            Err(e) => {
-                eprintln!("[ERROR] Failed to serialize game state: {:?}", e);
-                return Err(ErrorCode::InternalError(None));
-            }
-        };
-        
-        // Store serialized state in static variable (may get reset in Wasmtime)
-        LAST_SAVED_STATE = Some(serialized.clone());
-        GAME_STATE = Some(players.clone());
-        
-        // Try multiple persistence mechanisms in order of preference
-        let mut persistence_success = false;
-        
-        // 1. Try Oracle ORDS first (returns dummy values if fails, so always succeeds)
-        if let Err(e) = save_state_to_oracle(&serialized) {
-            eprintln!("[WARN] Failed to save state to Oracle: {}", e);
-            
-            // 2. Try TxEventQ snapshots as fallback (but avoid for empty states)
-            if player_count > 0 {
-                if let Err(kafka_err) = publish_state_snapshot_to_kafka(players) {
-                    eprintln!("[WARN] Failed to publish state snapshot to TxEventQ: {}", kafka_err);
-                } else {
-                    persistence_success = true;
-                    if is_debug_enabled() {
-                        eprintln!("[DEBUG] State snapshot published to TxEventQ as fallback");
-                    }
-                }
-            } else {
-                eprintln!("[DEBUG] Skipping TxEventQ snapshot for empty state");
-            }
-        } else {
-            persistence_success = true;
-            if is_debug_enabled() {
-                eprintln!("[DEBUG] State saved to Oracle successfully");
-            }
-        }
-        
-        if !persistence_success && player_count > 0 {
-            eprintln!("[ERROR] All persistence mechanisms failed for {} players", player_count);
-        }
-        
-        Ok(())
-    }
+fn is_paused() -> bool {
+    unsafe { GAME_PAUSED }
 }
 
-// Save state to Oracle database as backup
-fn save_state_to_oracle(state_json: &str) -> Result<(), String> {
-    let client = Client::new();
-    let state_data = json!({
-        "state_key": "wasmtime_game_state",
-        "state_data": state_json,
-        "runtime": "wasmtime",
-        "timestamp": get_timestamp()
-    });
+fn get_traced_endpoints() -> &'static mut std::collections::HashSet<String> {
+    unsafe { (*std::ptr::addr_of_mut!(TRACED_ENDPOINTS)).get_or_insert_with(std::collections::HashSet::new) }
+}
 
-    let auth = base64_encode(&format!("{}:{}", get_oracle_user(), get_oracle_password()));
-    let url = format!("{}/game_state/", get_ords_url());
+fn is_traced(path: &str) -> bool {
+    get_traced_endpoints().contains(path)
+}
 
-    let response = client
-        .post(&url)
-        .headers([
-            ("Content-Type", "application/json"),
-            ("Authorization", &format!("Basic {}", auth))
-        ])
-        .body(state_data.to_string().as_bytes().to_vec())
-        .send()
-        .map_err(|e| format!("Oracle state save request failed: {}", e))?;
+// Body logging for traced endpoints; JSON bodies go through redact() first
+fn trace_request_body(path: &str, body: &[u8]) {
+    if is_traced(path) {
+        eprintln!("[TRACE] {} request body: {}", path, redact(&String::from_utf8_lossy(body)));
+    }
+}
 
-    let status_code = response.status_code();
+// Logs a traced endpoint's response body, rebuilding the response since
+// reading the body consumes it
+fn trace_response(path: &str, response: Result<Response, ErrorCode>) -> Result<Response, ErrorCode> {
+    let response = response?;
+    let status = response.status_code();
+    let headers: Vec<(HeaderName, String)> = response.headers()
+        .iter()
+        .filter_map(|(name, value)| Some((name.clone(), value.to_str().ok()?.to_string())))
+        .collect();
+    let body = response.body().unwrap_or_default();
+    eprintln!("[TRACE] {} response {}: {}", path, status, redact(&String::from_utf8_lossy(&body)));
     
-    if status_code >= 200 && status_code < 300 {
-        Ok(())
-    } else {
-        Err(format!("Oracle state save error: {}", status_code))
+    let mut builder = Response::builder().status_code(status);
+    for (name, value) in headers {
+        builder = builder.header(name, value);
     }
+    builder.body(body).build()
 }
 
-// Load state from Oracle database
-fn load_state_from_oracle() -> Result<String, String> {
-    let client = Client::new();
-    let auth = base64_encode(&format!("{}:{}", get_oracle_user(), get_oracle_password()));
-    let url = format!("{}/game_state/wasmtime_game_state", get_ords_url());
+// Debug and state reads that honor ?pretty=true; everything else stays minified
+fn is_pretty_printable(path: &str) -> bool {
+    is_spectator_path(path)
+        || path.starts_with("/debug/")
+        || matches!(path, "/health" | "/consumers" | "/consume-kafka" | "/drain-messages")
+}
 
-    let response = client
-        .get(&url)
-        .headers([
-            ("Accept", "application/json"),
-            ("Authorization", &format!("Basic {}", auth))
-        ])
-        .send()
-        .map_err(|e| format!("Oracle state load request failed: {}", e))?;
+// Stamps the request id on the X-Request-Id header. The body is only parsed
+// and rewritten when the client asked for it: a "request_id" field on JSON
+// objects when it sent an X-Request-Id of its own (`echo_in_body`), and
+// to_string_pretty indentation for ?pretty=true. Otherwise, and for msgpack or
+// bodies that don't parse, the handler's bytes go out untouched.
+fn finish_response(response: Result<Response, ErrorCode>, request_id: &str, echo_in_body: bool, pretty: bool) -> Result<Response, ErrorCode> {
+    let response = response?;
+    let status = response.status_code();
+    let headers: Vec<(HeaderName, String)> = response.headers()
+        .iter()
+        .filter_map(|(name, value)| Some((name.clone(), value.to_str().ok()?.to_string())))
+        .collect();
+    let body = response.body().unwrap_or_default();
+    
+    let rewrite = (echo_in_body || pretty) && headers.iter()
+        .any(|(name, value)| name == CONTENT_TYPE && value.contains("application/json"));
+    let parsed = if rewrite { serde_json::from_slice::<Value>(&body).ok() } else { None };
+    let body = match parsed {
+        Some(mut value) => {
+            if let (true, Some(fields)) = (echo_in_body, value.as_object_mut()) {
+                fields.entry("request_id").or_insert_with(|| json!(request_id));
+            }
+            let serialized = if pretty { serde_json::to_vec_pretty(&value) } else { serde_json::to_vec(&value) };
+            serialized.unwrap_or(body)
+        },
+        None => body,
+    };
+    
+    let mut builder = Response::builder().status_code(status);
+    for (name, value) in headers {
+        builder = builder.header(name, value);
+    }
+    builder
+        .header("X-Request-Id", request_id)
+        .header("Access-Control-Expose-Headers", "X-Request-Id")
+        .body(body)
+        .build()
+}
 
-    let status_code = response.status_code();
+// Masks credential-looking fields (password, token, secret, api key,
+// authorization) anywhere in a JSON body. Non-JSON bodies are returned as is.
+fn redact(body: &str) -> String {
+    fn mask(value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                for (key, field) in map.iter_mut() {
+                    let key = key.to_ascii_lowercase();
+                    let sensitive = ["password", "token", "secret", "api_key", "apikey", "authorization"]
+                        .iter()
+                        .any(|s| key.contains(s));
+                    if sensitive {
+                        *field = Value::String("***".to_string());
+                    } else {
+                        mask(field);
+                    }
+                }
+            },
+            Value::Array(items) => items.iter_mut().for_each(mask),
+            _ => {}
+        }
+    }
+    
+    match serde_json::from_str::<Value>(body) {
+        Ok(mut value) => {
+            mask(&mut value);
+            value.to_string()
+        },
+        Err(_) => body.to_string(),
+    }
+}
+
+// POST /debug/trace and /debug/untrace with {"endpoint":"/move"}. Untrace with
+// no endpoint clears every trace.
+fn handle_debug_trace(req: Request, enable: bool) -> Result<Response, ErrorCode> {
+    let body = req.body().unwrap_or_default();
+    if let Some(rejection) = check_body_size(&body) {
+        return rejection;
+    }
+    let endpoint = serde_json::from_slice::<Value>(&body)
+        .ok()
+        .and_then(|payload| payload["endpoint"].as_str().map(|e| normalize_path(e).to_string()));
+    
+    let traced = get_traced_endpoints();
+    match (enable, endpoint) {
+        (true, Some(endpoint)) => {
+            eprintln!("[INFO] Tracing request/response bodies for {}", endpoint);
+            traced.insert(endpoint);
+        },
+        (false, Some(endpoint)) => {
+            eprintln!("[INFO] Stopped tracing {}", endpoint);
+            traced.remove(&endpoint);
+        },
+        (false, None) => {
+            eprintln!("[INFO] Cleared all endpoint traces");
+            traced.clear();
+        },
+        (true, None) => return error_response("Missing endpoint", 400),
+    }
+    
+    let mut endpoints: Vec<&String> = traced.iter().collect();
+    endpoints.sort();
+    let response = json!({
+        "status": "success",
+        "traced_endpoints": endpoints,
+        "timestamp": get_timestamp()
+    });
+    
+    Response::builder()
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(response.to_string())
+        .build()
+}
+
+// STATE_FILE keeps the players map on local disk for development without
+// Oracle (the directory must be preopened, e.g. `wasmtime serve --dir`)
+fn get_state_file() -> Option<String> {
+    std::env::var("STATE_FILE").ok().filter(|path| !path.is_empty())
+}
+
+// Written to a sibling temp file and renamed over the target, so a crash
+// mid-write never leaves a truncated state file behind
+fn write_state_file(path: &str, serialized: &str) -> Result<(), String> {
+    let tmp_path = format!("{}.tmp", path);
+    std::fs::write(&tmp_path, serialized).map_err(|e| e.to_string())?;
+    std::fs::rename(&tmp_path, path).map_err(|e| e.to_string())
+}
+
+// A place the players map can be saved to and restored from. Stores are tried
+// in order: save stops at the first Ok, and so does load. Ok from load is
+// authoritative even when empty (Oracle saying "no players" ends the search);
+// a store with nothing to offer returns Err so the next one gets a turn.
+trait StateStore {
+    fn name(&self) -> &'static str;
+    // Reported by /debug/state-source when this store served the last load
+    fn source(&self) -> &'static str;
+    fn save(&self, players: &HashMap<String, Player>) -> Result<(), String>;
+    fn load(&self) -> Result<HashMap<String, Player>, String>;
+}
+
+// The GAME_STATE static: free to read, but reset whenever Wasmtime spins up
+// a fresh instance, so it only ever heads the load chain
+struct MemoryStore;
+struct OracleStore;
+// Saves snapshots to TxEventQ and loads by replaying the event log
+struct TxEventQStore;
+struct FileStore;
+
+fn parse_state(serialized: &str) -> Result<HashMap<String, Player>, String> {
+    serde_json::from_str::<HashMap<String, Player>>(serialized).map_err(|e| format!("not a player map: {}", e))
+}
+
+impl StateStore for MemoryStore {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+    
+    fn source(&self) -> &'static str {
+        "static_variables"
+    }
+    
+    fn save(&self, players: &HashMap<String, Player>) -> Result<(), String> {
+        unsafe {
+            GAME_STATE = Some(players.clone());
+        }
+        Ok(())
+    }
+    
+    fn load(&self) -> Result<HashMap<String, Player>, String> {
+        let state = unsafe { (*std::ptr::addr_of!(GAME_STATE)).clone() }.ok_or_else(|| "no state in memory".to_string())?;
+        if state.is_empty() {
+            return Err("state in memory is empty".to_string());
+        }
+        Ok(state)
+    }
+}
+
+impl StateStore for OracleStore {
+    fn name(&self) -> &'static str {
+        "oracle"
+    }
+    
+    fn source(&self) -> &'static str {
+        "oracle_backup"
+    }
+    
+    fn save(&self, players: &HashMap<String, Player>) -> Result<(), String> {
+        let serialized = serialize_game_state(players).map_err(|e| e.to_string())?;
+        save_state_to_oracle(&serialized)
+    }
+    
+    fn load(&self) -> Result<HashMap<String, Player>, String> {
+        parse_state(&load_state_from_oracle()?)
+    }
+}
+
+impl StateStore for TxEventQStore {
+    fn name(&self) -> &'static str {
+        "txeventq"
+    }
+    
+    fn source(&self) -> &'static str {
+        "txeventq_events"
+    }
+    
+    // An empty snapshot would only shadow older, useful ones on reconstruction
+    fn save(&self, players: &HashMap<String, Player>) -> Result<(), String> {
+        if players.is_empty() {
+            return Err("skipping snapshot for empty state".to_string());
+        }
+        publish_state_snapshot_to_kafka(players)
+    }
+    
+    fn load(&self) -> Result<HashMap<String, Player>, String> {
+        let state = reconstruct_state_from_events();
+        if state.is_empty() {
+            return Err("no events available for reconstruction".to_string());
+        }
+        Ok(state)
+    }
+}
+
+impl StateStore for FileStore {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+    
+    fn source(&self) -> &'static str {
+        "state_file"
+    }
+    
+    fn save(&self, players: &HashMap<String, Player>) -> Result<(), String> {
+        let path = get_state_file().ok_or_else(|| "STATE_FILE is not set".to_string())?;
+        let serialized = serialize_game_state(players).map_err(|e| e.to_string())?;
+        write_state_file(&path, &serialized)
+    }
+    
+    fn load(&self) -> Result<HashMap<String, Player>, String> {
+        let path = get_state_file().ok_or_else(|| "STATE_FILE is not set".to_string())?;
+        let contents = std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path, e))?;
+        let state = parse_state(&contents)?;
+        if state.is_empty() {
+            return Err(format!("{} has no players", path));
+        }
+        Ok(state)
+    }
+}
+
+const DEFAULT_PERSISTENCE_ORDER: &str = "oracle,txeventq";
+
+fn state_store(name: &str) -> Option<Box<dyn StateStore>> {
+    match name {
+        "oracle" => Some(Box::new(OracleStore)),
+        "txeventq" => Some(Box::new(TxEventQStore)),
+        "file" => Some(Box::new(FileStore)),
+        _ => None,
+    }
+}
+
+fn get_persistence_order_names() -> Vec<String> {
+    std::env::var("PERSISTENCE_ORDER")
+        .ok()
+        .filter(|order| !order.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_PERSISTENCE_ORDER.to_string())
+        .split(',')
+        .map(|name| name.trim().to_ascii_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+// PERSISTENCE_ORDER, e.g. "txeventq,oracle" for deployments without ORDS.
+// Unknown names are skipped (config_problems reports them); if none are left
+// the default order applies.
+fn get_persistence_stores() -> Vec<Box<dyn StateStore>> {
+    let stores: Vec<Box<dyn StateStore>> = get_persistence_order_names()
+        .iter()
+        .filter_map(|name| state_store(name))
+        .collect();
+    if stores.is_empty() {
+        return DEFAULT_PERSISTENCE_ORDER.split(',').filter_map(state_store).collect();
+    }
+    stores
+}
+
+// Where get_game_state looks, in order: memory, then a STATE_FILE that isn't
+// already one of the ordered stores (it needs no Oracle round trip), then
+// PERSISTENCE_ORDER
+fn get_load_stores() -> Vec<Box<dyn StateStore>> {
+    let mut stores: Vec<Box<dyn StateStore>> = vec![Box::new(MemoryStore)];
+    let ordered = get_persistence_stores();
+    if get_state_file().is_some() && !ordered.iter().any(|store| store.name() == "file") {
+        stores.push(Box::new(FileStore));
+    }
+    stores.extend(ordered);
+    stores
+}
+
+// The first store that takes the state, by name; None if every one failed
+fn save_to_first_store(stores: &[Box<dyn StateStore>], players: &HashMap<String, Player>) -> Option<&'static str> {
+    for store in stores {
+        match store.save(players) {
+            Ok(()) => {
+                if is_debug_enabled() {
+                    eprintln!("[DEBUG] State saved via {}", store.name());
+                }
+                return Some(store.name());
+            },
+            Err(e) => eprintln!("[WARN] Failed to save state via {}: {}", store.name(), e),
+        }
+    }
+    None
+}
+
+// The state from the first store that has one, with that store's source
+fn load_from_first_store(stores: &[Box<dyn StateStore>]) -> Option<(HashMap<String, Player>, &'static str)> {
+    for store in stores {
+        match store.load() {
+            Ok(state) => return Some((state, store.source())),
+            Err(e) => {
+                if is_debug_enabled() {
+                    eprintln!("[DEBUG] No state from {}: {}", store.name(), e);
+                }
+            },
+        }
+    }
+    None
+}
+
+// Serialize and deserialize game state for persistence across requests
+fn save_game_state(players: &mut HashMap<String, Player>) -> Result<(), ErrorCode> {
+    unsafe {
+        // Minimal logging - only if debug enabled or non-empty state
+        let player_count = players.len();
+        if player_count > 0 {
+            eprintln!("[INFO] Saving game state with {} players", player_count);
+        }
+        
+        // Serialize game state to JSON
+        let serialized = match serialize_game_state(players) {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("[ERROR] Failed to serialize game state: {:?}", e);
+                return Err(ErrorCode::InternalError(None));
+            }
+        };
+        
+        // Identical bytes mean identical state (keys are sorted), so there is
+        // nothing new to push to Oracle/TxEventQ. LAST_SAVED_STATE only moves
+        // once a store has taken the state, so after a failed save the same
+        // state is tried again rather than skipped.
+        let unchanged = (*std::ptr::addr_of!(LAST_SAVED_STATE)).as_deref() == Some(serialized.as_str());
+        
+        // Keep the state in memory (may get reset in Wasmtime)
+        GAME_STATE = Some(players.clone());
+        
+        if unchanged {
+            eprintln!("[INFO] State unchanged, skipping save");
+            return Ok(());
+        }
+        
+        let stores = get_persistence_stores();
+        
+        // Unless "file" is one of the ordered stores, the local dev file is
+        // always written and doesn't count towards persistence
+        if !stores.iter().any(|store| store.name() == "file") {
+            if let Some(path) = get_state_file() {
+                if let Err(e) = write_state_file(&path, &serialized) {
+                    eprintln!("[WARN] Failed to write state file {}: {}", path, e);
+                }
+            }
+        }
+        
+        // Try each store in PERSISTENCE_ORDER until one takes the state
+        let saved_via = save_to_first_store(&stores, players);
+        if saved_via.is_some() {
+            LAST_SAVED_STATE = Some(serialized.clone());
+        }
+        
+        if saved_via.is_none() && player_count > 0 {
+            eprintln!("[ERROR] All persistence mechanisms failed for {} players", player_count);
+        }
+        
+        Ok(())
+    }
+}
+
+// Serialize players in sorted key order so identical state always yields identical
+// bytes (HashMap iteration order is random), which makes saved blobs comparable
+fn serialize_game_state(players: &HashMap<String, Player>) -> serde_json::Result<String> {
+    let ordered: BTreeMap<&String, &Player> = players.iter().collect();
+    serde_json::to_string(&ordered)
+}
+
+// Save state to Oracle database as backup
+fn save_state_to_oracle(state_json: &str) -> Result<(), String> {
+    let client = Client::new();
+    let state_data = json!({
+        "state_key": "wasmtime_game_state",
+        "state_data": state_json,
+        "runtime": "wasmtime",
+        "timestamp": get_timestamp()
+    });
+
+    let auth = oracle_auth_header();
+    let url = format!("{}/game_state/", get_ords_url());
+
+    let response = client
+        .post(&url)
+        .headers([
+            ("Content-Type", "application/json"),
+            ("Authorization", &auth)
+        ])
+        .body(state_data.to_string().as_bytes().to_vec())
+        .send()
+        .map_err(|e| format!("Oracle state save request failed: {}", e))?;
+
+    let status_code = response.status_code();
+    
+    if status_code >= 200 && status_code < 300 {
+        Ok(())
+    } else {
+        Err(format!("Oracle state save error: {}", status_code))
+    }
+}
+
+// Load state from Oracle database
+fn load_state_from_oracle() -> Result<String, String> {
+    let client = Client::new();
+    let auth = oracle_auth_header();
+    let url = format!("{}/game_state/wasmtime_game_state", get_ords_url());
+
+    let response = client
+        .get(&url)
+        .headers([
+            ("Accept", "application/json"),
+            ("Authorization", &auth)
+        ])
+        .send()
+        .map_err(|e| format!("Oracle state load request failed: {}", e))?;
+
+    let status_code = response.status_code();
     
     if status_code >= 200 && status_code < 300 {
         let body = response.body().map_err(|e| format!("Failed to read body: {}", e))?;
@@ -226,7 +1250,7 @@ fn load_game_state() -> HashMap<String, Player> {
         eprintln!("[INFO] Attempting to load game state");
         
         // First check if there's already a valid GAME_STATE and prefer that
-        if let Some(game_state) = &GAME_STATE {
+        if let Some(game_state) = &*std::ptr::addr_of!(GAME_STATE) {
             if !game_state.is_empty() {
                 let player_count = game_state.len();
                 eprintln!("[INFO] Using existing in-memory game state with {} players", player_count);
@@ -246,7 +1270,7 @@ fn load_game_state() -> HashMap<String, Player> {
         }
         
         // Otherwise try to load from LAST_SAVED_STATE
-        if let Some(serialized) = &LAST_SAVED_STATE {
+        if let Some(serialized) = &*std::ptr::addr_of!(LAST_SAVED_STATE) {
             eprintln!("[INFO] Found serialized state, deserializing");
             
             match serde_json::from_str::<HashMap<String, Player>>(serialized) {
@@ -295,75 +1319,16 @@ fn load_game_state() -> HashMap<String, Player> {
 
 fn get_game_state() -> &'static mut HashMap<String, Player> {
     unsafe {
-        // Always reload from LAST_SAVED_STATE to ensure persistence
-        let mut new_state = HashMap::new();
-        let mut state_source = "empty";
-        
-        // First try to load from LAST_SAVED_STATE (static variables - may be reset in Wasmtime)
-        if let Some(serialized) = &LAST_SAVED_STATE {
-            match serde_json::from_str::<HashMap<String, Player>>(serialized) {
-                Ok(state) => {
-                    new_state = state;
-                    state_source = "static_variables";
-                    // Only log when players actually exist
-                    if !new_state.is_empty() {
-                        eprintln!("[DEBUG] Loaded {} players from static variables", new_state.len());
-                        let player_ids: Vec<String> = new_state.keys().cloned().collect();
-                        eprintln!("[DEBUG] Loaded player IDs: {:?}", player_ids);
-                    }
-                },
-                Err(e) => {
-                    eprintln!("[ERROR] Failed to deserialize static state: {}", e);
-                }
-            }
-        } 
+        // Always reload, since the statics may have been reset in Wasmtime
+        let (new_state, state_source) = load_from_first_store(&get_load_stores())
+            .unwrap_or_else(|| (HashMap::new(), "empty_fallback"));
         
-        // If static variables are empty (Wasmtime reset), try Oracle backup first,
-        // then try TxEventQ event sourcing as fallback
-        if new_state.is_empty() {
-            // Try Oracle backup first (returns empty state on failure, so it's safe)
-            match load_state_from_oracle() {
-                Ok(backup_state) => {
-                    match serde_json::from_str::<HashMap<String, Player>>(&backup_state) {
-                        Ok(state) => {
-                            new_state = state;
-                            state_source = "oracle_backup";
-                            if !new_state.is_empty() {
-                                eprintln!("[INFO] Restored {} players from Oracle backup due to static reset", new_state.len());
-                                // Restore to static storage
-                                LAST_SAVED_STATE = Some(backup_state);
-                            }
-                        },
-                        Err(e) => {
-                            eprintln!("[WARN] Failed to deserialize Oracle backup state: {}", e);
-                            state_source = "empty_fallback";
-                        }
-                    }
-                },
-                Err(_) => {
-                    // Oracle backup failed, try TxEventQ event sourcing
-                    eprintln!("[INFO] Oracle backup unavailable, attempting TxEventQ event sourcing...");
-                    let reconstructed_state = reconstruct_state_from_events();
-                    if !reconstructed_state.is_empty() {
-                        new_state = reconstructed_state;
-                        state_source = "txeventq_events";
-                        eprintln!("[INFO] Reconstructed {} players from TxEventQ events", new_state.len());
-                        // Save the reconstructed state to static storage
-                        match serde_json::to_string(&new_state) {
-                            Ok(serialized) => {
-                                LAST_SAVED_STATE = Some(serialized);
-                            },
-                            Err(e) => {
-                                eprintln!("[ERROR] Failed to serialize reconstructed state: {}", e);
-                            }
-                        }
-                    } else {
-                        state_source = "empty_fallback";
-                        if is_debug_enabled() {
-                            eprintln!("[DEBUG] No events available for reconstruction, using empty state");
-                        }
-                    }
-                }
+        // Anything restored from outside memory goes back into the statics
+        if state_source != "static_variables" && !new_state.is_empty() {
+            eprintln!("[INFO] Restored {} players from {} after a static reset", new_state.len(), state_source);
+            match serialize_game_state(&new_state) {
+                Ok(serialized) => LAST_SAVED_STATE = Some(serialized),
+                Err(e) => eprintln!("[ERROR] Failed to serialize restored state: {}", e),
             }
         }
         
@@ -372,32 +1337,130 @@ fn get_game_state() -> &'static mut HashMap<String, Player> {
             eprintln!("[INFO] Game state loaded from: {} ({} players)", state_source, new_state.len());
         }
         
+        LAST_STATE_SOURCE = state_source;
+        LAST_STATE_SOURCE_AT = get_timestamp();
+        
         // Update the global state
-        GAME_STATE = Some(new_state);
-        GAME_STATE.as_mut().unwrap()
+        (*std::ptr::addr_of_mut!(GAME_STATE)).insert(new_state)
+    }
+}
+
+// A living, recently active record of a rejoining player: first from the
+// current state, then, only when the id is missing there, from the ORDS
+// players table. Stale rows (idle past PLAYER_IDLE_TIMEOUT_MS) would only be
+// swept again. A failed ORDS lookup is an Err under STRICT_ORDS; otherwise
+// the player is treated as new, loudly, since that may reset a real score.
+fn find_returning_player(players: &HashMap<String, Player>, player_id: &str, game_id: &str) -> Result<Option<Player>, String> {
+    returning_player(players, player_id, game_id, lookup_player_in_ords)
+}
+
+fn returning_player(
+    players: &HashMap<String, Player>,
+    player_id: &str,
+    game_id: &str,
+    lookup: impl FnOnce(&str) -> Result<Option<Player>, String>,
+) -> Result<Option<Player>, String> {
+    let now = get_timestamp();
+    let returnable = |p: &Player| {
+        p.alive && p.game_id == game_id && now.saturating_sub(p.last_seen) < get_player_idle_timeout_ms()
+    };
+    
+    if let Some(player) = players.get(player_id) {
+        return Ok(Some(player.clone()).filter(|p| returnable(p)));
+    }
+    
+    match lookup(player_id) {
+        Ok(Some(player)) if returnable(&player) => Ok(Some(player)),
+        Ok(_) => Ok(None),
+        Err(e) if is_strict_ords() => Err(format!("Could not check ORDS for returning player {}: {}", player_id, e)),
+        Err(e) => {
+            eprintln!("[WARN] !!! Could not check ORDS for returning player {} ({}); spawning them fresh with score 0 - set STRICT_ORDS=true to refuse the join instead", player_id, e);
+            Ok(None)
+        }
     }
 }
 
+fn get_last_state_source() -> (&'static str, u64) {
+    unsafe { (LAST_STATE_SOURCE, LAST_STATE_SOURCE_AT) }
+}
+
+// Which persistence tier served the most recent state load. Anything other
+// than static_variables means the statics were reset and we fell back.
+fn handle_state_source() -> Result<Response, ErrorCode> {
+    let (source, loaded_at) = get_last_state_source();
+    let response = json!({
+        "status": "success",
+        "runtime": "wasmtime",
+        "state_source": source,
+        "loaded_at": loaded_at,
+        "fallback": source != "static_variables",
+        "timestamp": get_timestamp()
+    });
+    
+    Response::builder()
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(response.to_string())
+        .build()
+}
+
 #[handler]
 fn hello(req: Request) -> Result<Response, ErrorCode> {
+    let supplied_id = incoming_request_id(&req);
+    let request_id = supplied_id.clone().unwrap_or_else(generate_request_id);
+    unsafe {
+        REQUEST_ID = Some(request_id.clone());
+    }
+    let pretty = is_pretty_printable(normalize_path(req.path()))
+        && req.query().get("pretty").map(|v| v == "true").unwrap_or(false);
+    
+    let response = route_request(req);
+    finish_response(response, &request_id, supplied_id.is_some(), pretty)
+}
+
+// A client's X-Request-Id when it is short printable ASCII, so it can't break
+// up the log lines it prefixes; otherwise a fresh one is generated
+fn incoming_request_id(req: &Request) -> Option<String> {
+    req.header("x-request-id")
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|id| !id.is_empty() && id.len() <= 128 && id.bytes().all(|b| b.is_ascii_graphic()))
+        .map(str::to_string)
+}
+
+// RandomState is keyed from the host's random source, which makes its hasher
+// a serviceable id generator without a rand crate
+fn generate_request_id() -> String {
+    use std::hash::{BuildHasher, Hasher};
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u64(get_timestamp());
+    format!("{:016x}", hasher.finish())
+}
+
+fn log_prefix() -> String {
+    unsafe { (*std::ptr::addr_of!(REQUEST_ID)).as_ref().map(|id| format!("[{}] ", id)).unwrap_or_default() }
+}
+
+fn route_request(req: Request) -> Result<Response, ErrorCode> {
     // Use the global counter to track requests across handler invocations
     unsafe {
         GLOBAL_REQUEST_COUNTER += 1;
+        let request_count = GLOBAL_REQUEST_COUNTER;
         
         // Only log every 20 requests to reduce noise
-        if GLOBAL_REQUEST_COUNTER % 20 == 0 {
-            eprintln!("[INFO] Request handler processing request #{}", GLOBAL_REQUEST_COUNTER);
+        if request_count % 20 == 0 {
+            eprintln!("[INFO] Request handler processing request #{}", request_count);
         }
     }
     
-    let path = req.path();
+    let path = normalize_path(req.path());
     let method = req.method();
     
     // Add CORS headers for all responses
     let response_builder = Response::builder()
         .header("Access-Control-Allow-Origin", "*")
-        .header("Access-Control-Allow-Methods", "GET, POST, OPTIONS")
-        .header("Access-Control-Allow-Headers", "Content-Type");
+        .header("Access-Control-Allow-Methods", "GET, POST, DELETE, OPTIONS")
+        .header("Access-Control-Allow-Headers", "Content-Type, X-Admin-Token, X-API-Key, X-Request-Id");
     
     // Handle OPTIONS requests for CORS preflight
     match method {
@@ -410,8 +1473,29 @@ fn hello(req: Request) -> Result<Response, ErrorCode> {
         _ => {}
     }
     
+    if let Some(rejection) = check_api_key(&req, &method, path) {
+        return rejection;
+    }
+    
+    if let Some(rejection) = check_json_content_type(&req, &method) {
+        return rejection;
+    }
+    
+    if let Some(rejection) = check_declared_body_size(&req) {
+        return rejection;
+    }
+    
+    // Publish whatever earlier requests queued; /flush does this itself
+    if path != "/flush" {
+        flush_event_queue();
+    }
+    
+    // Owned copy so the body can still be traced after a handler consumes req
+    let traced_path = if is_traced(path) { Some(path.to_string()) } else { None };
+    let flush_after = flush_before_response() && path != "/flush";
+    
     // Process the request
-    match (method, path) {
+    let response = match (method, path) {
         // Special routes to toggle debug logging
         (Method::Get, "/debug/enable") => {
             unsafe { DEBUG_LOGGING = true; }
@@ -433,12 +1517,16 @@ fn hello(req: Request) -> Result<Response, ErrorCode> {
         },
         (Method::Get, "/debug/status") => {
             let status = is_debug_enabled();
+            let mut traced: Vec<&String> = get_traced_endpoints().iter().collect();
+            traced.sort();
             response_builder
                 .status_code(200)
                 .header("Content-Type", "application/json")
-                .body(format!(r#"{{"status": "success", "debug_enabled": {}}}"#, status))
+                .body(json!({"status": "success", "debug_enabled": status, "traced_endpoints": traced}).to_string())
                 .build()
         },
+        (Method::Post, "/debug/trace") => handle_debug_trace(req, true),
+        (Method::Post, "/debug/untrace") => handle_debug_trace(req, false),
         (Method::Get, "/debug/wasmtime-behavior") => {
             let runtime_behavior = json!({
                 "runtime": "wasmtime",
@@ -475,6 +1563,11 @@ fn hello(req: Request) -> Result<Response, ErrorCode> {
                 .body(runtime_behavior.to_string())
                 .build()
         },
+        (Method::Get, "/debug/dump-events") => handle_dump_events(&req),
+        (Method::Post, "/debug/replay-events") => handle_replay_events(req),
+        (Method::Get, "/debug/state-source") => handle_state_source(),
+        (Method::Get, "/debug/state-diff") => handle_state_diff(),
+        (Method::Get, "/debug/env") => handle_debug_env(&req),
         (Method::Get, "/debug/reconstruct-from-events") => {
             eprintln!("[INFO] Manual TxEventQ state reconstruction triggered");
             let reconstructed_state = reconstruct_state_from_events();
@@ -482,11 +1575,15 @@ fn hello(req: Request) -> Result<Response, ErrorCode> {
             // Save reconstructed state if not empty
             unsafe {
                 if !reconstructed_state.is_empty() {
-                    if let Ok(serialized) = serde_json::to_string(&reconstructed_state) {
-                        LAST_SAVED_STATE = Some(serialized.clone());
-                        GAME_STATE = Some(reconstructed_state.clone());
-                        let _ = save_state_to_oracle(&serialized);
-                        eprintln!("[INFO] Reconstructed state saved to static variables and Oracle");
+                    GAME_STATE = Some(reconstructed_state.clone());
+                    if let Ok(serialized) = serialize_game_state(&reconstructed_state) {
+                        match save_state_to_oracle(&serialized) {
+                            Ok(()) => {
+                                LAST_SAVED_STATE = Some(serialized);
+                                eprintln!("[INFO] Reconstructed state saved to static variables and Oracle");
+                            },
+                            Err(e) => eprintln!("[WARN] Reconstructed state kept in memory but not saved to Oracle: {}", e),
+                        }
                     }
                 }
             }
@@ -497,6 +1594,10 @@ fn hello(req: Request) -> Result<Response, ErrorCode> {
                 "message": "TxEventQ state reconstruction completed",
                 "reconstructed_players": reconstructed_state.len(),
                 "players": reconstructed_state.keys().collect::<Vec<_>>(),
+                "unknown_event_types": get_unknown_event_types()
+                    .iter()
+                    .map(|(event_type, (seen, _))| (event_type.clone(), json!(seen)))
+                    .collect::<serde_json::Map<String, Value>>(),
                 "timestamp": get_timestamp()
             });
 
@@ -506,52 +1607,581 @@ fn hello(req: Request) -> Result<Response, ErrorCode> {
                 .body(response.to_string())
                 .build()
         },
-        // Standard game endpoints
+        // Standard game endpoints. There is no /ws here (see wasmedge): wasi:http
+        // handlers run once per request and return a complete response, so a
+        // connection can't be upgraded or held open for WebSocket frames.
         (Method::Get, "/") => handle_root(),
-        (Method::Get, "/health") => handle_health(),
+        (Method::Get, "/health") => handle_health(req.query().get("deep").map(|v| v == "true").unwrap_or(false)),
+        (Method::Get, "/ready") => handle_ready(),
+        (Method::Get, "/version") => handle_version(),
         (Method::Post, "/join") => handle_join(req),
         (Method::Post, "/move") => handle_move(req),
         (Method::Post, "/leave") => handle_leave(req),
-        (Method::Get, "/players") => handle_players(),
+        (Method::Post, "/respawn") => handle_respawn(req),
+        (Method::Post, "/heartbeat") => handle_heartbeat(req),
+        (Method::Get, "/players") => handle_players(accepts_msgpack(&req)),
+        (Method::Post, "/players/bulk") => handle_bulk_players(req),
+        (Method::Get, p) if p.starts_with("/players/") && p.ends_with("/history") => {
+            handle_player_history(&req, &p["/players/".len()..p.len() - "/history".len()])
+        },
+        (Method::Get, p) if p.starts_with("/players/") && !is_exact_route(p) => handle_player(&p["/players/".len()..]),
+        (Method::Delete, p) if p.starts_with("/players/") && !is_exact_route(p) => handle_kick_player(&req, &p["/players/".len()..]),
+        (Method::Get, "/game-state/export") => handle_export_state(),
+        (Method::Post, "/game-state/import") => handle_import_state(req),
+        (Method::Post, "/flush") => handle_flush(),
         (Method::Get, "/leaderboard") => handle_leaderboard(),
+        (Method::Post, "/leaderboard/reset") => handle_leaderboard_reset(&req),
+        (Method::Post, "/pause") => handle_pause(&req, true),
+        (Method::Post, "/resume") => handle_pause(&req, false),
+        (Method::Get, "/config") => handle_config(),
+        (Method::Get, "/metrics") => handle_metrics(),
         (Method::Post, "/test-kafka") => handle_test_kafka(req),
-        (Method::Get, "/consume-kafka") => handle_consume_kafka(),
+        (Method::Get, "/consume-kafka") => handle_consume_kafka(&req),
         (Method::Get, "/drain-messages") => handle_drain_messages(),
+        (Method::Get, "/ai-action") => handle_ai_strategy(),
         (Method::Post, "/ai-action") => handle_ai_action(req),
-        _ => Response::builder()
-            .status_code(404)
-            .header("Access-Control-Allow-Origin", "*")
-            .body(r#"{"error": "Endpoint not found", "runtime": "wasmtime", "castle": "Temporal Sanctuary"}"#.to_string())
-            .build(),
-    }
+        (Method::Post, "/simulate") => handle_simulate(req),
+        (Method::Get, "/consumers") => handle_consumers(),
+        (Method::Delete, p) if p.starts_with("/consumers/") => handle_delete_consumer(&req),
+        _ => match allowed_methods(path) {
+            Some(allow) => method_not_allowed_response(&allow),
+            None => not_found_response(),
+        },
+    };
+    
+    if flush_after {
+        flush_event_queue();
+    }
+    
+    match traced_path {
+        Some(path) => trace_response(&path, response),
+        None => response,
+    }
+}
+
+// Endpoints both runtimes serve, listed in 404 bodies. Error bodies use the
+// same keys in wasmedge so clients can handle either runtime the same way.
+const COMMON_ENDPOINTS: [&str; 15] = [
+    "/", "/health", "/ready", "/version", "/join", "/move", "/leave", "/respawn", "/heartbeat", "/players", "/players/{id}",
+    "/leaderboard", "/config", "/metrics", "/simulate",
+];
+
+fn not_found_response() -> Result<Response, ErrorCode> {
+    let error = json!({
+        "error": "Endpoint not found",
+        "runtime": "wasmtime",
+        "castle": get_castle_name(),
+        "status": 404,
+        "available_endpoints": COMMON_ENDPOINTS,
+        "note": "Use POST for game commands; GET / lists every endpoint"
+    });
+    
+    Response::builder()
+        .status_code(404)
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(error.to_string())
+        .build()
+}
+
+// (method, path, description) for each arm of route_request. handle_root
+// publishes this list and allowed_methods builds Allow headers from it, so it
+// must change whenever the routes do. {name} matches a single path segment.
+const ROUTES: &[(&str, &str, &str)] = &[
+    ("GET", "/", "Root information: castle theme and this route list"),
+    ("GET", "/health", "Server health check (?deep=true checks Oracle)"),
+    ("GET", "/ready", "Config valid and Oracle reachable"),
+    ("GET", "/version", "Crate version, git commit, build time"),
+    ("POST", "/join", "Join the game"),
+    ("POST", "/move", "Move a player (?ack=true publishes now for its event_offset)"),
+    ("POST", "/leave", "Leave the game"),
+    ("POST", "/respawn", "Respawn an eliminated player"),
+    ("POST", "/heartbeat", "Keep an idle player registered"),
+    ("GET", "/players", "Get all players"),
+    ("POST", "/players/bulk", "Add many players in one call (admin)"),
+    ("GET", "/players/{id}", "One player with stats"),
+    ("DELETE", "/players/{id}", "Kick a player (admin)"),
+    ("GET", "/players/{id}/history", "A player's recent events (?limit=N)"),
+    ("GET", "/game-state/export", "Downloadable snapshot of the whole game"),
+    ("POST", "/game-state/import", "Restore a snapshot (admin)"),
+    ("POST", "/flush", "Publish queued events now"),
+    ("GET", "/leaderboard", "Get the leaderboard"),
+    ("POST", "/leaderboard/reset", "Clear ORDS scores (admin)"),
+    ("POST", "/pause", "Pause the game (admin)"),
+    ("POST", "/resume", "Resume a paused game (admin)"),
+    ("GET", "/config", "Arena and game config"),
+    ("GET", "/metrics", "Prometheus metrics"),
+    ("POST", "/test-kafka", "Test Kafka publishing"),
+    ("GET", "/consume-kafka", "Consume Kafka messages (?limit=N per call)"),
+    ("GET", "/drain-messages", "Drain all messages"),
+    ("GET", "/ai-action", "AI strategy profile"),
+    ("POST", "/ai-action", "AI decision endpoint"),
+    ("POST", "/simulate", "Headless AI-vs-AI match"),
+    ("GET", "/consumers", "Consumer instances this server created"),
+    ("DELETE", "/consumers/{group}/{instance}", "Delete a consumer instance (admin)"),
+    ("GET", "/debug/enable", "Enable verbose debug logging"),
+    ("GET", "/debug/disable", "Disable verbose debug logging"),
+    ("GET", "/debug/status", "Debug logging status and traced endpoints"),
+    ("POST", "/debug/trace", "Trace an endpoint's bodies ({\"endpoint\": \"/move\"})"),
+    ("POST", "/debug/untrace", "Stop tracing an endpoint"),
+    ("GET", "/debug/wasmtime-behavior", "Why state is rebuilt between requests"),
+    ("GET", "/debug/dump-events", "Recent topic events (?limit=N)"),
+    ("POST", "/debug/replay-events", "Replay posted events into state (debug mode only)"),
+    ("GET", "/debug/state-source", "Where the current state was loaded from"),
+    ("GET", "/debug/state-diff", "Memory vs persisted state (debug mode only)"),
+    ("GET", "/debug/env", "Effective config, secrets redacted (admin)"),
+    ("GET", "/debug/reconstruct-from-events", "Rebuild state from TxEventQ now"),
+];
+
+// Pattern segments like {id} accept any one non-empty path segment
+fn route_matches(pattern: &str, path: &str) -> bool {
+    let mut pattern_segments = pattern.split('/');
+    let mut path_segments = path.split('/');
+    loop {
+        match (pattern_segments.next(), path_segments.next()) {
+            (None, None) => return true,
+            (Some(expected), Some(actual)) if expected.starts_with('{') && !actual.is_empty() => {}
+            (Some(expected), Some(actual)) if expected == actual => {}
+            _ => return false,
+        }
+    }
+}
+
+// A path spelled out in ROUTES, such as /players/bulk, is that route and
+// never a {param} pattern that happens to fit it
+fn is_exact_route(path: &str) -> bool {
+    ROUTES.iter().any(|&(_, pattern, _)| pattern == path)
+}
+
+// Allow header for a path the router knows but not with this method; None
+// for paths that aren't routes at all
+fn allowed_methods(path: &str) -> Option<String> {
+    let exact = is_exact_route(path);
+    let mut methods: Vec<&str> = Vec::new();
+    for &(method, pattern, _) in ROUTES {
+        let matches = if exact { pattern == path } else { route_matches(pattern, path) };
+        if matches && !methods.contains(&method) {
+            methods.push(method);
+        }
+    }
+    if methods.is_empty() {
+        None
+    } else {
+        Some(methods.join(", "))
+    }
+}
+
+fn method_not_allowed_response(allow: &str) -> Result<Response, ErrorCode> {
+    let error = json!({
+        "error": "Method not allowed",
+        "runtime": "wasmtime",
+        "castle": get_castle_name(),
+        "status": 405,
+        "allow": allow
+    });
+    
+    Response::builder()
+        .status_code(405)
+        .header("Content-Type", "application/json")
+        .header("Allow", allow)
+        .header("Access-Control-Allow-Origin", "*")
+        .body(error.to_string())
+        .build()
+}
+
+// Seconds clients are told to wait before retrying after an Oracle outage
+const ORACLE_RETRY_AFTER_SECS: u64 = 5;
+
+// Oracle (ORDS or TxEventQ) couldn't be reached or failed the call. 503 with
+// Retry-After tells clients to back off and retry; 500 is left for bugs here.
+fn upstream_unavailable_response(message: &str) -> Result<Response, ErrorCode> {
+    let error = json!({
+        "error": message,
+        "runtime": "wasmtime",
+        "castle": get_castle_name(),
+        "status": 503,
+        "upstream": "oracle",
+        "retry_after": ORACLE_RETRY_AFTER_SECS
+    });
+    
+    Response::builder()
+        .status_code(503)
+        .header("Content-Type", "application/json")
+        .header("Retry-After", ORACLE_RETRY_AFTER_SECS.to_string())
+        .header("Access-Control-Allow-Origin", "*")
+        .body(error.to_string())
+        .build()
+}
+
+fn error_response(message: &str, status: u16) -> Result<Response, ErrorCode> {
+    Response::builder()
+        .status_code(status)
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(error_body(message, status, json!({})).to_string())
+        .build()
+}
+
+// The JSON error_response sends, with any extra fields in `details` added
+fn error_body(message: &str, status: u16, details: Value) -> Value {
+    let mut error = json!({
+        "error": message,
+        "runtime": "wasmtime",
+        "castle": get_castle_name(),
+        "status": status
+    });
+    if let (Some(body), Value::Object(extra)) = (error.as_object_mut(), details) {
+        body.extend(extra);
+    }
+    error
+}
+
+// A join or respawn past MAX_PLAYERS: 503 with the current and maximum counts
+fn game_full_response(current: usize, max: usize) -> Result<Response, ErrorCode> {
+    Response::builder()
+        .status_code(503)
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(game_full_body(current, max).to_string())
+        .build()
+}
+
+fn game_full_body(current: usize, max: usize) -> Value {
+    error_body("game_full", 503, json!({
+        "current_players": current,
+        "max_players": max
+    }))
+}
+
+// Runs a posted event list through replay_game_events, the same replay that
+// reconstruction uses, against a scratch map; live state, ticks and Oracle
+// are untouched. Accepts bare GameEvents or records from /debug/dump-events.
+fn handle_replay_events(req: Request) -> Result<Response, ErrorCode> {
+    if !is_debug_enabled() {
+        return error_response("Debug mode is off (GET /debug/enable first)", 403);
+    }
+    
+    let body = match req.body() {
+        Ok(body) => body,
+        Err(_) => return error_response("Failed to read request body", 400),
+    };
+    if let Some(rejection) = check_body_size(&body) {
+        return rejection;
+    }
+    let entries: Vec<Value> = match serde_json::from_slice(&body) {
+        Ok(entries) => entries,
+        Err(_) => return error_response("Expected a JSON array of events", 400),
+    };
+    
+    let events_replayed = entries.len();
+    let players = replay_posted_events(entries);
+    
+    let response = json!({
+        "status": "success",
+        "runtime": "wasmtime",
+        "castle": get_castle_name(),
+        "events_replayed": events_replayed,
+        "players": players,
+        "timestamp": get_timestamp()
+    });
+    
+    Response::builder()
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(response.to_string())
+        .build()
+}
+
+// The players a posted event list replays to, one entry per (game, player)
+fn replay_posted_events(entries: Vec<Value>) -> Vec<Value> {
+    let messages: Vec<Value> = entries
+        .into_iter()
+        .map(|entry| if entry.get("data").is_some() { entry } else { json!({ "data": entry }) })
+        .collect();
+    replay_game_events(&messages)
+        .into_iter()
+        .map(|((game_id, player_id), player)| json!({"game_id": game_id, "player_id": player_id, "player": player}))
+        .collect()
+}
+
+// The configuration this instance is actually running with, read through the
+// same getters the handlers use so it can't drift from them. Passwords, tokens
+// and keys only report whether they are set. Admin-only, like wasmedge's:
+// the debug flag is itself toggled by an open GET, so it guards nothing.
+fn handle_debug_env(req: &Request) -> Result<Response, ErrorCode> {
+    if let Some(rejection) = check_admin_token(req) {
+        return rejection;
+    }
+    
+    fn secret_status(value: Result<String, std::env::VarError>) -> &'static str {
+        match value {
+            Ok(value) if !value.is_empty() => "set (redacted)",
+            _ => "unset",
+        }
+    }
+    let env_flag = |name: &str| std::env::var(name).ok();
+    let bearer = std::env::var("ORACLE_AUTH_MODE").map(|mode| mode.eq_ignore_ascii_case("bearer")).unwrap_or(false);
+    let mut secret_files: Vec<&str> = get_secret_files().iter()
+        .filter(|(_, contents)| contents.is_ok())
+        .map(|(name, _)| *name)
+        .collect();
+    secret_files.sort();
+    let config = get_game_config();
+    
+    let response = json!({
+        "runtime": "wasmtime",
+        "castle": get_castle_name(),
+        "oracle": {
+            "host": get_oracle_host(),
+            "db_name": get_oracle_db_name(),
+            "base_url": get_oracle_base_url(),
+            "ords_url": get_ords_url(),
+            "txeventq_url": get_txeventq_base_url(),
+            "topic": get_kafka_topic(),
+            "partitions": get_kafka_partitions(),
+            "username": get_oracle_user(),
+            "password": secret_status(secret_var("ORACLE_PASSWORD")),
+            "auth_mode": if bearer { "bearer" } else { "basic" },
+            "bearer_token": secret_status(std::env::var("ORACLE_BEARER_TOKEN")),
+            "from_secret_files": secret_files
+        },
+        "game": {
+            "game_id": get_default_game_id(),
+            "arena": {
+                "width": config.arena_width,
+                "height": config.arena_height
+            },
+            "move_step": config.move_step,
+            "min_players": config.min_players,
+            "max_players": config.max_players,
+            "spawn": {
+                "x": config.spawn_x,
+                "y": config.spawn_y
+            },
+            "color": config.color,
+            "trail_length": config.trail_length,
+            "score_multiplier": config.score_multiplier,
+            "validate_move_steps": config.validate_move_steps,
+            "allow_reverse": allow_reverse(),
+            "respawn_resets_score": respawn_resets_score(),
+            "move_cooldown_ms": get_move_cooldown_ms(),
+            "player_idle_timeout_ms": get_player_idle_timeout_ms()
+        },
+        "limits": {
+            "max_body_bytes": get_max_body_bytes(),
+            "reconstruct_event_limit": get_reconstruct_event_limit(),
+            "reconstruct_max_events": get_reconstruct_max_events(),
+            "reconstruct_max_age_ms": get_reconstruct_max_age_ms()
+        },
+        "persistence": {
+            "order": get_persistence_order_names(),
+            "state_file": get_state_file(),
+            "strict_ords": is_strict_ords(),
+            "flush_before_response": flush_before_response()
+        },
+        "leaderboard": {
+            "publish_events": publish_leaderboard_events(),
+            "event_points": get_leaderboard_event_points()
+        },
+        "minimal_events": minimal_events(),
+        "access": {
+            "admin_token": secret_status(std::env::var("ADMIN_TOKEN")),
+            "api_key": secret_status(std::env::var("API_KEY")),
+            "api_key_reads": env_flag("API_KEY_READS").as_deref() == Some("true"),
+            "spectate_token": secret_status(std::env::var("SPECTATE_TOKEN")),
+            "event_signing_key": secret_status(std::env::var("EVENT_SIGNING_KEY"))
+        },
+        "ready_check_oracle": env_flag("READY_CHECK_ORACLE").as_deref() != Some("false"),
+        "timestamp": get_timestamp()
+    });
+    
+    Response::builder()
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(response.to_string())
+        .build()
+}
+
+// Where the in-memory GAME_STATE and the Oracle game_state blob disagree:
+// players only one side has, and per-player fields with differing values.
+// Reads GAME_STATE as is rather than through get_game_state(), which would
+// reload it from the fallback tiers first.
+fn handle_state_diff() -> Result<Response, ErrorCode> {
+    if !is_debug_enabled() {
+        return error_response("Debug mode is off (GET /debug/enable first)", 403);
+    }
+    
+    let memory = unsafe { (*std::ptr::addr_of!(GAME_STATE)).clone() }.unwrap_or_default();
+    let oracle = match load_state_from_oracle()
+        .and_then(|blob| serde_json::from_str::<HashMap<String, Player>>(&blob).map_err(|e| format!("Oracle state blob is not a player map: {}", e)))
+    {
+        Ok(oracle) => oracle,
+        Err(e) => return error_response(&e, 502),
+    };
+    
+    let mut only_in_memory: Vec<&String> = memory.keys().filter(|id| !oracle.contains_key(*id)).collect();
+    let mut only_in_oracle: Vec<&String> = oracle.keys().filter(|id| !memory.contains_key(*id)).collect();
+    only_in_memory.sort();
+    only_in_oracle.sort();
+    
+    let mut differing = Vec::new();
+    let mut shared: Vec<&String> = memory.keys().filter(|id| oracle.contains_key(*id)).collect();
+    shared.sort();
+    for id in shared {
+        let in_memory = serde_json::to_value(&memory[id]).unwrap_or(Value::Null);
+        let in_oracle = serde_json::to_value(&oracle[id]).unwrap_or(Value::Null);
+        let (memory_fields, oracle_fields) = match (in_memory.as_object(), in_oracle.as_object()) {
+            (Some(memory_fields), Some(oracle_fields)) => (memory_fields, oracle_fields),
+            _ => continue,
+        };
+        let fields: Vec<Value> = memory_fields
+            .iter()
+            .filter(|(field, value)| oracle_fields.get(*field) != Some(*value))
+            .map(|(field, value)| json!({"field": field, "memory": value, "oracle": oracle_fields.get(field)}))
+            .collect();
+        if !fields.is_empty() {
+            differing.push(json!({"player_id": id, "fields": fields}));
+        }
+    }
+    
+    let response = json!({
+        "status": "success",
+        "runtime": "wasmtime",
+        "memory_players": memory.len(),
+        "oracle_players": oracle.len(),
+        "only_in_memory": only_in_memory,
+        "only_in_oracle": only_in_oracle,
+        "differing": differing,
+        "in_sync": only_in_memory.is_empty() && only_in_oracle.is_empty() && differing.is_empty(),
+        "timestamp": get_timestamp()
+    });
+    
+    Response::builder()
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(response.to_string())
+        .build()
+}
+
+// Return the raw records reconstruction would replay (sorted, with parsed data,
+// partition/offset and source topic) without applying them to state
+fn handle_dump_events(req: &Request) -> Result<Response, ErrorCode> {
+    let limit = req.query()
+        .get("limit")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(100)
+        .min(1000);
+    
+    let consumed = consume_recent_game_events();
+    if let Some(error) = consumed.error {
+        return upstream_unavailable_response(&error);
+    }
+    let events = consumed.records;
+    let total = events.len();
+    // Keep the most recent `limit` events, still in replay order
+    let events: Vec<Value> = events.into_iter().skip(total.saturating_sub(limit)).collect();
+    
+    let response = json!({
+        "status": "success",
+        "runtime": "wasmtime",
+        "castle": get_castle_name(),
+        "events": events,
+        "returned": events.len(),
+        "total_consumed": total,
+        "limit": limit,
+        "timestamp": get_timestamp()
+    });
+    
+    Response::builder()
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(response.to_string())
+        .build()
+}
+
+// Default and maximum entries returned by GET /players/{id}/history
+const PLAYER_HISTORY_DEFAULT_LIMIT: usize = 50;
+const PLAYER_HISTORY_MAX_LIMIT: usize = 500;
+
+// One player's timeline from the consumed events: the most recent `limit`
+// join/move/elimination/leave events, oldest first, with position and score
+// as each event recorded them
+fn handle_player_history(req: &Request, player_id: &str) -> Result<Response, ErrorCode> {
+    let limit = req.query()
+        .get("limit")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(PLAYER_HISTORY_DEFAULT_LIMIT)
+        .min(PLAYER_HISTORY_MAX_LIMIT);
+    
+    let consumed = consume_recent_game_events();
+    if let Some(error) = consumed.error {
+        return upstream_unavailable_response(&error);
+    }
+    
+    // consume_recent_game_events already sorts by (timestamp, seq)
+    let history: Vec<Value> = consumed.records.iter()
+        .filter(|record| record["data"]["player_id"].as_str() == Some(player_id))
+        .map(|record| {
+            let data = &record["data"];
+            json!({
+                "type": data["type"],
+                "timestamp": data["timestamp"],
+                "game_id": data["game_id"],
+                "position": data["position"],
+                "direction": data["direction"],
+                "score": data["score"],
+                "tick": data["tick"],
+                "partition": record["partition"],
+                "offset": record["offset"]
+            })
+        })
+        .collect();
+    let total = history.len();
+    let history: Vec<Value> = history.into_iter().skip(total.saturating_sub(limit)).collect();
+    
+    let response = json!({
+        "status": "success",
+        "runtime": "wasmtime",
+        "castle": get_castle_name(),
+        "player_id": player_id,
+        "history": history,
+        "returned": history.len(),
+        "total_events": total,
+        "limit": limit,
+        "timestamp": get_timestamp()
+    });
+    
+    Response::builder()
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(response.to_string())
+        .build()
 }
 
+// Castle name, color and arena come from config; the endpoint list is ROUTES,
+// the same table the 405 handling reads
 fn handle_root() -> Result<Response, ErrorCode> {
     let debug_status = is_debug_enabled();
+    let config = get_game_config();
+    let endpoints: Vec<Value> = ROUTES.iter()
+        .map(|&(method, path, about)| json!({"method": method, "path": path, "description": about}))
+        .collect();
     
     let info = json!({
-        "message": "WasiCycles Temporal Sanctuary is running!",
+        "message": format!("WasiCycles {} is running!", get_castle_name()),
         "runtime": "wasmtime",
-        "castle": "Temporal Sanctuary",
-        "color": "#06b6d4",
-        "endpoints": {
-            "health": "/health",
-            "join": "/join (POST)",
-            "move": "/move (POST)", 
-            "leave": "/leave (POST)",
-            "players": "/players",
-            "leaderboard": "/leaderboard",
-            "test-kafka": "/test-kafka (POST)",
-            "consume-kafka": "/consume-kafka",
-            "drain-messages": "/drain-messages",
-            "ai-action": "/ai-action (POST)",
-            "debug": {
-                "enable": "/debug/enable",
-                "disable": "/debug/disable", 
-                "status": "/debug/status",
-                "wasmtime-behavior": "/debug/wasmtime-behavior"
+        "castle": get_castle_name(),
+        "color": runtime_color(),
+        "theme": {
+            "castle": get_castle_name(),
+            "color": runtime_color(),
+            "arena": {
+                "width": config.arena_width,
+                "height": config.arena_height
             }
         },
+        "endpoints": endpoints,
+        "pretty": "append ?pretty=true to any /debug GET for indented JSON",
         "debug_logging": debug_status,
         "integration": {
             "kafka": "Oracle Database Kafka API",
@@ -568,17 +2198,35 @@ fn handle_root() -> Result<Response, ErrorCode> {
         .build()
 }
 
-fn handle_health() -> Result<Response, ErrorCode> {
-    let players = get_game_state();
+// ?deep=true also probes Oracle and answers 503 when it can't be reached
+fn handle_health(deep: bool) -> Result<Response, ErrorCode> {
+    let oracle = if deep {
+        match probe_oracle() {
+            Ok(()) => Some("reachable"),
+            Err(e) => {
+                eprintln!("[ERROR] Deep health check: Oracle unreachable: {}", e);
+                return upstream_unavailable_response("Oracle unreachable");
+            }
+        }
+    } else {
+        None
+    };
+    
+    // Liveness stays cheap: the statics are read as they are, since
+    // get_game_state() may go to Oracle to rebuild them
+    let active_players = unsafe { (*std::ptr::addr_of!(GAME_STATE)).as_ref().map_or(0, |state| state.len()) };
     let health = json!({
         "status": "healthy",
         "runtime": "wasmtime", 
-        "castle": "Wasmtime Cycle",
+        "castle": get_castle_name(),
         "service": "WasiCycles Wasmtime Cycle",
-        "version": "1.0.0",
-        "color": "#06b6d4",
-        "active_players": players.len(),
+        "version": VERSION,
+        "color": runtime_color(),
+        "active_players": active_players,
+        "state_source": get_last_state_source().0,
         "oracle_integration": "enabled",
+        "oracle": oracle,
+        "paused": is_paused(),
         "https_support": "enabled via waki",
         "timestamp": get_timestamp()
     });
@@ -590,10 +2238,109 @@ fn handle_health() -> Result<Response, ErrorCode> {
         .build()
 }
 
+// Set at build time by build.rs; /health reports the same VERSION
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+const GIT_COMMIT: &str = env!("GIT_COMMIT");
+const BUILD_TIMESTAMP: &str = env!("BUILD_TIMESTAMP");
+
+fn handle_version() -> Result<Response, ErrorCode> {
+    let version = json!({
+        "version": VERSION,
+        "git_commit": GIT_COMMIT,
+        "runtime": "wasmtime",
+        "built_at": BUILD_TIMESTAMP.parse::<u64>().unwrap_or(0) // Unix seconds
+    });
+    
+    Response::builder()
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(version.to_string())
+        .build()
+}
+
+// Kubernetes readiness. /health only says the component loads; this answers
+// 503 while the configuration is unusable or, unless READY_CHECK_ORACLE=false,
+// Oracle can't be reached.
+fn handle_ready() -> Result<Response, ErrorCode> {
+    let mut problems = config_problems();
+    let check_oracle = std::env::var("READY_CHECK_ORACLE").map(|v| v != "false").unwrap_or(true);
+    if check_oracle && problems.is_empty() {
+        if let Err(e) = probe_oracle() {
+            problems.push(format!("Oracle unreachable: {}", e));
+        }
+    }
+    
+    let ready = problems.is_empty();
+    if !ready {
+        eprintln!("[WARN] Not ready: {}", problems.join("; "));
+    }
+    let response = json!({
+        "status": if ready { "ready" } else { "not_ready" },
+        "runtime": "wasmtime",
+        "castle": get_castle_name(),
+        "oracle_checked": check_oracle,
+        "problems": problems,
+        "timestamp": get_timestamp()
+    });
+    
+    let builder = Response::builder()
+        .status_code(if ready { 200 } else { 503 })
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*");
+    let builder = if ready {
+        builder
+    } else {
+        builder.header("Retry-After", ORACLE_RETRY_AFTER_SECS.to_string())
+    };
+    builder.body(response.to_string()).build()
+}
+
+// Configuration mistakes that make the instance useless, as readable messages
+fn config_problems() -> Vec<String> {
+    let mut problems = Vec::new();
+    let base_url = get_oracle_base_url();
+    if !(base_url.starts_with("https://") || base_url.starts_with("http://")) {
+        problems.push(format!("ORACLE_BASE_URL is not an http(s) URL: {}", base_url));
+    }
+    let bearer = std::env::var("ORACLE_AUTH_MODE").map(|mode| mode.eq_ignore_ascii_case("bearer")).unwrap_or(false);
+    if bearer && std::env::var("ORACLE_BEARER_TOKEN").map(|t| t.is_empty()).unwrap_or(true) {
+        problems.push("ORACLE_AUTH_MODE=bearer but ORACLE_BEARER_TOKEN is not set".to_string());
+    }
+    for contents in get_secret_files().values() {
+        if let Err(e) = contents {
+            problems.push(e.clone());
+        }
+    }
+    let config = get_game_config();
+    if config.arena_width <= 0.0 || config.arena_height <= 0.0 {
+        problems.push("ARENA_WIDTH and ARENA_HEIGHT must be positive".to_string());
+    }
+    if config.move_step <= 0.0 {
+        problems.push("MOVE_STEP must be positive".to_string());
+    }
+    if config.score_multiplier <= 0 {
+        problems.push("SCORE_MULTIPLIER must be at least 1".to_string());
+    }
+    if config.max_players == 0 || config.min_players > config.max_players {
+        problems.push("MIN_PLAYERS must not exceed MAX_PLAYERS, which must be at least 1".to_string());
+    }
+    for name in get_persistence_order_names() {
+        if state_store(&name).is_none() {
+            problems.push(format!("PERSISTENCE_ORDER has unknown store '{}' (expected oracle, txeventq or file)", name));
+        }
+    }
+    problems
+}
+
 fn handle_join(req: Request) -> Result<Response, ErrorCode> {
     eprintln!("[INFO] Join request received");
     
+    let delta_only = wants_delta(&req);
     let body = req.body().unwrap_or_default();
+    if let Some(rejection) = check_body_size(&body) {
+        return rejection;
+    }
+    trace_request_body("/join", &body);
     let body_str = match String::from_utf8(body) {
         Ok(s) => {
             if is_debug_enabled() {
@@ -608,63 +2355,90 @@ fn handle_join(req: Request) -> Result<Response, ErrorCode> {
     };
     
     // Extract player_id from request
-    let player_id = match serde_json::from_str::<serde_json::Value>(&body_str) {
-        Ok(json) => {
-            if let Some(id) = json.get("player_id") {
-                if id.is_string() {
-                    id.as_str().unwrap().to_string()
-                } else {
-                    id.to_string().replace("\"", "")
-                }
-            } else {
-                match extract_json_field(&body_str, "player_id") {
-                    Some(id) => id,
-                    None => {
-                        eprintln!("[ERROR] Failed to extract player_id from request");
-                        return Response::builder()
-                            .status_code(400)
-                            .header("Content-Type", "application/json")
-                            .header("Access-Control-Allow-Origin", "*")
-                            .body(r#"{"error": "Missing player_id in request", "status": "error"}"#.to_string())
-                            .build();
-                    }
-                }
+    let player_id = match request_player_id(&body_str) {
+        Ok(id) => id,
+        Err(message) => {
+            eprintln!("[ERROR] Rejected player_id in join request: {}", message);
+            return error_response(message, 400);
+        }
+    };
+
+    let game_id = extract_json_field(&body_str, "game_id").unwrap_or_else(get_default_game_id);
+    
+    // An explicit starting direction must be valid; without one we pick the
+    // most open heading from the spawn cell
+    let requested_direction = match extract_json_field(&body_str, "direction") {
+        Some(dir) => match Direction::parse(&dir) {
+            Some(parsed) => Some(parsed),
+            None => {
+                return error_response("Invalid direction", 400);
             }
         },
-        Err(_) => {
-            match extract_json_field(&body_str, "player_id") {
-                Some(id) => id,
-                None => {
-                    eprintln!("[ERROR] Failed to extract player_id from request");
-                    return Response::builder()
-                        .status_code(400)
-                        .header("Content-Type", "application/json")
-                        .header("Access-Control-Allow-Origin", "*")
-                        .body(r#"{"error": "Missing player_id in request", "status": "error"}"#.to_string())
-                        .build();
-                }
-            }
-        }
+        None => None,
     };
 
-    eprintln!("[INFO] Creating new player with ID: {}", player_id);
+    eprintln!("[INFO] Creating new player with ID: {} in game {}", player_id, game_id);
     
     // Get game state directly using our helper function
     let players = get_game_state();
+    expire_idle_players(players);
     let in_memory = players.contains_key(&player_id);
+    let config = get_game_config();
+    
+    // Refuse to join a full arena. Dead cycles don't take a slot, and a player
+    // re-joining keeps theirs.
+    let live_count = players.values()
+        .filter(|p| p.alive && p.id != player_id)
+        .count();
+    if live_count >= config.max_players {
+        eprintln!("[WARN] Rejecting join for {}: game full ({}/{})", player_id, live_count, config.max_players);
+        return game_full_response(live_count, config.max_players);
+    }
+    
+    // A returning player may still be live in the loaded state or, when a
+    // static reset lost them, in ORDS; pick their cycle back up instead of
+    // respawning it
+    let restored_player = match find_returning_player(players, &player_id, &game_id) {
+        Ok(player) => player,
+        Err(e) => {
+            eprintln!("[ERROR] Refusing join for {}: {}", player_id, e);
+            return upstream_unavailable_response(&e);
+        }
+    };
+    let restored = restored_player.is_some();
     
     // Create new player
-    let new_player = Player {
+    let mut new_player = Player {
         id: player_id.clone(),
-        x: 25.0, // Starting position - match other runtimes
-        y: 25.0, // Starting position - match other runtimes
+        x: config.spawn_x,
+        y: config.spawn_y,
         direction: "up".to_string(),
         score: 0,
-        color: "#06b6d4".to_string(), // Cyan for Wasmtime
+        color: config.color.clone(),
         alive: true,
+        game_id: game_id.clone(),
+        last_seen: get_timestamp(),
+        joined_at: get_timestamp(),
+        trail: Vec::new(),
+        stats: PlayerStats::default(),
+        tick: 0,
+        last_move_at: 0,
     };
 
+    if let Some(player) = restored_player {
+        eprintln!("[INFO] Restored returning player {} at ({}, {}) with score {}", player_id, player.x, player.y, player.score);
+        new_player = Player { last_seen: get_timestamp(), ..player };
+        if let Some(direction) = requested_direction {
+            new_player.direction = direction.as_str().to_string();
+        }
+    } else {
+        let direction = requested_direction
+            .unwrap_or_else(|| open_direction(players, &new_player));
+        new_player.direction = direction.as_str().to_string();
+    }
+    
     // Add player to in-memory state
+    new_player.tick = advance_tick(players, &new_player.game_id);
     players.insert(player_id.clone(), new_player.clone());
     
     if in_memory {
@@ -683,17 +2457,7 @@ fn handle_join(req: Request) -> Result<Response, ErrorCode> {
     }
 
     // Create join event
-    let join_event = GameEvent {
-        event_type: "player_joined".to_string(),
-        player_id: player_id.clone(),
-        game_id: "wasicycles-multiplayer".to_string(),
-        runtime: "wasmtime".to_string(),
-        castle: "Temporal Sanctuary".to_string(),
-        timestamp: get_timestamp(),
-        position: Some(Position { x: new_player.x, y: new_player.y }),
-        direction: Some(new_player.direction.clone()),
-        score: Some(new_player.score),
-    };
+    let join_event = GameEvent::new("player_joined", &player_id).with_player(&new_player);
 
     // Publish to Oracle Kafka
     match publish_to_oracle_kafka(&join_event) {
@@ -712,27 +2476,37 @@ fn handle_join(req: Request) -> Result<Response, ErrorCode> {
         eprintln!("[INFO] Player {} saved to ORDS successfully", player_id);
     }
 
-    let response = json!({
-        "status": "success",
-        "runtime": "wasmtime",
-        "castle": "Temporal Sanctuary", 
-        "message": format!("Player {} joined Temporal Sanctuary", player_id),
-        "player": {
-            "id": new_player.id,
-            "x": new_player.x,
-            "y": new_player.y,
-            "direction": new_player.direction,
-            "score": new_player.score,
-            "color": new_player.color,
-            "alive": new_player.alive
-        },
-        "temporal_power": "activated",
-        "oracle_integration": "active",
-        "timestamp": get_timestamp()
-    });
-
-    eprintln!("[INFO] Sending join response for player {}", player_id);
-    
+    // The starting direction is chosen server-side, so a delta join reports it too
+    let response = if delta_only {
+        let mut delta = player_delta(&new_player);
+        delta["direction"] = json!(new_player.direction);
+        delta
+    } else {
+        json!({
+            "status": "success",
+            "runtime": "wasmtime",
+            "castle": get_castle_name(), 
+            "message": format!("Player {} joined {}", player_id, get_castle_name()),
+            "player": {
+                "id": new_player.id,
+                "x": new_player.x,
+                "y": new_player.y,
+                "direction": new_player.direction,
+                "score": new_player.score,
+                "color": new_player.color,
+                "alive": new_player.alive,
+                "game_id": new_player.game_id
+            },
+            "tick": new_player.tick,
+            "restored": restored,
+            "temporal_power": "activated",
+            "oracle_integration": "active",
+            "timestamp": get_timestamp()
+        })
+    };
+
+    eprintln!("[INFO] Sending join response for player {}", player_id);
+    
     Response::builder()
         .header("Content-Type", "application/json")
         .header("Access-Control-Allow-Origin", "*")
@@ -740,6 +2514,51 @@ fn handle_join(req: Request) -> Result<Response, ErrorCode> {
         .build()
 }
 
+// One step for `player` heading `direction`: the new position, the trail cell
+// it leaves, its stats and, if it survives, SCORE_MULTIPLIER points. Returns
+// whoever owns the cell it crashed into, credited with the kill.
+fn apply_move(players: &mut HashMap<String, Player>, player: &mut Player, direction: Direction, config: &GameConfig) -> Option<String> {
+    // Grid cells run 0..=width-1 / 0..=height-1
+    let step = config.move_step;
+    let from = Position { x: player.x, y: player.y };
+    let mut to = from;
+    match direction {
+        Direction::Up => to.y = (from.y - step).max(0.0),
+        Direction::Down => to.y = (from.y + step).min(config.arena_height - 1.0),
+        Direction::Left => to.x = (from.x - step).max(0.0),
+        Direction::Right => to.x = (from.x + step).min(config.arena_width - 1.0),
+    }
+    
+    // Pressed against the wall the cycle stays put, so it lays no trail there
+    let mut killer = None;
+    if to != from {
+        killer = collision_at(players, player, to);
+        player.lay_trail(from, config.trail_length);
+        player.x = to.x;
+        player.y = to.y;
+        player.stats.distance += step;
+    }
+    
+    player.stats.moves += 1;
+    player.last_seen = get_timestamp();
+    player.last_move_at = player.last_seen;
+    player.stats.survival_ms = player.survival_ms();
+    
+    match &killer {
+        Some(owner) => {
+            player.alive = false;
+            if *owner != player.id {
+                if let Some(other) = players.get_mut(owner) {
+                    other.stats.kills += 1;
+                }
+            }
+        },
+        // Surviving the move scores SCORE_MULTIPLIER points
+        None => player.score += config.score_multiplier,
+    }
+    killer
+}
+
 fn handle_move(req: Request) -> Result<Response, ErrorCode> {
     if is_debug_enabled() {
         eprintln!("[DEBUG] Move request received");
@@ -747,7 +2566,18 @@ fn handle_move(req: Request) -> Result<Response, ErrorCode> {
         eprintln!("[INFO] Move request received");
     }
     
+    if is_paused() {
+        return error_response("Game is paused", 423);
+    }
+    
+    let wants_msgpack = accepts_msgpack(&req);
+    let delta_only = wants_delta(&req);
+    let acknowledged = req.query().get("ack").map(|ack| ack == "true").unwrap_or(false);
     let body = req.body().unwrap_or_default();
+    if let Some(rejection) = check_body_size(&body) {
+        return rejection;
+    }
+    trace_request_body("/move", &body);
     let body_str = match String::from_utf8(body) {
         Ok(s) => {
             if is_debug_enabled() {
@@ -762,42 +2592,11 @@ fn handle_move(req: Request) -> Result<Response, ErrorCode> {
     };
     
     // Extract player_id from request
-    let player_id = match serde_json::from_str::<serde_json::Value>(&body_str) {
-        Ok(json) => {
-            if let Some(id) = json.get("player_id") {
-                if id.is_string() {
-                    id.as_str().unwrap().to_string()
-                } else {
-                    id.to_string().replace("\"", "")
-                }
-            } else {
-                match extract_json_field(&body_str, "player_id") {
-                    Some(id) => id,
-                    None => {
-                        eprintln!("[ERROR] Failed to extract player_id from move request");
-                        return Response::builder()
-                            .status_code(400)
-                            .header("Content-Type", "application/json")
-                            .header("Access-Control-Allow-Origin", "*")
-                            .body(r#"{"error": "Missing player_id in request", "status": "error"}"#.to_string())
-                            .build();
-                    }
-                }
-            }
-        },
-        Err(_) => {
-            match extract_json_field(&body_str, "player_id") {
-                Some(id) => id,
-                None => {
-                    eprintln!("[ERROR] Failed to extract player_id from move request");
-                    return Response::builder()
-                        .status_code(400)
-                        .header("Content-Type", "application/json")
-                        .header("Access-Control-Allow-Origin", "*")
-                        .body(r#"{"error": "Missing player_id in request", "status": "error"}"#.to_string())
-                        .build();
-                }
-            }
+    let player_id = match request_player_id(&body_str) {
+        Ok(id) => id,
+        Err(message) => {
+            eprintln!("[ERROR] Rejected player_id in move request: {}", message);
+            return error_response(message, 400);
         }
     };
     
@@ -815,12 +2614,7 @@ fn handle_move(req: Request) -> Result<Response, ErrorCode> {
                     Some(dir) => dir,
                     None => {
                         eprintln!("[ERROR] Failed to extract direction from move request");
-                        return Response::builder()
-                            .status_code(400)
-                            .header("Content-Type", "application/json")
-                            .header("Access-Control-Allow-Origin", "*")
-                            .body(r#"{"error": "Missing direction in request", "status": "error"}"#.to_string())
-                            .build();
+                        return error_response("Missing direction in request", 400);
                     }
                 }
             }
@@ -830,28 +2624,35 @@ fn handle_move(req: Request) -> Result<Response, ErrorCode> {
                 Some(dir) => dir,
                 None => {
                     eprintln!("[ERROR] Failed to extract direction from move request");
-                    return Response::builder()
-                        .status_code(400)
-                        .header("Content-Type", "application/json")
-                        .header("Access-Control-Allow-Origin", "*")
-                        .body(r#"{"error": "Missing direction in request", "status": "error"}"#.to_string())
-                        .build();
+                    return error_response("Missing direction in request", 400);
                 }
             }
         }
     };
     
+    // An explicit game_id moves the player into that game; otherwise they stay in theirs
+    let requested_game_id = extract_json_field(&body_str, "game_id");
+    
     // Use the game state with minimal logging
     let players = get_game_state();
+    expire_idle_players(players);
     
+    let config = get_game_config();
     let default_player = Player {
         id: player_id.clone(),
-        x: 25.0,
-        y: 25.0,
+        x: config.spawn_x,
+        y: config.spawn_y,
         direction: "up".to_string(),
         score: 0,
-        color: "#06b6d4".to_string(), // Cyan for Wasmtime
+        color: config.color.clone(),
         alive: true,
+        game_id: requested_game_id.clone().unwrap_or_else(get_default_game_id),
+        last_seen: get_timestamp(),
+        joined_at: get_timestamp(),
+        trail: Vec::new(),
+        stats: PlayerStats::default(),
+        tick: 0,
+        last_move_at: 0,
     };
     
     // Get existing player or create a new one
@@ -876,38 +2677,51 @@ fn handle_move(req: Request) -> Result<Response, ErrorCode> {
     let old_x = player.x;
     let old_y = player.y;
     
+    let parsed_direction = match Direction::parse(&direction) {
+        Some(dir) => dir,
+        None => {
+            eprintln!("[ERROR] Invalid direction: {}", direction);
+            return error_response("Invalid direction", 400);
+        }
+    };
+    
+    if !player.alive {
+        return error_response("Player has been eliminated", 400);
+    }
+    
+    let cooldown = get_move_cooldown_ms();
+    let since_last_move = get_timestamp().saturating_sub(player.last_move_at);
+    if cooldown > 0 && since_last_move < cooldown {
+        return error_response("Move cooldown active", 429);
+    }
+    
+    // A cycle can't turn straight back into its own trail
+    if !allow_reverse() && Direction::parse(&player.direction) == Some(parsed_direction.opposite()) {
+        eprintln!("[INFO] Rejected reverse move {} for player {} heading {}", direction, player_id, player.direction);
+        return error_response("Cannot reverse direction", 400);
+    }
+    
     // Create a mutable copy of the player to update
     let mut updated_player = player.clone();
     
     // Update direction
     updated_player.direction = direction.clone();
-    
-    // Move player based on direction
-    match direction.as_str() {
-        "up" => updated_player.y = (updated_player.y - 1.0).max(0.0),
-        "down" => updated_player.y = (updated_player.y + 1.0).min(49.0),
-        "left" => updated_player.x = (updated_player.x - 1.0).max(0.0),
-        "right" => updated_player.x = (updated_player.x + 1.0).min(49.0),
-        _ => {
-            eprintln!("[ERROR] Invalid direction: {}", direction);
-            return Response::builder()
-                .status_code(400)
-                .header("Content-Type", "application/json")
-                .header("Access-Control-Allow-Origin", "*")
-                .body(json!({"error": "Invalid direction", "received": direction}).to_string())
-                .build();
-        }
+    if let Some(game_id) = requested_game_id {
+        updated_player.game_id = game_id;
     }
     
+    let killer = apply_move(players, &mut updated_player, parsed_direction, &config);
+    
     // Log position change
     eprintln!("[INFO] Player {} moved {} from ({}, {}) to ({}, {})", 
         player_id, direction, old_x, old_y, updated_player.x, updated_player.y);
-    
-    // Increment score
-    updated_player.score += 1;
+    if let Some(owner) = &killer {
+        eprintln!("[INFO] Player {} crashed into {}'s trail", player_id, owner);
+    }
     
     // Update player in memory
-    players.insert(player_id.clone(), updated_player.clone());        
+    updated_player.tick = advance_tick(players, &updated_player.game_id);
+    players.insert(player_id.clone(), updated_player.clone());
     
     // Save game state to ensure persistence across requests
     if let Err(e) = save_game_state(players) {
@@ -917,27 +2731,22 @@ fn handle_move(req: Request) -> Result<Response, ErrorCode> {
     }
     
     // Create move event
-    let move_event = GameEvent {
-        event_type: "player_moved".to_string(),
-        player_id: player_id.clone(),
-        game_id: "wasicycles-multiplayer".to_string(),
-        runtime: "wasmtime".to_string(),
-        castle: "Temporal Sanctuary".to_string(),
-        timestamp: get_timestamp(),
-        position: Some(Position { x: updated_player.x, y: updated_player.y }),
-        direction: Some(updated_player.direction.clone()),
-        score: Some(updated_player.score),
-    };
+    let move_event = GameEvent::new("player_moved", &player_id).with_player(&updated_player);
 
-    // Publish to Oracle Kafka
-    // Publish to Oracle Kafka
-    match publish_to_oracle_kafka(&move_event) {
-        Ok(_) => {
-            if is_debug_enabled() {
-                eprintln!("[DEBUG] Successfully published move event to Kafka");
-            }
-        },
-        Err(e) => eprintln!("[WARN] Failed to publish move event to Kafka: {:?}", e),
+    // Queued like every other event, unless the client asked with ?ack=true to
+    // have it published now and learn where it landed on the topic
+    let event_offset = if acknowledged {
+        Some(publish_move_event(move_event, publish_to_oracle_kafka))
+    } else {
+        queue_event(move_event);
+        None
+    };
+    
+    if killer.is_some() {
+        queue_event(GameEvent::new("player_eliminated", &player_id).with_player(&updated_player));
+        publish_leaderboard_update(&updated_player, updated_player.tick);
+    } else if crossed_leaderboard_milestone(updated_player.score, config.score_multiplier) {
+        publish_leaderboard_update(&updated_player, updated_player.tick);
     }
     
     // Try to save updated player to ORDS as well (but don't fail if it doesn't work)
@@ -947,42 +2756,141 @@ fn handle_move(req: Request) -> Result<Response, ErrorCode> {
         eprintln!("[DEBUG] Updated player {} saved to ORDS successfully", player_id);
     }
 
-    let response = json!({
-        "status": "success",
-        "runtime": "wasmtime",
-        "castle": "Temporal Sanctuary",
-        "message": format!("Player {} moved {} in Temporal Sanctuary", player_id, direction),
-        "player": {
-            "id": updated_player.id,
-            "x": updated_player.x,
-            "y": updated_player.y,
-            "direction": updated_player.direction,
-            "score": updated_player.score,
-            "color": updated_player.color,
-            "alive": updated_player.alive
-        },
-        "temporal_power": "flowing",
-        "oracle_integration": "active",
-        "timestamp": get_timestamp()
+    let mut response = if delta_only {
+        player_delta(&updated_player)
+    } else {
+        json!({
+            "status": "success",
+            "runtime": "wasmtime",
+            "castle": get_castle_name(),
+            "message": format!("Player {} moved {} in {}", player_id, direction, get_castle_name()),
+            "player": {
+                "id": updated_player.id,
+                "x": updated_player.x,
+                "y": updated_player.y,
+                "direction": updated_player.direction,
+                "score": updated_player.score,
+                "color": updated_player.color,
+                "alive": updated_player.alive,
+                "stats": updated_player.stats
+            },
+            "tick": updated_player.tick,
+            "killed_by": killer,
+            "temporal_power": "flowing",
+            "oracle_integration": "active",
+            "timestamp": get_timestamp()
+        })
+    };
+    if let (Some(offset), Some(fields)) = (event_offset, response.as_object_mut()) {
+        fields.insert("event_offset".to_string(), json!(offset));
+    }
+
+    negotiated_response(&response, wants_msgpack)
+}
+
+fn get_game_ticks() -> &'static mut HashMap<String, u64> {
+    unsafe { (*std::ptr::addr_of_mut!(GAME_TICKS)).get_or_insert_with(HashMap::new) }
+}
+
+// Bumps and returns the game's tick. Saved players carry the tick of their
+// last change, so the counter picks up where it was after a static reset.
+fn advance_tick(players: &HashMap<String, Player>, game_id: &str) -> u64 {
+    let seen = players.values()
+        .filter(|p| p.game_id == game_id)
+        .map(|p| p.tick)
+        .max()
+        .unwrap_or(0);
+    let ticks = get_game_ticks();
+    let tick = ticks.get(game_id).copied().unwrap_or(0).max(seen) + 1;
+    ticks.insert(game_id.to_string(), tick);
+    tick
+}
+
+// ?fields=delta asks /join and /move for just the player's mutable fields
+// instead of the full envelope
+fn wants_delta(req: &Request) -> bool {
+    req.query().get("fields").map(|fields| fields == "delta").unwrap_or(false)
+}
+
+fn player_delta(player: &Player) -> Value {
+    json!({
+        "x": player.x,
+        "y": player.y,
+        "score": player.score,
+        "alive": player.alive,
+        "tick": player.tick
+    })
+}
+
+// Heading with the longest clear run from a new player's spawn cell before it
+// would meet a wall or another cycle in its game. Ties keep the earlier of
+// up/right/down/left.
+fn open_direction(players: &HashMap<String, Player>, player: &Player) -> Direction {
+    let config = get_game_config();
+    let step = config.move_step;
+    if step <= 0.0 {
+        return Direction::Up;
+    }
+    let others: Vec<&Player> = players.values()
+        .filter(|p| p.id != player.id && p.game_id == player.game_id)
+        .collect();
+    let occupied = |at: Position| others.iter().any(|p| {
+        p.trail.iter().any(|pos| pos.cell() == at.cell()) || (p.alive && grid_cell(p.x, p.y) == at.cell())
     });
+    
+    let clear_run = |direction: Direction| {
+        let (dx, dy) = direction.delta();
+        let mut at = Position { x: player.x, y: player.y };
+        let mut run = 0;
+        loop {
+            at = Position { x: at.x + dx * step, y: at.y + dy * step };
+            let in_arena = at.x >= 0.0 && at.y >= 0.0
+                && at.x <= config.arena_width - 1.0 && at.y <= config.arena_height - 1.0;
+            if !in_arena || occupied(at) {
+                return run;
+            }
+            run += 1;
+        }
+    };
+    
+    let mut best = Direction::Up;
+    let mut best_run = clear_run(best);
+    for direction in [Direction::Right, Direction::Down, Direction::Left] {
+        let run = clear_run(direction);
+        if run > best_run {
+            best = direction;
+            best_run = run;
+        }
+    }
+    best
+}
 
-    Response::builder()
-        .header("Content-Type", "application/json")
-        .header("Access-Control-Allow-Origin", "*")
-        .body(response.to_string())
-        .build()
+// Id of whoever owns the cell `at` for a cycle moving there: any trail cell (its
+// own included) or the head of another live cycle in the same game
+fn collision_at(players: &HashMap<String, Player>, mover: &Player, at: Position) -> Option<String> {
+    players.values()
+        .filter(|p| p.game_id == mover.game_id)
+        .find(|p| {
+            p.trail.iter().any(|pos| pos.cell() == at.cell())
+                || (p.id != mover.id && p.alive && grid_cell(p.x, p.y) == at.cell())
+        })
+        .map(|p| p.id.clone())
 }
 
 fn handle_leave(req: Request) -> Result<Response, ErrorCode> {
     let body = req.body().unwrap_or_default();
+    if let Some(rejection) = check_body_size(&body) {
+        return rejection;
+    }
+    trace_request_body("/leave", &body);
     let body_str = match String::from_utf8(body) {
         Ok(s) => s,
         Err(_) => return Err(ErrorCode::InternalError(None)),
     };
     
-    let player_id = match extract_json_field(&body_str, "player_id") {
-        Some(id) => id,
-        None => return Err(ErrorCode::InternalError(None)),
+    let player_id = match request_player_id(&body_str) {
+        Ok(id) => id,
+        Err(message) => return error_response(message, 400),
     };
 
     // Remove player from game state
@@ -990,20 +2898,16 @@ fn handle_leave(req: Request) -> Result<Response, ErrorCode> {
     let removed_player = players.remove(&player_id);
 
     if let Some(player) = removed_player {
+        let tick = advance_tick(players, &player.game_id);
+        
         // Create leave event
-        let leave_event = GameEvent {
-            event_type: "player_left".to_string(),
-            player_id: player_id.clone(),
-            game_id: "wasicycles-multiplayer".to_string(),
-            runtime: "wasmtime".to_string(),
-            castle: "Temporal Sanctuary".to_string(),
-            timestamp: get_timestamp(),
-            position: None,
-            direction: None,
-            score: Some(player.score),
-        };
+        let leave_event = GameEvent::new("player_left", &player_id)
+            .with_game_id(&player.game_id)
+            .with_score(player.score)
+            .with_tick(tick);
 
         let _ = publish_to_oracle_kafka(&leave_event);
+        publish_leaderboard_update(&player, tick);
         
         // Save game state after player leaves
         let _ = save_game_state(players);
@@ -1014,9 +2918,10 @@ fn handle_leave(req: Request) -> Result<Response, ErrorCode> {
         let response = json!({
             "status": "success",
             "runtime": "wasmtime",
-            "castle": "Temporal Sanctuary",
-            "message": format!("Player {} left Temporal Sanctuary", player_id),
+            "castle": get_castle_name(),
+            "message": format!("Player {} left {}", player_id, get_castle_name()),
             "final_score": player.score,
+            "tick": tick,
             "temporal_status": "disconnected",
             "timestamp": get_timestamp()
         });
@@ -1027,18 +2932,252 @@ fn handle_leave(req: Request) -> Result<Response, ErrorCode> {
             .body(response.to_string())
             .build()
     } else {
-        Response::builder()
-            .status_code(404)
-            .header("Access-Control-Allow-Origin", "*")
-            .body(json!({"error": "Player not found"}).to_string())
-            .build()
+        error_response("Player not found", 404)
+    }
+}
+
+// Keep-alive for clients that idle on purpose: bumps last_seen so the lazy
+// idle sweep leaves the player alone, without moving them, scoring or using
+// a tick. A player already swept gets a 404 and has to join again.
+fn handle_heartbeat(req: Request) -> Result<Response, ErrorCode> {
+    let body = req.body().unwrap_or_default();
+    if let Some(rejection) = check_body_size(&body) {
+        return rejection;
+    }
+    trace_request_body("/heartbeat", &body);
+    let body_str = match String::from_utf8(body) {
+        Ok(s) => s,
+        Err(_) => return error_response("Request body is not valid UTF-8", 400),
+    };
+    
+    let player_id = match request_player_id(&body_str) {
+        Ok(id) => id,
+        Err(message) => return error_response(message, 400),
+    };
+    
+    let players = get_game_state();
+    expire_idle_players(players);
+    let player = match players.get_mut(&player_id) {
+        Some(player) => {
+            player.last_seen = get_timestamp();
+            player.clone()
+        },
+        None => return error_response("Player not found", 404),
+    };
+    if let Err(e) = save_game_state(players) {
+        eprintln!("[WARN] Failed to save game state after heartbeat from {}: {:?}", player_id, e);
+    }
+    if is_debug_enabled() {
+        eprintln!("[DEBUG] Heartbeat from {}", player_id);
+    }
+    let tick = get_game_ticks().get(&player.game_id).copied().unwrap_or(player.tick);
+    
+    let response = json!({
+        "status": "success",
+        "runtime": "wasmtime",
+        "castle": get_castle_name(),
+        "player": {
+            "id": player.id,
+            "x": player.x,
+            "y": player.y,
+            "direction": player.direction,
+            "score": player.score,
+            "color": player.color,
+            "alive": player.alive,
+            "game_id": player.game_id,
+            "last_seen": player.last_seen
+        },
+        "tick": tick,
+        "timestamp": get_timestamp()
+    });
+    
+    Response::builder()
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(response.to_string())
+        .build()
+}
+
+// Brings an eliminated cycle back under the same id, so its history and
+// (unless RESPAWN_RESETS_SCORE) its score carry on. The old trail goes away
+// and the cycle restarts on the free cell nearest the spawn point.
+fn handle_respawn(req: Request) -> Result<Response, ErrorCode> {
+    let body = req.body().unwrap_or_default();
+    if let Some(rejection) = check_body_size(&body) {
+        return rejection;
+    }
+    trace_request_body("/respawn", &body);
+    let body_str = match String::from_utf8(body) {
+        Ok(s) => s,
+        Err(_) => return error_response("Request body is not valid UTF-8", 400),
+    };
+
+    let player_id = match request_player_id(&body_str) {
+        Ok(id) => id,
+        Err(message) => return error_response(message, 400),
+    };
+
+    let players = get_game_state();
+    expire_idle_players(players);
+    let config = get_game_config();
+
+    let mut player = match players.get(&player_id) {
+        Some(p) => p.clone(),
+        None => return error_response("Player not found", 404),
+    };
+    if player.alive {
+        return error_response("Player is still alive", 409);
+    }
+
+    let live_count = players.values().filter(|p| p.alive).count();
+    if live_count >= config.max_players {
+        eprintln!("[WARN] Rejecting respawn for {}: game full ({}/{})", player_id, live_count, config.max_players);
+        return game_full_response(live_count, config.max_players);
+    }
+
+    // The stored copy must lose its trail too, or it blocks the spawn search
+    player.trail.clear();
+    players.insert(player_id.clone(), player.clone());
+    let spawn = respawn_position(players, &player, &config);
+    player.x = spawn.x;
+    player.y = spawn.y;
+    player.alive = true;
+    if respawn_resets_score() {
+        player.score = 0;
+    }
+    player.last_seen = get_timestamp();
+    player.joined_at = player.last_seen;
+    player.last_move_at = 0;
+    player.direction = open_direction(players, &player).as_str().to_string();
+    player.tick = advance_tick(players, &player.game_id);
+    players.insert(player_id.clone(), player.clone());
+
+    if let Err(e) = save_game_state(players) {
+        eprintln!("[WARN] Failed to save game state after respawn: {:?}", e);
+    }
+    eprintln!("[INFO] Player {} respawned at ({}, {})", player_id, player.x, player.y);
+
+    let respawn_event = GameEvent::new("player_respawned", &player_id).with_player(&player);
+    if let Err(e) = publish_to_oracle_kafka(&respawn_event) {
+        eprintln!("[WARN] Failed to publish respawn event for {}: {}", player_id, e);
+    }
+
+    let response = json!({
+        "status": "success",
+        "runtime": "wasmtime",
+        "castle": get_castle_name(),
+        "message": format!("Player {} respawned in {}", player_id, get_castle_name()),
+        "player": {
+            "id": player.id,
+            "x": player.x,
+            "y": player.y,
+            "direction": player.direction,
+            "score": player.score,
+            "color": player.color,
+            "alive": player.alive,
+            "game_id": player.game_id
+        },
+        "tick": player.tick,
+        "timestamp": get_timestamp()
+    });
+
+    Response::builder()
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(response.to_string())
+        .build()
+}
+
+// Spawn point when it's clear, else the first free cell on successively
+// larger squares around it. If every cell is taken the spawn point is used.
+fn respawn_position(players: &HashMap<String, Player>, player: &Player, config: &GameConfig) -> Position {
+    let spawn = Position { x: config.spawn_x, y: config.spawn_y };
+    let step = config.move_step;
+    if step <= 0.0 {
+        return spawn;
+    }
+    let is_free = |at: Position| {
+        at.x >= 0.0 && at.y >= 0.0
+            && at.x <= config.arena_width && at.y <= config.arena_height
+            && collision_at(players, player, at).is_none()
+    };
+    if is_free(spawn) {
+        return spawn;
+    }
+
+    let max_ring = (config.arena_width.max(config.arena_height) / step).ceil() as i32;
+    for ring in 1..=max_ring {
+        for dx in -ring..=ring {
+            for dy in -ring..=ring {
+                if dx.abs() != ring && dy.abs() != ring {
+                    continue;
+                }
+                let at = Position { x: spawn.x + dx as f64 * step, y: spawn.y + dy as f64 * step };
+                if is_free(at) {
+                    return at;
+                }
+            }
+        }
+    }
+    spawn
+}
+
+// No background tasks survive between requests here, so idle players are swept
+// lazily whenever a handler loads the game state
+fn expire_idle_players(players: &mut HashMap<String, Player>) {
+    let timeout_ms = get_player_idle_timeout_ms();
+    let now = get_timestamp();
+    let mut changed = false;
+    
+    // Players restored without a last_seen (older snapshots) start their idle clock now
+    for player in players.values_mut().filter(|p| p.last_seen == 0) {
+        player.last_seen = now;
+        changed = true;
+    }
+    
+    let idle_ids: Vec<String> = players.values()
+        .filter(|p| now.saturating_sub(p.last_seen) > timeout_ms)
+        .map(|p| p.id.clone())
+        .collect();
+    
+    for player_id in idle_ids {
+        if let Some(player) = players.remove(&player_id) {
+            eprintln!("[INFO] Player {} timed out after {}ms idle", player_id, now.saturating_sub(player.last_seen));
+            
+            let tick = advance_tick(players, &player.game_id);
+            let timeout_event = GameEvent::new("player_timeout", &player_id)
+                .with_player(&player)
+                .with_tick(tick);
+            
+            if let Err(e) = publish_to_oracle_kafka(&timeout_event) {
+                eprintln!("[WARN] Failed to publish timeout event for {}: {}", player_id, e);
+            }
+            publish_leaderboard_update(&player, tick);
+            changed = true;
+        }
+    }
+    
+    if changed {
+        if let Err(e) = save_game_state(players) {
+            eprintln!("[WARN] Failed to save game state after idle sweep: {:?}", e);
+        }
     }
 }
 
-fn handle_players() -> Result<Response, ErrorCode> {
+// Unlike wasmedge, /players and /drain-messages are always sent uncompressed here:
+// waki doesn't expose a compression layer, so Accept-Encoding is ignored.
+fn handle_players(wants_msgpack: bool) -> Result<Response, ErrorCode> {
     // Use the same state loading mechanism as other handlers
     let players = get_game_state();
+    expire_idle_players(players);
     let players_vec: Vec<&Player> = players.values().collect();
+    let config = get_game_config();
+    let alive: Vec<&&Player> = players_vec.iter().filter(|p| p.alive).collect();
+    let dead_count = players_vec.len() - alive.len();
+    
+    // A round is over once a multi-player game is down to its last cycle
+    let game_over = players_vec.len() > 1 && alive.len() <= 1;
+    let winner = if game_over { alive.first().map(|p| p.id.clone()) } else { None };
     
     // Very minimal logging - only when debug is enabled
     if is_debug_enabled() && !players_vec.is_empty() {
@@ -1059,7 +3198,7 @@ fn handle_players() -> Result<Response, ErrorCode> {
 
     let response = json!({
         "runtime": "wasmtime",
-        "castle": "Temporal Sanctuary",
+        "castle": get_castle_name(),
         "players": players_vec.iter().map(|p| {
             json!({
                 "id": p.id,
@@ -1068,13 +3207,73 @@ fn handle_players() -> Result<Response, ErrorCode> {
                 "score": p.score,
                 "color": p.color,
                 "direction": p.direction,
-                "alive": p.alive
+                "alive": p.alive,
+                "game_id": p.game_id,
+                "last_seen": p.last_seen
             })
         }).collect::<Vec<_>>(),
         "count": players_vec.len(),
+        "game_id": get_default_game_id(),
+        "arena": {
+            "width": config.arena_width,
+            "height": config.arena_height
+        },
+        "alive_count": alive.len(),
+        "dead_count": dead_count,
+        "game_over": game_over,
+        "winner": winner,
+        "paused": is_paused(),
         "timestamp": get_timestamp()
     });
 
+    negotiated_response(&response, wants_msgpack)
+}
+
+// Admin removal. /leave is the player's own exit and keeps their final score;
+// a kick also deletes their ORDS player row and scores, and the event
+// published is player_kicked.
+fn handle_kick_player(req: &Request, player_id: &str) -> Result<Response, ErrorCode> {
+    if let Some(rejection) = check_admin_token(req) {
+        return rejection;
+    }
+    
+    let players = get_game_state();
+    let player = match players.remove(player_id) {
+        Some(player) => player,
+        None => return error_response("Player not found", 404),
+    };
+    let tick = advance_tick(players, &player.game_id);
+    if let Err(e) = save_game_state(players) {
+        eprintln!("[WARN] Failed to save game state after kicking {}: {:?}", player_id, e);
+    }
+    eprintln!("[INFO] Player {} kicked by an admin", player_id);
+    
+    let kick_event = GameEvent::new("player_kicked", player_id)
+        .with_player(&player)
+        .with_tick(tick);
+    if let Err(e) = publish_to_oracle_kafka(&kick_event) {
+        eprintln!("[WARN] Failed to publish kick event for {}: {}", player_id, e);
+    }
+    
+    let ords_deleted = match delete_player_from_ords(player_id) {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("[WARN] ORDS cleanup for kicked player {} failed: {}", player_id, e);
+            false
+        }
+    };
+    
+    let response = json!({
+        "status": "success",
+        "runtime": "wasmtime",
+        "castle": get_castle_name(),
+        "message": format!("Player {} was removed from {}", player_id, get_castle_name()),
+        "kicked": player_id,
+        "tick": tick,
+        "ords_deleted": ords_deleted,
+        "timestamp": get_timestamp()
+    });
+    
     Response::builder()
         .header("Content-Type", "application/json")
         .header("Access-Control-Allow-Origin", "*")
@@ -1082,38 +3281,163 @@ fn handle_players() -> Result<Response, ErrorCode> {
         .build()
 }
 
-fn handle_leaderboard() -> Result<Response, ErrorCode> {
-    match get_leaderboard_ords() {
-        Ok(leaderboard_str) => {
-            // Parse the leaderboard JSON string
-            let leaderboard: Value = serde_json::from_str(&leaderboard_str).unwrap_or(json!({"items": []}));
-            
-            let response = json!({
-                "runtime": "wasmtime",
-                "castle": "Temporal Sanctuary",
-                "leaderboard": leaderboard,
-                "source": "Oracle ORDS",
-                "timestamp": get_timestamp()
-            });
-
-            Response::builder()
-                .header("Content-Type", "application/json")
-                .header("Access-Control-Allow-Origin", "*")
-                .body(response.to_string())
-                .build()
+fn handle_player(player_id: &str) -> Result<Response, ErrorCode> {
+    let players = get_game_state();
+    let player = match players.get(player_id) {
+        Some(p) => p,
+        None => {
+            return error_response("Player not found", 404);
         }
-        Err(_) => {
-            Response::builder()
-                .status_code(500)
-                .header("Access-Control-Allow-Origin", "*")
-                .body(json!({"error": "Failed to get leaderboard"}).to_string())
+    };
+    
+    let response = json!({
+        "runtime": "wasmtime",
+        "castle": get_castle_name(),
+        "player": player,
+        "stats": {
+            "moves": player.stats.moves,
+            "distance": player.stats.distance,
+            "kills": player.stats.kills,
+            "survival_ms": player.survival_ms()
+        },
+        "timestamp": get_timestamp()
+    });
+
+    Response::builder()
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(response.to_string())
+        .build()
+}
+
+fn handle_config() -> Result<Response, ErrorCode> {
+    let config = get_game_config();
+    let response = json!({
+        "runtime": "wasmtime",
+        "castle": get_castle_name(),
+        "arena": {
+            "width": config.arena_width,
+            "height": config.arena_height
+        },
+        "move_step": config.move_step,
+        "min_players": config.min_players,
+        "max_players": config.max_players,
+        "spawn": {
+            "x": config.spawn_x,
+            "y": config.spawn_y
+        },
+        "color": config.color,
+        "trail_length": config.trail_length,
+        "score_multiplier": config.score_multiplier,
+        "validate_move_steps": config.validate_move_steps,
+        "timestamp": get_timestamp()
+    });
+
+    Response::builder()
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(response.to_string())
+        .build()
+}
+
+fn handle_leaderboard() -> Result<Response, ErrorCode> {
+    match get_leaderboard_ords() {
+        Ok(leaderboard) => {
+            let response = json!({
+                "runtime": "wasmtime",
+                "castle": get_castle_name(),
+                "leaderboard": leaderboard,
+                "source": "Oracle ORDS",
+                "timestamp": get_timestamp()
+            });
+
+            Response::builder()
+                .header("Content-Type", "application/json")
+                .header("Access-Control-Allow-Origin", "*")
+                .body(response.to_string())
+                .build()
+        }
+        Err(e) => {
+            eprintln!("[ERROR] Failed to get leaderboard: {}", e);
+            upstream_unavailable_response("Failed to get leaderboard")
+        }
+    }
+}
+
+// Admin pause for demos: moves get 423 and the AI answers with a no-op until
+// /resume; everything else keeps working
+fn handle_pause(req: &Request, paused: bool) -> Result<Response, ErrorCode> {
+    if let Some(rejection) = check_admin_token(req) {
+        return rejection;
+    }
+    
+    let was_paused = is_paused();
+    unsafe {
+        GAME_PAUSED = paused;
+    }
+    if was_paused != paused {
+        eprintln!("[INFO] Game {}", if paused { "paused" } else { "resumed" });
+    }
+    
+    let response = json!({
+        "status": "success",
+        "runtime": "wasmtime",
+        "castle": get_castle_name(),
+        "paused": paused,
+        "changed": was_paused != paused,
+        "timestamp": get_timestamp()
+    });
+    
+    Response::builder()
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(response.to_string())
+        .build()
+}
+
+// Leaderboard reads go straight to ORDS, so clearing the scores collection is
+// all a reset needs; there is no cached copy to invalidate
+fn handle_leaderboard_reset(req: &Request) -> Result<Response, ErrorCode> {
+    if let Some(rejection) = check_admin_token(req) {
+        return rejection;
+    }
+    
+    match reset_leaderboard_ords() {
+        Ok((200..=299, rows_deleted)) => {
+            eprintln!("[INFO] Leaderboard reset ({:?} rows deleted)", rows_deleted);
+            let response = json!({
+                "status": "success",
+                "runtime": "wasmtime",
+                "castle": get_castle_name(),
+                "rows_deleted": rows_deleted,
+                "timestamp": get_timestamp()
+            });
+            
+            Response::builder()
+                .header("Content-Type", "application/json")
+                .header("Access-Control-Allow-Origin", "*")
+                .body(response.to_string())
                 .build()
+        },
+        Ok((404, _)) => error_response("ORDS scores endpoint not found", 404),
+        Ok((405, _)) => error_response("ORDS doesn't allow DELETE on the scores endpoint", 501),
+        Ok((status, _)) => {
+            eprintln!("[ERROR] Unexpected ORDS status resetting leaderboard: {}", status);
+            error_response("Failed to reset leaderboard", 502)
+        },
+        Err(e) => {
+            eprintln!("[ERROR] Failed to reset leaderboard: {}", e);
+            upstream_unavailable_response("Failed to reset leaderboard")
         }
     }
 }
 
 fn handle_test_kafka(req: Request) -> Result<Response, ErrorCode> {
     let body = req.body().unwrap_or_default();
+    if let Some(rejection) = check_body_size(&body) {
+        return rejection;
+    }
+    trace_request_body("/test-kafka", &body);
     let body_str = match String::from_utf8(body) {
         Ok(s) => s,
         Err(_) => "{}".to_string(),
@@ -1123,27 +3447,33 @@ fn handle_test_kafka(req: Request) -> Result<Response, ErrorCode> {
     let test_message = extract_json_field(&body_str, "test_message")
         .unwrap_or_else(|| "default_test_from_wasmtime".to_string());
     
-    let test_event = GameEvent {
-        event_type: "connectivity_test".to_string(),
-        player_id: format!("test-wasmtime-{}", test_message),
-        game_id: "wasicycles-multiplayer".to_string(),
-        runtime: "wasmtime".to_string(),
-        castle: "Temporal Sanctuary".to_string(),
-        timestamp: get_timestamp(),
-        position: Some(Position { x: 0.0, y: 0.0 }),
-        direction: Some("test".to_string()),
-        score: Some(42),
-    };
+    let test_event = GameEvent::new("connectivity_test", &format!("test-wasmtime-{}", test_message))
+        .with_position(0.0, 0.0)
+        .with_direction("test")
+        .with_score(42);
+    
+    // wasmtime has no startup hook, so the connectivity test is where every
+    // referenced topic gets set up ahead of its first use
+    let topics: BTreeMap<String, Value> = referenced_topics().into_iter()
+        .map(|topic| {
+            let status = match create_txeventq_topic(&topic) {
+                Ok(()) => json!("ready"),
+                Err(e) => json!({"error": e}),
+            };
+            (topic, status)
+        })
+        .collect();
 
     match publish_to_oracle_kafka(&test_event) {
         Ok(_) => {
             let response = json!({
                 "status": "success",
                 "runtime": "wasmtime",
-                "castle": "Temporal Sanctuary",
+                "castle": get_castle_name(),
                 "message": "Oracle TxEventQ connectivity test successful",
                 "test_event": test_event,
                 "kafka_topic": get_kafka_topic(),
+                "topics": topics,
                 "oracle_url": get_oracle_base_url(),
                 "timestamp": get_timestamp()
             });
@@ -1158,7 +3488,7 @@ fn handle_test_kafka(req: Request) -> Result<Response, ErrorCode> {
             let response = json!({
                 "status": "error",
                 "runtime": "wasmtime",
-                "castle": "Temporal Sanctuary",
+                "castle": get_castle_name(),
                 "message": "Oracle TxEventQ connectivity test failed",
                 "error": error,
                 "kafka_topic": get_kafka_topic(),
@@ -1176,17 +3506,34 @@ fn handle_test_kafka(req: Request) -> Result<Response, ErrorCode> {
     }
 }
 
-fn handle_consume_kafka() -> Result<Response, ErrorCode> {
+fn handle_consume_kafka(req: &Request) -> Result<Response, ErrorCode> {
+    // An explicit ?limit=N is passed on to TxEventQ's records call, letting a
+    // client peek at a handful of messages; leaving it off keeps the old
+    // uncapped call
+    let limit = match req.query().get("limit") {
+        Some(value) => match value.parse::<usize>() {
+            Ok(limit) if limit > 0 => Some(limit),
+            _ => return error_response("limit must be a positive integer", 400),
+        },
+        None => None,
+    };
+    
     // Consume messages from Oracle TxEventQ using consumer group pattern
-    let messages = consume_from_oracle_kafka();
+    let result = consume_from_oracle_kafka(limit);
+    if let Some(error) = &result.error {
+        return upstream_unavailable_response(error);
+    }
+    let messages = &result.records;
     
     let response_data = json!({
         "status": "success", 
         "runtime": "wasmtime",
-        "castle": "Temporal Sanctuary",
+        "castle": get_castle_name(),
         "endpoint": "consume_kafka",
         "messages": messages,
         "count": messages.len(),
+        "empty": result.empty,
+        "error": result.error,
         "timestamp": get_timestamp()
     });
 
@@ -1197,45 +3544,94 @@ fn handle_consume_kafka() -> Result<Response, ErrorCode> {
         .build()
 }
 
-fn handle_drain_messages() -> Result<Response, ErrorCode> {
-    // Drain all messages from Oracle TxEventQ for this runtime
-    let mut all_messages = Vec::new();
-    let mut batch_count = 0;
-    let max_batches = 10;  // Prevent infinite loop
+// Per-partition tally kept while draining, so a partition left unread on a
+// multi-partition topic shows up in the /drain-messages response
+#[derive(Debug, Default, Serialize)]
+struct PartitionProgress {
+    records: usize,
+    last_offset: Option<i64>,
+}
 
-    // Keep consuming until no more messages or max batches reached
-    loop {
-        if batch_count >= max_batches {
-            break;
+fn record_partition_progress(progress: &mut BTreeMap<i64, PartitionProgress>, records: &[Value]) {
+    let as_i64 = |v: &Value| v.as_i64().or_else(|| v.as_str()?.parse().ok());
+    for record in records {
+        let entry = progress.entry(as_i64(&record["partition"]).unwrap_or(0)).or_default();
+        entry.records += 1;
+        if let Some(offset) = as_i64(&record["offset"]) {
+            entry.last_offset = Some(entry.last_offset.map_or(offset, |last| last.max(offset)));
         }
+    }
+}
+
+// Batches /drain-messages may read per configured partition
+const DRAIN_BATCHES_PER_PARTITION: usize = 10;
+
+// What a drain read: every record, per-partition progress, and whether it
+// reached the end of every partition rather than the batch cap or an error
+struct DrainOutcome {
+    messages: Vec<Value>,
+    partitions: BTreeMap<i64, PartitionProgress>,
+    batches: usize,
+    drained: bool,
+}
+
+// Consume batches until each of `partition_count` partitions has come back
+// empty. The consumer instance fetches from one assigned partition at a time,
+// so a single empty batch only says that partition is done; it takes that many
+// empty batches in a row before the whole topic is. Err if the very first
+// fetch fails, since that means Oracle is down rather than a partial drain.
+fn drain_partitions(partition_count: usize, mut consume: impl FnMut() -> ConsumeResult) -> Result<DrainOutcome, String> {
+    let mut outcome = DrainOutcome { messages: Vec::new(), partitions: BTreeMap::new(), batches: 0, drained: false };
+    let max_batches = DRAIN_BATCHES_PER_PARTITION * partition_count;  // Prevent infinite loop
+    let mut empty_in_a_row = 0;
+    
+    while outcome.batches < max_batches {
+        let result = consume();
         
-        let messages = consume_from_oracle_kafka();
-        
-        // Check if we got any actual messages (not just empty/error responses)
-        let has_actual_messages = messages.iter().any(|msg| {
-            !msg.get("error").is_some() && 
-            !msg.get("message").map(|m| m.as_str().unwrap_or("").contains("No messages available")).unwrap_or(false)
-        });
-        
-        if !has_actual_messages {
+        if let Some(error) = result.error {
+            eprintln!("[WARN] Drain stopped after {} batches: {}", outcome.batches, error);
+            if outcome.batches == 0 && empty_in_a_row == 0 {
+                return Err(error);
+            }
             break;
         }
+        if result.empty {
+            empty_in_a_row += 1;
+            if empty_in_a_row >= partition_count {
+                outcome.drained = true;
+                break;
+            }
+            continue;
+        }
         
-        all_messages.extend(messages);
-        batch_count += 1;
+        empty_in_a_row = 0;
+        record_partition_progress(&mut outcome.partitions, &result.records);
+        outcome.messages.extend(result.records);
+        outcome.batches += 1;
         
         // Small delay between batches to avoid overwhelming the server
         std::thread::sleep(std::time::Duration::from_millis(100));
     }
+    Ok(outcome)
+}
+
+fn handle_drain_messages() -> Result<Response, ErrorCode> {
+    // Drain all messages from Oracle TxEventQ for this runtime
+    let outcome = match drain_partitions(get_kafka_partitions(), || consume_from_oracle_kafka(None)) {
+        Ok(outcome) => outcome,
+        Err(error) => return upstream_unavailable_response(&error),
+    };
 
     let response_data = json!({
         "status": "success",
         "runtime": "wasmtime",
-        "castle": "Temporal Sanctuary", 
+        "castle": get_castle_name(), 
         "endpoint": "drain_messages",
-        "messages_drained": all_messages,
-        "total_count": all_messages.len(),
-        "batches_processed": batch_count,
+        "messages_drained": outcome.messages,
+        "total_count": outcome.messages.len(),
+        "batches_processed": outcome.batches,
+        "partitions": outcome.partitions,
+        "fully_drained": outcome.drained,
         "timestamp": get_timestamp()
     });
 
@@ -1246,13 +3642,14 @@ fn handle_drain_messages() -> Result<Response, ErrorCode> {
         .build()
 }
 
-fn consume_from_oracle_kafka() -> Vec<Value> {
+// With a limit the records GET carries ?limit=, otherwise TxEventQ's default
+fn consume_from_oracle_kafka(limit: Option<usize>) -> ConsumeResult {
     // Use the correct Oracle TxEventQ pattern as per reference documentation
     let client = Client::new();
     let topic_name = get_kafka_topic();
     let topic_safe = topic_name.to_lowercase().replace("_", "");
     let consumer_group_id = format!("wasmtime_{}_grp", topic_safe);
-    let auth = base64_encode(&format!("{}:{}", get_oracle_user(), get_oracle_password()));
+    let auth = oracle_auth_header();
     
     eprintln!("[DEBUG] Topic: {}, Consumer Group: {}", topic_name, consumer_group_id);
     
@@ -1260,6 +3657,11 @@ fn consume_from_oracle_kafka() -> Vec<Value> {
     let txeventq_base_url = get_txeventq_base_url();
     let cluster_name = get_oracle_db_name();
     
+    if let Err(e) = create_txeventq_topic(&topic_name) {
+        eprintln!("[ERROR] {}", e);
+        return ConsumeResult::error(e);
+    }
+    
     // Step 1: Create consumer group using correct URL pattern
     let create_group_url = format!("{}/clusters/{}/consumer-groups/{}", 
         txeventq_base_url, cluster_name, consumer_group_id);
@@ -1271,14 +3673,10 @@ fn consume_from_oracle_kafka() -> Vec<Value> {
     eprintln!("[DEBUG] Consumer group URL: {}", create_group_url);
     eprintln!("[DEBUG] Consumer group payload: {}", group_payload);
     
-    let _group_result = client
-        .post(&create_group_url)
-        .headers([
-            ("Content-Type", "application/json"),
-            ("Authorization", &format!("Basic {}", auth))
-        ])
-        .body(group_payload.to_string().as_bytes().to_vec())
-        .send();
+    if let Err(e) = create_consumer_group(&client, &create_group_url, &auth, &group_payload) {
+        eprintln!("[ERROR] {}", e);
+        return ConsumeResult::error(e);
+    }
     
     // Step 2: Create consumer instance using correct pattern (POST /consumers/{group})
     let consumer_instance_url = format!("{}/consumers/{}", 
@@ -1289,48 +3687,31 @@ fn consume_from_oracle_kafka() -> Vec<Value> {
         .post(&consumer_instance_url)
         .headers([
             ("Content-Type", "application/json"),
-            ("Authorization", &format!("Basic {}", auth))
+            ("Authorization", &auth)
         ])
         .body(consumer_payload.to_string().as_bytes().to_vec())
         .send();
     
-    // Extract consumer instance ID from response
-    let consumer_instance_id = if let Ok(resp) = consumer_instance_result {
-        if resp.status_code() >= 200 && resp.status_code() < 300 {
-            if let Ok(body) = resp.body() {
-                if let Ok(body_str) = String::from_utf8(body.clone()) {
-                    eprintln!("[DEBUG] Consumer instance response: {}", body_str);
-                    if let Ok(consumer_data) = serde_json::from_str::<Value>(&body_str) {
-                        let instance_id = consumer_data.get("instance_id")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("unknown_instance")
-                            .to_string();
-                        eprintln!("[INFO] Created consumer instance: {}", instance_id);
-                        instance_id
-                    } else {
-                        eprintln!("[WARN] Could not parse consumer instance response as JSON");
-                        "unknown_instance".to_string()
-                    }
-                } else {
-                    eprintln!("[WARN] Could not decode consumer instance response");
-                    "unknown_instance".to_string()
-                }
-            } else {
-                eprintln!("[WARN] Could not read consumer instance response body");
-                "unknown_instance".to_string()
-            }
-        } else {
-            eprintln!("[WARN] Consumer instance creation failed: status {}", resp.status_code());
-            "unknown_instance".to_string()
+    // Extract consumer instance ID from response. Without one the records GET
+    // below can only fail, so give up here instead of making it.
+    let consumer_instance_id = match consumer_instance_id_from(consumer_instance_result.map_err(|e| e.to_string())) {
+        Ok(instance_id) => {
+            eprintln!("[INFO] Created consumer instance: {}", instance_id);
+            record_created_consumer(&consumer_group_id, &instance_id, &consumers_api_instance_url(&consumer_group_id, &instance_id));
+            instance_id
+        },
+        Err(reason) => {
+            eprintln!("[WARN] Consumer setup failed: {}", reason);
+            return ConsumeResult::error(format!("consumer_setup_failed: {}", reason));
         }
-    } else {
-        eprintln!("[WARN] Consumer instance request failed");
-        "unknown_instance".to_string()
     };
     
     // Step 3: Consume records using correct pattern: /consumers/{group}/instances/{instance_id}/records
-    let consume_url = format!("{}/consumers/{}/instances/{}/records", 
+    let mut consume_url = format!("{}/consumers/{}/instances/{}/records", 
         txeventq_base_url, consumer_group_id, consumer_instance_id);
+    if let Some(limit) = limit {
+        consume_url.push_str(&format!("?limit={}", limit));
+    }
     
     eprintln!("[DEBUG] Consuming from URL: {}", consume_url);
     eprintln!("[DEBUG] Consumer group: {}", consumer_group_id);
@@ -1341,314 +3722,1426 @@ fn consume_from_oracle_kafka() -> Vec<Value> {
         .get(&consume_url)
         .headers([
             ("Accept", "application/json"),
-            ("Authorization", &format!("Basic {}", auth))
+            ("Authorization", &auth)
         ])
         .send();
     
-    if let Ok(resp) = consume_result {
-        if resp.status_code() >= 200 && resp.status_code() < 300 {
-            if let Ok(body) = resp.body() {
-                if let Ok(body_str) = String::from_utf8(body.clone()) {
-                    eprintln!("[DEBUG] TxEventQ consume response: {}", body_str);
-                    
-                    if body_str.trim() == "[]" {
-                        eprintln!("[DEBUG] Empty response - no messages available or consumer offset is at end");
-                    }
-                    
-                    if !body_str.trim().is_empty() && body_str != "[]" {
-                        // Try to parse the response as an array of records
-                        if let Ok(records) = serde_json::from_slice::<Vec<Value>>(&body) {
-                            let mut processed_messages = Vec::new();
-                            
-                            for record in records {
-                                // Process each record using correct pattern
-                                let value = record.get("value").cloned().unwrap_or(Value::Null);
-                                let parsed_value = if let Value::String(s) = &value {
-                                    // Try to parse the value string as JSON
-                                    serde_json::from_str(s).unwrap_or(value.clone())
-                                } else {
-                                    value.clone()
-                                };
-                                
-                                let processed_msg = json!({
-                                    "topic": record.get("topic").cloned().unwrap_or(json!(get_kafka_topic())),
-                                    "partition": record.get("partition").cloned().unwrap_or(json!(0)),
-                                    "offset": record.get("offset").cloned().unwrap_or(json!("unknown")),
-                                    "timestamp": record.get("timestamp").cloned().unwrap_or(json!(get_timestamp())),
-                                    "key": record.get("key").cloned(),
-                                    "data": parsed_value,
-                                    "consumed_by": "wasmtime",
-                                    "consumed_at": get_timestamp(),
-                                    "instance_id": consumer_instance_id.clone()  // Include instance ID for debugging
-                                });
-                                processed_messages.push(processed_msg);
-                            }
-                            
-                            eprintln!("[INFO] Processed {} messages with instance_id: {}", processed_messages.len(), consumer_instance_id);
-                            return processed_messages;
-                        }
-                    }
-                }
-            }
-        } else {
-            eprintln!("[WARN] TxEventQ consume failed: status {}", resp.status_code());
+    let resp = match consume_result {
+        Ok(resp) => resp,
+        Err(e) => {
+            eprintln!("[WARN] TxEventQ consume request failed");
+            return ConsumeResult::error(format!("Request failed: {}", e));
+        }
+    };
+    
+    let status_code = resp.status_code();
+    if !(200..300).contains(&status_code) {
+        eprintln!("[WARN] TxEventQ consume failed: status {}", status_code);
+        return ConsumeResult::error(format!("HTTP {}", status_code));
+    }
+    
+    let body = match resp.body() {
+        Ok(body) => body,
+        Err(e) => return ConsumeResult::error(format!("Failed to read consume response: {}", e)),
+    };
+    consume_result_from_body(&body, &consumer_instance_id)
+}
+
+// Turns a records response body into a ConsumeResult: an empty body or []
+// is the end of the topic (empty), anything unparseable an error
+fn consume_result_from_body(body: &[u8], consumer_instance_id: &str) -> ConsumeResult {
+    let body_str = String::from_utf8_lossy(body);
+    eprintln!("[DEBUG] TxEventQ consume response: {}", body_str);
+    
+    if body_str.trim().is_empty() || body_str.trim() == "[]" {
+        eprintln!("[DEBUG] Empty response - no messages available or consumer offset is at end");
+        return ConsumeResult::empty();
+    }
+    
+    // Try to parse the response as an array of records
+    match decode_consumed_records(body) {
+        Ok(records) => {
+            let mut processed_messages = Vec::new();
+            let mut unsigned = 0;
+            
+            for (record, lossy) in records {
+                // Process each record using correct pattern
+                let value = record.get("value").cloned().unwrap_or(Value::Null);
+                let parsed_value = if let Value::String(s) = &value {
+                    // Try to parse the value string as JSON
+                    serde_json::from_str(s).unwrap_or(value.clone())
+                } else {
+                    value.clone()
+                };
+                if !has_valid_signature(&parsed_value) {
+                    unsigned += 1;
+                    continue;
+                }
+                
+                let mut processed_msg = json!({
+                    "topic": record.get("topic").cloned().unwrap_or(json!(get_kafka_topic())),
+                    "partition": record.get("partition").cloned().unwrap_or(json!(0)),
+                    "offset": record.get("offset").cloned().unwrap_or(json!("unknown")),
+                    "timestamp": record.get("timestamp").cloned().unwrap_or(json!(get_timestamp())),
+                    "key": record.get("key").cloned(),
+                    "data": parsed_value,
+                    "consumed_by": "wasmtime",
+                    "consumed_at": get_timestamp(),
+                    "instance_id": consumer_instance_id  // Include instance ID for debugging
+                });
+                if lossy {
+                    processed_msg["lossy_decode"] = json!(true);
+                }
+                processed_messages.push(processed_msg);
+            }
+            
+            eprintln!("[INFO] Processed {} messages with instance_id: {}", processed_messages.len(), consumer_instance_id);
+            let mut result = ConsumeResult::records(processed_messages);
+            if unsigned > 0 {
+                eprintln!("[WARN] Dropped {} events with a missing or bad signature", unsigned);
+                // Skipped records still moved the offset, so there may be more to read
+                result.empty = false;
+            }
+            result
+        },
+        Err(e) => {
+            eprintln!("[WARN] Failed to parse TxEventQ records: {}", e);
+            ConsumeResult::error(format!("Parse error: {}", e))
+        },
+    }
+}
+
+// The instance_id from a create-consumer-instance response, or why there
+// isn't one
+fn consumer_instance_id_from(result: Result<Response, String>) -> Result<String, String> {
+    let resp = result.map_err(|e| format!("consumer instance request failed: {}", e))?;
+    let status_code = resp.status_code();
+    if !(200..300).contains(&status_code) {
+        return Err(format!("consumer instance creation returned status {}", status_code));
+    }
+    let body = resp.body().map_err(|e| format!("could not read consumer instance response: {}", e))?;
+    eprintln!("[DEBUG] Consumer instance response: {}", String::from_utf8_lossy(&body));
+    let consumer_data: Value = serde_json::from_slice(&body)
+        .map_err(|e| format!("consumer instance response is not JSON: {}", e))?;
+    consumer_data.get("instance_id")
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+        .ok_or_else(|| "consumer instance response has no instance_id".to_string())
+}
+
+// Parses a consume response into records. Invalid UTF-8 is replaced rather
+// than failing the whole batch (as wasmedge does with from_utf8_lossy); the
+// flag marks records whose value came out with replacement characters.
+fn decode_consumed_records(body: &[u8]) -> Result<Vec<(Value, bool)>, serde_json::Error> {
+    let text = String::from_utf8_lossy(body);
+    let body_lossy = matches!(text, std::borrow::Cow::Owned(_));
+    if body_lossy {
+        eprintln!("[WARN] TxEventQ response contained invalid UTF-8; decoding lossily");
+    }
+    
+    let records = serde_json::from_str::<Vec<Value>>(&text)?;
+    Ok(records
+        .into_iter()
+        .map(|record| {
+            let lossy = body_lossy && record.get("value").is_some_and(|v| v.to_string().contains('\u{FFFD}'));
+            (record, lossy)
+        })
+        .collect())
+}
+
+// TxEventQ Event Sourcing for State Reconstruction
+// This addresses Wasmtime's static variable reset between requests
+fn reconstruct_state_from_events() -> HashMap<String, Player> {
+    unsafe {
+        let current_time = get_timestamp();
+        
+        // Rate limit: only allow reconstruction once every 10 seconds to prevent excessive calls
+        if current_time - LAST_RECONSTRUCTION_TIME < 10000 {
+            eprintln!("[INFO] Skipping state reconstruction due to rate limit (last: {}ms ago)", current_time - LAST_RECONSTRUCTION_TIME);
+            return HashMap::new();
+        }
+        
+        LAST_RECONSTRUCTION_TIME = current_time;
+    }
+    
+    eprintln!("[INFO] Reconstructing game state from TxEventQ events due to Wasmtime static reset");
+    
+    // Consume recent events from TxEventQ to rebuild state
+    let consumed = consume_recent_game_events();
+    if let Some(error) = consumed.error {
+        eprintln!("[ERROR] State reconstruction could not read TxEventQ: {}", error);
+        return HashMap::new();
+    }
+    rebuild_state_from_messages(consumed.records)
+}
+
+// Replays consumed messages into the active game's players. Messages older
+// than RECONSTRUCT_MAX_AGE_MS are dropped first, and game ticks are seeded
+// from what is left.
+fn rebuild_state_from_messages(mut messages: Vec<Value>) -> HashMap<String, Player> {
+    if let Some(max_age) = get_reconstruct_max_age_ms() {
+        let cutoff = get_timestamp().saturating_sub(max_age);
+        let before = messages.len();
+        messages.retain(|m| {
+            m.get("data")
+                .and_then(|d| d.get("timestamp"))
+                .and_then(|t| t.as_u64())
+                .is_some_and(|ts| ts >= cutoff)
+        });
+        if messages.len() < before {
+            eprintln!("[INFO] Skipped {} events older than {}ms", before - messages.len(), max_age);
+        }
+    }
+    let active_game_id = get_default_game_id();
+    
+    seed_game_ticks(&messages);
+    
+    // Events from other matches share the topic; only the active game is kept
+    let mut active_players: Vec<Player> = replay_game_events(&messages)
+        .into_iter()
+        .filter(|((game_id, _), _)| *game_id == active_game_id)
+        .map(|(_, player)| player)
+        .collect();
+    
+    // A game never holds more than MAX_PLAYERS, so anything beyond that is stale
+    // history; the most recently seen players are the ones kept
+    let max_players = get_game_config().max_players;
+    if active_players.len() > max_players {
+        eprintln!("[WARN] Reconstructed {} players, keeping the {} most recently seen (MAX_PLAYERS)", active_players.len(), max_players);
+        active_players.sort_by_key(|p| std::cmp::Reverse(p.last_seen));
+        active_players.truncate(max_players);
+    }
+    
+    let reconstructed_state: HashMap<String, Player> = active_players
+        .into_iter()
+        .map(|player| (player.id.clone(), player))
+        .collect();
+    
+    let player_count = reconstructed_state.len();
+    if player_count > 0 {
+        eprintln!("[INFO] Reconstructed state with {} players from TxEventQ events", player_count);
+        let player_ids: Vec<&String> = reconstructed_state.keys().collect();
+        eprintln!("[INFO] Reconstructed players: {:?}", player_ids);
+    } else {
+        eprintln!("[INFO] No players found in TxEventQ event history");
+    }
+    
+    reconstructed_state
+}
+
+// Replays consumed events into players keyed by (game_id, player_id), so two
+// games that reuse a player id rebuild independently
+fn replay_game_events(messages: &[Value]) -> HashMap<(String, String), Player> {
+    fn mark_applied(last_applied: &mut HashMap<(String, String), (u64, u64)>, key: &(String, String), order: (u64, u64)) {
+        let applied = last_applied.entry(key.clone()).or_insert(order);
+        *applied = (*applied).max(order);
+    }
+    
+    let mut players: HashMap<(String, String), Player> = HashMap::new();
+    // Newest (timestamp, seq) applied per player. Records from different
+    // partitions can arrive out of order; a move older than this would rewind
+    // the cycle, or bring back a player whose leave was already applied.
+    let mut last_applied: HashMap<(String, String), (u64, u64)> = HashMap::new();
+    // Players removed by a leave/timeout/kick, kept so a later delta move
+    // has their last known state to build on
+    let mut departed: HashMap<(String, String), Player> = HashMap::new();
+    let config = get_game_config();
+    
+    for message in messages {
+        let event = match message.get("data").and_then(|data| serde_json::from_value::<GameEvent>(data.clone()).ok()) {
+            Some(event) => event,
+            None => continue,
+        };
+        let key = (event.game_id.clone(), event.player_id.clone());
+        
+        match event.event_type.as_str() {
+            "player_snapshot" => {
+                // State snapshots have the most recent player state
+                mark_applied(&mut last_applied, &key, event.order_key());
+                players.insert(key, player_from_event(&event));
+                eprintln!("[DEBUG] Reconstructed player {} from snapshot", event.player_id);
+            },
+            "player_joined" => {
+                // Only use join events if we don't have a snapshot
+                if let std::collections::hash_map::Entry::Vacant(slot) = players.entry(key.clone()) {
+                    mark_applied(&mut last_applied, &key, event.order_key());
+                    slot.insert(player_from_event(&event));
+                    eprintln!("[DEBUG] Reconstructed player {} from join event", event.player_id);
+                }
+            },
+            "player_moved" => {
+                if last_applied.get(&key).is_some_and(|&applied| event.order_key() < applied) {
+                    eprintln!("[DEBUG] Skipped stale move for player {} at {}", event.player_id, event.timestamp);
+                    continue;
+                }
+                mark_applied(&mut last_applied, &key, event.order_key());
+                if let Some(player) = players.get_mut(&key) {
+                    apply_move_event(player, &event, config.trail_length);
+                    eprintln!("[DEBUG] Updated player {} from move event", event.player_id);
+                } else if let Some(mut player) = departed.remove(&key) {
+                    // Back after a leave: apply the move on top of where they were
+                    apply_move_event(&mut player, &event, config.trail_length);
+                    players.insert(key, player);
+                    eprintln!("[DEBUG] Restored departed player {} from move event", event.player_id);
+                } else if event.position.is_some() {
+                    // Player not found, create from move event
+                    players.insert(key, player_from_event(&event));
+                    eprintln!("[DEBUG] Created player {} from move event", event.player_id);
+                } else {
+                    // A delta with nothing earlier to apply it to; placing the
+                    // cycle at the spawn point would be a guess
+                    eprintln!("[DEBUG] Skipped delta move for unknown player {}", event.player_id);
+                }
+            },
+            "player_eliminated" => {
+                if let Some(player) = players.get_mut(&key) {
+                    player.alive = false;
+                    eprintln!("[DEBUG] Marked player {} eliminated", event.player_id);
+                }
+            },
+            "player_respawned" => {
+                // Comes back alive at a new cell; anything older is superseded
+                mark_applied(&mut last_applied, &key, event.order_key());
+                players.insert(key, player_from_event(&event));
+                eprintln!("[DEBUG] Respawned player {} from event", event.player_id);
+            },
+            "player_left" | "player_timeout" | "player_kicked" => {
+                mark_applied(&mut last_applied, &key, event.order_key());
+                if let Some(player) = players.remove(&key) {
+                    departed.insert(key.clone(), player);
+                }
+                eprintln!("[DEBUG] Removed player {} from {} event", event.player_id, event.event_type);
+            },
+            other if IGNORED_EVENT_TYPES.contains(&other) => {},
+            other => note_unknown_event_type(other),
+        }
+    }
+    
+    players
+}
+
+// Published to the game topic but carrying no player state to replay
+const IGNORED_EVENT_TYPES: [&str; 4] = ["state_snapshot", "connectivity_test", "test_connectivity", "leaderboard_update"];
+// Each unknown type is warned about at most once per this window
+const UNKNOWN_EVENT_WARN_INTERVAL_MS: u64 = 60_000;
+
+fn next_event_seq(player_id: &str) -> u64 {
+    let seqs = unsafe { (*std::ptr::addr_of_mut!(EVENT_SEQS)).get_or_insert_with(HashMap::new) };
+    let seq = seqs.entry(player_id.to_string()).or_insert(0);
+    *seq += 1;
+    *seq
+}
+
+fn get_last_player_events() -> &'static mut HashMap<String, GameEvent> {
+    unsafe { (*std::ptr::addr_of_mut!(LAST_PLAYER_EVENTS)).get_or_insert_with(HashMap::new) }
+}
+
+fn get_unknown_event_types() -> &'static mut HashMap<String, (u64, u64)> {
+    unsafe { (*std::ptr::addr_of_mut!(UNKNOWN_EVENT_TYPES)).get_or_insert_with(HashMap::new) }
+}
+
+// Counts an event type replay doesn't handle. A typo'd type on the publisher
+// side ("player_move") would otherwise drop those events without a trace.
+fn note_unknown_event_type(event_type: &str) {
+    let now = get_timestamp();
+    let (seen, warned_at) = get_unknown_event_types().entry(event_type.to_string()).or_insert((0, 0));
+    *seen += 1;
+    if now.saturating_sub(*warned_at) >= UNKNOWN_EVENT_WARN_INTERVAL_MS {
+        eprintln!("[WARN] Replay ignored unrecognized event type '{}' ({} seen so far)", event_type, seen);
+        *warned_at = now;
+    }
+}
+
+// The tick counter resumes from the highest tick any replayed event carried,
+// leave/timeout events included, so it never runs backwards after a reset
+fn seed_game_ticks(messages: &[Value]) {
+    let ticks = get_game_ticks();
+    for message in messages {
+        let data = &message["data"];
+        if let (Some(game_id), Some(tick)) = (data["game_id"].as_str(), data["tick"].as_u64()) {
+            let seen = ticks.entry(game_id.to_string()).or_insert(0);
+            *seen = (*seen).max(tick);
+        }
+    }
+}
+
+// Updates a replayed player from a move event. Whatever a MINIMAL_EVENTS
+// delta left out keeps its previous value, and trail_added cells are laid on
+// the end of the trail as the move handler would have.
+fn apply_move_event(player: &mut Player, event: &GameEvent, trail_length: usize) {
+    if let Some(pos) = &event.position {
+        player.x = pos.x;
+        player.y = pos.y;
+    }
+    if let Some(dir) = &event.direction {
+        player.direction = dir.clone();
+    }
+    if let Some(score) = event.score {
+        player.score = score;
+    }
+    if let Some(tick) = event.tick {
+        player.tick = tick;
+    }
+    if let Some(color) = &event.color {
+        player.color = color.clone();
+    }
+    if let Some(trail) = &event.trail {
+        player.trail = trail.clone();
+    }
+    for cell in event.trail_added.iter().flatten() {
+        player.lay_trail(*cell, trail_length);
+    }
+    // Same bound full events have, however many deltas were applied
+    let excess = player.trail.len().saturating_sub(EVENT_TRAIL_CELLS);
+    player.trail.drain(..excess);
+    player.last_seen = event.timestamp;
+}
+
+// A live player as described by a snapshot/join/move event, trail included
+fn player_from_event(event: &GameEvent) -> Player {
+    let config = get_game_config();
+    Player {
+        id: event.player_id.clone(),
+        x: event.position.as_ref().map(|p| p.x).unwrap_or(config.spawn_x),
+        y: event.position.as_ref().map(|p| p.y).unwrap_or(config.spawn_y),
+        direction: event.direction.clone().unwrap_or_else(|| "up".to_string()),
+        score: event.score.unwrap_or(0),
+        color: event.color.clone().unwrap_or_else(|| color_for_runtime(&event.runtime)),
+        alive: true,
+        game_id: event.game_id.clone(),
+        last_seen: event.timestamp,
+        joined_at: event.timestamp,
+        // Events from before trails were published rebuild with none; a
+        // delta move's trail_added cells count as trail as well
+        trail: event.trail.iter().chain(&event.trail_added).flatten().copied().collect(),
+        stats: PlayerStats::default(),
+        tick: event.tick.unwrap_or(0),
+        last_move_at: 0,
+    }
+}
+
+// Consume recent game events for state reconstruction. Only reports an error
+// when no topic could be read at all; one readable topic is enough to replay.
+fn consume_recent_game_events() -> ConsumeResult {
+    let client = Client::new();
+    let auth = oracle_auth_header();
+    
+    // Try to consume from a dedicated topic for state reconstruction if available
+    // Otherwise use the main game events topic
+    let topics_to_try = vec![
+        format!("{}_STATE", get_kafka_topic()), // Try dedicated state topic first
+        get_kafka_topic(),                       // Fall back to main topic
+    ];
+    
+    collect_reconstruction_events(
+        &topics_to_try,
+        create_txeventq_topic,
+        |topic, messages| read_reconstruction_topic(&client, &auth, topic, messages),
+    )
+}
+
+// Reads `topics` in order until one yields events. Each topic is created
+// first: a fresh deployment has no state topic yet, and reading one that
+// doesn't exist fails every reconstruction against it.
+fn collect_reconstruction_events(
+    topics: &[String],
+    mut create_topic: impl FnMut(&str) -> Result<(), String>,
+    mut read_topic: impl FnMut(&str, &mut Vec<Value>) -> Result<usize, String>,
+) -> ConsumeResult {
+    let mut messages = Vec::new();
+    let mut group_errors = Vec::new();
+    
+    for topic in topics {
+        if let Err(e) = create_topic(topic) {
+            eprintln!("[ERROR] {} (topic {})", e, topic);
+            group_errors.push(format!("{}: {}", topic, e));
+            continue;
+        }
+        
+        match read_topic(topic, &mut messages) {
+            Ok(pages) if !messages.is_empty() => {
+                eprintln!("[INFO] Found {} events in topic {} for state reconstruction ({} pages)", messages.len(), topic, pages);
+                break; // Found events, no need to try other topics
+            },
+            Ok(_) => {},
+            Err(e) => {
+                eprintln!("[ERROR] {} (topic {})", e, topic);
+                group_errors.push(format!("{}: {}", topic, e));
+            },
+        }
+    }
+    
+    // Sort messages by (timestamp, seq) so replay applies them in the order
+    // they happened, even when several share a millisecond
+    messages.sort_by_key(|message| {
+        let data = message.get("data");
+        let field = |name: &str| data.and_then(|d| d.get(name)).and_then(|v| v.as_u64()).unwrap_or(0);
+        (field("timestamp"), field("seq"))
+    });
+    
+    if messages.is_empty() && !group_errors.is_empty() {
+        return ConsumeResult::error(group_errors.join("; "));
+    }
+    ConsumeResult::records(messages)
+}
+
+// Sets up the reconstruction consumer on `topic` and pages its records into
+// `messages`. Returns the number of pages read.
+fn read_reconstruction_topic(client: &Client, auth: &str, topic: &str, messages: &mut Vec<Value>) -> Result<usize, String> {
+    let consumer_group_id = "wasmtime_state_reconstruction";
+    let consumer_id = "wasmtime_consumer_state";
+    
+    // Create consumer group for this topic
+    let create_group_url = format!("{}/clusters/{}/consumer-groups/{}", 
+        get_txeventq_base_url(), get_oracle_db_name(), consumer_group_id);
+    
+    let group_payload = json!({
+        "topic_name": topic
+    });
+    
+    create_consumer_group(client, &create_group_url, auth, &group_payload)?;
+    
+    // Create consumer
+    let create_consumer_url = format!("{}/clusters/{}/consumer-groups/{}/consumers/{}", 
+        get_txeventq_base_url(), get_oracle_db_name(), consumer_group_id, consumer_id);
+    
+    let consumer_result = client
+        .post(&create_consumer_url)
+        .headers([
+            ("Content-Type", "application/json"),
+            ("Authorization", auth)
+        ])
+        .body("{}".as_bytes().to_vec())
+        .send();
+    if let Ok(resp) = consumer_result {
+        if (200..300).contains(&resp.status_code()) {
+            record_created_consumer(consumer_group_id, consumer_id, &create_consumer_url);
+        }
+    }
+    
+    // Subscribe to topic
+    let subscribe_url = format!("{}/clusters/{}/consumer-groups/{}/consumers/{}/subscription", 
+        get_txeventq_base_url(), get_oracle_db_name(), consumer_group_id, consumer_id);
+    
+    let subscription_payload = json!({
+        "topic_name": topic
+    });
+    
+    let _subscribe_result = client
+        .post(&subscribe_url)
+        .headers([
+            ("Content-Type", "application/json"),
+            ("Authorization", auth)
+        ])
+        .body(subscription_payload.to_string().as_bytes().to_vec())
+        .send();
+    
+    // Consume records in pages of RECONSTRUCT_EVENT_LIMIT; a full page means
+    // there may be more history behind it, so keep paging until a short page
+    // comes back or the page cap is hit
+    let event_limit = get_reconstruct_event_limit();
+    let consume_url = format!("{}/clusters/{}/consumer-groups/{}/consumers/{}/records?limit={}", 
+        get_txeventq_base_url(), get_oracle_db_name(), consumer_group_id, consumer_id, event_limit);
+    
+    Ok(page_reconstruction_records(topic, event_limit, messages, |messages| {
+        consume_reconstruction_page(client, &consume_url, auth, topic, messages)
+    }))
+}
+
+// Calls `fetch_page` until a short page comes back, RECONSTRUCT_MAX_EVENTS
+// messages are held (the excess is dropped) or RECONSTRUCT_MAX_PAGES is hit.
+// Returns the number of pages fetched.
+fn page_reconstruction_records(
+    topic: &str,
+    event_limit: usize,
+    messages: &mut Vec<Value>,
+    mut fetch_page: impl FnMut(&mut Vec<Value>) -> usize,
+) -> usize {
+    let max_events = get_reconstruct_max_events();
+    let mut pages = 0;
+    loop {
+        pages += 1;
+        let batch_size = fetch_page(messages);
+        
+        if messages.len() >= max_events {
+            messages.truncate(max_events);
+            eprintln!("[WARN] Stopped reconstruction at RECONSTRUCT_MAX_EVENTS={} events on topic {}", max_events, topic);
+            break;
+        }
+        if batch_size < event_limit {
+            break;
+        }
+        if pages >= RECONSTRUCT_MAX_PAGES {
+            eprintln!("[WARN] Stopped reconstruction paging after {} pages of {} events on topic {}", pages, event_limit, topic);
+            break;
+        }
+    }
+    pages
+}
+
+// POST the consumer group. 409 or an "already exists" body means an earlier
+// request created it, which is fine; anything else is a real failure that
+// would make the following reads look empty rather than broken.
+fn create_consumer_group(client: &Client, url: &str, auth: &str, payload: &Value) -> Result<(), String> {
+    let resp = client
+        .post(url)
+        .headers([
+            ("Content-Type", "application/json"),
+            ("Authorization", auth)
+        ])
+        .body(payload.to_string().as_bytes().to_vec())
+        .send()
+        .map_err(|e| format!("Consumer group request failed: {}", e))?;
+    
+    let status_code = resp.status_code();
+    let body = if (200..=299).contains(&status_code) {
+        String::new()
+    } else {
+        String::from_utf8(resp.body().unwrap_or_default()).unwrap_or_default()
+    };
+    let outcome = consumer_group_outcome(status_code, &body);
+    if outcome.is_ok() && status_code == 409 && is_debug_enabled() {
+        eprintln!("[DEBUG] Consumer group already exists: {}", url);
+    }
+    outcome
+}
+
+// Classify a consumer-group POST answer: success or "already exists" is fine,
+// everything else is an error the consume path has to surface.
+fn consumer_group_outcome(status_code: u16, body: &str) -> Result<(), String> {
+    match status_code {
+        200..=299 | 409 => Ok(()),
+        _ if body.to_lowercase().contains("already exists") || body.contains("ALREADY_EXISTS") => Ok(()),
+        _ => Err(format!("Consumer group creation failed with status {}: {}", status_code, body)),
+    }
+}
+
+// Fetch one page of records for state reconstruction, appending them to `messages`.
+// Returns the number of records in the page (0 on any error).
+fn consume_reconstruction_page(client: &Client, consume_url: &str, auth: &str, topic: &str, messages: &mut Vec<Value>) -> usize {
+    let resp = match client
+        .get(consume_url)
+        .headers([
+            ("Accept", "application/json"),
+            ("Authorization", auth)
+        ])
+        .send()
+    {
+        Ok(resp) => resp,
+        Err(_) => return 0,
+    };
+    
+    if resp.status_code() < 200 || resp.status_code() >= 300 {
+        return 0;
+    }
+    
+    let body = match resp.body() {
+        Ok(body) => body,
+        Err(_) => return 0,
+    };
+    
+    append_reconstruction_records(&body, topic, messages)
+}
+
+// Turns one TxEventQ records response into reconstruction messages, appended
+// to `messages`. Returns the number of records in the page, counting any
+// skipped for a bad signature (0 if the page doesn't parse).
+fn append_reconstruction_records(body: &[u8], topic: &str, messages: &mut Vec<Value>) -> usize {
+    if body.is_empty() || body == b"[]" {
+        return 0;
+    }
+    
+    let records = match decode_consumed_records(body) {
+        Ok(records) => records,
+        Err(_) => return 0,
+    };
+    
+    let batch_size = records.len();
+    for (record, lossy) in records {
+        let value = record.get("value").cloned().unwrap_or(Value::Null);
+        let value_str = if let Value::String(s) = &value {
+            s.clone()
+        } else {
+            serde_json::to_string(&value).unwrap_or_default()
+        };
+        
+        let parsed_value: Value = serde_json::from_str(&value_str).unwrap_or(value.clone());
+        if !has_valid_signature(&parsed_value) {
+            eprintln!("[WARN] Skipping event at offset {} with a missing or bad signature", record.get("offset").cloned().unwrap_or(Value::Null));
+            continue;
+        }
+        
+        let mut message = json!({
+            "topic": record.get("topic").cloned().unwrap_or(Value::Null),
+            "partition": record.get("partition").cloned().unwrap_or(Value::Null),
+            "offset": record.get("offset").cloned().unwrap_or(Value::Null),
+            "timestamp": record.get("timestamp").cloned().unwrap_or(Value::Null),
+            "data": parsed_value,
+            "reconstructed_from": topic
+        });
+        if lossy {
+            message["lossy_decode"] = json!(true);
+        }
+        messages.push(message);
+    }
+    
+    batch_size
+}
+
+// wasmtime can't spawn background work like wasmedge's tokio::spawn, so events
+// are queued and published at the start of the next request (at most one
+// flush per request) or on POST /flush. A /move?ack=true is the exception
+// while the queue is empty: publish_move_event sends its event inline so the
+// response can report event_offset. Like the other statics, the queue is lost if the instance is
+// recycled before the next request arrives, unless FLUSH_BEFORE_RESPONSE is set.
+fn get_pending_events() -> &'static mut Vec<GameEvent> {
+    unsafe { (*std::ptr::addr_of_mut!(PENDING_EVENTS)).get_or_insert_with(Vec::new) }
+}
+
+fn queue_event(event: GameEvent) {
+    let pending = get_pending_events();
+    if pending.len() >= MAX_PENDING_EVENTS {
+        let dropped = pending.remove(0);
+        eprintln!("[WARN] Event queue full, dropping oldest {} event for {}", dropped.event_type, dropped.player_id);
+    }
+    pending.push(event);
+}
+
+// Publishes queued events in order, stopping at the first failure so it and
+// everything behind it stay queued for the next flush in their original order.
+// Returns (published, still_pending).
+fn flush_event_queue() -> (usize, usize) {
+    let pending = get_pending_events();
+    if pending.is_empty() {
+        return (0, 0);
+    }
+    
+    let published = publish_queued(pending, |event| publish_to_oracle_kafka(event).map(|_| ()));
+    
+    let remaining = pending.len();
+    if is_debug_enabled() || remaining > 0 {
+        eprintln!("[INFO] Flushed {} queued events ({} still pending)", published, remaining);
+    }
+    (published, remaining)
+}
+
+// The /move?ack=true publish: sends the event straight away and returns where
+// TxEventQ put it. Behind a backlog (Oracle failed the flush at the start of
+// this request) the event is queued instead so it can't overtake older events,
+// and a failed publish is queued for the next flush; both report no offset.
+fn publish_move_event(
+    event: GameEvent,
+    publish: impl FnOnce(&GameEvent) -> Result<Option<PublishReceipt>, String>,
+) -> Option<PublishReceipt> {
+    if !get_pending_events().is_empty() {
+        queue_event(event);
+        return None;
+    }
+    match publish(&event) {
+        Ok(receipt) => receipt,
+        Err(e) => {
+            eprintln!("[WARN] Failed to publish move event, queued for the next flush: {}", e);
+            queue_event(event);
+            None
+        }
+    }
+}
+
+// Removes events from the front of `queue` as `publish` accepts them; the
+// first rejection ends the flush with the failed event still at the front
+fn publish_queued(queue: &mut Vec<GameEvent>, mut publish: impl FnMut(&GameEvent) -> Result<(), String>) -> usize {
+    let mut published = 0;
+    for event in queue.iter() {
+        if let Err(e) = publish(event) {
+            eprintln!("[WARN] Failed to publish queued {} event: {}", event.event_type, e);
+            break;
+        }
+        published += 1;
+    }
+    queue.drain(..published);
+    published
+}
+
+fn handle_flush() -> Result<Response, ErrorCode> {
+    let (published, pending) = flush_event_queue();
+    
+    let response = json!({
+        "status": "success",
+        "runtime": "wasmtime",
+        "castle": get_castle_name(),
+        "published": published,
+        "pending": pending,
+        "timestamp": get_timestamp()
+    });
+    
+    Response::builder()
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(response.to_string())
+        .build()
+}
+
+// Prometheus text exposition. Reads the statics directly rather than going
+// through get_game_state(), so a scrape never triggers an ORDS/event reload.
+fn handle_metrics() -> Result<Response, ErrorCode> {
+    let (players, alive) = unsafe {
+        match &*std::ptr::addr_of!(GAME_STATE) {
+            Some(state) => (state.len(), state.values().filter(|p| p.alive).count()),
+            None => (0, 0),
+        }
+    };
+    let body = format!(
+        "# HELP wasicycles_event_buffer_depth Game events waiting to be published to TxEventQ\n\
+         # TYPE wasicycles_event_buffer_depth gauge\n\
+         wasicycles_event_buffer_depth{{runtime=\"wasmtime\"}} {}\n\
+         # HELP wasicycles_players Players in the arena\n\
+         # TYPE wasicycles_players gauge\n\
+         wasicycles_players{{runtime=\"wasmtime\"}} {}\n\
+         # HELP wasicycles_players_alive Players whose cycle is still running\n\
+         # TYPE wasicycles_players_alive gauge\n\
+         wasicycles_players_alive{{runtime=\"wasmtime\"}} {}\n",
+        get_pending_events().len(),
+        players,
+        alive
+    );
+    
+    Response::builder()
+        .header("Content-Type", "text/plain; version=0.0.4")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(body)
+        .build()
+}
+
+fn get_created_consumers() -> &'static mut Vec<ConsumerInstance> {
+    unsafe { (*std::ptr::addr_of_mut!(CREATED_CONSUMERS)).get_or_insert_with(Vec::new) }
+}
+
+fn record_created_consumer(group: &str, instance_id: &str, url: &str) {
+    let consumers = get_created_consumers();
+    if !consumers.iter().any(|c| c.group == group && c.instance_id == instance_id) {
+        consumers.push(ConsumerInstance {
+            group: group.to_string(),
+            instance_id: instance_id.to_string(),
+            url: url.to_string(),
+            created_at: get_timestamp(),
+        });
+    }
+}
+
+// Instance URL of the /consumers/{group} API, which consume_from_oracle_kafka
+// creates instances through
+fn consumers_api_instance_url(group: &str, instance_id: &str) -> String {
+    format!("{}/consumers/{}/instances/{}", get_txeventq_base_url(), group, instance_id)
+}
+
+// The URL a consumer was created under when this instance recorded it;
+// otherwise (e.g. after a static reset) the /consumers/{group} instance URL
+fn consumer_delete_url(group: &str, instance_id: &str) -> String {
+    get_created_consumers().iter()
+        .find(|c| c.group == group && c.instance_id == instance_id)
+        .map(|c| c.url.clone())
+        .unwrap_or_else(|| consumers_api_instance_url(group, instance_id))
+}
+
+// Optional API_KEY: when set, every write (non-GET) request needs a matching
+// X-API-Key header; reads are only gated too with API_KEY_READS=true.
+// /health is always open. SPECTATE_TOKEN, sent in the same header, only opens
+// the read-only spectator endpoints.
+fn check_api_key(req: &Request, method: &Method, path: &str) -> Option<Result<Response, ErrorCode>> {
+    let provided = req.header("x-api-key")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    
+    if api_key_accepts(provided, method, path) {
+        None
+    } else {
+        Some(error_response("Invalid or missing X-API-Key", 401))
+    }
+}
+
+// Whether a request sending `provided` as its X-API-Key may go ahead
+fn api_key_accepts(provided: &str, method: &Method, path: &str) -> bool {
+    let expected = match std::env::var("API_KEY") {
+        Ok(key) if !key.is_empty() => key,
+        _ => return true,
+    };
+    
+    if path == "/health" {
+        return true;
+    }
+    
+    let is_read = matches!(method, Method::Get | Method::Head);
+    let gate_reads = std::env::var("API_KEY_READS").map(|v| v == "true").unwrap_or(false);
+    if is_read && !gate_reads {
+        return true;
+    }
+    
+    let spectating = is_read
+        && is_spectator_path(path)
+        && std::env::var("SPECTATE_TOKEN").map(|token| !token.is_empty() && provided == token).unwrap_or(false);
+    
+    provided == expected || spectating
+}
+
+// Endpoints a public scoreboard reads: players, leaderboard and config
+fn is_spectator_path(path: &str) -> bool {
+    matches!(path, "/players" | "/leaderboard" | "/config") || path.starts_with("/players/")
+}
+
+// Write endpoints only speak JSON. A missing Content-Type is tolerated (the
+// handler's parse decides), as is an empty body, but anything else is a 415.
+fn check_json_content_type(req: &Request, method: &Method) -> Option<Result<Response, ErrorCode>> {
+    if !matches!(method, Method::Post | Method::Put | Method::Patch) {
+        return None;
+    }
+    
+    let is_empty = req.header("content-length")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim() == "0")
+        .unwrap_or(false);
+    let content_type = match req.header("content-type").and_then(|v| v.to_str().ok()) {
+        Some(ct) if !is_empty => ct.to_string(),
+        _ => return None,
+    };
+    
+    if is_json_media_type(&content_type) {
+        None
+    } else {
+        eprintln!("[WARN] Rejecting {} request body with Content-Type {}", req.path(), content_type);
+        Some(error_response(
+            &format!("Unsupported Content-Type '{}': send the request body as application/json", content_type),
+            415,
+        ))
+    }
+}
+
+// Route lookup key: one trailing slash is dropped so /players/ finds /players,
+// while "/" stays as is. Matching stays case-sensitive (/Players is a 404).
+fn normalize_path(path: &str) -> &str {
+    match path.strip_suffix('/') {
+        Some(trimmed) if !trimmed.is_empty() => trimmed,
+        _ => path,
+    }
+}
+
+fn accepts_msgpack(req: &Request) -> bool {
+    req.header("accept")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.contains("application/msgpack") || v.contains("application/x-msgpack"))
+        .unwrap_or(false)
+}
+
+// 200 response in MessagePack when the client asked for it, JSON otherwise
+// (including if msgpack encoding fails)
+fn negotiated_response(value: &serde_json::Value, wants_msgpack: bool) -> Result<Response, ErrorCode> {
+    if wants_msgpack {
+        match rmp_serde::to_vec_named(value) {
+            Ok(bytes) => {
+                return Response::builder()
+                    .header("Content-Type", "application/msgpack")
+                    .header("Access-Control-Allow-Origin", "*")
+                    .header("Vary", "Accept")
+                    .body(bytes)
+                    .build();
+            }
+            Err(e) => eprintln!("[WARN] MessagePack encoding failed, sending JSON: {}", e),
+        }
+    }
+    
+    Response::builder()
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .header("Vary", "Accept")
+        .body(value.to_string())
+        .build()
+}
+
+// Demo seeding: joins every valid entry of a JSON array in one request. Join
+// events are queued and flushed together at the end rather than published one
+// at a time; unlike /join, nothing is written to ORDS.
+fn handle_bulk_players(req: Request) -> Result<Response, ErrorCode> {
+    if let Some(rejection) = check_admin_token(&req) {
+        return rejection;
+    }
+    
+    let body = match req.body() {
+        Ok(body) => body,
+        Err(_) => return error_response("Failed to read request body", 400),
+    };
+    if let Some(rejection) = check_body_size(&body) {
+        return rejection;
+    }
+    let entries: Vec<Value> = match serde_json::from_slice(&body) {
+        Ok(entries) => entries,
+        Err(_) => return error_response("Expected a JSON array of players", 400),
+    };
+    
+    let players = get_game_state();
+    expire_idle_players(players);
+    let config = get_game_config();
+    let mut results = Vec::with_capacity(entries.len());
+    let mut imported = 0;
+    
+    for (index, entry) in entries.iter().enumerate() {
+        let mut player = match bulk_player_from_value(entry, &config) {
+            Ok(player) => player,
+            Err(error) => {
+                results.push(json!({"index": index, "player_id": entry["player_id"], "status": "failed", "error": error}));
+                continue;
+            }
+        };
+        
+        let live_count = players.values()
+            .filter(|p| p.alive && p.id != player.id)
+            .count();
+        if live_count >= config.max_players {
+            results.push(json!({"index": index, "player_id": player.id, "status": "failed", "error": "game_full"}));
+            continue;
+        }
+        
+        player.tick = advance_tick(players, &player.game_id);
+        queue_event(GameEvent::new("player_joined", &player.id).with_player(&player));
+        // Like /join, an ORDS failure leaves the in-memory player in place
+        if let Err(e) = save_player_to_ords(&player) {
+            eprintln!("[WARN] Failed to save bulk player {} to ORDS: {}", player.id, e);
+        }
+        results.push(json!({"index": index, "player_id": player.id, "status": "joined", "tick": player.tick}));
+        players.insert(player.id.clone(), player);
+        imported += 1;
+    }
+    
+    if imported > 0 {
+        if let Err(e) = save_game_state(players) {
+            eprintln!("[WARN] Failed to save game state after bulk import: {:?}", e);
+        }
+    }
+    let (published, pending) = flush_event_queue();
+    eprintln!("[INFO] Bulk import: {} of {} players joined ({} events published, {} pending)", imported, entries.len(), published, pending);
+    
+    let response = json!({
+        "status": "success",
+        "runtime": "wasmtime",
+        "castle": get_castle_name(),
+        "imported": imported,
+        "failed": entries.len() - imported,
+        "results": results,
+        "timestamp": get_timestamp()
+    });
+    
+    Response::builder()
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(response.to_string())
+        .build()
+}
+
+// Validates one /players/bulk entry. Only player_id is required; the rest
+// falls back to what /join would use.
+fn bulk_player_from_value(entry: &Value, config: &GameConfig) -> Result<Player, String> {
+    let id = player_id_from_json(&entry["player_id"])?;
+    
+    let direction = match entry["direction"].as_str() {
+        Some(dir) => Direction::parse(dir).ok_or_else(|| "Invalid direction".to_string())?,
+        None => Direction::Up,
+    };
+    
+    let x = entry["x"].as_f64().unwrap_or(config.spawn_x);
+    let y = entry["y"].as_f64().unwrap_or(config.spawn_y);
+    if !in_arena(Position { x, y }, config) {
+        return Err("Position outside the arena".to_string());
+    }
+    
+    let score = match &entry["score"] {
+        Value::Null => 0,
+        value => match value.as_i64() {
+            Some(score) if score >= 0 && score <= i32::MAX as i64 => score as i32,
+            _ => return Err("Invalid score".to_string()),
+        },
+    };
+    
+    Ok(Player {
+        id,
+        x,
+        y,
+        direction: direction.as_str().to_string(),
+        score,
+        color: config.color.clone(),
+        alive: true,
+        game_id: entry["game_id"].as_str().map(str::to_string).unwrap_or_else(get_default_game_id),
+        last_seen: get_timestamp(),
+        joined_at: get_timestamp(),
+        trail: Vec::new(),
+        stats: PlayerStats::default(),
+        tick: 0,
+        last_move_at: 0,
+    })
+}
+
+// /game-state/export blobs. The layout matches wasmedge's, which is what lets
+// a game move between runtimes; bump the version on any breaking change.
+const STATE_EXPORT_FORMAT: &str = "wasicycles-game-state";
+const STATE_EXPORT_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StateExport {
+    format: String,
+    version: u32,
+    #[serde(default)]
+    runtime: String,
+    #[serde(default)]
+    exported_at: u64,
+    // Exporter's GAME_ID, for reference; players keep their own game_id
+    #[serde(default = "get_default_game_id")]
+    game_id: String,
+    players: HashMap<String, Player>,
+    #[serde(default)]
+    ticks: HashMap<String, u64>,
+}
+
+fn handle_export_state() -> Result<Response, ErrorCode> {
+    let players = get_game_state();
+    expire_idle_players(players);
+    let export = StateExport {
+        format: STATE_EXPORT_FORMAT.to_string(),
+        version: STATE_EXPORT_VERSION,
+        runtime: "wasmtime".to_string(),
+        exported_at: get_timestamp(),
+        game_id: get_default_game_id(),
+        players: players.clone(),
+        ticks: get_game_ticks().clone(),
+    };
+    
+    let body = match serde_json::to_string(&export) {
+        Ok(body) => body,
+        Err(e) => return error_response(&format!("Failed to serialize game state: {}", e), 500),
+    };
+    
+    Response::builder()
+        .status_code(200)
+        .header("Content-Type", "application/json")
+        .header("Content-Disposition", format!("attachment; filename=\"wasicycles-state-{}.json\"", export.exported_at))
+        .header("Access-Control-Allow-Origin", "*")
+        .body(body)
+        .build()
+}
+
+// Swaps in an exported game wholesale, or leaves state alone if any part of
+// the blob is rejected. Large games may need a higher MAX_BODY_BYTES.
+fn handle_import_state(req: Request) -> Result<Response, ErrorCode> {
+    if let Some(rejection) = check_admin_token(&req) {
+        return rejection;
+    }
+    
+    let body = match req.body() {
+        Ok(body) => body,
+        Err(_) => return error_response("Failed to read request body", 400),
+    };
+    if let Some(rejection) = check_body_size(&body) {
+        return rejection;
+    }
+    let export: StateExport = match serde_json::from_slice(&body) {
+        Ok(export) => export,
+        Err(e) => return error_response(&format!("Invalid game state export: {}", e), 400),
+    };
+    if let Err(problem) = validate_state_export(&export, &get_game_config()) {
+        return error_response(&problem, 400);
+    }
+    
+    let imported = export.players.len();
+    let players = get_game_state();
+    *players = export.players;
+    *get_game_ticks() = export.ticks;
+    if let Err(e) = save_game_state(players) {
+        eprintln!("[WARN] Failed to save game state after import: {:?}", e);
+    }
+    eprintln!("[INFO] Imported {} players from a {} export of {}", imported, export.runtime, export.game_id);
+    
+    let response = json!({
+        "status": "success",
+        "runtime": "wasmtime",
+        "castle": get_castle_name(),
+        "imported": imported,
+        "source_runtime": export.runtime,
+        "exported_at": export.exported_at,
+        "timestamp": get_timestamp()
+    });
+    
+    Response::builder()
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(response.to_string())
+        .build()
+}
+
+fn validate_state_export(export: &StateExport, config: &GameConfig) -> Result<(), String> {
+    if export.format != STATE_EXPORT_FORMAT {
+        return Err(format!("Unrecognized export format '{}'", export.format));
+    }
+    if export.version != STATE_EXPORT_VERSION {
+        return Err(format!("Unsupported export version {}", export.version));
+    }
+    
+    for (key, player) in &export.players {
+        if player.id.trim().is_empty() || *key != player.id {
+            return Err(format!("Player entry '{}' does not match its id '{}'", key, player.id));
+        }
+        if Direction::parse(&player.direction).is_none() {
+            return Err(format!("Player {} has an invalid direction", player.id));
+        }
+        if player.score < 0 {
+            return Err(format!("Player {} has a negative score", player.id));
+        }
+        let head = Position { x: player.x, y: player.y };
+        if !in_arena(head, config) || player.trail.iter().any(|cell| !in_arena(*cell, config)) {
+            return Err(format!("Player {} lies outside the arena", player.id));
+        }
+        // Trails grow a cell per move and stop one step short of the head, so
+        // walking trail-then-head must be a chain of valid moves
+        let path: Vec<Position> = player.trail.iter().copied().chain(std::iter::once(head)).collect();
+        for hop in path.windows(2) {
+            validate_move(hop[0], hop[1], config)
+                .map_err(|e| format!("Player {} has an impossible trail: {}", player.id, e))?;
         }
-    } else {
-        eprintln!("[WARN] TxEventQ consume request failed");
     }
     
-    // If we reached here, either there were no messages or an error occurred
-    vec![json!({
-        "message": "Either no messages available or error in consumption",
-        "consumed_by": "wasmtime",
-        "consumed_at": get_timestamp(),
-        "instance_id": consumer_instance_id,
-        "note": "Updated to use correct instance_id pattern"
-    })]
+    let alive = export.players.values().filter(|p| p.alive).count();
+    if alive > config.max_players {
+        return Err(format!("Export has {} live players, more than MAX_PLAYERS ({})", alive, config.max_players));
+    }
+    Ok(())
 }
 
-// TxEventQ Event Sourcing for State Reconstruction
-// This addresses Wasmtime's static variable reset between requests
-fn reconstruct_state_from_events() -> HashMap<String, Player> {
-    unsafe {
-        let current_time = get_timestamp();
-        
-        // Rate limit: only allow reconstruction once every 10 seconds to prevent excessive calls
-        if current_time - LAST_RECONSTRUCTION_TIME < 10000 {
-            eprintln!("[INFO] Skipping state reconstruction due to rate limit (last: {}ms ago)", current_time - LAST_RECONSTRUCTION_TIME);
-            return HashMap::new();
+// MAX_BODY_BYTES caps request bodies (default 64 KiB)
+fn get_max_body_bytes() -> usize {
+    std::env::var("MAX_BODY_BYTES")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(64 * 1024)
+}
+
+fn payload_too_large_response(limit: usize) -> Result<Response, ErrorCode> {
+    error_response(&format!("Request body exceeds MAX_BODY_BYTES ({} bytes)", limit), 413)
+}
+
+// Rejects a declared Content-Length over the limit before any handler reads
+// the body. waki only hands bodies over whole, so check_body_size repeats the
+// check on what was actually read (chunked uploads declare no length).
+fn check_declared_body_size(req: &Request) -> Option<Result<Response, ErrorCode>> {
+    let limit = get_max_body_bytes();
+    let declared = req.header("content-length")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<usize>().ok())?;
+    if declared > limit {
+        eprintln!("[WARN] Rejecting {} byte body for {} (limit {})", declared, req.path(), limit);
+        return Some(payload_too_large_response(limit));
+    }
+    None
+}
+
+fn check_body_size(body: &[u8]) -> Option<Result<Response, ErrorCode>> {
+    let limit = get_max_body_bytes();
+    if body.len() > limit {
+        return Some(payload_too_large_response(limit));
+    }
+    None
+}
+
+// application/json, or a +json suffix type, with any parameters (charset etc.) ignored
+fn is_json_media_type(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+    essence == "application/json" || essence.ends_with("+json")
+}
+
+// Admin-only endpoints require ADMIN_TOKEN to be configured and sent as X-Admin-Token.
+// Returns the rejection response when the request isn't authorized.
+fn check_admin_token(req: &Request) -> Option<Result<Response, ErrorCode>> {
+    let expected = match std::env::var("ADMIN_TOKEN") {
+        Ok(token) if !token.is_empty() => token,
+        _ => {
+            return Some(error_response("Admin endpoints are disabled (ADMIN_TOKEN not set)", 403));
         }
-        
-        LAST_RECONSTRUCTION_TIME = current_time;
+    };
+    
+    let provided = req.header("x-admin-token")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    
+    if provided == expected {
+        None
+    } else {
+        Some(error_response("Invalid or missing X-Admin-Token", 401))
     }
+}
+
+fn handle_consumers() -> Result<Response, ErrorCode> {
+    // Note: like the other statics, this cache only covers consumers created
+    // since the current instance started
+    let consumers = get_created_consumers().clone();
     
-    let mut reconstructed_state = HashMap::new();
+    let response = json!({
+        "runtime": "wasmtime",
+        "castle": get_castle_name(),
+        "consumers": consumers,
+        "count": consumers.len(),
+        "timestamp": get_timestamp()
+    });
     
-    eprintln!("[INFO] Reconstructing game state from TxEventQ events due to Wasmtime static reset");
+    Response::builder()
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(response.to_string())
+        .build()
+}
+
+fn handle_delete_consumer(req: &Request) -> Result<Response, ErrorCode> {
+    if let Some(rejection) = check_admin_token(req) {
+        return rejection;
+    }
     
-    // Consume recent events from TxEventQ to rebuild state
-    let messages = consume_recent_game_events();
+    // Expected path: /consumers/{group}/{instance}
+    let segments: Vec<&str> = req.path()
+        .trim_start_matches("/consumers/")
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect();
+    let (group, instance_id) = match segments.as_slice() {
+        [group, instance_id] => (group.to_string(), instance_id.to_string()),
+        _ => {
+            return error_response("Expected /consumers/{group}/{instance}", 400);
+        }
+    };
     
-    for message in messages {
-        if let Some(event_data) = message.get("data") {
-            if let Ok(event) = serde_json::from_value::<GameEvent>(event_data.clone()) {
-                match event.event_type.as_str() {
-                    "player_snapshot" => {
-                        // State snapshots have the most recent player state
-                        let player = Player {
-                            id: event.player_id.clone(),
-                            x: event.position.as_ref().map(|p| p.x).unwrap_or(25.0),
-                            y: event.position.as_ref().map(|p| p.y).unwrap_or(25.0),
-                            direction: event.direction.unwrap_or_else(|| "up".to_string()),
-                            score: event.score.unwrap_or(0),
-                            color: "#06b6d4".to_string(), // Wasmtime color
-                            alive: true,
-                        };
-                        reconstructed_state.insert(event.player_id.clone(), player);
-                        eprintln!("[DEBUG] Reconstructed player {} from snapshot", event.player_id);
-                    },
-                    "player_joined" => {
-                        // Only use join events if we don't have a snapshot
-                        if !reconstructed_state.contains_key(&event.player_id) {
-                            let player = Player {
-                                id: event.player_id.clone(),
-                                x: event.position.as_ref().map(|p| p.x).unwrap_or(25.0),
-                                y: event.position.as_ref().map(|p| p.y).unwrap_or(25.0),
-                                direction: event.direction.unwrap_or_else(|| "up".to_string()),
-                                score: event.score.unwrap_or(0),
-                                color: "#06b6d4".to_string(), // Wasmtime color
-                                alive: true,
-                            };
-                            reconstructed_state.insert(event.player_id.clone(), player);
-                            eprintln!("[DEBUG] Reconstructed player {} from join event", event.player_id);
-                        }
-                    },
-                    "player_moved" => {
-                        if let Some(player) = reconstructed_state.get_mut(&event.player_id) {
-                            if let Some(pos) = &event.position {
-                                player.x = pos.x;
-                                player.y = pos.y;
-                            }
-                            if let Some(dir) = &event.direction {
-                                player.direction = dir.clone();
-                            }
-                            if let Some(score) = event.score {
-                                player.score = score;
-                            }
-                            eprintln!("[DEBUG] Updated player {} from move event", event.player_id);
-                        } else {
-                            // Player not found, create from move event
-                            let player = Player {
-                                id: event.player_id.clone(),
-                                x: event.position.as_ref().map(|p| p.x).unwrap_or(25.0),
-                                y: event.position.as_ref().map(|p| p.y).unwrap_or(25.0),
-                                direction: event.direction.unwrap_or_else(|| "up".to_string()),
-                                score: event.score.unwrap_or(0),
-                                color: "#06b6d4".to_string(),
-                                alive: true,
-                            };
-                            reconstructed_state.insert(event.player_id.clone(), player);
-                            eprintln!("[DEBUG] Created player {} from move event", event.player_id);
-                        }
-                    },
-                    "player_left" => {
-                        reconstructed_state.remove(&event.player_id);
-                        eprintln!("[DEBUG] Removed player {} from leave event", event.player_id);
-                    },
-                    _ => {
-                        // Ignore other event types for now
-                    }
-                }
+    match delete_oracle_consumer(&group, &instance_id) {
+        Ok(found) => {
+            get_created_consumers().retain(|c| !(c.group == group && c.instance_id == instance_id));
+            
+            if !found {
+                return error_response("Consumer instance not found in Oracle", 404);
             }
+            
+            let response = json!({
+                "status": "success",
+                "runtime": "wasmtime",
+                "castle": get_castle_name(),
+                "deleted": {
+                    "group": group,
+                    "instance_id": instance_id
+                },
+                "timestamp": get_timestamp()
+            });
+            
+            Response::builder()
+                .header("Content-Type", "application/json")
+                .header("Access-Control-Allow-Origin", "*")
+                .body(response.to_string())
+                .build()
+        }
+        Err(e) => {
+            eprintln!("[ERROR] Failed to delete consumer {}/{}: {}", group, instance_id, e);
+            error_response("Failed to delete consumer instance", 502)
         }
     }
-    
-    let player_count = reconstructed_state.len();
-    if player_count > 0 {
-        eprintln!("[INFO] Reconstructed state with {} players from TxEventQ events", player_count);
-        let player_ids: Vec<&String> = reconstructed_state.keys().collect();
-        eprintln!("[INFO] Reconstructed players: {:?}", player_ids);
-    } else {
-        eprintln!("[INFO] No players found in TxEventQ event history");
-    }
-    
-    reconstructed_state
 }
 
-// Consume recent game events for state reconstruction
-fn consume_recent_game_events() -> Vec<Value> {
+// Deletes every document in the ORDS scores collection (q={} matches all).
+// Returns the ORDS status and, when reported, the number of rows deleted.
+fn reset_leaderboard_ords() -> Result<(u16, Option<u64>), String> {
     let client = Client::new();
-    let consumer_group_id = "wasmtime_state_reconstruction";
-    let consumer_id = "wasmtime_consumer_state";
-    let auth = base64_encode(&format!("{}:{}", get_oracle_user(), get_oracle_password()));
+    let auth = oracle_auth_header();
+    let url = format!("{}/scores/?q=%7B%7D", get_ords_url());
     
-    let mut messages = Vec::new();
+    let response = client
+        .delete(&url)
+        .headers([
+            ("Accept", "application/json"),
+            ("Authorization", &auth)
+        ])
+        .send()
+        .map_err(|e| format!("Leaderboard reset request failed: {}", e))?;
     
-    // Try to consume from a dedicated topic for state reconstruction if available
-    // Otherwise use the main game events topic
-    let topics_to_try = vec![
-        format!("{}_STATE", get_kafka_topic()), // Try dedicated state topic first
-        get_kafka_topic(),                       // Fall back to main topic
+    let status_code = response.status_code();
+    let rows_deleted = response.body().ok()
+        .and_then(|body| serde_json::from_slice::<Value>(&body).ok())
+        .and_then(|v| v["rowsDeleted"].as_u64());
+    
+    Ok((status_code, rows_deleted))
+}
+
+// Removes a player's ORDS row (players/{id}) and their documents in the scores
+// collection. A 404 for the row is fine: not every player was saved there.
+fn delete_player_from_ords(player_id: &str) -> Result<(), String> {
+    let client = Client::new();
+    let auth = oracle_auth_header();
+    let filter = json!({ "player_id": player_id }).to_string();
+    let urls = [
+        format!("{}/players/{}", get_ords_url(), percent_encode(player_id)),
+        format!("{}/scores/?q={}", get_ords_url(), percent_encode(&filter)),
     ];
     
-    for topic in topics_to_try {
-        // Create consumer group for this topic
-        let create_group_url = format!("{}/clusters/{}/consumer-groups/{}", 
-            get_txeventq_base_url(), get_oracle_db_name(), consumer_group_id);
-        
-        let group_payload = json!({
-            "topic_name": topic
-        });
-        
-        let _group_result = client
-            .post(&create_group_url)
-            .headers([
-                ("Content-Type", "application/json"),
-                ("Authorization", &format!("Basic {}", auth))
-            ])
-            .body(group_payload.to_string().as_bytes().to_vec())
-            .send();
-        
-        // Create consumer
-        let create_consumer_url = format!("{}/clusters/{}/consumer-groups/{}/consumers/{}", 
-            get_txeventq_base_url(), get_oracle_db_name(), consumer_group_id, consumer_id);
-        
-        let _consumer_result = client
-            .post(&create_consumer_url)
-            .headers([
-                ("Content-Type", "application/json"),
-                ("Authorization", &format!("Basic {}", auth))
-            ])
-            .body("{}".as_bytes().to_vec())
-            .send();
-        
-        // Subscribe to topic
-        let subscribe_url = format!("{}/clusters/{}/consumer-groups/{}/consumers/{}/subscription", 
-            get_txeventq_base_url(), get_oracle_db_name(), consumer_group_id, consumer_id);
-        
-        let subscription_payload = json!({
-            "topic_name": topic
-        });
-        
-        let _subscribe_result = client
-            .post(&subscribe_url)
-            .headers([
-                ("Content-Type", "application/json"),
-                ("Authorization", &format!("Basic {}", auth))
-            ])
-            .body(subscription_payload.to_string().as_bytes().to_vec())
-            .send();
-        
-        // Consume records with a limit to get recent events
-        let consume_url = format!("{}/clusters/{}/consumer-groups/{}/consumers/{}/records?limit=100", 
-            get_txeventq_base_url(), get_oracle_db_name(), consumer_group_id, consumer_id);
-        
-        if let Ok(resp) = client
-            .get(&consume_url)
+    for url in &urls {
+        let response = client
+            .delete(url)
             .headers([
                 ("Accept", "application/json"),
-                ("Authorization", &format!("Basic {}", auth))
+                ("Authorization", &auth)
             ])
-            .send() 
-        {
-            if resp.status_code() >= 200 && resp.status_code() < 300 {
-                if let Ok(body) = resp.body() {
-                    if let Ok(body_str) = String::from_utf8(body.clone()) {
-                        if !body_str.is_empty() && body_str != "[]" {
-                            if let Ok(records) = serde_json::from_slice::<Vec<Value>>(&body) {
-                                for record in records {
-                                    let value = record.get("value").cloned().unwrap_or(Value::Null);
-                                    let value_str = if let Value::String(s) = &value {
-                                        s.clone()
-                                    } else {
-                                        serde_json::to_string(&value).unwrap_or_default()
-                                    };
-                                    
-                                    let parsed_value = serde_json::from_str(&value_str).unwrap_or(value.clone());
-                                    
-                                    messages.push(json!({
-                                        "topic": record.get("topic").cloned().unwrap_or(Value::Null),
-                                        "data": parsed_value,
-                                        "reconstructed_from": topic
-                                    }));
-                                }
-                                
-                                if !messages.is_empty() {
-                                    eprintln!("[INFO] Found {} events in topic {} for state reconstruction", messages.len(), topic);
-                                    break; // Found events, no need to try other topics
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+            .send()
+            .map_err(|e| format!("ORDS delete request failed: {}", e))?;
+        let status_code = response.status_code();
+        if !(200..300).contains(&status_code) && status_code != 404 {
+            return Err(format!("ORDS delete {} returned {}", url, status_code));
+        }
+    }
+    Ok(())
+}
+
+// Query-string encoding: RFC 3986 unreserved bytes pass through, the rest
+// become %XX
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for b in value.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'~') {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", b));
         }
     }
+    encoded
+}
+
+// Delete a consumer instance in Oracle. Ok(false) means Oracle didn't know it.
+fn delete_oracle_consumer(group: &str, instance_id: &str) -> Result<bool, String> {
+    let client = Client::new();
+    let auth = oracle_auth_header();
+    let url = consumer_delete_url(group, instance_id);
     
-    // Sort messages by timestamp if available to replay in order
-    messages.sort_by(|a, b| {
-        let ts_a = a.get("data")
-            .and_then(|d| d.get("timestamp"))
-            .and_then(|t| t.as_u64())
-            .unwrap_or(0);
-        let ts_b = b.get("data")
-            .and_then(|d| d.get("timestamp"))
-            .and_then(|t| t.as_u64())
-            .unwrap_or(0);
-        ts_a.cmp(&ts_b)
-    });
+    let response = client
+        .delete(&url)
+        .headers([
+            ("Accept", "application/json"),
+            ("Authorization", &auth)
+        ])
+        .send()
+        .map_err(|e| format!("Consumer delete request failed: {}", e))?;
+    
+    let status_code = response.status_code();
     
-    messages
+    if (200..300).contains(&status_code) {
+        eprintln!("[INFO] Deleted consumer instance {}/{}", group, instance_id);
+        Ok(true)
+    } else if status_code == 404 {
+        eprintln!("[INFO] Consumer instance {}/{} not found in Oracle", group, instance_id);
+        Ok(false)
+    } else {
+        let error_body = response.body().unwrap_or_default();
+        Err(format!("Oracle consumer delete error: {} - {}", status_code, String::from_utf8_lossy(&error_body)))
+    }
 }
 
 // Oracle integration functions
 fn get_created_topics() -> &'static mut std::collections::HashSet<String> {
-    unsafe {
-        if CREATED_TOPICS.is_none() {
-            CREATED_TOPICS = Some(std::collections::HashSet::new());
-        }
-        CREATED_TOPICS.as_mut().unwrap()
-    }
+    unsafe { (*std::ptr::addr_of_mut!(CREATED_TOPICS)).get_or_insert_with(std::collections::HashSet::new) }
 }
 
 fn create_txeventq_topic(topic_name: &str) -> Result<(), String> {
@@ -1661,17 +5154,17 @@ fn create_txeventq_topic(topic_name: &str) -> Result<(), String> {
     let client = Client::new();
     let topic_config = json!({
         "topic_name": topic_name,
-        "partitions_count": "1"
+        "partitions_count": get_kafka_partitions().to_string()
     });
     
-    let auth = base64_encode(&format!("{}:{}", get_oracle_user(), get_oracle_password()));
+    let auth = oracle_auth_header();
     let url = format!("{}/clusters/{}/topics", get_txeventq_base_url(), get_oracle_db_name());
 
     let response = client
         .post(&url)
         .headers([
             ("Content-Type", "application/json"),
-            ("Authorization", &format!("Basic {}", auth))
+            ("Authorization", &auth)
         ])
         .body(topic_config.to_string().as_bytes().to_vec())
         .send()
@@ -1705,28 +5198,80 @@ fn create_txeventq_topic(topic_name: &str) -> Result<(), String> {
     }
 }
 
-fn publish_to_oracle_kafka(event: &GameEvent) -> Result<(), String> {
+// Optional HMAC signing of published events (EVENT_SIGNING_KEY). Consumers
+// holding the key ignore events that aren't signed with it.
+fn event_signing_key() -> Option<String> {
+    std::env::var("EVENT_SIGNING_KEY").ok().filter(|key| !key.is_empty())
+}
+
+// Hex HMAC-SHA256 of the event JSON with "signature" removed. Keys serialize in
+// sorted order, which keeps this byte-for-byte identical to wasmedge's.
+fn event_signature(key: &str, event: &Value) -> String {
+    let mut unsigned = event.clone();
+    if let Some(fields) = unsigned.as_object_mut() {
+        fields.remove("signature");
+    }
+    hmac_sha256(key.as_bytes(), unsigned.to_string().as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn event_payload(event: &GameEvent) -> Result<String, serde_json::Error> {
+    let mut value = serde_json::to_value(event)?;
+    if let Some(key) = event_signing_key() {
+        value["signature"] = json!(event_signature(&key, &value));
+    }
+    serde_json::to_string(&value)
+}
+
+fn has_valid_signature(event: &Value) -> bool {
+    let key = match event_signing_key() {
+        Some(key) => key,
+        None => return true,
+    };
+    let provided = match event["signature"].as_str() {
+        Some(signature) => signature,
+        None => return false,
+    };
+    let expected = event_signature(&key, event);
+    // Constant-time comparison
+    provided.len() == expected.len()
+        && provided.bytes().zip(expected.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+// Returns the record's partition/offset when TxEventQ reports one
+fn publish_to_oracle_kafka(event: &GameEvent) -> Result<Option<PublishReceipt>, String> {
+    publish_to_topic(&get_kafka_topic(), event)
+}
+
+fn publish_to_topic(topic: &str, event: &GameEvent) -> Result<Option<PublishReceipt>, String> {
     let client = Client::new();
     
     // Oracle TxEventQ REST API format (key difference: value must be JSON string, not object)
     let kafka_payload = json!({
         "records": [{
             "key": format!("wasmtime-{}", get_timestamp()),
-            "value": serde_json::to_string(event).map_err(|e| format!("JSON serialization failed: {}", e))?  // JSON string, not object!
+            "value": event_payload(event).map_err(|e| format!("JSON serialization failed: {}", e))?  // JSON string, not object!
         }]
     });
     
-    let auth = base64_encode(&format!("{}:{}", get_oracle_user(), get_oracle_password()));
+    let auth = oracle_auth_header();
+    
+    // Cached per instance, so this is a round trip only on first use
+    if let Err(e) = create_txeventq_topic(topic) {
+        eprintln!("[WARN] Topic creation failed, attempting to publish anyway: {}", e);
+    }
     
     // Use the same base URL pattern as wasmedge
-    let url = format!("{}/topics/{}", get_txeventq_base_url(), get_kafka_topic());
+    let url = format!("{}/topics/{}", get_txeventq_base_url(), topic);
 
     let response = client
         .post(&url)
         .headers([
             ("Content-Type", "application/json"),
             ("Accept", "application/json"),
-            ("Authorization", &format!("Basic {}", auth))
+            ("Authorization", &auth)
         ])
         .body(kafka_payload.to_string().as_bytes().to_vec())
         .send()
@@ -1738,7 +5283,17 @@ fn publish_to_oracle_kafka(event: &GameEvent) -> Result<(), String> {
         // Log success (similar to wasmedge pattern)
         eprintln!("✅ TxEventQ message published from Wasmtime: {} for player {}", 
                 event.event_type, event.player_id);
-        Ok(())
+        let response_body = response.body().unwrap_or_default();
+        let receipt = parse_publish_receipt(&String::from_utf8_lossy(&response_body));
+        if is_debug_enabled() {
+            match receipt {
+                Some(r) => eprintln!("[DEBUG] {} for player {} at partition {} offset {}",
+                        event.event_type, event.player_id, r.partition, r.offset),
+                None => eprintln!("[DEBUG] {} for player {}: no offset in TxEventQ response",
+                        event.event_type, event.player_id),
+            }
+        }
+        Ok(receipt)
     } else {
         // Get error details
         let error_body = response.body().unwrap_or_default();
@@ -1748,6 +5303,19 @@ fn publish_to_oracle_kafka(event: &GameEvent) -> Result<(), String> {
     }
 }
 
+// TxEventQ answers a produce call Kafka REST style:
+// {"offsets":[{"partition":0,"offset":42}]}. We publish one record per call,
+// so the first entry is ours. Offsets may come back as strings.
+fn parse_publish_receipt(response_text: &str) -> Option<PublishReceipt> {
+    let body: serde_json::Value = serde_json::from_str(response_text).ok()?;
+    let entry = body["offsets"].get(0)?;
+    let as_i64 = |v: &serde_json::Value| v.as_i64().or_else(|| v.as_str()?.parse().ok());
+    Some(PublishReceipt {
+        partition: as_i64(&entry["partition"])?,
+        offset: as_i64(&entry["offset"])?,
+    })
+}
+
 fn update_player_score_ords(player_id: &str, score: i32, game_id: &str) -> Result<(), String> {
     let client = Client::new();
     let score_data = json!({
@@ -1755,18 +5323,18 @@ fn update_player_score_ords(player_id: &str, score: i32, game_id: &str) -> Resul
         "score": score,
         "game_id": game_id,
         "runtime": "wasmtime",
-        "castle": "Temporal Sanctuary",
+        "castle": get_castle_name(),
         "timestamp": get_timestamp()
     });
 
-    let auth = base64_encode(&format!("{}:{}", get_oracle_user(), get_oracle_password()));
+    let auth = oracle_auth_header();
     let url = format!("{}/scores/", get_ords_url());
 
     let response = client
         .post(&url)
         .headers([
             ("Content-Type", "application/json"),
-            ("Authorization", &format!("Basic {}", auth))
+            ("Authorization", &auth)
         ])
         .body(score_data.to_string().as_bytes().to_vec())
         .send()
@@ -1781,28 +5349,65 @@ fn update_player_score_ords(player_id: &str, score: i32, game_id: &str) -> Resul
     }
 }
 
-fn get_leaderboard_ords() -> Result<String, String> {
-    let client = Client::new();
-    let auth = base64_encode(&format!("{}:{}", get_oracle_user(), get_oracle_password()));
-    let url = format!("{}/leaderboard/", get_ords_url());
-
-    let response = client
-        .get(&url)
+// ORDS answering at all (even 404 for a missing collection) means the
+// database is up; only transport failures and 5xx count as down
+fn probe_oracle() -> Result<(), String> {
+    let auth = oracle_auth_header();
+    let response = Client::new()
+        .get(&format!("{}/leaderboard/", get_ords_url()))
         .headers([
             ("Accept", "application/json"),
-            ("Authorization", &format!("Basic {}", auth))
+            ("Authorization", &auth)
         ])
         .send()
         .map_err(|e| format!("Request failed: {}", e))?;
-
-    let status_code = response.status_code();
     
-    if status_code >= 200 && status_code < 300 {
+    if response.status_code() >= 500 {
+        return Err(format!("ORDS returned {}", response.status_code()));
+    }
+    Ok(())
+}
+
+// Every leaderboard row, following ORDS "next" links up to ORDS_MAX_PAGES.
+// hasMore in the result is only true when that cap cut the listing short;
+// partial (with the failure under "error") when a page after the first failed.
+fn get_leaderboard_ords() -> Result<Value, String> {
+    let client = Client::new();
+    let auth = oracle_auth_header();
+    let url = format!("{}/leaderboard/", get_ords_url());
+    let listing = collect_ords_pages(url, |url| {
+        let response = client
+            .get(url)
+            .headers([
+                ("Accept", "application/json"),
+                ("Authorization", &auth)
+            ])
+            .send()
+            .map_err(|e| format!("Request failed: {}", e))?;
+        
+        let status_code = response.status_code();
+        if !(200..300).contains(&status_code) {
+            return Err(format!("ORDS leaderboard error: {}", status_code));
+        }
+        
         let body = response.body().map_err(|e| format!("Failed to read body: {}", e))?;
-        String::from_utf8(body).map_err(|e| format!("Invalid UTF-8: {}", e))
-    } else {
-        Err(format!("ORDS leaderboard error: {}", status_code))
+        serde_json::from_slice(&body).map_err(|e| format!("Invalid leaderboard JSON: {}", e))
+    })?;
+    
+    Ok(leaderboard_listing(listing))
+}
+
+fn leaderboard_listing(listing: OrdsListing) -> Value {
+    let mut result = json!({
+        "items": listing.rows,
+        "count": listing.rows.len(),
+        "hasMore": listing.truncated,
+        "partial": listing.partial.is_some()
+    });
+    if let Some(error) = listing.partial {
+        result["error"] = json!(error);
     }
+    result
 }
 
 // Function to save player data to Oracle ORDS
@@ -1816,7 +5421,7 @@ fn save_player_to_ords(player: &Player) -> Result<(), String> {
         }
     };
     
-    let auth = base64_encode(&format!("{}:{}", get_oracle_user(), get_oracle_password()));
+    let auth = oracle_auth_header();
     let url = format!("{}/players/", get_ords_url());
 
     eprintln!("[INFO] Saving player {} to ORDS", player.id);
@@ -1826,7 +5431,7 @@ fn save_player_to_ords(player: &Player) -> Result<(), String> {
         .post(&url)
         .headers([
             ("Content-Type", "application/json"),
-            ("Authorization", &format!("Basic {}", auth))
+            ("Authorization", &auth)
         ])
         .body(player_data.to_string().as_bytes().to_vec())
         .send() {
@@ -1861,215 +5466,189 @@ fn save_player_to_ords(player: &Player) -> Result<(), String> {
     }
 }
 
-// Function to fetch player data from Oracle ORDS
-fn fetch_player_from_ords(player_id: &str) -> Result<Player, String> {
+// STRICT_ORDS=true refuses a join whose returning-player lookup in ORDS
+// failed, instead of spawning the player afresh with score 0
+fn is_strict_ords() -> bool {
+    std::env::var("STRICT_ORDS").map(|v| v == "true").unwrap_or(false)
+}
+
+// The player's ORDS row, or None on 404. Never fabricates a player, so callers
+// can tell a real record from a miss.
+fn lookup_player_in_ords(player_id: &str) -> Result<Option<Player>, String> {
     let client = Client::new();
-    let auth = base64_encode(&format!("{}:{}", get_oracle_user(), get_oracle_password()));
+    let auth = oracle_auth_header();
     let url = format!("{}/players/{}", get_ords_url(), player_id);
 
     eprintln!("[INFO] Fetching player {} from ORDS", player_id);
     
-    // Get the status code first before consuming the response
-    let response = match client
+    let response = client
         .get(&url)
         .headers([
             ("Accept", "application/json"),
-            ("Authorization", &format!("Basic {}", auth))
+            ("Authorization", &auth)
         ])
-        .send() {
-            Ok(resp) => resp,
-            Err(e) => {
-                eprintln!("[WARN] ORDS player fetch request failed: {} - returning dummy player", e);
-                // Return dummy player instead of error
-                return Ok(Player {
-                    id: player_id.to_string(),
-                    x: 25.0,
-                    y: 25.0,
-                    direction: "up".to_string(),
-                    score: 0,
-                    color: "#06b6d4".to_string(), // Cyan for Wasmtime
-                    alive: true,
-                });
-            }
-        };
+        .send()
+        .map_err(|e| format!("ORDS player fetch request failed: {}", e))?;
 
     let status_code = response.status_code();
     
     if status_code >= 200 && status_code < 300 {
-        let body = match response.body() {
-            Ok(b) => b,
-            Err(e) => {
-                eprintln!("[WARN] Failed to read ORDS response body: {} - returning dummy player", e);
-                // Return dummy player
-                return Ok(Player {
-                    id: player_id.to_string(),
-                    x: 25.0,
-                    y: 25.0,
-                    direction: "up".to_string(),
-                    score: 0,
-                    color: "#06b6d4".to_string(),
-                    alive: true,
-                });
-            }
-        };
-        
-        let body_str = match String::from_utf8(body) {
-            Ok(s) => s,
-            Err(e) => {
-                eprintln!("[WARN] Failed to parse ORDS response as UTF-8: {} - returning dummy player", e);
-                // Return dummy player
-                return Ok(Player {
-                    id: player_id.to_string(),
-                    x: 25.0,
-                    y: 25.0,
-                    direction: "up".to_string(),
-                    score: 0,
-                    color: "#06b6d4".to_string(),
-                    alive: true,
-                });
-            }
-        };
-        
-        match serde_json::from_str::<Player>(&body_str) {
-            Ok(player) => {
-                eprintln!("[INFO] Successfully fetched player {} from ORDS", player_id);
-                return Ok(player);
-            },
-            Err(e) => {
-                eprintln!("[WARN] Failed to parse ORDS player data: {} - returning dummy player", e);
-                // Return dummy player
-                return Ok(Player {
-                    id: player_id.to_string(),
-                    x: 25.0,
-                    y: 25.0,
-                    direction: "up".to_string(),
-                    score: 0,
-                    color: "#06b6d4".to_string(),
-                    alive: true,
-                });
-            }
-        }
+        let body = response.body()
+            .map_err(|e| format!("Failed to read ORDS response body: {}", e))?;
+        let body_str = String::from_utf8(body)
+            .map_err(|e| format!("Failed to parse ORDS response as UTF-8: {}", e))?;
+        let row = serde_json::from_str::<Value>(&body_str)
+            .map_err(|e| format!("Failed to parse ORDS player data: {}", e))?;
+        let player = serde_json::from_value::<Player>(normalize_ords_row(row))
+            .map_err(|e| format!("Failed to parse ORDS player data: {}", e))?;
+        eprintln!("[INFO] Successfully fetched player {} from ORDS", player_id);
+        Ok(Some(player))
     } else if status_code == 404 {
-        // 404 means player not found - return dummy player
-        eprintln!("[INFO] Player {} not found in ORDS (404) - returning dummy player", player_id);
-        return Ok(Player {
-            id: player_id.to_string(),
-            x: 25.0,
-            y: 25.0,
-            direction: "up".to_string(),
-            score: 0,
-            color: "#06b6d4".to_string(),
-            alive: true,
-        });
+        Ok(None)
     } else if status_code == 405 {
-        // 405 means method not allowed - return dummy player
-        eprintln!("[INFO] ORDS doesn't allow GET for player endpoint (405) - returning dummy player");
-        return Ok(Player {
-            id: player_id.to_string(),
-            x: 25.0,
-            y: 25.0,
-            direction: "up".to_string(),
-            score: 0,
-            color: "#06b6d4".to_string(),
-            alive: true,
-        });
+        // 405 means method not allowed
+        Err("ORDS doesn't allow GET for player endpoint (405)".to_string())
     } else {
-        // Other error codes - return dummy player
-        eprintln!("[WARN] ORDS player fetch error: {} - returning dummy player", status_code);
-        return Ok(Player {
-            id: player_id.to_string(),
-            x: 25.0,
-            y: 25.0,
-            direction: "up".to_string(),
-            score: 0,
-            color: "#06b6d4".to_string(),
-            alive: true,
-        });
+        Err(format!("ORDS player fetch error: {}", status_code))
     }
 }
 
+// Upper bound on ORDS pages followed via "next" links in one fetch
+const ORDS_MAX_PAGES: usize = 20;
+
 // Function to fetch all players from Oracle ORDS
 fn fetch_all_players_from_ords() -> Result<HashMap<String, Player>, String> {
     let client = Client::new();
-    let auth = base64_encode(&format!("{}:{}", get_oracle_user(), get_oracle_password()));
+    let auth = oracle_auth_header();
     let url = format!("{}/players/", get_ords_url());
 
     eprintln!("[INFO] Fetching all players from ORDS");
     
-    // Get the status code first before consuming the response
-    let response = match client
-        .get(&url)
-        .headers([
-            ("Accept", "application/json"),
-            ("Authorization", &format!("Basic {}", auth))
-        ])
-        .send() {
-            Ok(resp) => resp,
-            Err(e) => {
-                eprintln!("[WARN] ORDS all players fetch request failed: {} - returning empty state", e);
-                return Ok(HashMap::new()); // Return dummy empty state instead of error
-            }
-        };
+    let listing = collect_ords_pages(url, |url| {
+        let response = client
+            .get(url)
+            .headers([
+                ("Accept", "application/json"),
+                ("Authorization", &auth)
+            ])
+            .send()
+            .map_err(|e| format!("ORDS all players fetch request failed: {}", e))?;
+
+        let status_code = response.status_code();
+        if status_code == 404 {
+            // Not an error condition: the collection just has nothing in it yet
+            eprintln!("[INFO] ORDS endpoint returned 404 - assuming no players exist yet");
+            return Ok(json!([]));
+        } else if status_code == 405 {
+            // For 405 Method Not Allowed, the endpoint doesn't support the HTTP method
+            return Err("ORDS endpoint returned 405 (Method Not Allowed)".to_string());
+        } else if !(200..300).contains(&status_code) {
+            return Err(format!("ORDS all players fetch error: {}", status_code));
+        }
+        
+        let body = response.body().map_err(|e| format!("Failed to read ORDS response body: {}", e))?;
+        serde_json::from_slice(&body).map_err(|e| format!("Failed to parse ORDS response JSON: {}", e))
+    });
+    let listing = listing?;
+    if let Some(e) = &listing.partial {
+        eprintln!("[WARN] Using the {} player rows ORDS returned before a page failed: {}", listing.rows.len(), e);
+    }
+    
+    let players_map: HashMap<String, Player> = listing.rows.into_iter()
+        .filter_map(|row| serde_json::from_value::<Player>(normalize_ords_row(row)).ok())
+        .map(|player| (player.id.clone(), player))
+        .collect();
+    
+    eprintln!("[INFO] Successfully loaded {} players from ORDS", players_map.len());
+    Ok(players_map)
+}
 
-    let status_code = response.status_code();
+// Rows of a paginated ORDS collection, as collect_ords_pages gathered them
+struct OrdsListing {
+    rows: Vec<Value>,
+    // ORDS_MAX_PAGES ran out while there were still more pages
+    truncated: bool,
+    // Why a page after the first failed; the rows before it are kept
+    partial: Option<String>,
+}
+
+// Walks an ORDS collection from `url`, with `fetch_page` doing each GET and
+// parse. A first page that fails fails the whole listing, since there is
+// nothing to show; a later failure keeps the earlier pages' rows instead.
+fn collect_ords_pages(mut url: String, mut fetch_page: impl FnMut(&str) -> Result<Value, String>) -> Result<OrdsListing, String> {
+    let mut listing = OrdsListing { rows: Vec::new(), truncated: false, partial: None };
     
-    if status_code >= 200 && status_code < 300 {
-        let body = match response.body() {
-            Ok(b) => b,
+    for page in 1..=ORDS_MAX_PAGES {
+        let json_value = match fetch_page(&url) {
+            Ok(json_value) => json_value,
+            Err(e) if page == 1 => return Err(e),
             Err(e) => {
-                eprintln!("[WARN] Failed to read ORDS response body: {} - returning empty state", e);
-                return Ok(HashMap::new()); // Return dummy empty state
+                eprintln!("[WARN] ORDS page {} of {} failed after {} rows: {}", page, url, listing.rows.len(), e);
+                listing.partial = Some(e);
+                break;
             }
         };
         
-        let body_str = match String::from_utf8(body) {
-            Ok(s) => s,
-            Err(e) => {
-                eprintln!("[WARN] Failed to parse ORDS response as UTF-8: {} - returning empty state", e);
-                return Ok(HashMap::new()); // Return dummy empty state
+        match ords_items(&json_value) {
+            Some(rows) => listing.rows.extend(rows.iter().cloned()),
+            None => {
+                // Valid JSON but neither shape we know; keep whatever earlier pages gave us
+                eprintln!("[WARN] ORDS page {} of {} has no items array or bare array", page, url);
+                break;
             }
-        };
+        }
         
-        // ORDS typically returns items in a "items" array
-        match serde_json::from_str::<serde_json::Value>(&body_str) {
-            Ok(json_value) => {
-                if let Some(items) = json_value.get("items") {
-                    if let Some(items_array) = items.as_array() {
-                        let mut players_map = HashMap::new();
-                        
-                        for item in items_array {
-                            if let Ok(player) = serde_json::from_value::<Player>(item.clone()) {
-                                players_map.insert(player.id.clone(), player);
-                            }
-                        }
-                        
-                        eprintln!("[INFO] Successfully loaded {} players from ORDS", players_map.len());
-                        return Ok(players_map);
-                    }
-                }
-                
-                // If we got here, the JSON was valid but didn't have the expected structure
-                eprintln!("[INFO] ORDS returned valid JSON but no players were found");
-                return Ok(HashMap::new()); // Return empty map instead of error
-            },
-            Err(e) => {
-                eprintln!("[WARN] Failed to parse ORDS response JSON: {} - returning empty state", e);
-                return Ok(HashMap::new()); // Return dummy empty state
-            }
+        match ords_next_link(&json_value) {
+            Some(next) => url = next,
+            None => break,
         }
-    } else if status_code == 404 {
-        // For 404, return empty map - this is not an error condition
-        eprintln!("[INFO] ORDS endpoint returned 404 - assuming no players exist yet");
-        return Ok(HashMap::new());
-    } else if status_code == 405 {
-        // For 405 Method Not Allowed, the endpoint doesn't support the HTTP method
-        eprintln!("[INFO] ORDS endpoint returned 405 (Method Not Allowed) - proceeding with in-memory state");
-        return Ok(HashMap::new());
-    } else {
-        // For other error codes, log but return empty map to continue with in-memory state
-        eprintln!("[WARN] ORDS all players fetch error: {} - proceeding with in-memory state", status_code);
-        return Ok(HashMap::new());
+        if page == ORDS_MAX_PAGES {
+            eprintln!("[WARN] Stopped following ORDS pagination after {} pages", ORDS_MAX_PAGES);
+            listing.truncated = true;
+        }
+    }
+    
+    Ok(listing)
+}
+
+// ORDS collections come back as {"items":[...]} by default, but some
+// configurations return the bare array
+fn ords_items(json_value: &serde_json::Value) -> Option<&Vec<serde_json::Value>> {
+    json_value.as_array()
+        .or_else(|| json_value.get("items").and_then(|items| items.as_array()))
+}
+
+// ORDS names row keys after the table's columns, which depending on how the
+// table was created can be PLAYER_ID/SCORE rather than id/score. Keys are
+// lowercased and a player_id column stands in for id, so such rows still
+// become Players instead of failing to parse.
+fn normalize_ords_row(row: Value) -> Value {
+    let columns = match row {
+        Value::Object(columns) => columns,
+        other => return other,
+    };
+    let mut normalized: serde_json::Map<String, Value> = columns
+        .into_iter()
+        .map(|(key, value)| (key.to_lowercase(), value))
+        .collect();
+    if !normalized.contains_key("id") {
+        if let Some(player_id) = normalized.get("player_id").cloned() {
+            normalized.insert("id".to_string(), player_id);
+        }
+    }
+    Value::Object(normalized)
+}
+
+// The "next" link of a paginated ORDS response, only while hasMore is set
+fn ords_next_link(json_value: &serde_json::Value) -> Option<String> {
+    if !json_value["hasMore"].as_bool().unwrap_or(false) {
+        return None;
     }
+    json_value["links"].as_array()?
+        .iter()
+        .find(|link| link["rel"] == "next")
+        .and_then(|link| link["href"].as_str())
+        .map(str::to_string)
 }
 
 // Publish state snapshots to TxEventQ for efficient reconstruction
@@ -2078,48 +5657,32 @@ fn publish_state_snapshot_to_kafka(players: &HashMap<String, Player>) -> Result<
         return Ok(()); // Don't publish empty snapshots
     }
     
-    let state_snapshot = GameEvent {
-        event_type: "state_snapshot".to_string(),
-        player_id: "wasmtime_server".to_string(),
-        game_id: "wasicycles-multiplayer".to_string(),
-        runtime: "wasmtime".to_string(),
-        castle: "Temporal Sanctuary".to_string(),
-        timestamp: get_timestamp(),
-        position: None,
-        direction: None,
-        score: None,
-    };
+    let state_snapshot = GameEvent::new("state_snapshot", "wasmtime_server");
     
     // For now, we'll use the existing publish mechanism
     // In the future, we could enhance this to include the full state
-    publish_to_oracle_kafka(&state_snapshot)
+    publish_to_oracle_kafka(&state_snapshot).map(|_| ())
 }
 
 // AI handling functions
 fn handle_ai_action(req: Request) -> Result<Response, ErrorCode> {
     let body_str = match req.body() {
         Ok(body_bytes) => {
+            if let Some(rejection) = check_body_size(&body_bytes) {
+                return rejection;
+            }
+            trace_request_body("/ai-action", &body_bytes);
             match String::from_utf8(body_bytes) {
                 Ok(s) => s,
                 Err(e) => {
                     eprintln!("[ERROR] Failed to parse request body as UTF-8: {:?}", e);
-                    return Response::builder()
-                        .status_code(400)
-                        .header("Access-Control-Allow-Origin", "*")
-                        .header("Content-Type", "application/json")
-                        .body(r#"{"error": "Invalid UTF-8", "runtime": "wasmtime"}"#.to_string())
-                        .build();
+                    return error_response("Invalid UTF-8", 400);
                 }
             }
         },
         Err(e) => {
             eprintln!("[ERROR] Failed to read request body: {:?}", e);
-            return Response::builder()
-                .status_code(400)
-                .header("Access-Control-Allow-Origin", "*")
-                .header("Content-Type", "application/json")
-                .body(r#"{"error": "Failed to read body", "runtime": "wasmtime"}"#.to_string())
-                .build();
+            return error_response("Failed to read body", 400);
         }
     };
 
@@ -2127,22 +5690,37 @@ fn handle_ai_action(req: Request) -> Result<Response, ErrorCode> {
         Ok(data) => data,
         Err(e) => {
             eprintln!("[ERROR] Failed to parse AI request: {}", e);
-            return Response::builder()
-                .status_code(400)
-                .header("Access-Control-Allow-Origin", "*")
-                .header("Content-Type", "application/json")
-                .body(r#"{"error": "Invalid JSON", "runtime": "wasmtime"}"#.to_string())
-                .build();
+            return error_response("Invalid JSON", 400);
         }
     };
 
-    // Wasmtime AI: Aggressive hunter strategy
-    let action = get_wasmtime_ai_action(&game_data);
+    // Wasmtime AI: Aggressive hunter strategy. "my_id" lets the frontend run
+    // several bots, each finding itself among the players.
+    let my_id = game_data["my_id"].as_str().unwrap_or(AI_DEFAULT_PLAYER_ID);
+    // A "seed" in the request makes the answer reproducible for that game
+    // state; without one the clock seeds it. The response reports the seed.
+    let mut rng = game_data["seed"].as_u64().map(AiRng::seeded).unwrap_or_else(AiRng::from_clock);
+    // "aggression" slides between territory (0.0) and hunter (1.0) play
+    let aggression = match &game_data["aggression"] {
+        Value::Null => AI_DEFAULT_AGGRESSION,
+        value => match value.as_f64() {
+            Some(aggression) if (0.0..=1.0).contains(&aggression) => aggression,
+            _ => return error_response("aggression must be a number from 0.0 to 1.0", 400),
+        },
+    };
+    let action = if is_paused() {
+        AiAction::forward("game_paused")
+    } else {
+        blended_ai_action(&game_data, my_id, aggression, &mut rng)
+    };
     
     let response = json!({
         "runtime": "wasmtime",
-        "castle": "Temporal Sanctuary", 
+        "castle": get_castle_name(), 
+        "ai_player_id": my_id,
         "action": action,
+        "aggression": aggression,
+        "seed": rng.seed,
         "timestamp": get_timestamp()
     });
 
@@ -2154,13 +5732,133 @@ fn handle_ai_action(req: Request) -> Result<Response, ErrorCode> {
         .build()
 }
 
-fn get_wasmtime_ai_action(game_data: &Value) -> Value {
+fn handle_ai_strategy() -> Result<Response, ErrorCode> {
+    let strategy = json!({
+        "runtime": "wasmtime",
+        "castle": get_castle_name(),
+        "ai_player_id": AI_DEFAULT_PLAYER_ID,
+        "strategy": "aggressive_hunter",
+        "description": "Aggressive hunter: chases the nearest living opponent and tries to cut them off, only breaking off the hunt to dodge a collision.",
+        "behaviors": [
+            "avoid collisions over the next few steps, counting the trail it lays itself",
+            "when forced to turn, prefer the turn that closes on the nearest opponent",
+            "turn toward the nearest living opponent along the dominant axis when that turn is safe",
+            "otherwise keep moving forward"
+        ],
+        "parameters": {
+            "default_arena_size": AI_DEFAULT_ARENA_SIZE,
+            "target_selection": "nearest_alive_opponent",
+            "danger_lookahead": AI_DANGER_LOOKAHEAD,
+            "default_aggression": AI_DEFAULT_AGGRESSION
+        },
+        "difficulty": AI_DEFAULT_DIFFICULTY,
+        "timestamp": get_timestamp()
+    });
+
+    Response::builder()
+        .status_code(200)
+        .header("Access-Control-Allow-Origin", "*")
+        .header("Content-Type", "application/json")
+        .body(strategy.to_string())
+        .build()
+}
+
+// Decision returned by the AI. Serializes to {"type", "direction", "reason"},
+// the shape /ai-action clients and /simulate already read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum AiActionType {
+    Move,
+    Turn,
+}
+
+// Relative to the cycle's current heading, unlike the absolute Direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum AiDirection {
+    Forward,
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct AiAction {
+    #[serde(rename = "type")]
+    action_type: AiActionType,
+    direction: AiDirection,
+    reason: String,
+}
+
+impl AiAction {
+    fn new(action_type: AiActionType, direction: AiDirection, reason: impl Into<String>) -> Self {
+        AiAction { action_type, direction, reason: reason.into() }
+    }
+
+    fn forward(reason: impl Into<String>) -> Self {
+        AiAction::new(AiActionType::Move, AiDirection::Forward, reason)
+    }
+
+    fn turn(direction: AiDirection, reason: impl Into<String>) -> Self {
+        AiAction::new(AiActionType::Turn, direction, reason)
+    }
+
+    // Heading (0=north, 1=east, 2=south, 3=west) after applying this action
+    fn heading_from(&self, direction: i32) -> i32 {
+        match self.direction {
+            AiDirection::Forward => direction,
+            AiDirection::Left => (direction + 3) % 4,
+            AiDirection::Right => (direction + 1) % 4,
+        }
+    }
+}
+
+// Wasmtime AI tuning parameters (also reported by GET /ai-action)
+const AI_DEFAULT_ARENA_SIZE: u64 = 20;
+// Player id the AI looks for when a request doesn't send "my_id"
+const AI_DEFAULT_PLAYER_ID: &str = "ai-wasmtime";
+// Steps a direction must stay survivable, own projected trail included,
+// before the AI counts it as safe
+const AI_DANGER_LOOKAHEAD: u32 = 6;
+const AI_DEFAULT_DIFFICULTY: &str = "normal";
+// Without an "aggression" in the request this AI hunts, as it always has
+const AI_DEFAULT_AGGRESSION: f64 = 1.0;
+// Territory-mode tuning, matching wasmedge's territory controller: how far out
+// (as a fraction of the arena) it heads back to the centre, how few open cells
+// ahead send it looking for a better lane, and how much longer that lane must be
+const AI_CENTER_RADIUS_RATIO: f64 = 0.25;
+const AI_WALL_LOOKAHEAD: i32 = 8;
+const AI_EXPANSION_MARGIN: i32 = 2;
+
+// Blends the hunter below with the territory controller (wasmedge's default
+// AI). 1.0 and 0.0 run just one of them, so seeded answers don't change for
+// either default. Otherwise both pick a move, and a disagreement goes to the
+// hunter with probability `aggression`; both picks have already passed their
+// own danger checks.
+fn blended_ai_action(game_data: &Value, my_player_id: &str, aggression: f64, rng: &mut AiRng) -> AiAction {
+    if aggression >= 1.0 {
+        return get_wasmtime_ai_action(game_data, my_player_id, rng);
+    }
+    if aggression <= 0.0 {
+        return get_territory_ai_action(game_data, my_player_id, rng);
+    }
+    
+    let hunter = get_wasmtime_ai_action(game_data, my_player_id, rng);
+    let territory = get_territory_ai_action(game_data, my_player_id, rng);
+    let take_hunter = if hunter.direction == territory.direction {
+        aggression >= 0.5
+    } else {
+        rng.chance(aggression)
+    };
+    if take_hunter { hunter } else { territory }
+}
+
+fn get_wasmtime_ai_action(game_data: &Value, my_player_id: &str, rng: &mut AiRng) -> AiAction {
     // Wasmtime Temporal Sanctuary AI: Aggressive hunter
     // Strategy: Chase nearest opponent, try to cut them off
     
     let empty_map = serde_json::Map::new();
     let arena = game_data.get("arena").and_then(|a| a.as_object()).unwrap_or(&empty_map);
-    let arena_size = arena.get("size").and_then(|s| s.as_u64()).unwrap_or(20) as f64;
+    let arena_size = arena.get("size").and_then(|s| s.as_u64()).unwrap_or(AI_DEFAULT_ARENA_SIZE) as f64;
     
     let empty_players_map = serde_json::Map::new();
     let players = game_data.get("players").and_then(|p| p.as_object()).unwrap_or(&empty_players_map);
@@ -2168,16 +5866,10 @@ fn get_wasmtime_ai_action(game_data: &Value) -> Value {
     let empty_trails_map = serde_json::Map::new();
     let trails = game_data.get("trails").and_then(|t| t.as_object()).unwrap_or(&empty_trails_map);
     
-    let my_player_id = "ai-wasmtime";
-    
     // Find my player data
     let my_player = players.get(my_player_id).and_then(|p| p.as_object());
     if my_player.is_none() {
-        return json!({
-            "type": "turn",
-            "direction": "forward",
-            "reason": "initializing"
-        });
+        return AiAction::new(AiActionType::Turn, AiDirection::Forward, "initializing");
     }
     
     let my_player = my_player.unwrap();
@@ -2215,64 +5907,56 @@ fn get_wasmtime_ai_action(game_data: &Value) -> Value {
         }
     }
     
-    // Check immediate danger ahead
-    let (next_x, next_z) = calculate_next_pos(my_x, my_z, my_direction);
-    if is_pos_dangerous(next_x, next_z, trails, arena_size) {
-        // Immediate danger - turn to avoid
-        let left_dir = (my_direction - 1 + 4) % 4;
-        let right_dir = (my_direction + 1) % 4;
-        
-        let (left_x, left_z) = calculate_next_pos(my_x, my_z, left_dir);
-        let (right_x, right_z) = calculate_next_pos(my_x, my_z, right_dir);
-        
-        let left_safe = !is_pos_dangerous(left_x, left_z, trails, arena_size);
-        let right_safe = !is_pos_dangerous(right_x, right_z, trails, arena_size);
+    // Cells already taken, plus room in each direction once the cycle's own
+    // new trail is counted; a direction is safe with the full lookahead free
+    let blocked = trail_cells(trails);
+    let room = |direction: i32| projected_room(my_x, my_z, direction, &blocked, arena_size, AI_DANGER_LOOKAHEAD);
+    let left_dir = (my_direction - 1 + 4) % 4;
+    let right_dir = (my_direction + 1) % 4;
+    
+    // Check danger ahead
+    let forward_room = room(my_direction);
+    if forward_room < AI_DANGER_LOOKAHEAD {
+        let left_room = room(left_dir);
+        let right_room = room(right_dir);
+        let left_safe = left_room == AI_DANGER_LOOKAHEAD;
+        let right_safe = right_room == AI_DANGER_LOOKAHEAD;
         
         if left_safe && right_safe {
             // Both safe - choose based on opponent position
             if let Some((_, opp_x, opp_z)) = nearest_opponent {
+                let (left_x, left_z) = calculate_next_pos(my_x, my_z, left_dir);
+                let (right_x, right_z) = calculate_next_pos(my_x, my_z, right_dir);
                 let left_dist = ((left_x - opp_x).powi(2) + (left_z - opp_z).powi(2)).sqrt();
                 let right_dist = ((right_x - opp_x).powi(2) + (right_z - opp_z).powi(2)).sqrt();
                 
-                if left_dist < right_dist {
-                    return json!({
-                        "type": "turn",
-                        "direction": "left",
-                        "reason": "avoid_and_hunt_left"
-                    });
+                let go_left = if left_dist == right_dist { rng.coin() } else { left_dist < right_dist };
+                if go_left {
+                    return AiAction::turn(AiDirection::Left, "avoid_and_hunt_left");
                 } else {
-                    return json!({
-                        "type": "turn", 
-                        "direction": "right",
-                        "reason": "avoid_and_hunt_right"
-                    });
+                    return AiAction::turn(AiDirection::Right, "avoid_and_hunt_right");
                 }
+            } else if rng.coin() {
+                return AiAction::turn(AiDirection::Left, "avoid_collision_left");
             } else {
-                return json!({
-                    "type": "turn",
-                    "direction": "left",
-                    "reason": "avoid_collision_left"
-                });
+                return AiAction::turn(AiDirection::Right, "avoid_collision_right");
             }
         } else if left_safe {
-            return json!({
-                "type": "turn",
-                "direction": "left",
-                "reason": "avoid_collision_only_left_safe"
-            });
+            return AiAction::turn(AiDirection::Left, "avoid_collision_only_left_safe");
         } else if right_safe {
-            return json!({
-                "type": "turn",
-                "direction": "right", 
-                "reason": "avoid_collision_only_right_safe"
-            });
+            return AiAction::turn(AiDirection::Right, "avoid_collision_only_right_safe");
+        } else if forward_room > 0 && forward_room >= left_room.max(right_room) {
+            // Every way is closing in; ahead buys the most time
+            return AiAction::forward("longest_escape_forward");
+        } else if left_room > 0 || right_room > 0 {
+            let go_right = if right_room == left_room { rng.coin() } else { right_room > left_room };
+            let direction = if go_right { AiDirection::Right } else { AiDirection::Left };
+            let reason = if direction == AiDirection::Left { "longest_escape_left" } else { "longest_escape_right" };
+            return AiAction::turn(direction, reason);
         } else {
             // Desperate - just turn
-            return json!({
-                "type": "turn",
-                "direction": "right",
-                "reason": "desperate_turn"
-            });
+            let direction = if rng.coin() { AiDirection::Right } else { AiDirection::Left };
+            return AiAction::turn(direction, "desperate_turn");
         }
     }
     
@@ -2289,30 +5973,386 @@ fn get_wasmtime_ai_action(game_data: &Value) -> Value {
             if dz < 0.0 { 0 } else { 2 }  // North or South  
         };
         
-        if target_direction != my_direction {
+        // Only chase through a turn that won't box us in
+        if target_direction != my_direction && room(target_direction) == AI_DANGER_LOOKAHEAD {
             // Need to turn toward target
             let turn_diff = (target_direction - my_direction + 4) % 4;
             if turn_diff == 1 {
-                return json!({
-                    "type": "turn",
-                    "direction": "right",
-                    "reason": "hunting_opponent_right"
-                });
+                return AiAction::turn(AiDirection::Right, "hunting_opponent_right");
             } else if turn_diff == 3 {
-                return json!({
-                    "type": "turn",
-                    "direction": "left", 
-                    "reason": "hunting_opponent_left"
-                });
+                return AiAction::turn(AiDirection::Left, "hunting_opponent_left");
             }
         }
     }
     
     // Default: continue forward
+    AiAction::forward("continuing_hunt")
+}
+
+// Territory controller for low aggression, ported from wasmedge: hold the
+// centre, build walls, and turn into longer open lanes
+fn get_territory_ai_action(game_data: &Value, my_player_id: &str, rng: &mut AiRng) -> AiAction {
+    let empty_map = serde_json::Map::new();
+    let arena = game_data.get("arena").and_then(|a| a.as_object()).unwrap_or(&empty_map);
+    let arena_size = arena.get("size").and_then(|s| s.as_u64()).unwrap_or(AI_DEFAULT_ARENA_SIZE) as f64;
+    let players = game_data.get("players").and_then(|p| p.as_object()).unwrap_or(&empty_map);
+    let trails = game_data.get("trails").and_then(|t| t.as_object()).unwrap_or(&empty_map);
+    
+    let my_player = match players.get(my_player_id).and_then(|p| p.as_object()) {
+        Some(player) => player,
+        None => return AiAction::new(AiActionType::Turn, AiDirection::Forward, "initializing"),
+    };
+    let my_pos = my_player.get("position").and_then(|p| p.as_object()).unwrap_or(&empty_map);
+    let my_x = my_pos.get("x").and_then(|x| x.as_f64()).unwrap_or(0.0);
+    let my_z = my_pos.get("z").and_then(|z| z.as_f64()).unwrap_or(0.0);
+    let my_direction = my_player.get("direction").and_then(|d| d.as_u64()).unwrap_or(0) as i32;
+    
+    let blocked = trail_cells(trails);
+    let room = |direction: i32| projected_room(my_x, my_z, direction, &blocked, arena_size, AI_DANGER_LOOKAHEAD);
+    let left_dir = (my_direction - 1 + 4) % 4;
+    let right_dir = (my_direction + 1) % 4;
+    let center = arena_size / 2.0;
+    
+    let forward_room = room(my_direction);
+    if forward_room < AI_DANGER_LOOKAHEAD {
+        let left_room = room(left_dir);
+        let right_room = room(right_dir);
+        let left_safe = left_room == AI_DANGER_LOOKAHEAD;
+        let right_safe = right_room == AI_DANGER_LOOKAHEAD;
+        
+        if left_safe && right_safe {
+            // Both safe - keep to whichever side stays nearer the centre
+            let (left_x, left_z) = calculate_next_pos(my_x, my_z, left_dir);
+            let (right_x, right_z) = calculate_next_pos(my_x, my_z, right_dir);
+            let left_to_center = ((left_x - center).powi(2) + (left_z - center).powi(2)).sqrt();
+            let right_to_center = ((right_x - center).powi(2) + (right_z - center).powi(2)).sqrt();
+            let go_left = if left_to_center == right_to_center { rng.coin() } else { left_to_center < right_to_center };
+            if go_left {
+                return AiAction::turn(AiDirection::Left, "avoid_and_control_territory_left");
+            } else {
+                return AiAction::turn(AiDirection::Right, "avoid_and_control_territory_right");
+            }
+        } else if left_safe {
+            return AiAction::turn(AiDirection::Left, "avoid_collision_left_only");
+        } else if right_safe {
+            return AiAction::turn(AiDirection::Right, "avoid_collision_right_only");
+        } else if forward_room > 0 && forward_room >= left_room.max(right_room) {
+            return AiAction::forward("longest_escape_forward");
+        } else if left_room > 0 || right_room > 0 {
+            let go_left = if left_room == right_room { rng.coin() } else { left_room > right_room };
+            let direction = if go_left { AiDirection::Left } else { AiDirection::Right };
+            let reason = if direction == AiDirection::Left { "longest_escape_left" } else { "longest_escape_right" };
+            return AiAction::turn(direction, reason);
+        } else {
+            let direction = if rng.coin() { AiDirection::Left } else { AiDirection::Right };
+            return AiAction::turn(direction, "desperate_turn");
+        }
+    }
+    
+    // Drifted too far out: head back toward the centre if that turn is safe
+    let distance_to_center = ((my_x - center).powi(2) + (my_z - center).powi(2)).sqrt();
+    if distance_to_center > arena_size * AI_CENTER_RADIUS_RATIO {
+        let dx = center - my_x;
+        let dz = center - my_z;
+        let target_direction = if dx.abs() > dz.abs() {
+            if dx > 0.0 { 1 } else { 3 }
+        } else if dz < 0.0 {
+            0
+        } else {
+            2
+        };
+        
+        if target_direction != my_direction && room(target_direction) == AI_DANGER_LOOKAHEAD {
+            let turn_diff = (target_direction - my_direction + 4) % 4;
+            if turn_diff == 1 {
+                return AiAction::turn(AiDirection::Right, "moving_to_center_right");
+            } else if turn_diff == 3 {
+                return AiAction::turn(AiDirection::Left, "moving_to_center_left");
+            }
+        }
+    }
+    
+    // The lane ahead is getting short: expand into a clearly longer side lane
+    let steps_ahead = open_run(my_x, my_z, my_direction, &blocked, arena_size);
+    if steps_ahead < AI_WALL_LOOKAHEAD {
+        let left_distance = open_run(my_x, my_z, left_dir, &blocked, arena_size);
+        let right_distance = open_run(my_x, my_z, right_dir, &blocked, arena_size);
+        
+        if left_distance > steps_ahead + AI_EXPANSION_MARGIN && room(left_dir) == AI_DANGER_LOOKAHEAD {
+            return AiAction::turn(AiDirection::Left, "strategic_territory_expansion_left");
+        } else if right_distance > steps_ahead + AI_EXPANSION_MARGIN && room(right_dir) == AI_DANGER_LOOKAHEAD {
+            return AiAction::turn(AiDirection::Right, "strategic_territory_expansion_right");
+        }
+    }
+    
+    AiAction::forward("building_defensive_wall")
+}
+
+// Open cells in a straight line from (x, z) before a wall or trail
+fn open_run(x: f64, z: f64, direction: i32, blocked: &std::collections::HashSet<(i32, i32)>, arena_size: f64) -> i32 {
+    let size = arena_size as i32;
+    let (mut current_x, mut current_z) = (x, z);
+    let mut distance = 0;
+    while distance < size * 2 {
+        let (next_x, next_z) = calculate_next_pos(current_x, current_z, direction);
+        let cell = grid_cell(next_x, next_z);
+        if cell.0 < 0 || cell.1 < 0 || cell.0 >= size || cell.1 >= size || blocked.contains(&cell) {
+            break;
+        }
+        distance += 1;
+        current_x = next_x;
+        current_z = next_z;
+    }
+    distance
+}
+
+// POST /simulate limits
+const SIMULATE_DEFAULT_TICKS: u64 = 500;
+const SIMULATE_MAX_TICKS: u64 = 5000;
+const SIMULATE_MAX_PLAYERS: usize = 8;
+const SIMULATE_MAX_ARENA_SIZE: u64 = 200;
+
+type AiDecision = fn(&Value, &str, &mut AiRng) -> AiAction;
+
+// Maps a /simulate "runtimes" entry to its AI and the label its cycle gets;
+// "wasmtime" and "wasmedge" mean each runtime's default personality
+fn simulate_strategy(name: &str) -> Option<(&'static str, AiDecision)> {
+    match name {
+        "hunter" | "wasmtime" => Some(("hunter", get_wasmtime_ai_action)),
+        "territory" | "wasmedge" => Some(("territory", get_territory_ai_action)),
+        _ => None,
+    }
+}
+
+// Headless match between AI cycles: {"runtimes": ["hunter", "territory"],
+// "arena_size": 20, "ticks": 500, "seed": 42}. Each entry adds a cycle
+// driven by that strategy.
+fn handle_simulate(req: Request) -> Result<Response, ErrorCode> {
+    let bad_request = |message: String| error_response(&message, 400);
+    
+    let body = req.body().unwrap_or_default();
+    if let Some(rejection) = check_body_size(&body) {
+        return rejection;
+    }
+    trace_request_body("/simulate", &body);
+    let payload: Value = match serde_json::from_slice(&body) {
+        Ok(payload) => payload,
+        Err(_) => return bad_request("Invalid JSON".to_string()),
+    };
+    
+    let strategies: Vec<&str> = payload["runtimes"].as_array()
+        .map(|r| r.iter().filter_map(|s| s.as_str()).collect())
+        .unwrap_or_default();
+    if strategies.len() < 2 || strategies.len() > SIMULATE_MAX_PLAYERS {
+        return bad_request("runtimes must list between 2 and 8 AI strategies".to_string());
+    }
+    let mut resolved = Vec::new();
+    for name in &strategies {
+        match simulate_strategy(name) {
+            Some(strategy) => resolved.push(strategy),
+            None => return bad_request(format!("Unknown strategy '{}' (supported: hunter, territory)", name)),
+        }
+    }
+    
+    let arena_size = payload["arena_size"].as_u64()
+        .unwrap_or(AI_DEFAULT_ARENA_SIZE)
+        .clamp(5, SIMULATE_MAX_ARENA_SIZE) as i32;
+    let ticks = payload["ticks"].as_u64()
+        .unwrap_or(SIMULATE_DEFAULT_TICKS)
+        .min(SIMULATE_MAX_TICKS);
+    let seed = payload["seed"].as_u64();
+    
+    let mut result = run_simulation(&resolved, arena_size, ticks, seed);
+    result["runtime"] = json!("wasmtime");
+    result["castle"] = json!(get_castle_name());
+    result["timestamp"] = json!(get_timestamp());
+    
+    Response::builder()
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(result.to_string())
+        .build()
+}
+
+struct SimCycle {
+    id: String,
+    strategy: &'static str,
+    decide: AiDecision,
+    x: i32,
+    z: i32,
+    direction: i32,
+    alive: bool,
+    kills: u32,
+    trail: Vec<(i32, i32)>,
+}
+
+// Deterministic xorshift64, so a given seed always replays the same match
+fn next_random(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+// The AI's only source of randomness, used to break exact ties between turns.
+// Seeded from the request, it replays identically for identical game state;
+// otherwise it starts from the clock.
+struct AiRng {
+    seed: u64,
+    state: u64,
+}
+
+impl AiRng {
+    fn seeded(seed: u64) -> Self {
+        // Zero would leave xorshift at zero
+        AiRng { seed, state: seed.max(1) }
+    }
+    
+    fn from_clock() -> Self {
+        use std::time::{SystemTime, UNIX_EPOCH};
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        AiRng::seeded(nanos)
+    }
+    
+    fn coin(&mut self) -> bool {
+        next_random(&mut self.state) & 1 == 1
+    }
+    
+    // Weighted coin: true with probability `p`
+    fn chance(&mut self, p: f64) -> bool {
+        (next_random(&mut self.state) % 1_000_000) as f64 / 1_000_000.0 < p
+    }
+}
+
+fn run_simulation(strategies: &[(&'static str, AiDecision)], arena_size: i32, max_ticks: u64, seed: Option<u64>) -> Value {
+    let player_count = strategies.len();
+    let mut rng = seed.map(|s| s.max(1));
+    let mut ai_rng = seed.map(AiRng::seeded).unwrap_or_else(AiRng::from_clock);
+    let mut occupied: HashMap<(i32, i32), String> = HashMap::new();
+    let mut cycles: Vec<SimCycle> = Vec::new();
+    
+    // Without a seed cycles start in fixed columns, alternating top and bottom
+    for (i, &(strategy, decide)) in strategies.iter().enumerate() {
+        let (x, z, direction) = match rng.as_mut() {
+            Some(state) => loop {
+                let x = (next_random(state) % arena_size as u64) as i32;
+                let z = (next_random(state) % arena_size as u64) as i32;
+                if !occupied.contains_key(&(x, z)) {
+                    break (x, z, (next_random(state) % 4) as i32);
+                }
+            },
+            None => {
+                let x = arena_size * (i as i32 + 1) / (player_count as i32 + 1);
+                if i % 2 == 0 { (x, arena_size / 4, 2) } else { (x, arena_size - 1 - arena_size / 4, 0) }
+            }
+        };
+        let id = format!("{}-{}", strategy, i + 1);
+        occupied.insert((x, z), id.clone());
+        cycles.push(SimCycle { id, strategy, decide, x, z, direction, alive: true, kills: 0, trail: vec![(x, z)] });
+    }
+    
+    let mut log = Vec::new();
+    let mut ticks_played = 0;
+    
+    while ticks_played < max_ticks && cycles.iter().filter(|c| c.alive).count() > 1 {
+        ticks_played += 1;
+        
+        // Every cycle decides against the same snapshot, then all moves resolve together
+        let game_data = json!({
+            "arena": { "size": arena_size },
+            "players": cycles.iter().map(|c| (c.id.clone(), json!({
+                "position": { "x": c.x, "z": c.z },
+                "direction": c.direction,
+                "alive": c.alive
+            }))).collect::<serde_json::Map<_, _>>(),
+            "trails": cycles.iter().map(|c| (c.id.clone(), json!(
+                c.trail.iter().map(|(x, z)| format!("{},{}", x, z)).collect::<Vec<_>>()
+            ))).collect::<serde_json::Map<_, _>>()
+        });
+        
+        let mut planned = Vec::new();
+        for (i, cycle) in cycles.iter().enumerate().filter(|(_, c)| c.alive) {
+            let action = (cycle.decide)(&game_data, &cycle.id, &mut ai_rng);
+            let direction = action.heading_from(cycle.direction);
+            let (x, z) = calculate_next_pos(cycle.x as f64, cycle.z as f64, direction);
+            let (x, z) = grid_cell(x, z);
+            planned.push((i, direction, x, z, action.reason));
+        }
+        
+        for (i, direction, x, z, reason) in &planned {
+            let (i, x, z) = (*i, *x, *z);
+            let head_on = planned.iter().any(|(j, _, ox, oz, _)| *j != i && *ox == x && *oz == z);
+            let crash = if x < 0 || z < 0 || x >= arena_size || z >= arena_size {
+                Some("wall".to_string())
+            } else if let Some(owner) = occupied.get(&(x, z)) {
+                Some(owner.clone())
+            } else if head_on {
+                Some("head_on".to_string())
+            } else {
+                None
+            };
+            
+            // Running into someone else's trail credits them with the kill
+            if let Some(owner) = crash.as_ref().and_then(|c| cycles.iter().position(|o| &o.id == c)) {
+                if owner != i {
+                    cycles[owner].kills += 1;
+                }
+            }
+            
+            let cycle = &mut cycles[i];
+            cycle.direction = *direction;
+            if crash.is_some() {
+                cycle.alive = false;
+            } else {
+                cycle.x = x;
+                cycle.z = z;
+                cycle.trail.push((x, z));
+            }
+            
+            log.push(json!({
+                "tick": ticks_played,
+                "player": cycle.id,
+                "direction": direction,
+                "x": x,
+                "z": z,
+                "reason": reason,
+                "crashed_into": crash
+            }));
+        }
+        
+        for (i, _, x, z, _) in &planned {
+            if cycles[*i].alive {
+                occupied.insert((*x, *z), cycles[*i].id.clone());
+            }
+        }
+    }
+    
+    let survivors: Vec<&SimCycle> = cycles.iter().filter(|c| c.alive).collect();
+    let (outcome, winner) = match survivors.as_slice() {
+        [winner] => ("winner", Some(winner.id.clone())),
+        [] => ("draw", None),
+        _ => ("tick_limit", None),
+    };
+    
     json!({
-        "type": "move",
-        "direction": "forward",
-        "reason": "continuing_hunt"
+        "result": outcome,
+        "winner": winner,
+        "ticks": ticks_played,
+        "arena_size": arena_size,
+        "seed": seed,
+        "players": cycles.iter().map(|c| json!({
+            "id": c.id,
+            "strategy": c.strategy,
+            "alive": c.alive,
+            "x": c.x,
+            "z": c.z,
+            "trail_length": c.trail.len(),
+            "kills": c.kills
+        })).collect::<Vec<_>>(),
+        "moves": log
     })
 }
 
@@ -2326,27 +6366,50 @@ fn calculate_next_pos(x: f64, z: f64, direction: i32) -> (f64, f64) {
     }
 }
 
-fn is_pos_dangerous(x: f64, z: f64, trails: &serde_json::Map<String, Value>, arena_size: f64) -> bool {
-    // Check arena bounds
-    if x < 0.0 || x >= arena_size || z < 0.0 || z >= arena_size {
-        return true;
-    }
-    
-    // Check trail collisions  
-    let pos_key = format!("{},{}", x as i32, z as i32);
-    for (_, trail_positions) in trails.iter() {
-        if let Some(positions) = trail_positions.as_array() {
-            for pos in positions {
-                if let Some(pos_str) = pos.as_str() {
-                    if pos_str == pos_key {
-                        return true;
-                    }
-                }
+// Every "x,z" cell listed in the request's trails
+fn trail_cells(trails: &serde_json::Map<String, Value>) -> std::collections::HashSet<(i32, i32)> {
+    trails.values()
+        .filter_map(|positions| positions.as_array())
+        .flatten()
+        .filter_map(|pos| {
+            let (x, z) = pos.as_str()?.split_once(',')?;
+            Some((x.trim().parse().ok()?, z.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+// How many of the next `depth` steps the cycle can survive heading off in
+// `direction` and turning freely after that. The cells it leaves behind,
+// starting with the one it is on, count as trail, so a turn that curls back
+// into its own fresh wall scores low even when the first cell is open.
+fn projected_room(x: f64, z: f64, direction: i32, blocked: &std::collections::HashSet<(i32, i32)>, arena_size: f64, depth: u32) -> u32 {
+    fn search(x: i32, z: i32, direction: i32, blocked: &std::collections::HashSet<(i32, i32)>, arena_size: i32,
+              depth: u32, laid: &mut Vec<(i32, i32)>) -> u32 {
+        if depth == 0 {
+            return 0;
+        }
+        let (next_x, next_z) = calculate_next_pos(x as f64, z as f64, direction);
+        let next = grid_cell(next_x, next_z);
+        let in_arena = next.0 >= 0 && next.1 >= 0 && next.0 < arena_size && next.1 < arena_size;
+        if !in_arena || blocked.contains(&next) || laid.contains(&next) {
+            return 0;
+        }
+        
+        laid.push(next);
+        let mut best = 0;
+        for turn in [0, 3, 1] {
+            best = best.max(search(next.0, next.1, (direction + turn) % 4, blocked, arena_size, depth - 1, laid));
+            if best == depth - 1 {
+                break;
             }
         }
+        laid.pop();
+        best + 1
     }
     
-    false
+    let start = grid_cell(x, z);
+    let mut laid = vec![start];
+    search(start.0, start.1, direction, blocked, arena_size as i32, depth, &mut laid)
 }
 
 // Helper functions for AI and general utilities
@@ -2395,6 +6458,105 @@ fn base64_encode(input: &str) -> String {
     result
 }
 
+// No crypto crate in the component build either, so event signing carries
+// its own SHA-256/HMAC
+fn sha256(data: &[u8]) -> [u8; 32] {
+    const K: [u32; 64] = [
+        0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+        0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+        0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+        0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+        0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+        0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+        0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+        0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+    ];
+    let mut h: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+    ];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 64];
+        for (word, bytes) in w.iter_mut().zip(block.chunks(4)) {
+            *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        for (k, wi) in K.iter().zip(w.iter()) {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = hh.wrapping_add(s1).wrapping_add(ch).wrapping_add(*k).wrapping_add(*wi);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            hh = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0u8; 32];
+    for (bytes, word) in digest.chunks_mut(4).zip(h.iter()) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; 64];
+    if key.len() > 64 {
+        block[..32].copy_from_slice(&sha256(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner: Vec<u8> = block.iter().map(|b| b ^ 0x36).collect();
+    inner.extend_from_slice(message);
+    let mut outer: Vec<u8> = block.iter().map(|b| b ^ 0x5c).collect();
+    outer.extend_from_slice(&sha256(&inner));
+    sha256(&outer)
+}
+
+// The one place a request's player_id is read. Clients send either a string
+// or a plain integer; both come back as the same canonical string, so
+// "player_id": 7 and "player_id": "7" address the same cycle.
+fn player_id_from_json(value: &Value) -> Result<String, &'static str> {
+    match value {
+        Value::String(id) if !id.trim().is_empty() => Ok(id.clone()),
+        Value::String(_) | Value::Null => Err("Missing player_id"),
+        Value::Number(n) if n.is_i64() || n.is_u64() => Ok(n.to_string()),
+        _ => Err("player_id must be a string or an integer"),
+    }
+}
+
+// player_id from a raw request body; a body that isn't JSON has no id
+fn request_player_id(body_str: &str) -> Result<String, &'static str> {
+    match serde_json::from_str::<Value>(body_str) {
+        Ok(json) => player_id_from_json(&json["player_id"]),
+        Err(_) => Err("Missing player_id"),
+    }
+}
+
 fn extract_json_field(json_str: &str, field_name: &str) -> Option<String> {
     // Simple JSON field extraction for WASM environment
     // This is a basic implementation - in production you'd use serde_json
@@ -2428,3 +6590,6 @@ fn extract_json_field(json_str: &str, field_name: &str) -> Option<String> {
     }
     None
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,1325 @@
+use super::*;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+// The runtime keeps its state in `static mut`s, so tests touching them take
+// turns
+fn serial() -> MutexGuard<'static, ()> {
+    static LOCK: Mutex<()> = Mutex::new(());
+    LOCK.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// Overrides an environment variable for the scope of a test
+struct EnvVar {
+    name: &'static str,
+    previous: Option<String>,
+}
+
+impl EnvVar {
+    fn set(name: &'static str, value: &str) -> Self {
+        let previous = std::env::var(name).ok();
+        std::env::set_var(name, value);
+        Self { name, previous }
+    }
+}
+
+impl Drop for EnvVar {
+    fn drop(&mut self) {
+        match self.previous.take() {
+            Some(value) => std::env::set_var(self.name, value),
+            None => std::env::remove_var(self.name),
+        }
+    }
+}
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("wasmtime-{}-{}", name, generate_request_id()))
+}
+
+fn test_player(id: &str, x: f64, y: f64, direction: &str) -> Player {
+    Player {
+        id: id.to_string(),
+        x,
+        y,
+        direction: direction.to_string(),
+        score: 0,
+        color: runtime_color(),
+        alive: true,
+        game_id: get_default_game_id(),
+        last_seen: get_timestamp(),
+        joined_at: get_timestamp(),
+        trail: Vec::new(),
+        stats: PlayerStats::default(),
+        tick: 0,
+        last_move_at: 0,
+    }
+}
+
+// A TxEventQ records response carrying `events` in order, as the consume
+// endpoint returns it: the event JSON is a string under "value"
+fn txeventq_page(events: &[GameEvent]) -> Vec<u8> {
+    let records: Vec<Value> = events.iter()
+        .enumerate()
+        .map(|(offset, event)| json!({
+            "topic": get_kafka_topic(),
+            "partition": 0,
+            "offset": offset,
+            "key": format!("wasmtime-{}", offset),
+            "value": event_payload(event).unwrap(),
+        }))
+        .collect();
+    serde_json::to_vec(&records).unwrap()
+}
+
+// Reconstruction as it runs after a static reset, minus the HTTP calls
+fn reconstruct_from(events: &[GameEvent]) -> HashMap<String, Player> {
+    let mut messages = Vec::new();
+    append_reconstruction_records(&txeventq_page(events), &get_kafka_topic(), &mut messages);
+    rebuild_state_from_messages(messages)
+}
+
+#[test]
+fn back_to_back_events_have_monotonic_ms_timestamps() {
+    let _serial = serial();
+    let first = GameEvent::new("player_moved", "ts-player");
+    let second = GameEvent::new("player_moved", "ts-player");
+    assert!(second.timestamp >= first.timestamp);
+    assert!(second.order_key() > first.order_key());
+    // Seconds since the epoch are ~1.7e9; milliseconds are ~1.7e12
+    assert!(first.timestamp > 1_000_000_000_000);
+}
+
+#[test]
+fn reconstructs_joined_and_moved_players_from_a_txeventq_page() {
+    let _serial = serial();
+    let mut alice = test_player("replay-alice", 10.0, 10.0, "right");
+    let bob = test_player("replay-bob", 30.0, 30.0, "up");
+    let alice_joined = GameEvent::new("player_joined", &alice.id).with_player(&alice);
+    let bob_joined = GameEvent::new("player_joined", &bob.id).with_player(&bob);
+    alice.lay_trail(Position { x: 10.0, y: 10.0 }, 0);
+    alice.x = 11.0;
+    alice.score = 1;
+    let alice_moved = GameEvent::new("player_moved", &alice.id).with_player(&alice);
+
+    let players = reconstruct_from(&[alice_joined, bob_joined, alice_moved]);
+    assert_eq!(players.len(), 2);
+    let alice = &players["replay-alice"];
+    assert_eq!((alice.x, alice.y), (11.0, 10.0));
+    assert_eq!(alice.score, 1);
+    assert_eq!(alice.trail, vec![Position { x: 10.0, y: 10.0 }]);
+    assert_eq!((players["replay-bob"].x, players["replay-bob"].y), (30.0, 30.0));
+}
+
+#[test]
+fn players_who_left_are_not_reconstructed() {
+    let _serial = serial();
+    let carol = test_player("replay-carol", 5.0, 5.0, "down");
+    let joined = GameEvent::new("player_joined", &carol.id).with_player(&carol);
+    let left = GameEvent::new("player_left", &carol.id).with_game_id(&carol.game_id);
+
+    assert!(reconstruct_from(&[joined, left]).is_empty());
+}
+
+#[test]
+fn unparseable_records_page_adds_nothing() {
+    let mut messages = Vec::new();
+    assert_eq!(append_reconstruction_records(b"<html>Bad gateway</html>", "topic", &mut messages), 0);
+    assert_eq!(append_reconstruction_records(b"[]", "topic", &mut messages), 0);
+    assert!(messages.is_empty());
+}
+
+#[test]
+fn reversal_pairs_are_opposites() {
+    let parse = |d: &str| Direction::parse(d).unwrap();
+    assert_eq!(parse("up").opposite(), parse("down"));
+    assert_eq!(parse("left").opposite(), parse("right"));
+    for direction in ["up", "down", "left", "right"] {
+        assert_eq!(parse(direction).opposite().opposite(), parse(direction));
+        assert_ne!(parse(direction).opposite(), parse(direction));
+    }
+}
+
+#[test]
+fn same_state_serializes_identically() {
+    let players: Vec<Player> = (0..20)
+        .map(|i| test_player(&format!("snapshot-{:02}", i), i as f64, 2.0 * i as f64, "up"))
+        .collect();
+    let forwards: HashMap<String, Player> = players.iter().map(|p| (p.id.clone(), p.clone())).collect();
+    let backwards: HashMap<String, Player> = players.iter().rev().map(|p| (p.id.clone(), p.clone())).collect();
+
+    let first = serialize_game_state(&forwards).unwrap();
+    assert_eq!(first, serialize_game_state(&forwards).unwrap());
+    assert_eq!(first, serialize_game_state(&backwards).unwrap());
+    // Keys come out sorted
+    assert!(first.find("snapshot-00").unwrap() < first.find("snapshot-19").unwrap());
+}
+
+#[test]
+fn failed_save_is_retried_instead_of_skipped() {
+    let _serial = serial();
+    let _order = EnvVar::set("PERSISTENCE_ORDER", "file");
+    let path = temp_path("state.json");
+    unsafe {
+        LAST_SAVED_STATE = None;
+    }
+    let mut players: HashMap<String, Player> = HashMap::new();
+    players.insert("save-alice".to_string(), test_player("save-alice", 3.0, 4.0, "up"));
+
+    // No store takes the state: nothing counts as saved
+    {
+        let _unwritable = EnvVar::set("STATE_FILE", "/nonexistent-dir/state.json");
+        save_game_state(&mut players).unwrap();
+        assert!(unsafe { (*std::ptr::addr_of!(LAST_SAVED_STATE)).clone() }.is_none());
+    }
+
+    // The same state again, now with a working store, is saved rather than
+    // skipped as unchanged
+    let _writable = EnvVar::set("STATE_FILE", path.to_str().unwrap());
+    save_game_state(&mut players).unwrap();
+    let saved = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(unsafe { (*std::ptr::addr_of!(LAST_SAVED_STATE)).clone() }, Some(saved.clone()));
+    assert!(parse_state(&saved).unwrap().contains_key("save-alice"));
+
+    // Once saved, an unchanged state is skipped
+    std::fs::remove_file(&path).unwrap();
+    save_game_state(&mut players).unwrap();
+    assert!(!path.exists());
+}
+
+#[test]
+fn events_from_another_game_do_not_leak_into_reconstruction() {
+    let _serial = serial();
+    let _active = EnvVar::set("GAME_ID", "B");
+    let mut in_a = test_player("game-a-player", 5.0, 5.0, "up");
+    in_a.game_id = "A".to_string();
+    let mut shared_in_a = test_player("game-shared", 1.0, 1.0, "up");
+    shared_in_a.game_id = "A".to_string();
+    let mut shared_in_b = test_player("game-shared", 9.0, 9.0, "left");
+    shared_in_b.game_id = "B".to_string();
+
+    let players = reconstruct_from(&[
+        GameEvent::new("player_joined", &in_a.id).with_player(&in_a),
+        GameEvent::new("player_joined", &shared_in_b.id).with_player(&shared_in_b),
+        GameEvent::new("player_joined", &shared_in_a.id).with_player(&shared_in_a),
+        GameEvent::new("player_moved", &shared_in_a.id).with_player(&Player { x: 2.0, ..shared_in_a.clone() }),
+    ]);
+
+    assert_eq!(players.len(), 1);
+    let shared = &players["game-shared"];
+    assert_eq!(shared.game_id, "B");
+    assert_eq!((shared.x, shared.y), (9.0, 9.0));
+}
+
+#[test]
+fn api_key_is_required_for_writes_once_configured() {
+    let _serial = serial();
+    let _key = EnvVar::set("API_KEY", "secret");
+    assert!(!api_key_accepts("", &Method::Post, "/join"));
+    assert!(!api_key_accepts("guess", &Method::Post, "/join"));
+    assert!(api_key_accepts("secret", &Method::Post, "/join"));
+    assert!(api_key_accepts("", &Method::Get, "/players"));
+
+    let _reads = EnvVar::set("API_KEY_READS", "true");
+    assert!(!api_key_accepts("", &Method::Get, "/players"));
+    assert!(api_key_accepts("", &Method::Get, "/health"));
+}
+
+#[test]
+fn game_full_rejection_is_a_standard_error_body() {
+    let body = game_full_body(4, 4);
+    assert_eq!(body["error"], "game_full");
+    assert_eq!(body["status"], 503);
+    assert_eq!(body["current_players"], 4);
+    assert_eq!(body["max_players"], 4);
+    let mut keys: Vec<&str> = body.as_object().unwrap().keys().map(String::as_str).collect();
+    keys.sort_unstable();
+    assert_eq!(keys, ["castle", "current_players", "error", "max_players", "runtime", "status"]);
+}
+
+#[test]
+fn unlimited_trail_length_still_stops_at_the_hard_cap() {
+    let mut player = test_player("capped", 0.0, 0.0, "right");
+    for i in 0..MAX_TRAIL_CELLS + 2 {
+        player.lay_trail(Position { x: i as f64, y: 0.0 }, 0);
+    }
+    assert_eq!(player.trail.len(), MAX_TRAIL_CELLS);
+    assert_eq!(player.trail[0].x, 2.0);
+
+    // TRAIL_LENGTH can shorten the trail but not lift the cap
+    player.lay_trail(Position { x: -1.0, y: 0.0 }, MAX_TRAIL_CELLS * 2);
+    assert_eq!(player.trail.len(), MAX_TRAIL_CELLS);
+    player.lay_trail(Position { x: -2.0, y: 0.0 }, 2);
+    assert_eq!(player.trail, vec![Position { x: -1.0, y: 0.0 }, Position { x: -2.0, y: 0.0 }]);
+}
+
+#[test]
+fn flush_stops_at_the_first_failed_publish() {
+    let mut queue: Vec<GameEvent> = ["first", "second", "third", "fourth"].iter()
+        .map(|id| GameEvent::new("player_moved", id))
+        .collect();
+    let mut attempts = Vec::new();
+    let published = publish_queued(&mut queue, |event| {
+        attempts.push(event.player_id.clone());
+        if event.player_id == "second" { Err("503".to_string()) } else { Ok(()) }
+    });
+
+    assert_eq!(published, 1);
+    // Nothing after the failure was tried, so nothing can overtake it
+    assert_eq!(attempts, ["first", "second"]);
+    let left: Vec<&str> = queue.iter().map(|e| e.player_id.as_str()).collect();
+    assert_eq!(left, ["second", "third", "fourth"]);
+
+    assert_eq!(publish_queued(&mut queue, |_| Ok(())), 3);
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn simulation_mixes_strategies_and_replays_by_seed() {
+    let strategies: Vec<_> = ["territory", "wasmtime", "hunter"].iter()
+        .map(|name| simulate_strategy(name).unwrap())
+        .collect();
+    let first = run_simulation(&strategies, 15, 300, Some(11));
+    let labels: Vec<&str> = first["players"].as_array().unwrap().iter()
+        .map(|p| p["strategy"].as_str().unwrap())
+        .collect();
+    assert_eq!(labels, ["territory", "hunter", "hunter"]);
+    assert_eq!(first["players"][0]["id"], "territory-1");
+
+    let second = run_simulation(&strategies, 15, 300, Some(11));
+    assert_eq!(first["moves"], second["moves"]);
+    assert!(simulate_strategy("chess").is_none());
+}
+
+#[test]
+fn only_json_media_types_are_accepted_for_writes() {
+    assert!(is_json_media_type("application/json"));
+    assert!(is_json_media_type("Application/JSON; charset=utf-8"));
+    assert!(is_json_media_type("application/merge-patch+json"));
+    assert!(!is_json_media_type("text/plain"));
+    assert!(!is_json_media_type("application/x-www-form-urlencoded"));
+}
+
+#[test]
+fn msgpack_responses_decode_to_the_json_value() {
+    let players = json!({
+        "players": [test_player("packed", 3.0, 4.0, "left")],
+        "count": 1
+    });
+    let response = negotiated_response(&players, true).unwrap();
+    assert_eq!(response.header("content-type").unwrap(), "application/msgpack");
+    let decoded: Value = rmp_serde::from_slice(&response.body().unwrap()).unwrap();
+    assert_eq!(decoded, players);
+
+    let fallback = negotiated_response(&players, false).unwrap();
+    assert_eq!(fallback.header("content-type").unwrap(), "application/json");
+}
+
+#[test]
+fn publish_receipt_reads_numeric_or_string_offsets() {
+    assert_eq!(parse_publish_receipt(r#"{"offsets":[{"partition":1,"offset":42}]}"#),
+        Some(PublishReceipt { partition: 1, offset: 42 }));
+    assert_eq!(parse_publish_receipt(r#"{"offsets":[{"partition":"0","offset":"7"}]}"#),
+        Some(PublishReceipt { partition: 0, offset: 7 }));
+    assert_eq!(parse_publish_receipt(r#"{"offsets":[]}"#), None);
+    assert_eq!(parse_publish_receipt("created"), None);
+}
+
+#[test]
+fn adjacent_spawns_face_away_from_each_other() {
+    let step = get_game_config().move_step;
+    let upper = test_player("adjacent-upper", 20.0, 20.0, "up");
+    let lower = test_player("adjacent-lower", 20.0, 20.0 + step, "up");
+    let players: HashMap<String, Player> = [upper.clone(), lower.clone()].into_iter()
+        .map(|p| (p.id.clone(), p))
+        .collect();
+
+    assert_ne!(open_direction(&players, &upper), Direction::Down);
+    assert_ne!(open_direction(&players, &lower), Direction::Up);
+    assert_eq!(Direction::parse("sideways"), None);
+}
+
+fn ords_page(rows: Value, next: Option<&str>) -> Value {
+    json!({
+        "items": rows,
+        "hasMore": next.is_some(),
+        "links": next.map(|href| vec![json!({"rel": "next", "href": href})]).unwrap_or_default()
+    })
+}
+
+#[test]
+fn ords_listing_reads_items_pages_and_bare_arrays() {
+    let mut requested = Vec::new();
+    let listing = collect_ords_pages("ords/players/".to_string(), |url| {
+        requested.push(url.to_string());
+        Ok(match url {
+            "ords/players/" => ords_page(json!([{"id": "a"}, {"id": "b"}]), Some("ords/players/?offset=2")),
+            _ => ords_page(json!([{"id": "c"}]), None),
+        })
+    }).unwrap();
+    assert_eq!(requested, ["ords/players/", "ords/players/?offset=2"]);
+    assert_eq!(listing.rows.len(), 3);
+    assert!(listing.partial.is_none());
+    assert!(!listing.truncated);
+
+    let bare = collect_ords_pages("ords/players/".to_string(), |_| Ok(json!([{"id": "solo"}]))).unwrap();
+    assert_eq!(bare.rows, vec![json!({"id": "solo"})]);
+}
+
+#[test]
+fn ords_listing_keeps_earlier_pages_when_a_later_one_fails() {
+    let listing = collect_ords_pages("ords/leaderboard/".to_string(), |url| match url {
+        "ords/leaderboard/" => Ok(ords_page(json!([{"player_id": "first", "score": 5}]), Some("ords/leaderboard/?offset=1"))),
+        _ => Err("ORDS leaderboard error: 500".to_string()),
+    }).unwrap();
+    let result = leaderboard_listing(listing);
+    assert_eq!(result["partial"], true);
+    assert_eq!(result["count"], 1);
+    assert_eq!(result["items"][0]["player_id"], "first");
+    assert_eq!(result["error"], "ORDS leaderboard error: 500");
+
+    // Nothing to fall back on when the very first page fails
+    assert!(collect_ords_pages("ords/leaderboard/".to_string(), |_| Err("down".to_string())).is_err());
+}
+
+#[test]
+fn one_trailing_slash_is_ignored_when_routing() {
+    assert_eq!(normalize_path("/health/"), "/health");
+    assert_eq!(normalize_path("/players/"), "/players");
+    assert_eq!(normalize_path("/players"), "/players");
+    assert_eq!(normalize_path("/"), "/");
+    assert_eq!(normalize_path("/Players/"), "/Players");
+}
+
+// Same keys wasmedge's error bodies carry (it checks the same list)
+#[test]
+fn error_responses_carry_the_shared_keys() {
+    for (message, status) in [("Player not found", 404), ("Move cooldown active", 429), ("Invalid or missing X-API-Key", 401)] {
+        let response = error_response(message, status).unwrap();
+        assert_eq!(response.status_code(), status);
+        let body: Value = serde_json::from_slice(&response.body().unwrap()).unwrap();
+        assert_eq!(body["error"], message);
+        assert_eq!(body["status"], status);
+        let mut keys: Vec<&str> = body.as_object().unwrap().keys().map(String::as_str).collect();
+        keys.sort_unstable();
+        assert_eq!(keys, ["castle", "error", "runtime", "status"]);
+    }
+}
+
+#[test]
+fn consumer_group_failures_are_not_mistaken_for_already_exists() {
+    assert_eq!(consumer_group_outcome(201, ""), Ok(()));
+    assert_eq!(consumer_group_outcome(409, ""), Ok(()));
+    assert_eq!(consumer_group_outcome(400, "Consumer group ALREADY_EXISTS"), Ok(()));
+
+    let err = consumer_group_outcome(500, "internal error").unwrap_err();
+    assert!(err.contains("500"), "{}", err);
+    assert!(err.contains("internal error"), "{}", err);
+    // Surfaced as an error, which the consume result keeps distinct from empty
+    let result = ConsumeResult::error(err);
+    assert!(!result.empty);
+    assert!(result.error.is_some());
+}
+
+#[test]
+fn empty_batch_is_reported_as_empty() {
+    for body in [&b""[..], b"[]", b"  [] \n"] {
+        let result = consume_result_from_body(body, "instance-1");
+        assert!(result.empty);
+        assert!(result.records.is_empty());
+        assert_eq!(result.error, None);
+    }
+
+    let page = txeventq_page(&[GameEvent::new("player_joined", "consumed")]);
+    let result = consume_result_from_body(&page, "instance-1");
+    assert!(!result.empty);
+    assert_eq!(result.records.len(), 1);
+    assert_eq!(result.records[0]["data"]["player_id"], "consumed");
+
+    let garbled = consume_result_from_body(b"<html>gateway timeout</html>", "instance-1");
+    assert!(!garbled.empty);
+    assert!(garbled.error.unwrap().starts_with("Parse error"));
+}
+
+// Heading north from (10,10) into its own curled-up trail: blocked ahead, a
+// walled four-cell pocket to the west, and to the east three straight cells
+// before open space
+fn spiral_game() -> Value {
+    let mut trail = vec!["10,10".to_string(), "10,9".to_string(), "5,10".to_string(), "14,10".to_string()];
+    for x in 6..=9 {
+        trail.push(format!("{},9", x));
+        trail.push(format!("{},11", x));
+    }
+    json!({
+        "arena": {"size": 20},
+        "players": {"spiral": {"position": {"x": 10, "z": 10}, "direction": 0, "alive": true}},
+        "trails": {"spiral": trail}
+    })
+}
+
+fn straight_run(blocked: &std::collections::HashSet<(i32, i32)>, mut cell: (i32, i32), (dx, dz): (i32, i32)) -> u32 {
+    let mut run = 0;
+    loop {
+        cell = (cell.0 + dx, cell.1 + dz);
+        if blocked.contains(&cell) || cell.0 < 0 || cell.1 < 0 || cell.0 >= 20 || cell.1 >= 20 {
+            return run;
+        }
+        run += 1;
+    }
+}
+
+#[test]
+fn ai_escapes_the_pocket_its_own_trail_forms() {
+    let game = spiral_game();
+    let blocked = trail_cells(game["trails"].as_object().unwrap());
+    // The longer straight run is the trap
+    assert!(straight_run(&blocked, (10, 10), (-1, 0)) > straight_run(&blocked, (10, 10), (1, 0)));
+    assert!(projected_room(10.0, 10.0, 3, &blocked, 20.0, AI_DANGER_LOOKAHEAD) < AI_DANGER_LOOKAHEAD);
+
+    for seed in 1..20 {
+        let action = get_wasmtime_ai_action(&game, "spiral", &mut AiRng::seeded(seed));
+        assert_eq!(action.direction, AiDirection::Right, "{:?}", action);
+    }
+}
+
+#[test]
+fn hunter_ai_returns_the_expected_typed_action() {
+    let game = |me: (i32, i32, i32), rival: Option<(i32, i32)>| {
+        let mut game = json!({
+            "arena": {"size": 20},
+            "players": {"hunter": {"position": {"x": me.0, "z": me.1}, "direction": me.2, "alive": true}},
+            "trails": {"hunter": [format!("{},{}", me.0, me.1)]}
+        });
+        if let Some((x, z)) = rival {
+            game["players"]["rival"] = json!({"position": {"x": x, "z": z}, "direction": 2, "alive": true});
+        }
+        game
+    };
+    let decide = |game: Value| get_wasmtime_ai_action(&game, "hunter", &mut AiRng::seeded(1));
+
+    assert_eq!(decide(game((10, 10, 0), None)), AiAction::forward("continuing_hunt"));
+    // Rival off to the east while heading north: cut across toward it
+    assert_eq!(
+        decide(game((10, 10, 0), Some((16, 10)))),
+        AiAction::turn(AiDirection::Right, "hunting_opponent_right")
+    );
+    // Top-left corner facing north: wall ahead and to the left
+    assert_eq!(
+        decide(game((0, 0, 0), None)),
+        AiAction::turn(AiDirection::Right, "avoid_collision_only_right_safe")
+    );
+
+    let json = serde_json::to_value(AiAction::forward("continuing_hunt")).unwrap();
+    assert_eq!(json, json!({"type": "move", "direction": "forward", "reason": "continuing_hunt"}));
+}
+
+#[test]
+fn ai_finds_itself_under_a_custom_id() {
+    let game = json!({
+        "arena": {"size": 20},
+        "players": {"bot-7": {"position": {"x": 10, "z": 10}, "direction": 0, "alive": true}},
+        "trails": {"bot-7": ["10,10"]}
+    });
+    let mine = get_wasmtime_ai_action(&game, "bot-7", &mut AiRng::seeded(3));
+    assert_ne!(mine.reason, "initializing");
+    let default = get_wasmtime_ai_action(&game, AI_DEFAULT_PLAYER_ID, &mut AiRng::seeded(3));
+    assert_eq!(default.reason, "initializing");
+}
+
+#[test]
+fn joins_older_than_the_max_age_are_not_replayed() {
+    let _serial = serial();
+    let _max_age = EnvVar::set("RECONSTRUCT_MAX_AGE_MS", "3600000");
+    let mut ghost = GameEvent::new("player_joined", "ghost").with_player(&test_player("ghost", 5.0, 5.0, "up"));
+    // Joined two hours ago; the leave fell out of the consume window
+    ghost.timestamp -= 2 * 3_600_000;
+    let fresh = GameEvent::new("player_joined", "fresh").with_player(&test_player("fresh", 9.0, 9.0, "down"));
+
+    let players = reconstruct_from(&[ghost, fresh]);
+    assert!(!players.contains_key("ghost"));
+    assert!(players.contains_key("fresh"));
+}
+
+#[test]
+fn interleaved_games_replay_independently() {
+    let _serial = serial();
+    let mut in_a = test_player("twin", 1.0, 1.0, "right");
+    in_a.game_id = "A".to_string();
+    let mut in_b = test_player("twin", 20.0, 20.0, "left");
+    in_b.game_id = "B".to_string();
+
+    let mut events = Vec::new();
+    events.push(GameEvent::new("player_joined", "twin").with_player(&in_a));
+    events.push(GameEvent::new("player_joined", "twin").with_player(&in_b));
+    for step in 1..=3 {
+        events.push(GameEvent::new("player_moved", "twin").with_player(&Player { x: 1.0 + step as f64, ..in_a.clone() }));
+        events.push(GameEvent::new("player_moved", "twin").with_player(&Player { x: 20.0 - step as f64, ..in_b.clone() }));
+    }
+    events.push(GameEvent::new("player_left", "twin").with_game_id("B"));
+
+    let mut messages = Vec::new();
+    append_reconstruction_records(&txeventq_page(&events), &get_kafka_topic(), &mut messages);
+    let replayed = replay_game_events(&messages);
+
+    let a = &replayed[&("A".to_string(), "twin".to_string())];
+    assert_eq!((a.x, a.y), (4.0, 1.0));
+    // B's leave doesn't touch A's copy of the same id
+    assert!(!replayed.contains_key(&("B".to_string(), "twin".to_string())));
+
+    let _active = EnvVar::set("GAME_ID", "A");
+    assert_eq!(reconstruct_from(&events)["twin"].x, 4.0);
+}
+
+#[test]
+fn metrics_report_the_event_buffer_depth() {
+    let _serial = serial();
+    get_pending_events().clear();
+    queue_event(GameEvent::new("player_moved", "buffered-1"));
+    queue_event(GameEvent::new("player_moved", "buffered-2"));
+
+    let metrics = String::from_utf8(handle_metrics().unwrap().body().unwrap()).unwrap();
+    assert!(metrics.contains("wasicycles_event_buffer_depth{runtime=\"wasmtime\"} 2\n"), "{}", metrics);
+    get_pending_events().clear();
+}
+
+#[test]
+fn a_player_known_only_to_ords_is_restored_on_rejoin() {
+    let _serial = serial();
+    let game_id = get_default_game_id();
+    let mut players = HashMap::new();
+    players.insert("present".to_string(), test_player("present", 3.0, 3.0, "up"));
+
+    let saved = Player { score: 40, ..test_player("returning", 7.0, 8.0, "left") };
+    let found = returning_player(&players, "returning", &game_id, |id| {
+        assert_eq!(id, "returning");
+        Ok(Some(saved.clone()))
+    });
+    let found = found.unwrap().expect("ORDS row should be restored");
+    assert_eq!((found.x, found.y, found.score), (7.0, 8.0, 40));
+
+    // Already in the current map: ORDS isn't consulted
+    let present = returning_player(&players, "present", &game_id, |_| panic!("looked up a player already in memory"));
+    assert_eq!(present.unwrap().map(|p| (p.x, p.y)), Some((3.0, 3.0)));
+
+    // A dead or lookup-failing player gets a fresh spawn
+    let dead = returning_player(&players, "gone", &game_id, |_| Ok(Some(Player { alive: false, ..saved.clone() })));
+    assert!(dead.unwrap().is_none());
+    assert!(returning_player(&players, "gone", &game_id, |_| Err("timeout".to_string())).unwrap().is_none());
+}
+
+#[test]
+fn strict_ords_refuses_a_join_it_could_not_check() {
+    let _serial = serial();
+    let _strict = EnvVar::set("STRICT_ORDS", "true");
+    let game_id = get_default_game_id();
+    let players = HashMap::new();
+
+    let refused = returning_player(&players, "unknown", &game_id, |_| Err("ORDS player fetch error: 503".to_string()));
+    assert_eq!(refused.unwrap_err(), "Could not check ORDS for returning player unknown: ORDS player fetch error: 503");
+    // A clean miss is still just a new player
+    assert!(returning_player(&players, "unknown", &game_id, |_| Ok(None)).unwrap().is_none());
+}
+
+#[test]
+fn drain_reads_every_partition_before_calling_it_done() {
+    let record = |partition: i64, offset: i64| json!({"partition": partition, "offset": offset, "data": {}});
+    // The consumer works through partition 0, finds it empty, then moves on to 1
+    let script = || vec![
+        ConsumeResult::records(vec![record(0, 0), record(0, 1)]),
+        ConsumeResult::empty(),
+        ConsumeResult::records(vec![record(1, 0)]),
+        ConsumeResult::empty(),
+        ConsumeResult::empty(),
+    ].into_iter();
+
+    let mut batches = script();
+    let outcome = drain_partitions(2, || batches.next().expect("drain read past the scripted batches")).unwrap();
+    assert!(outcome.drained);
+    assert_eq!(outcome.messages.len(), 3);
+    assert_eq!(outcome.partitions[&0].last_offset, Some(1));
+    assert_eq!(outcome.partitions[&1].records, 1);
+
+    // Counting one partition, the first empty batch ends it and partition 1 is stranded
+    let mut batches = script();
+    let single = drain_partitions(1, || batches.next().unwrap()).unwrap();
+    assert!(!single.partitions.contains_key(&1));
+
+    let mut failing = vec![ConsumeResult::error("down".to_string())].into_iter();
+    assert_eq!(drain_partitions(2, || failing.next().unwrap()).err(), Some("down".to_string()));
+}
+
+#[test]
+fn ticks_count_up_one_per_change_and_resume_after_replay() {
+    let _serial = serial();
+    let mut players = HashMap::new();
+    let ticks: Vec<u64> = (0..3).map(|_| advance_tick(&players, "tick-game")).collect();
+    assert_eq!(ticks, vec![1, 2, 3]);
+
+    // A restored player carrying a later tick moves the counter past it
+    players.insert("late".to_string(), Player { game_id: "tick-game".to_string(), tick: 10, ..test_player("late", 1.0, 1.0, "up") });
+    assert_eq!(advance_tick(&players, "tick-game"), 11);
+
+    seed_game_ticks(&[json!({"data": {"game_id": "replayed-game", "tick": 41}}), json!({"data": {"game_id": "replayed-game", "tick": 7}})]);
+    assert_eq!(advance_tick(&HashMap::new(), "replayed-game"), 42);
+}
+
+#[test]
+fn castle_name_comes_from_the_environment_everywhere() {
+    let _serial = serial();
+    let _castle = EnvVar::set("CASTLE_NAME", "Renamed Bastion");
+    let responses = [
+        handle_root(),
+        handle_version(),
+        handle_config(),
+        handle_state_source(),
+        handle_ai_strategy(),
+        handle_consumers(),
+        error_response("nope", 400),
+    ];
+    let bodies: Vec<Value> = responses.into_iter()
+        .map(|response| serde_json::from_slice(&response.unwrap().body().unwrap()).unwrap())
+        .collect();
+    for body in &bodies {
+        if let Some(castle) = body.get("castle") {
+            assert_eq!(castle, "Renamed Bastion", "{}", body);
+        }
+        assert!(!body.to_string().contains("Temporal Sanctuary"), "{}", body);
+    }
+    assert!(bodies.iter().filter(|b| b.get("castle").is_some()).count() >= 3);
+
+    assert_eq!(GameEvent::new("player_joined", "anyone").castle, "Renamed Bastion");
+}
+
+#[test]
+fn oracle_outages_answer_503_with_retry_after() {
+    let response = upstream_unavailable_response("connection refused").unwrap();
+    assert_eq!(response.status_code(), 503);
+    assert_eq!(response.header("retry-after").unwrap(), ORACLE_RETRY_AFTER_SECS.to_string().as_str());
+    let body: Value = serde_json::from_slice(&response.body().unwrap()).unwrap();
+    assert_eq!(body["upstream"], "oracle");
+    assert_eq!(body["error"], "connection refused");
+
+    // Our own failures stay 500 and don't tell clients to retry
+    let internal = error_response("bug", 500).unwrap();
+    assert_eq!(internal.status_code(), 500);
+    assert!(internal.header("retry-after").is_none());
+}
+
+#[test]
+fn a_late_arriving_older_move_does_not_win() {
+    let _serial = serial();
+    let joined = GameEvent::new("player_joined", "racer").with_player(&test_player("racer", 1.0, 1.0, "right"));
+    let mut newer = GameEvent::new("player_moved", "racer").with_player(&test_player("racer", 5.0, 1.0, "right"));
+    let mut older = GameEvent::new("player_moved", "racer").with_player(&test_player("racer", 3.0, 1.0, "right"));
+    newer.timestamp = joined.timestamp + 20;
+    older.timestamp = joined.timestamp + 10;
+
+    // The newer move's partition was read first
+    let mut messages = Vec::new();
+    append_reconstruction_records(&txeventq_page(&[joined, newer]), &get_kafka_topic(), &mut messages);
+    append_reconstruction_records(&txeventq_page(&[older]), &get_kafka_topic(), &mut messages);
+    let replayed = replay_game_events(&messages);
+
+    let racer = &replayed[&(get_default_game_id(), "racer".to_string())];
+    assert_eq!((racer.x, racer.y), (5.0, 1.0));
+}
+
+#[test]
+fn auth_header_follows_the_configured_mode() {
+    let _serial = serial();
+    let _user = EnvVar::set("ORACLE_USERNAME", "cycles");
+    let _password = EnvVar::set("ORACLE_PASSWORD", "hunter2");
+    assert_eq!(oracle_auth_header(), format!("Basic {}", base64_encode("cycles:hunter2")));
+    assert_eq!(base64_encode("cycles:hunter2"), "Y3ljbGVzOmh1bnRlcjI=");
+
+    let _mode = EnvVar::set("ORACLE_AUTH_MODE", "Bearer");
+    let _token = EnvVar::set("ORACLE_BEARER_TOKEN", "tok-123");
+    assert_eq!(oracle_auth_header(), "Bearer tok-123");
+
+    // Bearer without a token can't authenticate; Basic is the fallback
+    let _no_token = EnvVar::set("ORACLE_BEARER_TOKEN", "");
+    assert!(oracle_auth_header().starts_with("Basic "));
+}
+
+#[test]
+fn consumers_are_deleted_through_the_path_that_created_them() {
+    let _serial = serial();
+    get_created_consumers().clear();
+    let base = get_txeventq_base_url();
+    let cluster_url = format!("{}/clusters/{}/consumer-groups/wasmtime_state_reconstruction/consumers/wasmtime_consumer_state",
+        base, get_oracle_db_name());
+    record_created_consumer("wasmtime_state_reconstruction", "wasmtime_consumer_state", &cluster_url);
+    record_created_consumer("wasmtime_grp", "instance-7", &consumers_api_instance_url("wasmtime_grp", "instance-7"));
+
+    assert_eq!(consumer_delete_url("wasmtime_state_reconstruction", "wasmtime_consumer_state"), cluster_url);
+    assert_eq!(consumer_delete_url("wasmtime_grp", "instance-7"), format!("{}/consumers/wasmtime_grp/instances/instance-7", base));
+    // Unknown here (created before a static reset): the /consumers/{group} API
+    assert_eq!(consumer_delete_url("other_grp", "lost"), format!("{}/consumers/other_grp/instances/lost", base));
+    get_created_consumers().clear();
+}
+
+#[test]
+fn move_events_publish_inline_unless_a_backlog_is_queued() {
+    let _serial = serial();
+    get_pending_events().clear();
+    let receipt = PublishReceipt { partition: 1, offset: 42 };
+    let landed = publish_move_event(GameEvent::new("player_moved", "inline"), |_| Ok(Some(receipt)));
+    assert_eq!(landed, Some(receipt));
+    assert!(get_pending_events().is_empty());
+
+    // A failed publish is kept for the next flush
+    assert_eq!(publish_move_event(GameEvent::new("player_moved", "refused"), |_| Err("503".to_string())), None);
+    assert_eq!(get_pending_events().len(), 1);
+
+    // Behind that backlog the next move waits its turn instead of overtaking it
+    let skipped = publish_move_event(GameEvent::new("player_moved", "behind"), |_| panic!("published ahead of the queue"));
+    assert_eq!(skipped, None);
+    let queued: Vec<&str> = get_pending_events().iter().map(|e| e.player_id.as_str()).collect();
+    assert_eq!(queued, ["refused", "behind"]);
+    get_pending_events().clear();
+}
+
+#[test]
+fn a_fresh_state_topic_is_created_before_it_is_read() {
+    let topics = ["cycles_STATE".to_string(), "cycles".to_string()];
+    let calls = std::cell::RefCell::new(Vec::new());
+    let result = collect_reconstruction_events(
+        &topics,
+        |topic| { calls.borrow_mut().push(format!("create {}", topic)); Ok(()) },
+        |topic, messages| {
+            calls.borrow_mut().push(format!("read {}", topic));
+            messages.push(json!({"data": {"timestamp": 1, "seq": 0}}));
+            Ok(1)
+        },
+    );
+    assert_eq!(result.records.len(), 1);
+    // The state topic had events, so the main topic is never touched
+    assert_eq!(*calls.borrow(), ["create cycles_STATE", "read cycles_STATE"]);
+
+    // A topic that can't be created is skipped, not read, and reported if nothing else is readable
+    calls.borrow_mut().clear();
+    let result = collect_reconstruction_events(
+        &topics,
+        |topic| { calls.borrow_mut().push(format!("create {}", topic)); Err("HTTP 500".to_string()) },
+        |topic, _| panic!("read {} without creating it", topic),
+    );
+    assert_eq!(*calls.borrow(), ["create cycles_STATE", "create cycles"]);
+    assert!(result.error.unwrap().contains("cycles_STATE: HTTP 500"));
+}
+
+#[test]
+fn reconstruction_stops_at_the_event_and_player_caps() {
+    let _serial = serial();
+    let _max_events = EnvVar::set("RECONSTRUCT_MAX_EVENTS", "250");
+    let mut messages = Vec::new();
+    // A topic that never runs dry: every page is full
+    let pages = page_reconstruction_records("endless", 100, &mut messages, |messages| {
+        messages.extend((0..100).map(|i| json!({"offset": i})));
+        100
+    });
+    assert_eq!(pages, 3);
+    assert_eq!(messages.len(), 250);
+
+    let _max_players = EnvVar::set("MAX_PLAYERS", "4");
+    let joins: Vec<GameEvent> = (0..10).map(|i| GameEvent::new("player_joined", &format!("p{}", i))).collect();
+    assert_eq!(reconstruct_from(&joins).len(), 4);
+}
+
+#[test]
+fn an_invalid_byte_decodes_lossily_and_is_tagged() {
+    let _serial = serial();
+    let mut body = br#"[{"offset":0,"value":"{\"player_id\":\"ok\"}"},{"offset":1,"value":"{\"player_id\":\"bad"#.to_vec();
+    body.push(0xFF);
+    body.extend_from_slice(br#"\"}"}]"#);
+
+    let records = decode_consumed_records(&body).unwrap();
+    assert_eq!(records.len(), 2, "one bad byte must not drop the batch");
+    assert!(!records[0].1);
+    assert!(records[1].1);
+    assert_eq!(records[1].0["value"], "{\"player_id\":\"bad\u{FFFD}\"}");
+
+    let mut messages = Vec::new();
+    append_reconstruction_records(&body, "cycles", &mut messages);
+    assert_eq!(messages[0].get("lossy_decode"), None);
+    assert_eq!(messages[1]["lossy_decode"], true);
+    assert_eq!(messages[1]["data"]["player_id"], "bad\u{FFFD}");
+}
+
+#[test]
+fn known_paths_with_the_wrong_method_get_405_with_allow() {
+    let _serial = serial();
+    get_pending_events().clear();
+    let route = |method, path: &str| route_request(Request::builder(method, &format!("http://localhost{}", path)).build().unwrap()).unwrap();
+
+    let join = route(Method::Get, "/join");
+    assert_eq!(join.status_code(), 405);
+    assert_eq!(join.header("Allow").map(|v| v.to_str().unwrap()), Some("POST"));
+    // /players/bulk is its own route, not a player called "bulk"
+    let bulk = route(Method::Get, "/players/bulk");
+    assert_eq!(bulk.status_code(), 405);
+    assert_eq!(bulk.header("Allow").map(|v| v.to_str().unwrap()), Some("POST"));
+    assert_eq!(route(Method::Get, "/nonexistent").status_code(), 404);
+}
+
+#[test]
+fn posted_events_replay_to_the_players_they_describe() {
+    let _serial = serial();
+    let mut dave = test_player("replay-dave", 20.0, 20.0, "left");
+    let joined = GameEvent::new("player_joined", &dave.id).with_player(&dave);
+    dave.x = 19.0;
+    let moved = GameEvent::new("player_moved", &dave.id).with_player(&dave);
+    let erin = test_player("replay-erin", 40.0, 40.0, "up");
+    let erin_joined = GameEvent::new("player_joined", &erin.id).with_player(&erin);
+    let erin_left = GameEvent::new("player_left", &erin.id).with_game_id(&erin.game_id);
+    let events = serde_json::to_value([joined, moved, erin_joined, erin_left]).unwrap();
+
+    let players = replay_posted_events(serde_json::from_value(events).unwrap());
+    assert_eq!(players.len(), 1);
+    assert_eq!(players[0]["player_id"], "replay-dave");
+    assert_eq!(players[0]["player"]["x"], 19.0);
+}
+
+#[test]
+fn uppercase_ords_rows_become_players() {
+    let row = json!({
+        "PLAYER_ID": "ords-upper", "X": 12.0, "Y": 34.0, "DIRECTION": "left",
+        "SCORE": 7, "COLOR": "#ff00ff", "ALIVE": true
+    });
+    let player: Player = serde_json::from_value(normalize_ords_row(row)).unwrap();
+    assert_eq!(player.id, "ords-upper");
+    assert_eq!((player.x, player.y), (12.0, 34.0));
+    assert_eq!(player.direction, "left");
+    assert_eq!(player.score, 7);
+    assert_eq!(player.color, "#ff00ff");
+    assert!(player.alive);
+}
+
+#[test]
+fn a_drifted_coordinate_is_its_rounded_cell_everywhere() {
+    assert_eq!(grid_cell(24.999999, 10.0), (25, 10));
+    assert_eq!(Position { x: 24.999999, y: 10.0 }.cell(), (25, 10));
+
+    let mut wall = test_player("drift-wall", 40.0, 40.0, "up");
+    wall.trail.push(Position { x: 25.0, y: 10.0 });
+    let mover = test_player("drift-mover", 24.0, 10.0, "right");
+    let players: HashMap<String, Player> = [wall, mover.clone()].into_iter().map(|p| (p.id.clone(), p)).collect();
+    assert_eq!(collision_at(&players, &mover, Position { x: 24.999999, y: 10.0 }), Some("drift-wall".to_string()));
+}
+
+#[test]
+fn castles_sharing_an_arena_spawn_in_different_quadrants() {
+    let _serial = serial();
+    let _width = EnvVar::set("ARENA_WIDTH", "800");
+    let _height = EnvVar::set("ARENA_HEIGHT", "600");
+    let _step = EnvVar::set("MOVE_STEP", "20");
+    let wasmtime = get_game_config();
+    assert_eq!((wasmtime.spawn_x, wasmtime.spawn_y), (600.0, 140.0));
+
+    let _index = EnvVar::set("RUNTIME_INDEX", "0");
+    let wasmedge = get_game_config();
+    assert_eq!((wasmedge.spawn_x, wasmedge.spawn_y), (200.0, 140.0));
+}
+
+#[test]
+fn hmac_sha256_matches_rfc_4231() {
+    let hex = |bytes: [u8; 32]| bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+    let long_key = [0xaa; 131];
+    let cases: [(&[u8], &[u8], &str); 5] = [
+        (&[0x0b; 20], b"Hi There", "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"),
+        (b"Jefe", b"what do ya want for nothing?", "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"),
+        (&[0xaa; 20], &[0xdd; 50], "773ea91e36800e46854db8ebd09181a72959098b3ef8c122d9635514ced565fe"),
+        (&long_key, b"Test Using Larger Than Block-Size Key - Hash Key First", "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"),
+        (&long_key, b"This is a test using a larger than block-size key and a larger than block-size data. The key needs to be hashed before being used by the HMAC algorithm.", "9b09ffa71b942fcb27635fbcd5b0e944bfdc63644f0713938a7f51535c3a35e2"),
+    ];
+    for (key, message, expected) in cases {
+        assert_eq!(hex(hmac_sha256(key, message)), expected);
+    }
+}
+
+#[test]
+fn signed_events_verify_until_tampered_with() {
+    let _serial = serial();
+    let _key = EnvVar::set("EVENT_SIGNING_KEY", "castle-key");
+    let player = test_player("signed", 3.0, 4.0, "up");
+    let payload = event_payload(&GameEvent::new("player_moved", &player.id).with_player(&player)).unwrap();
+    let mut event: Value = serde_json::from_str(&payload).unwrap();
+    assert!(has_valid_signature(&event));
+
+    event["score"] = json!(9999);
+    assert!(!has_valid_signature(&event));
+    event.as_object_mut().unwrap().remove("signature");
+    assert!(!has_valid_signature(&event));
+}
+
+#[test]
+fn a_custom_persistence_order_puts_txeventq_first() {
+    let _serial = serial();
+    let _order = EnvVar::set("PERSISTENCE_ORDER", "txeventq,oracle");
+    let _file = EnvVar::set("STATE_FILE", "");
+    let names = |stores: Vec<Box<dyn StateStore>>| stores.iter().map(|s| s.name()).collect::<Vec<_>>();
+    assert_eq!(names(get_persistence_stores()), ["txeventq", "oracle"]);
+    // Memory always answers first when it has anything
+    assert_eq!(names(get_load_stores()), ["memory", "txeventq", "oracle"]);
+
+    let _default = EnvVar::set("PERSISTENCE_ORDER", "");
+    assert_eq!(names(get_persistence_stores()), ["oracle", "txeventq"]);
+}
+
+// A store that records the calls it gets and fails them on request
+struct MockStore {
+    name: &'static str,
+    fails: bool,
+    calls: std::rc::Rc<std::cell::RefCell<Vec<String>>>,
+}
+
+impl StateStore for MockStore {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn source(&self) -> &'static str {
+        self.name
+    }
+
+    fn save(&self, _players: &HashMap<String, Player>) -> Result<(), String> {
+        self.calls.borrow_mut().push(format!("save {}", self.name));
+        if self.fails { Err("down".to_string()) } else { Ok(()) }
+    }
+
+    fn load(&self) -> Result<HashMap<String, Player>, String> {
+        self.calls.borrow_mut().push(format!("load {}", self.name));
+        if self.fails {
+            return Err("down".to_string());
+        }
+        let player = test_player(self.name, 1.0, 1.0, "up");
+        Ok(HashMap::from([(player.id.clone(), player)]))
+    }
+}
+
+#[test]
+fn a_failing_store_falls_through_to_the_next() {
+    let calls = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+    let store = |name, fails| Box::new(MockStore { name, fails, calls: calls.clone() }) as Box<dyn StateStore>;
+    let stores = vec![store("broken", true), store("backup", false), store("unused", false)];
+
+    assert_eq!(save_to_first_store(&stores, &HashMap::new()), Some("backup"));
+    let (state, source) = load_from_first_store(&stores).unwrap();
+    assert_eq!(source, "backup");
+    assert!(state.contains_key("backup"));
+    assert_eq!(*calls.borrow(), ["save broken", "save backup", "load broken", "load backup"]);
+
+    assert_eq!(save_to_first_store(&[store("broken", true)], &HashMap::new()), None);
+    assert!(load_from_first_store(&[store("broken", true)]).is_none());
+}
+
+#[test]
+fn unknown_event_types_are_skipped_and_counted() {
+    let _serial = serial();
+    get_unknown_event_types().clear();
+    let frank = test_player("replay-frank", 8.0, 8.0, "up");
+    let joined = GameEvent::new("player_joined", &frank.id).with_player(&frank);
+    let mut typo = frank.clone();
+    typo.y = 9.0;
+    // "player_move" is not a type replay knows
+    let typo_a = GameEvent::new("player_move", &frank.id).with_player(&typo);
+    let typo_b = GameEvent::new("player_move", &frank.id).with_player(&typo);
+
+    let players = reconstruct_from(&[joined, typo_a, typo_b]);
+    assert_eq!((players["replay-frank"].x, players["replay-frank"].y), (8.0, 8.0));
+    assert_eq!(get_unknown_event_types()["player_move"].0, 2);
+    assert!(!get_unknown_event_types().contains_key("player_joined"));
+    get_unknown_event_types().clear();
+}
+
+#[test]
+fn a_purple_wasmedge_player_reconstructs_purple() {
+    let _serial = serial();
+    let grace = test_player("replay-grace", 12.0, 12.0, "up");
+    let mut colored = GameEvent::new("player_joined", &grace.id).with_player(&grace);
+    colored.runtime = "wasmedge".to_string();
+    colored.color = Some("#9333ea".to_string());
+    // Older wasmedge events carried no color; the runtime still says purple
+    let heidi = test_player("replay-heidi", 30.0, 12.0, "up");
+    let mut uncolored = GameEvent::new("player_joined", &heidi.id).with_player(&heidi);
+    uncolored.runtime = "wasmedge".to_string();
+    uncolored.color = None;
+
+    let players = reconstruct_from(&[colored, uncolored]);
+    assert_eq!(players["replay-grace"].color, "#9333ea");
+    assert_eq!(players["replay-heidi"].color, "#9333ea");
+}
+
+#[test]
+fn bodies_over_max_body_bytes_get_413() {
+    let _serial = serial();
+    let _limit = EnvVar::set("MAX_BODY_BYTES", "1024");
+    assert!(check_body_size(&[b'x'; 1024]).is_none());
+    assert_eq!(check_body_size(&[b'x'; 1025]).unwrap().unwrap().status_code(), 413);
+
+    let declared = Request::builder(Method::Post, "http://localhost/join")
+        .header("Content-Length", "4096")
+        .build()
+        .unwrap();
+    assert_eq!(check_declared_body_size(&declared).unwrap().unwrap().status_code(), 413);
+}
+
+#[test]
+fn a_fading_trail_frees_the_cells_behind_it() {
+    // A tight loop back onto the starting cell: right, down, left, up
+    let loop_back = |trail_length: usize| {
+        let mut cycle = test_player("fader", 10.0, 10.0, "right");
+        for (x, y) in [(11.0, 10.0), (11.0, 11.0), (10.0, 11.0)] {
+            cycle.lay_trail(Position { x: cycle.x, y: cycle.y }, trail_length);
+            (cycle.x, cycle.y) = (x, y);
+        }
+        cycle.lay_trail(Position { x: cycle.x, y: cycle.y }, trail_length);
+        let players = HashMap::from([(cycle.id.clone(), cycle.clone())]);
+        collision_at(&players, &cycle, Position { x: 10.0, y: 10.0 })
+    };
+
+    // With TRAIL_LENGTH=3 the start cell has faded by the time the cycle returns
+    assert_eq!(loop_back(3), None);
+    assert_eq!(loop_back(0), Some("fader".to_string()));
+}
+
+#[test]
+fn same_millisecond_moves_replay_in_seq_order() {
+    let _serial = serial();
+    let mut ivy = test_player("replay-ivy", 5.0, 5.0, "right");
+    let mut joined = GameEvent::new("player_joined", &ivy.id).with_player(&ivy);
+    ivy.x = 6.0;
+    let mut first = GameEvent::new("player_moved", &ivy.id).with_player(&ivy);
+    ivy.x = 7.0;
+    let mut second = GameEvent::new("player_moved", &ivy.id).with_player(&ivy);
+    for event in [&mut joined, &mut first, &mut second] {
+        event.timestamp = 1_700_000_000_000;
+    }
+    (joined.seq, first.seq, second.seq) = (Some(1), Some(2), Some(3));
+
+    // Published (and so consumed) out of order, as wasmedge's spawned tasks can
+    let page = txeventq_page(&[second, joined, first]);
+    let topics = ["cycles".to_string()];
+    let consumed = collect_reconstruction_events(&topics, |_| Ok(()), |topic, messages| {
+        append_reconstruction_records(&page, topic, messages);
+        Ok(1)
+    });
+    let players = rebuild_state_from_messages(consumed.records);
+    assert_eq!(players["replay-ivy"].x, 7.0);
+}
+
+#[test]
+fn secrets_are_read_from_mounted_files_before_the_environment() {
+    let _serial = serial();
+    let path = temp_path("oracle-password");
+    std::fs::write(&path, "from-the-file\n").unwrap();
+    let _file = EnvVar::set("ORACLE_PASSWORD_FILE", path.to_str().unwrap());
+    let _plain = EnvVar::set("ORACLE_PASSWORD", "from-the-env");
+    let _missing = EnvVar::set("ORACLE_USERNAME_FILE", "/nonexistent/oracle-username");
+    let _username = EnvVar::set("ORACLE_USERNAME", "env-user");
+    unsafe { SECRET_FILES = None; }
+
+    assert_eq!(secret_var("ORACLE_PASSWORD").unwrap(), "from-the-file");
+    // An unreadable file falls back to the plain variable
+    assert_eq!(secret_var("ORACLE_USERNAME").unwrap(), "env-user");
+    assert!(get_secret_files()["ORACLE_USERNAME"].is_err());
+
+    unsafe { SECRET_FILES = None; }
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn aggression_extremes_play_hunter_and_territory() {
+    // Heading north at the centre with a rival off to the east
+    let game = json!({
+        "arena": {"size": 20},
+        "players": {
+            "blend": {"position": {"x": 10, "z": 10}, "direction": 0, "alive": true},
+            "rival": {"position": {"x": 16, "z": 10}, "direction": 2, "alive": true}
+        },
+        "trails": {"blend": ["10,10"]}
+    });
+    let decide = |aggression| blended_ai_action(&game, "blend", aggression, &mut AiRng::seeded(7));
+
+    assert_eq!(decide(1.0), AiAction::turn(AiDirection::Right, "hunting_opponent_right"));
+    // Territory ignores the rival and keeps walling off its own ground
+    assert_eq!(decide(0.0), AiAction::forward("building_defensive_wall"));
+    assert_eq!(decide(0.0), get_territory_ai_action(&game, "blend", &mut AiRng::seeded(7)));
+}
+
+#[test]
+fn debug_env_is_admin_only_and_redacts_secrets() {
+    let _serial = serial();
+    let secrets = [
+        ("ORACLE_PASSWORD", "pw-hunter2"),
+        ("ORACLE_BEARER_TOKEN", "bearer-abc"),
+        ("API_KEY", "api-def"),
+        ("SPECTATE_TOKEN", "spectate-ghi"),
+        ("EVENT_SIGNING_KEY", "signing-jkl"),
+        ("ADMIN_TOKEN", "admin-mno"),
+    ];
+    let _vars: Vec<EnvVar> = secrets.iter().map(|(name, value)| EnvVar::set(name, value)).collect();
+    let request = |token: &str| Request::builder(Method::Get, "http://localhost/debug/env")
+        .header("X-Admin-Token", token)
+        .build()
+        .unwrap();
+    // Debug mode doesn't open it
+    unsafe { DEBUG_LOGGING = true; }
+    assert_eq!(handle_debug_env(&request("guess")).unwrap().status_code(), 401);
+    unsafe { DEBUG_LOGGING = false; }
+
+    let response = handle_debug_env(&request("admin-mno")).unwrap();
+    assert_eq!(response.status_code(), 200);
+    let text = String::from_utf8(response.body().unwrap()).unwrap();
+    for (name, value) in secrets {
+        assert!(!text.contains(value), "{} leaked: {}", name, text);
+    }
+    let body: Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(body["oracle"]["password"], "set (redacted)");
+    assert_eq!(body["access"]["event_signing_key"], "set (redacted)");
+}
+
+#[test]
+fn equal_travel_scores_equally_across_step_sizes() {
+    let _lock = serial();
+    let _width = EnvVar::set("ARENA_WIDTH", "800");
+    let _height = EnvVar::set("ARENA_HEIGHT", "600");
+    // This runtime's 1-unit steps at multiplier 1, against wasmedge's 20px
+    // steps at multiplier 20, each covering 20 units
+    let travel = |step: &str, multiplier: &str, moves: usize| {
+        let _step = EnvVar::set("MOVE_STEP", step);
+        let _multiplier = EnvVar::set("SCORE_MULTIPLIER", multiplier);
+        let config = get_game_config();
+        let mut players = HashMap::new();
+        let mut player = test_player("p1", 400.0, 300.0, "right");
+        for _ in 0..moves {
+            assert_eq!(apply_move(&mut players, &mut player, Direction::Right, &config), None);
+        }
+        (player.stats.distance, player.score)
+    };
+    
+    assert_eq!(travel("1", "1", 20), (20.0, 20));
+    assert_eq!(travel("20", "20", 1), (20.0, 20));
+}
+
+#[test]
+fn numeric_and_quoted_player_ids_are_the_same_and_booleans_are_refused() {
+    assert_eq!(request_player_id(r#"{"player_id": 42}"#), Ok("42".to_string()));
+    assert_eq!(request_player_id(r#"{"player_id": "42"}"#), Ok("42".to_string()));
+    assert_eq!(request_player_id(r#"{"player_id": true}"#), Err("player_id must be a string or an integer"));
+    assert_eq!(request_player_id(r#"{"direction": "up"}"#), Err("Missing player_id"));
+}
+
+#[test]
+fn a_reconstructed_trail_still_causes_collisions() {
+    let _serial = serial();
+    let mut builder = test_player("builder", 12.0, 10.0, "right");
+    builder.trail = vec![Position { x: 10.0, y: 10.0 }, Position { x: 11.0, y: 10.0 }];
+    let mut long = test_player("long", 0.0, 40.0, "right");
+    long.trail = (0..EVENT_TRAIL_CELLS + 10).map(|i| Position { x: i as f64, y: 41.0 }).collect();
+    let events = [
+        GameEvent::new("player_moved", "builder").with_player(&builder),
+        GameEvent::new("player_moved", "long").with_player(&long),
+    ];
+
+    let mut players = reconstruct_from(&events);
+    assert_eq!(players["builder"].trail, builder.trail);
+    // Only the newest EVENT_TRAIL_CELLS cells travel with the event
+    assert_eq!(players["long"].trail, long.trail[10..].to_vec());
+
+    let config = get_game_config();
+    let mut crosser = test_player("crosser", 11.0, 9.0, "down");
+    assert_eq!(apply_move(&mut players, &mut crosser, Direction::Down, &config), Some("builder".to_string()));
+    assert!(!crosser.alive);
+    assert_eq!(players["builder"].stats.kills, 1);
+}
+
+#[test]
+fn root_lists_every_route() {
+    let _serial = serial();
+    let info: Value = serde_json::from_slice(&handle_root().unwrap().body().unwrap()).unwrap();
+    let listed: Vec<(&str, &str)> = info["endpoints"].as_array().unwrap().iter()
+        .map(|e| (e["method"].as_str().unwrap(), e["path"].as_str().unwrap()))
+        .collect();
+    for &(method, path, _) in ROUTES {
+        assert!(listed.contains(&(method, path)), "{} {} missing from /", method, path);
+    }
+    assert_eq!(listed.len(), ROUTES.len());
+}
+
+#[test]
+fn an_import_with_a_player_outside_the_arena_is_rejected() {
+    let _serial = serial();
+    let config = get_game_config();
+    let export = |player: Player| StateExport {
+        format: STATE_EXPORT_FORMAT.to_string(),
+        version: STATE_EXPORT_VERSION,
+        runtime: "wasmedge".to_string(),
+        exported_at: get_timestamp(),
+        game_id: get_default_game_id(),
+        players: HashMap::from([(player.id.clone(), player)]),
+        ticks: HashMap::new(),
+    };
+
+    assert_eq!(validate_state_export(&export(test_player("inside", 10.0, 10.0, "up")), &config), Ok(()));
+    let far = test_player("far", config.arena_width + 100.0, 10.0, "up");
+    assert_eq!(validate_state_export(&export(far), &config), Err("Player far lies outside the arena".to_string()));
+    let negative = test_player("negative", 10.0, -5.0, "up");
+    assert!(validate_state_export(&export(negative), &config).is_err());
+}
+
+#[test]
+fn minimal_delta_moves_replay_to_the_full_player() {
+    let _serial = serial();
+    let _minimal = EnvVar::set("MINIMAL_EVENTS", "true");
+    unsafe { LAST_PLAYER_EVENTS = None; }
+    let config = get_game_config();
+    let mut players = HashMap::new();
+    let mut player = test_player("delta", 10.0, 10.0, "up");
+    let mut events = vec![GameEvent::new("player_joined", "delta").with_player(&player)];
+
+    apply_move(&mut players, &mut player, Direction::Up, &config);
+    let straight = GameEvent::new("player_moved", "delta").with_player(&player);
+    let sent: Value = serde_json::from_str(&event_payload(&straight).unwrap()).unwrap();
+    assert!(sent.get("direction").is_none() && sent.get("trail").is_none() && sent.get("color").is_none());
+    assert_eq!(sent["trail_added"], json!([{"x": 10.0, "y": 10.0}]));
+    events.push(straight);
+
+    player.direction = "right".to_string();
+    apply_move(&mut players, &mut player, Direction::Right, &config);
+    let turn = GameEvent::new("player_moved", "delta").with_player(&player);
+    assert_eq!(turn.direction.as_deref(), Some("right"));
+    assert!(turn.trail.is_none());
+    events.push(turn);
+
+    let replayed = &reconstruct_from(&events)["delta"];
+    assert_eq!((replayed.x, replayed.y), (11.0, 9.0));
+    assert_eq!(replayed.direction, "right");
+    assert_eq!(replayed.score, 2);
+    assert_eq!(replayed.color, player.color);
+    assert_eq!(replayed.trail, vec![Position { x: 10.0, y: 10.0 }, Position { x: 10.0, y: 9.0 }]);
+    unsafe { LAST_PLAYER_EVENTS = None; }
+}
+
+#[test]
+fn a_supplied_request_id_is_echoed_and_a_missing_one_generated() {
+    let json_response = || Response::builder()
+        .header("Content-Type", "application/json")
+        .body(r#"{"status":"ok"}"#)
+        .build();
+
+    let req = Request::builder(Method::Get, "/health").header("X-Request-Id", "trace-abc-123").build().unwrap();
+    let supplied = incoming_request_id(&req);
+    assert_eq!(supplied.as_deref(), Some("trace-abc-123"));
+    let response = finish_response(json_response(), "trace-abc-123", true, false).unwrap();
+    assert_eq!(response.headers().get("X-Request-Id").unwrap(), "trace-abc-123");
+    let body: Value = serde_json::from_slice(&response.body().unwrap()).unwrap();
+    assert_eq!(body["request_id"], "trace-abc-123");
+
+    // Without one the id is made up and only sent back in the header; the
+    // handler's bytes go out untouched
+    let req = Request::builder(Method::Get, "/health").build().unwrap();
+    assert_eq!(incoming_request_id(&req), None);
+    let generated = generate_request_id();
+    assert_eq!(generated.len(), 16);
+    let response = finish_response(json_response(), &generated, false, false).unwrap();
+    assert_eq!(response.headers().get("X-Request-Id").unwrap(), generated.as_str());
+    assert_eq!(response.body().unwrap(), br#"{"status":"ok"}"#);
+}